@@ -1,10 +1,30 @@
 package build.wallet.firmware
 
 class HardwareAttestationFake : HardwareAttestation {
+  var loadedTrustStoreBundle: List<UByte>? = null
+  var trustStoreBundleSequence: ULong = 1UL
+
   override fun verifyCertChain(
     identityCert: List<UByte>,
     batchCert: List<UByte>,
   ): String = "mock"
 
   override fun generateChallenge(): List<UByte> = emptyList()
+
+  override fun loadTrustStoreBundle(
+    bundle: List<UByte>,
+    signature: List<UByte>,
+    lastAcceptedSequence: ULong,
+  ): ULong {
+    if (trustStoreBundleSequence <= lastAcceptedSequence) {
+      throw AttestationException("Trust store bundle is older than the last accepted one")
+    }
+    loadedTrustStoreBundle = bundle
+    return trustStoreBundleSequence
+  }
+
+  fun reset() {
+    loadedTrustStoreBundle = null
+    trustStoreBundleSequence = 1UL
+  }
 }
@@ -0,0 +1,18 @@
+package build.wallet.firmware
+
+import build.wallet.di.AppScope
+import build.wallet.di.SingleIn
+import me.tatarka.inject.annotations.Provides
+import software.amazon.lastmile.kotlin.inject.anvil.ContributesTo
+import build.wallet.rust.firmware.Attestation as AttestationCore
+
+@ContributesTo(AppScope::class)
+interface AttestationCoreComponent {
+  /**
+   * Single attestation store shared by [HardwareAttestationImpl] and the NFC commands, so the
+   * trust store bundle loaded at startup is the one used to verify every device.
+   */
+  @Provides
+  @SingleIn(AppScope::class)
+  fun provideAttestationCore(): AttestationCore = AttestationCore()
+}
@@ -5,18 +5,32 @@ import build.wallet.di.BitkeyInject
 import build.wallet.rust.firmware.Attestation as AttestationCore
 
 @BitkeyInject(AppScope::class)
-class HardwareAttestationImpl : HardwareAttestation {
+class HardwareAttestationImpl(
+  private val attestation: AttestationCore,
+) : HardwareAttestation {
   override fun verifyCertChain(
     identityCert: List<UByte>,
     batchCert: List<UByte>,
   ): String {
-    return AttestationCore().verifyDeviceIdentityCertChain(
+    return attestation.verifyDeviceIdentityCertChain(
       identityCertDer = identityCert,
       batchCertDer = batchCert
     )
   }
 
   override fun generateChallenge(): List<UByte> {
-    return AttestationCore().generateChallenge()
+    return attestation.generateChallenge()
+  }
+
+  override fun loadTrustStoreBundle(
+    bundle: List<UByte>,
+    signature: List<UByte>,
+    lastAcceptedSequence: ULong,
+  ): ULong {
+    return attestation.loadTrustStoreBundle(
+      bundle = bundle,
+      signature = signature,
+      lastAcceptedSequence = lastAcceptedSequence
+    )
   }
 }
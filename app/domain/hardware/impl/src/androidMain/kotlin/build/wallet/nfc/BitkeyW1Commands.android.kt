@@ -64,6 +64,7 @@ import okio.ByteString
 import okio.ByteString.Companion.encodeUtf8
 import okio.ByteString.Companion.toByteString
 import kotlin.reflect.KClass
+import build.wallet.rust.firmware.Attestation as AttestationCore
 import build.wallet.rust.firmware.CoredumpFragment as CoreCoredumpFragment
 import build.wallet.rust.firmware.EnrolledFingerprints as CoreEnrolledFingerprints
 import build.wallet.rust.firmware.EventFragment as CoreEventFragment
@@ -83,6 +84,7 @@ import build.wallet.rust.firmware.UnlockMethod as CoreUnlockMethod
 @BitkeyInject(AppScope::class)
 class BitkeyW1Commands(
   private val clock: Clock,
+  private val attestation: AttestationCore,
 ) : NfcCommands {
   override suspend fun fwupStart(
     session: NfcSession,
@@ -634,7 +636,7 @@ class BitkeyW1Commands(
     challenge: List<UByte>,
   ) = executeCommand(
     session = session,
-    generateCommand = { SignVerifyAttestationChallenge(deviceIdentityDer, challenge) },
+    generateCommand = { SignVerifyAttestationChallenge(deviceIdentityDer, challenge, attestation) },
     getNext = { command, data -> command.next(data) },
     getResponse = { state: BooleanState.Data -> state.response },
     generateResult = { state: BooleanState.Result -> state.value }
@@ -0,0 +1,72 @@
+package build.wallet.firmware
+
+import build.wallet.catchingResult
+import build.wallet.di.AppScope
+import build.wallet.di.BitkeyInject
+import build.wallet.logging.logError
+import build.wallet.store.KeyValueStoreFactory
+import build.wallet.toUByteList
+import com.github.michaelbull.result.Result
+import com.github.michaelbull.result.onFailure
+import com.russhwolf.settings.ExperimentalSettingsApi
+import com.russhwolf.settings.coroutines.SuspendSettings
+import kotlinx.coroutines.sync.Mutex
+import kotlinx.coroutines.sync.withLock
+import okio.ByteString
+import okio.ByteString.Companion.decodeBase64
+
+@BitkeyInject(AppScope::class)
+@OptIn(ExperimentalSettingsApi::class)
+class AttestationTrustStoreServiceImpl(
+  private val hardwareAttestation: HardwareAttestation,
+  private val keyValueStoreFactory: KeyValueStoreFactory,
+) : AttestationTrustStoreService, AttestationTrustStoreWorker {
+  private companion object {
+    const val STORE_NAME = "ATTESTATION_TRUST_STORE"
+    const val BUNDLE_KEY = "bundle"
+    const val SIGNATURE_KEY = "signature"
+    const val SEQUENCE_KEY = "sequence"
+  }
+
+  private val lock = Mutex()
+
+  private suspend fun store(): SuspendSettings = keyValueStoreFactory.getOrCreate(STORE_NAME)
+
+  override suspend fun executeWork() {
+    lock.withLock {
+      val store = store()
+      val bundle = store.getStringOrNull(BUNDLE_KEY)?.decodeBase64() ?: return
+      val signature = store.getStringOrNull(SIGNATURE_KEY)?.decodeBase64() ?: return
+      // The persisted bundle was accepted at this sequence, so it must not be rejected as a
+      // replay of itself.
+      val lastAcceptedSequence = store.getLong(SEQUENCE_KEY, 0L).toULong()
+      catchingResult {
+        hardwareAttestation.loadTrustStoreBundle(
+          bundle = bundle.toUByteList(),
+          signature = signature.toUByteList(),
+          lastAcceptedSequence = lastAcceptedSequence - 1UL
+        )
+      }.onFailure {
+        logError(throwable = it) { "Failed to load persisted attestation trust store bundle" }
+      }
+    }
+  }
+
+  override suspend fun updateTrustStoreBundle(
+    bundle: ByteString,
+    signature: ByteString,
+  ): Result<Unit, Throwable> =
+    lock.withLock {
+      val store = store()
+      catchingResult {
+        val sequence = hardwareAttestation.loadTrustStoreBundle(
+          bundle = bundle.toUByteList(),
+          signature = signature.toUByteList(),
+          lastAcceptedSequence = store.getLong(SEQUENCE_KEY, 0L).toULong()
+        )
+        store.putString(BUNDLE_KEY, bundle.base64())
+        store.putString(SIGNATURE_KEY, signature.base64())
+        store.putLong(SEQUENCE_KEY, sequence.toLong())
+      }
+    }
+}
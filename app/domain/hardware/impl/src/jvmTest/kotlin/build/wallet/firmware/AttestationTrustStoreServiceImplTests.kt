@@ -0,0 +1,53 @@
+package build.wallet.firmware
+
+import build.wallet.store.KeyValueStoreFactoryFake
+import build.wallet.testing.shouldBeErrOfType
+import build.wallet.testing.shouldBeOk
+import build.wallet.toUByteList
+import io.kotest.core.spec.style.FunSpec
+import io.kotest.matchers.shouldBe
+import okio.ByteString.Companion.encodeUtf8
+
+class AttestationTrustStoreServiceImplTests : FunSpec({
+  val hardwareAttestation = HardwareAttestationFake()
+  val keyValueStoreFactory = KeyValueStoreFactoryFake()
+  val service = AttestationTrustStoreServiceImpl(hardwareAttestation, keyValueStoreFactory)
+
+  val bundle = "bundle".encodeUtf8()
+  val signature = "signature".encodeUtf8()
+
+  beforeTest {
+    hardwareAttestation.reset()
+    keyValueStoreFactory.clear()
+  }
+
+  test("persisted bundle is loaded on launch") {
+    service.updateTrustStoreBundle(bundle, signature).shouldBeOk()
+    hardwareAttestation.reset()
+
+    service.executeWork()
+
+    hardwareAttestation.loadedTrustStoreBundle.shouldBe(bundle.toUByteList())
+  }
+
+  test("nothing is loaded on launch without a persisted bundle") {
+    service.executeWork()
+
+    hardwareAttestation.loadedTrustStoreBundle.shouldBe(null)
+  }
+
+  test("replayed bundle is rejected and not persisted") {
+    hardwareAttestation.trustStoreBundleSequence = 2UL
+    service.updateTrustStoreBundle(bundle, signature).shouldBeOk()
+
+    hardwareAttestation.trustStoreBundleSequence = 1UL
+    service.updateTrustStoreBundle("older".encodeUtf8(), signature)
+      .shouldBeErrOfType<AttestationException>()
+
+    hardwareAttestation.reset()
+    hardwareAttestation.trustStoreBundleSequence = 2UL
+    service.executeWork()
+
+    hardwareAttestation.loadedTrustStoreBundle.shouldBe(bundle.toUByteList())
+  }
+})
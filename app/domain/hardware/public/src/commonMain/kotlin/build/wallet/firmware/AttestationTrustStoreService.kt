@@ -0,0 +1,21 @@
+package build.wallet.firmware
+
+import com.github.michaelbull.result.Result
+import okio.ByteString
+
+/**
+ * Keeps the trust store of the app's [HardwareAttestation] on the latest signed bundle.
+ *
+ * Accepted bundles are persisted and loaded again by [AttestationTrustStoreWorker] on every app
+ * launch, so device identity chains are never checked against a stale trust store.
+ */
+interface AttestationTrustStoreService {
+  /**
+   * Verifies [bundle] against the pinned bundle signing keys, loads it into [HardwareAttestation]
+   * and persists it. Bundles older than the last accepted one are rejected.
+   */
+  suspend fun updateTrustStoreBundle(
+    bundle: ByteString,
+    signature: ByteString,
+  ): Result<Unit, Throwable>
+}
@@ -0,0 +1,8 @@
+package build.wallet.firmware
+
+import build.wallet.worker.AppWorker
+
+/**
+ * Loads the persisted trust store bundle into [HardwareAttestation] on app launch.
+ */
+interface AttestationTrustStoreWorker : AppWorker
@@ -28,4 +28,20 @@ interface HardwareAttestation {
    */
   @Throws(AttestationException::class)
   fun generateChallenge(): List<UByte>
+
+  /**
+   * Replaces the trust store used to verify device identity certificates with a signed bundle.
+   *
+   * @param bundle The serialized trust store bundle.
+   * @param signature The signature over [bundle] by a pinned bundle signing key.
+   * @param lastAcceptedSequence The sequence number of the last bundle accepted, used to reject
+   * replayed bundles.
+   * @return The sequence number of the loaded bundle.
+   */
+  @Throws(AttestationException::class)
+  fun loadTrustStoreBundle(
+    bundle: List<UByte>,
+    signature: List<UByte>,
+    lastAcceptedSequence: ULong,
+  ): ULong
 }
@@ -24,6 +24,7 @@ import build.wallet.di.AppScope
 import build.wallet.di.BitkeyInject
 import build.wallet.f8e.debug.NetworkingDebugService
 import build.wallet.feature.FeatureFlagSyncWorker
+import build.wallet.firmware.AttestationTrustStoreWorker
 import build.wallet.firmware.FirmwareCoredumpEventPeriodicProcessor
 import build.wallet.firmware.FirmwareTelemetryEventPeriodicProcessor
 import build.wallet.fwup.FirmwareDataSyncWorker
@@ -95,6 +96,7 @@ class AppWorkerProviderImpl(
   private val deviceTokenAppWorker: DeviceTokenAppWorker,
   private val descriptorBackupHealthSyncWorker: DescriptorBackupHealthSyncWorker,
   private val keysetRepairWorker: KeysetRepairWorker,
+  private val attestationTrustStoreWorker: AttestationTrustStoreWorker,
 ) : AppWorkerProvider {
   override fun allWorkers(): Set<AppWorker> {
     return setOf(
@@ -136,7 +138,8 @@ class AppWorkerProviderImpl(
       pushPermissionCheckerWorker,
       deviceTokenAppWorker,
       descriptorBackupHealthSyncWorker,
-      keysetRepairWorker
+      keysetRepairWorker,
+      attestationTrustStoreWorker
     )
   }
 }
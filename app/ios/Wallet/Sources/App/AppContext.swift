@@ -1,3 +1,4 @@
+import firmware
 import Foundation
 import Shared
 import UIKit
@@ -34,8 +35,12 @@ class AppContext {
             symmetricKeyGenerator: SymmetricKeyGeneratorImpl()
         )
 
+        // Shared by hardware attestation and the NFC commands so both verify devices against the
+        // trust store bundle loaded at startup.
+        let attestation = firmware.Attestation()
+
         // Create NFC command implementations
-        let w1Commands = BitkeyW1Commands()
+        let w1Commands = BitkeyW1Commands(attestation: attestation)
         let w3Commands = BitkeyW3Commands(delegate: w1Commands)
 
         // Create IosAppComponent with iOS implementations
@@ -62,7 +67,7 @@ class AppContext {
             fileManagerProvider: { FileManagerImpl(fileDirectoryProvider: $0) },
             firmwareCommsLogBuffer: FirmwareCommsLogBufferImpl(),
             frostWalletDescriptorFactory: FrostWalletDescriptorFactoryImpl(),
-            hardwareAttestation: HardwareAttestationImpl(),
+            hardwareAttestation: HardwareAttestationImpl(attestation: attestation),
             inAppBrowserNavigator: InAppBrowserNavigatorImpl(window: window),
             lightningInvoiceParser: LightningInvoiceParserImpl(),
             logWritersProvider: { context in [DatadogLogWriter(
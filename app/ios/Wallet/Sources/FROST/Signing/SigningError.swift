@@ -19,6 +19,12 @@ extension Shared.SigningError {
             return UnableToRetrieveSighash(cause: throwable, message: message)
         case let .InvalidCounterpartyCommitments(message: message):
             return InvalidCounterpartyCommitments(cause: throwable, message: message)
+        case let .InvalidCounterpartyPartialSignatures(message: message):
+            return InvalidCounterpartyPartialSignatures(cause: throwable, message: message)
+        case let .InvalidSigningParticipants(message: message):
+            return InvalidSigningParticipants(cause: throwable, message: message)
+        case let .InvalidShareDetails(message: message):
+            return InvalidShareDetails(cause: throwable, message: message)
         case let .NonceAlreadyUsed(message: message):
             return NonceAlreadyUsed(cause: throwable, message: message)
         case let .CommitmentMismatch(message: message):
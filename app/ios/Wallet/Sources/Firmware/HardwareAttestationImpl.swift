@@ -3,10 +3,14 @@ import Foundation
 import Shared
 
 public final class HardwareAttestationImpl: Shared.HardwareAttestation {
-    public init() {}
+    private let attestation: firmware.Attestation
+
+    public init(attestation: firmware.Attestation) {
+        self.attestation = attestation
+    }
 
     public func generateChallenge() throws -> [KotlinUByte] {
-        return try firmware.Attestation().generateChallenge().map {
+        return try attestation.generateChallenge().map {
             KotlinUByte(unsignedChar: $0)
         }
     }
@@ -15,9 +19,21 @@ public final class HardwareAttestationImpl: Shared.HardwareAttestation {
         identityCert: [KotlinUByte],
         batchCert: [KotlinUByte]
     ) throws -> String {
-        return try firmware.Attestation().verifyDeviceIdentityCertChain(
+        return try attestation.verifyDeviceIdentityCertChain(
             identityCertDer: identityCert.map(\.uint8Value),
             batchCertDer: batchCert.map(\.uint8Value)
         )
     }
+
+    public func loadTrustStoreBundle(
+        bundle: [KotlinUByte],
+        signature: [KotlinUByte],
+        lastAcceptedSequence: UInt64
+    ) throws -> UInt64 {
+        return try attestation.loadTrustStoreBundle(
+            bundle: bundle.map(\.uint8Value),
+            signature: signature.map(\.uint8Value),
+            lastAcceptedSequence: lastAcceptedSequence
+        )
+    }
 }
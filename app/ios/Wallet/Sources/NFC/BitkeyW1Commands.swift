@@ -36,6 +36,12 @@ private extension Shared.HwSpendingKeyResult {
 }
 
 public final class BitkeyW1Commands: NfcCommands {
+    private let attestation: firmware.Attestation
+
+    public init(attestation: firmware.Attestation) {
+        self.attestation = attestation
+    }
+
     public func fwupStart(
         session: NfcSession,
         patchSize: KotlinUInt?,
@@ -517,7 +523,8 @@ public final class BitkeyW1Commands: NfcCommands {
         return try await .init(
             bool: SignVerifyAttestationChallenge(
                 deviceIdentityDer: deviceIdentityDer.map(\.uint8Value),
-                challenge: challenge.map(\.uint8Value)
+                challenge: challenge.map(\.uint8Value),
+                attestation: attestation
             )
             .transceive(session: session)
         )
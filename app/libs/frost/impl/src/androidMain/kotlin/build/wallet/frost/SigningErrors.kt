@@ -36,6 +36,9 @@ internal fun SigningException.toSigningError(): SigningError {
   return when (this) {
     is SigningException.CommitmentMismatch -> build.wallet.frost.SigningError.CommitmentMismatch(cause, message)
     is SigningException.InvalidCounterpartyCommitments -> build.wallet.frost.SigningError.InvalidCounterpartyCommitments(cause, message)
+    is SigningException.InvalidCounterpartyPartialSignatures -> build.wallet.frost.SigningError.InvalidCounterpartyPartialSignatures(cause, message)
+    is SigningException.InvalidSigningParticipants -> build.wallet.frost.SigningError.InvalidSigningParticipants(cause, message)
+    is SigningException.InvalidShareDetails -> build.wallet.frost.SigningError.InvalidShareDetails(cause, message)
     is SigningException.InvalidPsbt -> build.wallet.frost.SigningError.InvalidPsbt(cause, message)
     is SigningException.MissingCounterpartyNonces -> build.wallet.frost.SigningError.MissingCounterpartyNonces(cause, message)
     is SigningException.NonceAlreadyUsed -> build.wallet.frost.SigningError.NonceAlreadyUsed(cause, message)
@@ -10,6 +10,12 @@ sealed class SigningError(
 
   class InvalidCounterpartyCommitments(cause: Throwable?, message: String?) : SigningError(cause, message)
 
+  class InvalidCounterpartyPartialSignatures(cause: Throwable?, message: String?) : SigningError(cause, message)
+
+  class InvalidSigningParticipants(cause: Throwable?, message: String?) : SigningError(cause, message)
+
+  class InvalidShareDetails(cause: Throwable?, message: String?) : SigningError(cause, message)
+
   class NonceAlreadyUsed(cause: Throwable?, message: String?) : SigningError(cause, message)
 
   class CommitmentMismatch(cause: Throwable?, message: String?) : SigningError(cause, message)
@@ -1,7 +1,7 @@
 // Allow empty line warnings in UniFFI-generated scaffolding code
 #![allow(clippy::empty_line_after_doc_comments)]
 
-use action_proof::{BuildError, ValidationError};
+use action_proof::{BuildError, ParseError, ValidationError};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Action {
@@ -108,6 +108,39 @@ pub struct ContextBindingPair {
     pub value: String,
 }
 
+pub struct ActionField {
+    pub name: String,
+    pub value: String,
+}
+
+pub struct DisplayLine {
+    pub title: String,
+    pub value: String,
+}
+
+pub struct RenderedPayload {
+    pub title: String,
+    pub lines: Vec<DisplayLine>,
+    pub truncated: bool,
+}
+
+impl From<action_proof::RenderedPayload> for RenderedPayload {
+    fn from(r: action_proof::RenderedPayload) -> Self {
+        RenderedPayload {
+            title: r.title,
+            lines: r
+                .lines
+                .into_iter()
+                .map(|l| DisplayLine {
+                    title: l.title,
+                    value: l.value,
+                })
+                .collect(),
+            truncated: r.truncated,
+        }
+    }
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum ActionProofError {
     #[error("value exceeds maximum length")]
@@ -132,6 +165,12 @@ pub enum ActionProofError {
     DuplicateBindingKey,
     #[error("bindings not sorted")]
     BindingsNotSorted,
+    #[error("fields do not match action schema")]
+    InvalidFields,
+    #[error("unsupported payload version")]
+    UnsupportedVersion,
+    #[error("invalid payload")]
+    InvalidPayload,
 }
 
 impl From<ValidationError> for ActionProofError {
@@ -156,10 +195,18 @@ impl From<BuildError> for ActionProofError {
             BuildError::EmptyBindingKey => ActionProofError::EmptyBindingKey,
             BuildError::DuplicateBindingKey(_) => ActionProofError::DuplicateBindingKey,
             BuildError::InvalidValue(v) => v.into(),
+            BuildError::InvalidFields(_) => ActionProofError::InvalidFields,
+            BuildError::UnsupportedVersion(_) => ActionProofError::UnsupportedVersion,
         }
     }
 }
 
+impl From<ParseError> for ActionProofError {
+    fn from(_: ParseError) -> Self {
+        ActionProofError::InvalidPayload
+    }
+}
+
 pub fn compute_token_binding(jwt: String) -> String {
     action_proof::compute_token_binding(&jwt)
 }
@@ -168,23 +215,50 @@ pub fn validate_value(value: String) -> Result<(), ActionProofError> {
     action_proof::validate_value(&value).map_err(ActionProofError::from)
 }
 
+fn sorted_binding_tuples(bindings: &mut [ContextBindingPair]) -> Vec<(&str, &str)> {
+    bindings.sort_by(|a, b| a.key.cmp(&b.key));
+
+    bindings
+        .iter()
+        .map(|b| (b.key.as_str(), b.value.as_str()))
+        .collect()
+}
+
 pub fn build_payload(
     action: Action,
     value: Option<String>,
     bindings: Vec<ContextBindingPair>,
 ) -> Result<Vec<u8>, ActionProofError> {
     let mut bindings = bindings;
-    bindings.sort_by(|a, b| a.key.cmp(&b.key));
+    let binding_tuples = sorted_binding_tuples(&mut bindings);
+
+    action_proof::build_payload(action.into(), value.as_deref(), &binding_tuples)
+        .map_err(ActionProofError::from)
+}
 
-    let binding_tuples: Vec<(&str, &str)> = bindings
+pub fn build_payload_v2(
+    action: Action,
+    fields: Vec<ActionField>,
+    bindings: Vec<ContextBindingPair>,
+) -> Result<Vec<u8>, ActionProofError> {
+    let mut bindings = bindings;
+    let binding_tuples = sorted_binding_tuples(&mut bindings);
+
+    // Field order is significant (schema order), so fields are never re-sorted.
+    let field_tuples: Vec<(&str, &str)> = fields
         .iter()
-        .map(|b| (b.key.as_str(), b.value.as_str()))
+        .map(|f| (f.name.as_str(), f.value.as_str()))
         .collect();
 
-    action_proof::build_payload(action.into(), value.as_deref(), &binding_tuples)
+    action_proof::build_payload_v2(action.into(), &field_tuples, &binding_tuples)
         .map_err(ActionProofError::from)
 }
 
+pub fn render_payload(payload: Vec<u8>) -> Result<RenderedPayload, ActionProofError> {
+    let parsed = action_proof::parse_payload(&payload)?;
+    Ok(action_proof::render_payload(&parsed, &action_proof::W3_RENDER_POLICY).into())
+}
+
 uniffi::include_scaffolding!("action-proof");
 
 #[cfg(test)]
@@ -214,6 +288,50 @@ mod tests {
         assert!(!payload.is_empty());
     }
 
+    #[test]
+    fn build_payload_v2_keeps_field_order() {
+        let fields = vec![
+            ActionField {
+                name: "amount".to_string(),
+                value: "50000".to_string(),
+            },
+            ActionField {
+                name: "currency".to_string(),
+                value: "USD".to_string(),
+            },
+        ];
+        let payload = build_payload_v2(Action::SetSpendWithoutHardware, fields, vec![]).unwrap();
+        let parsed = action_proof::parse_payload(&payload).unwrap();
+        assert_eq!(
+            parsed.fields,
+            vec![("amount", "50000"), ("currency", "USD")]
+        );
+
+        assert!(matches!(
+            build_payload_v2(Action::SetSpendWithoutHardware, vec![], vec![]),
+            Err(ActionProofError::InvalidFields)
+        ));
+    }
+
+    #[test]
+    fn render_payload_matches_core() {
+        let payload = build_payload(
+            Action::SetRecoveryEmail,
+            Some("alice@example.com".to_string()),
+            vec![],
+        )
+        .unwrap();
+        let rendered = render_payload(payload).unwrap();
+        assert_eq!(rendered.title, "Set Recovery Email");
+        assert_eq!(rendered.lines.len(), 1);
+        assert_eq!(rendered.lines[0].value, "alice@example.com");
+
+        assert!(matches!(
+            render_payload(b"garbage".to_vec()),
+            Err(ActionProofError::InvalidPayload)
+        ));
+    }
+
     // Enum completeness tests: ensure FFI enums stay in sync with core
     #[test]
     fn all_actions_mapped() {
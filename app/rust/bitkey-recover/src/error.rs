@@ -0,0 +1,31 @@
+use bdk_wallet::error::CreateTxError;
+use bitcoin::psbt::ExtractTxError;
+use wca::pcsc::TransactorError;
+
+#[derive(Debug, thiserror::Error)]
+pub enum RecoveryError {
+    #[error("invalid descriptor: {0}")]
+    InvalidDescriptor(String),
+    #[error("not a Bitkey 2-of-3 descriptor: {0}")]
+    UnsupportedDescriptor(String),
+    #[error("no keyset at index {0}")]
+    UnknownKeyset(usize),
+    #[error("unable to create wallet: {0}")]
+    Wallet(String),
+    #[error("no funds to sweep")]
+    NothingToSweep,
+    #[error("unable to build sweep")]
+    CreateTx(#[from] CreateTxError),
+    #[error("the App key must be an extended private key")]
+    UnsupportedAppKey,
+    #[error("unable to sign: {0}")]
+    Signing(String),
+    #[error("no inputs belong to this key")]
+    NoMatchingInputs,
+    #[error("hardware error")]
+    Hardware(#[from] TransactorError),
+    #[error("unable to finalize: {0}")]
+    Finalize(String),
+    #[error("unable to extract transaction")]
+    ExtractTx(#[from] ExtractTxError),
+}
@@ -0,0 +1,98 @@
+use std::str::FromStr;
+
+use bitcoin::{bip32::ChildNumber, Network};
+use miniscript::{
+    descriptor::{Descriptor, Wildcard},
+    DescriptorPublicKey, ForEachKey,
+};
+use serde::Deserialize;
+use wca::signing::ExtendDerivationPath;
+
+use crate::RecoveryError;
+
+const RECEIVING_PATH: [ChildNumber; 1] = [ChildNumber::Normal { index: 0 }];
+const CHANGE_PATH: [ChildNumber; 1] = [ChildNumber::Normal { index: 1 }];
+
+/// An account's descriptors, as exported by `GET /api/accounts/:account_id/descriptors`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct DescriptorExport {
+    pub active_descriptor: String,
+    #[serde(default)]
+    pub inactive_descriptors: Vec<String>,
+}
+
+impl DescriptorExport {
+    /// The active keyset first, then the inactive ones in export order.
+    pub fn keysets(&self, network: Network) -> Result<Vec<DescriptorKeyset>, RecoveryError> {
+        std::iter::once(&self.active_descriptor)
+            .chain(&self.inactive_descriptors)
+            .map(|descriptor| DescriptorKeyset::from_descriptor(network, descriptor))
+            .collect()
+    }
+}
+
+/// The App, hardware and server account keys of one keyset, in descriptor order.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DescriptorKeyset {
+    network: Network,
+    keys: Vec<DescriptorPublicKey>,
+}
+
+impl DescriptorKeyset {
+    /// Parses a `wsh(sortedmulti(2,...))` descriptor over three account-level keys ending in
+    /// `/*`, which is the only kind the server exports.
+    pub fn from_descriptor(network: Network, descriptor: &str) -> Result<Self, RecoveryError> {
+        let descriptor = Descriptor::<DescriptorPublicKey>::from_str(descriptor)
+            .map_err(|e| RecoveryError::InvalidDescriptor(e.to_string()))?;
+
+        let mut keys = vec![];
+        descriptor.for_each_key(|key| {
+            keys.push(key.clone());
+            true
+        });
+        let supported_keys = keys.len() == 3
+            && keys.iter().all(|key| {
+                matches!(key, DescriptorPublicKey::XPub(xpub)
+                    if xpub.wildcard == Wildcard::Unhardened && xpub.origin.is_some())
+            });
+        if !supported_keys {
+            return Err(RecoveryError::UnsupportedDescriptor(descriptor.to_string()));
+        }
+
+        let keyset = Self { network, keys };
+        if keyset.clone().into_multisig_descriptor()? != descriptor {
+            return Err(RecoveryError::UnsupportedDescriptor(descriptor.to_string()));
+        }
+        Ok(keyset)
+    }
+
+    pub fn network(&self) -> Network {
+        self.network
+    }
+
+    pub fn receiving(&self) -> DescriptorKeyset {
+        self.derive(&RECEIVING_PATH)
+    }
+
+    pub fn change(&self) -> DescriptorKeyset {
+        self.derive(&CHANGE_PATH)
+    }
+
+    fn derive(&self, path: &[ChildNumber]) -> DescriptorKeyset {
+        DescriptorKeyset {
+            network: self.network,
+            keys: self
+                .keys
+                .iter()
+                .map(|key| key.extend_derivation_path(path))
+                .collect(),
+        }
+    }
+
+    pub fn into_multisig_descriptor(
+        self,
+    ) -> Result<Descriptor<DescriptorPublicKey>, RecoveryError> {
+        Descriptor::<DescriptorPublicKey>::new_wsh_sortedmulti(2, self.keys)
+            .map_err(|e| RecoveryError::InvalidDescriptor(e.to_string()))
+    }
+}
@@ -0,0 +1,16 @@
+//! Sweep a Bitkey 2-of-3 wallet with the App key and the hardware, without the server.
+//!
+//! Everything here works offline: the wallet is rebuilt from the account's exported descriptors,
+//! funding transactions are looked up separately (by any block explorer or node, using
+//! [`RecoveryWallet::addresses`]) and handed in, and the finalized sweep is handed back out to be
+//! broadcast.
+
+mod error;
+mod keyset;
+mod signing;
+mod sweep;
+
+pub use error::RecoveryError;
+pub use keyset::{DescriptorExport, DescriptorKeyset};
+pub use signing::{hardware_fingerprint, sign_with_hardware, AppKeySigner};
+pub use sweep::{finalize, RecoveryWallet};
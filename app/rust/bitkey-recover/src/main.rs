@@ -0,0 +1,155 @@
+use std::{fs, path::PathBuf, str::FromStr};
+
+use anyhow::{Context, Result};
+use bitcoin::{
+    bip32::Fingerprint, consensus::encode, psbt::Psbt, Address, FeeRate, Network, Transaction,
+};
+use bitkey_recover::{
+    finalize, hardware_fingerprint, sign_with_hardware, AppKeySigner, DescriptorExport,
+    RecoveryError, RecoveryWallet,
+};
+use clap::{Parser, Subcommand};
+use miniscript::descriptor::DescriptorSecretKey;
+use wca::pcsc::PCSCTransactor;
+
+/// Sweep a Bitkey wallet using the App key and the hardware, without the Bitkey server.
+#[derive(Parser)]
+#[command(version)]
+struct Cli {
+    #[arg(long, default_value_t = Network::Bitcoin)]
+    network: Network,
+
+    #[command(subcommand)]
+    command: Commands,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Print the wallet's addresses, to look up funding transactions for
+    Addresses {
+        #[command(flatten)]
+        wallet: WalletArgs,
+        #[arg(long, default_value_t = 20)]
+        count: u32,
+    },
+    /// Build an unsigned PSBT sweeping the wallet to a destination address
+    Build {
+        #[command(flatten)]
+        wallet: WalletArgs,
+        /// Raw hex of a transaction paying to the wallet; repeat for each one
+        #[arg(long = "funding-tx", required = true)]
+        funding_txs: Vec<String>,
+        #[arg(long)]
+        destination: Address<bitcoin::address::NetworkUnchecked>,
+        /// Fee rate in sat/vB
+        #[arg(long)]
+        fee_rate: u64,
+    },
+    /// Sign a PSBT with the App key
+    SignApp {
+        psbt: PathBuf,
+        /// The App's extended private key, with its origin
+        #[arg(long)]
+        app_key: String,
+    },
+    /// Sign a PSBT with the hardware over a PC/SC reader
+    SignHardware {
+        psbt: PathBuf,
+        /// The hardware's master fingerprint; read from the hardware if not given
+        #[arg(long)]
+        hw_fingerprint: Option<Fingerprint>,
+    },
+    /// Finalize a fully signed PSBT and print the raw transaction to broadcast
+    Finalize { psbt: PathBuf },
+}
+
+#[derive(clap::Args)]
+struct WalletArgs {
+    /// The account's exported descriptors, as JSON
+    #[arg(long)]
+    descriptors: PathBuf,
+    /// Which keyset to use: 0 is the active one, then the inactive ones in export order
+    #[arg(long, default_value_t = 0)]
+    keyset: usize,
+    #[arg(long, default_value_t = 100)]
+    lookahead: u32,
+}
+
+impl WalletArgs {
+    fn wallet(&self, network: Network) -> Result<RecoveryWallet> {
+        let export: DescriptorExport = serde_json::from_slice(
+            &fs::read(&self.descriptors).context("unable to read descriptors")?,
+        )
+        .context("unable to parse descriptors")?;
+        let keyset = export
+            .keysets(network)?
+            .into_iter()
+            .nth(self.keyset)
+            .ok_or(RecoveryError::UnknownKeyset(self.keyset))?;
+        Ok(RecoveryWallet::new(&keyset, self.lookahead)?)
+    }
+}
+
+fn read_psbt(path: &PathBuf) -> Result<Psbt> {
+    let psbt = fs::read_to_string(path).context("unable to read PSBT")?;
+    Psbt::from_str(psbt.trim()).context("unable to parse PSBT")
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+
+    match cli.command {
+        Commands::Addresses { wallet, count } => {
+            for (keychain, index, address) in wallet.wallet(cli.network)?.addresses(count) {
+                println!("{:?}\t{}\t{}", keychain, index, address);
+            }
+        }
+        Commands::Build {
+            wallet,
+            funding_txs,
+            destination,
+            fee_rate,
+        } => {
+            let mut wallet = wallet.wallet(cli.network)?;
+            let funding_txs = funding_txs
+                .iter()
+                .map(|tx| encode::deserialize_hex::<Transaction>(tx))
+                .collect::<Result<Vec<_>, _>>()
+                .context("unable to parse funding transaction")?;
+            let balance = wallet.add_funding_transactions(funding_txs);
+            eprintln!("Sweeping {}", balance);
+
+            let destination = destination
+                .require_network(cli.network)
+                .context("destination is for a different network")?;
+            let fee_rate = FeeRate::from_sat_per_vb(fee_rate).context("fee rate is too high")?;
+            println!("{}", wallet.build_sweep(&destination, fee_rate)?);
+        }
+        Commands::SignApp { psbt, app_key } => {
+            let mut psbt = read_psbt(&psbt)?;
+            let app_key =
+                DescriptorSecretKey::from_str(&app_key).context("unable to parse App key")?;
+            let signed = AppKeySigner::new(app_key)?.sign(&mut psbt)?;
+            eprintln!("Signed {} inputs", signed);
+            println!("{}", psbt);
+        }
+        Commands::SignHardware {
+            psbt,
+            hw_fingerprint,
+        } => {
+            let psbt = read_psbt(&psbt)?;
+            let transactor = PCSCTransactor::new()?;
+            let hw_fingerprint = match hw_fingerprint {
+                Some(fingerprint) => fingerprint,
+                None => hardware_fingerprint(&transactor, cli.network)?,
+            };
+            println!("{}", sign_with_hardware(&transactor, psbt, hw_fingerprint)?);
+        }
+        Commands::Finalize { psbt } => {
+            let tx = finalize(read_psbt(&psbt)?)?;
+            println!("{}", encode::serialize_hex(&tx));
+        }
+    }
+
+    Ok(())
+}
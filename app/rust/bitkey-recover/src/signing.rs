@@ -0,0 +1,94 @@
+use std::str::FromStr;
+
+use bitcoin::{
+    bip32::{DerivationPath, Fingerprint, Xpriv},
+    psbt::{GetKey, KeyRequest, Psbt},
+    secp256k1::{Secp256k1, Signing},
+    Network, PrivateKey,
+};
+use miniscript::{
+    descriptor::{DescriptorSecretKey, DescriptorXKey},
+    DescriptorPublicKey,
+};
+use wca::{
+    commands::{GetInitialSpendingKey, SignTransaction},
+    pcsc::{Performer, Transactor},
+};
+
+use crate::RecoveryError;
+
+/// Signs with the App's extended private key, e.g. `[fingerprint/84'/0'/0']xprv.../*` or the
+/// App's root xprv.
+pub struct AppKeySigner {
+    key: DescriptorXKey<Xpriv>,
+}
+
+impl AppKeySigner {
+    pub fn new(key: DescriptorSecretKey) -> Result<Self, RecoveryError> {
+        match key {
+            DescriptorSecretKey::XPrv(key) => Ok(Self { key }),
+            _ => Err(RecoveryError::UnsupportedAppKey),
+        }
+    }
+
+    /// Adds the App's signature to every input it can sign, and returns how many it signed.
+    pub fn sign(&self, psbt: &mut Psbt) -> Result<usize, RecoveryError> {
+        let signature_count =
+            |psbt: &Psbt| -> usize { psbt.inputs.iter().map(|i| i.partial_sigs.len()).sum() };
+
+        let before = signature_count(psbt);
+        psbt.sign(self, &Secp256k1::new())
+            .map_err(|(_, errors)| RecoveryError::Signing(format!("{:?}", errors)))?;
+        match signature_count(psbt) - before {
+            0 => Err(RecoveryError::NoMatchingInputs),
+            signed => Ok(signed),
+        }
+    }
+}
+
+impl GetKey for AppKeySigner {
+    type Error = bitcoin::bip32::Error;
+
+    fn get_key<C: Signing>(
+        &self,
+        key_request: KeyRequest,
+        secp: &Secp256k1<C>,
+    ) -> Result<Option<PrivateKey>, Self::Error> {
+        let KeyRequest::Bip32((fingerprint, path)) = key_request else {
+            return Ok(None);
+        };
+        let (origin_fingerprint, origin_path) = match &self.key.origin {
+            Some((fingerprint, path)) => (*fingerprint, path.clone()),
+            None => (self.key.xkey.fingerprint(secp), DerivationPath::master()),
+        };
+        if fingerprint != origin_fingerprint || !path.as_ref().starts_with(origin_path.as_ref()) {
+            return Ok(None);
+        }
+
+        let path_from_key = &path[origin_path.len()..];
+        Ok(Some(
+            self.key.xkey.derive_priv(secp, &path_from_key)?.to_priv(),
+        ))
+    }
+}
+
+/// Has the hardware sign every input it holds a key for. The hardware asks for a fingerprint
+/// to be confirmed before signing.
+pub fn sign_with_hardware<T: Transactor + ?Sized>(
+    transactor: &T,
+    psbt: Psbt,
+    hw_fingerprint: Fingerprint,
+) -> Result<Psbt, RecoveryError> {
+    Ok(transactor.perform(SignTransaction::new(psbt, hw_fingerprint, false, None))?)
+}
+
+/// The master fingerprint of the hardware's keys, for when it isn't known from the descriptor.
+pub fn hardware_fingerprint<T: Transactor + ?Sized>(
+    transactor: &T,
+    network: Network,
+) -> Result<Fingerprint, RecoveryError> {
+    let key = transactor.perform(GetInitialSpendingKey::new(network.into()))?;
+    let dpub = DescriptorPublicKey::from_str(&key.dpub)
+        .map_err(|e| RecoveryError::InvalidDescriptor(e.to_string()))?;
+    Ok(dpub.master_fingerprint())
+}
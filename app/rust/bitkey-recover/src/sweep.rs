@@ -0,0 +1,98 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bdk_wallet::{KeychainKind, Wallet};
+use bitcoin::{psbt::Psbt, secp256k1::Secp256k1, Address, Amount, FeeRate, Transaction};
+use miniscript::psbt::PsbtExt;
+
+use crate::{DescriptorKeyset, RecoveryError};
+
+/// A wallet over one keyset, fed with funding transactions rather than synced from the network.
+pub struct RecoveryWallet {
+    wallet: Wallet,
+}
+
+impl RecoveryWallet {
+    /// `lookahead` is how many addresses past the last one in use funding transactions are
+    /// matched against, on both the receive and change keychains.
+    pub fn new(keyset: &DescriptorKeyset, lookahead: u32) -> Result<Self, RecoveryError> {
+        let wallet = Wallet::create(
+            keyset.receiving().into_multisig_descriptor()?,
+            keyset.change().into_multisig_descriptor()?,
+        )
+        .network(keyset.network())
+        .lookahead(lookahead)
+        .create_wallet_no_persist()
+        .map_err(|e| RecoveryError::Wallet(e.to_string()))?;
+
+        Ok(Self { wallet })
+    }
+
+    /// The first `count` receive and change addresses, to look up funding transactions for.
+    pub fn addresses(&self, count: u32) -> Vec<(KeychainKind, u32, Address)> {
+        [KeychainKind::External, KeychainKind::Internal]
+            .into_iter()
+            .flat_map(|keychain| {
+                (0..count).map(move |index| {
+                    let address = self.wallet.peek_address(keychain, index).address;
+                    (keychain, index, address)
+                })
+            })
+            .collect()
+    }
+
+    /// Adds transactions paying to the wallet, and returns the resulting balance. Transactions
+    /// that don't pay to the wallet are ignored.
+    pub fn add_funding_transactions(&mut self, transactions: Vec<Transaction>) -> Amount {
+        // Funding transactions are treated as unconfirmed and last seen now, which is all the
+        // wallet needs to spend them.
+        let last_seen = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        self.wallet
+            .apply_unconfirmed_txs(transactions.into_iter().map(|tx| (tx, last_seen)));
+        self.wallet.balance().total()
+    }
+
+    /// Builds an unsigned PSBT spending every UTXO of the wallet to `destination`.
+    pub fn build_sweep(
+        &mut self,
+        destination: &Address,
+        fee_rate: FeeRate,
+    ) -> Result<Psbt, RecoveryError> {
+        if self.wallet.balance().total() == Amount::ZERO {
+            return Err(RecoveryError::NothingToSweep);
+        }
+
+        let mut builder = self.wallet.build_tx();
+        builder
+            .drain_wallet()
+            .drain_to(destination.script_pubkey())
+            .fee_rate(fee_rate);
+        Ok(builder.finish()?)
+    }
+}
+
+/// Finalizes a PSBT signed by both the App and the hardware, and extracts the transaction to
+/// broadcast.
+pub fn finalize(mut psbt: Psbt) -> Result<Transaction, RecoveryError> {
+    // The hardware finalizes the PSBT itself when it adds the last signature.
+    let finalized = psbt
+        .inputs
+        .iter()
+        .all(|input| input.final_script_sig.is_some() || input.final_script_witness.is_some());
+    if !finalized {
+        psbt.finalize_mut(&Secp256k1::verification_only())
+            .map_err(|errors| {
+                RecoveryError::Finalize(
+                    errors
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect::<Vec<_>>()
+                        .join("; "),
+                )
+            })?;
+    }
+
+    Ok(psbt.extract_tx()?)
+}
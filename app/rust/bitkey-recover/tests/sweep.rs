@@ -0,0 +1,248 @@
+use std::str::FromStr;
+
+use bitcoin::{
+    absolute::LockTime,
+    bip32::{DerivationPath, Fingerprint, Xpriv, Xpub},
+    hashes::Hash,
+    secp256k1::{Message, Secp256k1},
+    transaction::Version,
+    Address, Amount, FeeRate, Network, OutPoint, ScriptBuf, Sequence, Transaction, TxIn, TxOut,
+    Txid, Witness,
+};
+use bitkey_recover::{
+    finalize, sign_with_hardware, AppKeySigner, DescriptorExport, RecoveryError, RecoveryWallet,
+};
+use miniscript::descriptor::DescriptorSecretKey;
+use prost::Message as _;
+use wca::{
+    fwpb::{self, derive_and_sign_rsp::DeriveAndSignRspStatus, wallet_cmd::Msg},
+    pcsc::Transactor,
+};
+
+const NETWORK: Network = Network::Regtest;
+const ACCOUNT_PATH: &str = "m/84'/1'/0'";
+
+/// Stands in for the hardware: answers derive-and-sign commands with its root key.
+struct SimulatedHardware {
+    root: Xpriv,
+}
+
+impl Transactor for SimulatedHardware {
+    fn transmit(&self, buffer: &[u8]) -> Result<Vec<u8>, pcsc::Error> {
+        let data = match buffer[4] {
+            0 => &buffer[7..],
+            _ => &buffer[5..],
+        };
+        let Some(Msg::DeriveKeyDescriptorAndSignCmd(cmd)) =
+            fwpb::WalletCmd::decode(data).expect("wallet command").msg
+        else {
+            panic!("unexpected command");
+        };
+
+        let secp = Secp256k1::new();
+        let path: DerivationPath = cmd
+            .derivation_path
+            .expect("derivation path")
+            .child
+            .into_iter()
+            .map(Into::into)
+            .collect::<Vec<_>>()
+            .into();
+        let key = self.root.derive_priv(&secp, &path).unwrap();
+        let message = Message::from_digest(cmd.hash.try_into().expect("32 byte hash"));
+        let signature = secp.sign_ecdsa(&message, &key.private_key);
+
+        let mut response = fwpb::WalletRsp {
+            msg: Some(fwpb::wallet_rsp::Msg::DeriveAndSignRsp(
+                fwpb::DeriveAndSignRsp {
+                    status: DeriveAndSignRspStatus::Success.into(),
+                    signature: signature.serialize_compact().to_vec(),
+                },
+            )),
+            status: fwpb::Status::Success.into(),
+            ..Default::default()
+        }
+        .encode_to_vec();
+        response.extend([0x90, 0x00]);
+        Ok(response)
+    }
+
+    fn reset(&mut self) -> Result<(), pcsc::Error> {
+        Ok(())
+    }
+}
+
+fn root_key(seed: u8) -> Xpriv {
+    Xpriv::new_master(NETWORK, &[seed; 32]).unwrap()
+}
+
+fn account_dpub(root: &Xpriv) -> String {
+    let secp = Secp256k1::new();
+    let path = DerivationPath::from_str(ACCOUNT_PATH).unwrap();
+    let xpub = Xpub::from_priv(&secp, &root.derive_priv(&secp, &path).unwrap());
+    format!(
+        "[{}/{}]{}/*",
+        root.fingerprint(&secp),
+        &ACCOUNT_PATH[2..],
+        xpub
+    )
+}
+
+// Each funding transaction spends a distinct made-up outpoint so they don't conflict.
+fn funding_transaction(address: &Address, amount: Amount, vout: u32) -> Transaction {
+    Transaction {
+        version: Version::TWO,
+        lock_time: LockTime::ZERO,
+        input: vec![TxIn {
+            previous_output: OutPoint::new(Txid::all_zeros(), vout),
+            script_sig: ScriptBuf::new(),
+            sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+            witness: Witness::new(),
+        }],
+        output: vec![TxOut {
+            value: amount,
+            script_pubkey: address.script_pubkey(),
+        }],
+    }
+}
+
+#[test]
+fn test_sweep_with_app_and_hardware() {
+    let secp = Secp256k1::new();
+    let app_root = root_key(1);
+    let hw_root = root_key(2);
+    let server_root = root_key(3);
+
+    let export: DescriptorExport = serde_json::from_value(serde_json::json!({
+        "active_descriptor": format!(
+            "wsh(sortedmulti(2,{},{},{}))",
+            account_dpub(&app_root),
+            account_dpub(&hw_root),
+            account_dpub(&server_root),
+        ),
+    }))
+    .unwrap();
+    let keysets = export.keysets(NETWORK).unwrap();
+    assert_eq!(keysets.len(), 1);
+
+    let mut wallet = RecoveryWallet::new(&keysets[0], 20).unwrap();
+    let addresses = wallet.addresses(5);
+    assert_eq!(addresses.len(), 10);
+
+    // Fund a receive address and a change address, as if both were looked up on chain.
+    let funding = [
+        funding_transaction(&addresses[0].2, Amount::from_sat(60_000), 0),
+        funding_transaction(&addresses[7].2, Amount::from_sat(40_000), 1),
+    ];
+    let balance = wallet.add_funding_transactions(funding.to_vec());
+    assert_eq!(balance, Amount::from_sat(100_000));
+
+    let destination = Address::p2wpkh(
+        &bitcoin::CompressedPublicKey(root_key(4).to_priv().public_key(&secp).inner),
+        NETWORK,
+    );
+    let mut psbt = wallet
+        .build_sweep(&destination, FeeRate::from_sat_per_vb(2).unwrap())
+        .unwrap();
+    assert_eq!(psbt.inputs.len(), 2);
+    assert_eq!(psbt.unsigned_tx.output.len(), 1);
+
+    let app_key = DescriptorSecretKey::from_str(&format!(
+        "[{}/{}]{}/*",
+        app_root.fingerprint(&secp),
+        &ACCOUNT_PATH[2..],
+        app_root
+            .derive_priv(&secp, &DerivationPath::from_str(ACCOUNT_PATH).unwrap())
+            .unwrap()
+    ))
+    .unwrap();
+    assert_eq!(
+        AppKeySigner::new(app_key).unwrap().sign(&mut psbt).unwrap(),
+        2
+    );
+
+    // One signature isn't enough.
+    assert!(matches!(
+        finalize(psbt.clone()),
+        Err(RecoveryError::Finalize(_))
+    ));
+
+    let hardware = SimulatedHardware { root: hw_root };
+    let psbt = sign_with_hardware(&hardware, psbt, hw_root.fingerprint(&secp)).unwrap();
+
+    let tx = finalize(psbt).unwrap();
+    assert_eq!(tx.input.len(), 2);
+    assert!(tx.input.iter().all(|input| input.witness.len() == 4));
+    assert_eq!(tx.output.len(), 1);
+    assert_eq!(tx.output[0].script_pubkey, destination.script_pubkey());
+    assert!(tx.output[0].value < balance);
+}
+
+#[test]
+fn test_app_key_from_another_wallet_signs_nothing() {
+    let export = DescriptorExport {
+        active_descriptor: format!(
+            "wsh(sortedmulti(2,{},{},{}))",
+            account_dpub(&root_key(1)),
+            account_dpub(&root_key(2)),
+            account_dpub(&root_key(3)),
+        ),
+        inactive_descriptors: vec![],
+    };
+    let keyset = &export.keysets(NETWORK).unwrap()[0];
+    let mut wallet = RecoveryWallet::new(keyset, 20).unwrap();
+    let address = wallet.addresses(1).remove(0).2;
+    wallet.add_funding_transactions(vec![funding_transaction(
+        &address,
+        Amount::from_sat(50_000),
+        0,
+    )]);
+    let mut psbt = wallet
+        .build_sweep(&address, FeeRate::from_sat_per_vb(1).unwrap())
+        .unwrap();
+
+    let other_app_key = DescriptorSecretKey::XPrv(miniscript::descriptor::DescriptorXKey {
+        origin: None,
+        xkey: root_key(9),
+        derivation_path: DerivationPath::master(),
+        wildcard: miniscript::descriptor::Wildcard::None,
+    });
+    assert!(matches!(
+        AppKeySigner::new(other_app_key).unwrap().sign(&mut psbt),
+        Err(RecoveryError::NoMatchingInputs)
+    ));
+    assert!(matches!(
+        sign_with_hardware(
+            &SimulatedHardware { root: root_key(9) },
+            psbt,
+            Fingerprint::from([0; 4])
+        ),
+        Err(RecoveryError::Hardware(_))
+    ));
+}
+
+#[test]
+fn test_rejects_non_bitkey_descriptors() {
+    let single_key = DescriptorExport {
+        active_descriptor: format!("wpkh({})", account_dpub(&root_key(1))),
+        inactive_descriptors: vec![],
+    };
+    assert!(matches!(
+        single_key.keysets(NETWORK),
+        Err(RecoveryError::UnsupportedDescriptor(_))
+    ));
+
+    let unsorted = DescriptorExport {
+        active_descriptor: format!(
+            "wsh(multi(2,{},{},{}))",
+            account_dpub(&root_key(1)),
+            account_dpub(&root_key(2)),
+            account_dpub(&root_key(3)),
+        ),
+        inactive_descriptors: vec![],
+    };
+    assert!(matches!(
+        unsorted.keysets(NETWORK),
+        Err(RecoveryError::UnsupportedDescriptor(_))
+    ));
+}
@@ -64,6 +64,46 @@ pub fn sweep_psbt_with_tweaks(
         .map(|p| p.into_psbt())
 }
 
+/// The public keys of a keyset being swept from.
+pub struct SweepSourceKeyset {
+    pub app_account_dpub: DescriptorPublicKey,
+    pub server_root_xpub: ExtendedPubKey,
+    pub hw_dpub: DescriptorPublicKey,
+}
+
+/// Like [`sweep_psbt_with_tweaks`], for a PSBT whose inputs are spread across several source
+/// keysets. Each input is matched to its keyset by master fingerprint and derivation.
+pub fn multi_keyset_sweep_psbt_with_tweaks(
+    psbt: Psbt,
+    source_keysets: Vec<SweepSourceKeyset>,
+    target_app_account_dprv: DescriptorSecretKey,
+    target_server_root_xpub: ExtendedPubKey,
+    target_hw_dpub: DescriptorPublicKey,
+) -> Result<Psbt, ChaincodeDelegationError> {
+    let source_keysets: Vec<Keyset> = source_keysets
+        .into_iter()
+        .map(|keyset| {
+            KeysetComponents::from_dpub(
+                keyset.app_account_dpub,
+                keyset.server_root_xpub,
+                keyset.hw_dpub,
+            )
+            .into()
+        })
+        .collect();
+    let target_keyset = KeysetComponents::from_dprv(
+        &Secp256k1::new(),
+        target_app_account_dprv,
+        target_server_root_xpub,
+        target_hw_dpub,
+    )?;
+
+    UntweakedPsbt::new(psbt)
+        .with_source_wallets_tweaks(&source_keysets)
+        .and_then(|p| p.with_sweep_prepared_tweaks(&target_keyset.into()))
+        .map(|p| p.into_psbt())
+}
+
 pub fn migration_sweep_psbt_with_tweaks(
     psbt: Psbt,
     target_app_account_dprv: DescriptorSecretKey,
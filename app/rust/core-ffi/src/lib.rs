@@ -8,19 +8,19 @@ use bitcoin::{
     Network,
 };
 use chaincode_delegation::{
-    migration_sweep_psbt_with_tweaks, psbt_with_tweaks, server_account_dpub, server_root_xpub,
-    sweep_psbt_with_tweaks,
+    migration_sweep_psbt_with_tweaks, multi_keyset_sweep_psbt_with_tweaks, psbt_with_tweaks,
+    server_account_dpub, server_root_xpub, sweep_psbt_with_tweaks, SweepSourceKeyset,
 };
 use crypto::chacha20poly1305::{ChaCha20Poly1305Error, XChaCha20Poly1305};
 use crypto::chaincode_delegation::ChaincodeDelegationError;
 use crypto::crypto_box::{CryptoBox, CryptoBoxError, CryptoBoxKeyPair, CryptoBoxKeyPairError};
 use crypto::ecdh::Secp256k1SharedSecret;
-use crypto::frost::{signing::SigningError, FrostShare};
+use crypto::frost::{signing::SigningError, FrostShare, ParticipantIndex};
 use crypto::hkdf::{Hkdf, HkdfError};
 use crypto::keys::{extract_public_key, PublicKey, PublicKeyError, SecretKey, SecretKeyError};
 use crypto::noise::{
     DhError, HardwareBackedDh, HardwareBackedKeyPair, NoiseContext, NoiseRole, NoiseWrapperError,
-    PrivateKey,
+    PrivateKey, RekeyPolicy,
 };
 use crypto::p256_box::{P256Box, P256BoxError, P256BoxKeyPair, P256BoxKeyPairError};
 use crypto::signature_utils::{
@@ -29,6 +29,7 @@ use crypto::signature_utils::{
 };
 use crypto::signature_verifier::{SignatureVerifier, SignatureVerifierError};
 use crypto::spake2::{Spake2Context, Spake2Error, Spake2Keys, Spake2Role};
+use crypto::streaming_aead::{StreamDecryptor, StreamEncryptor, StreamingAeadError};
 use frost::{
     compute_frost_wallet_descriptor, FrostSigner, KeyCommitments, KeygenError, ShareDetails,
     ShareGenerator, SharePackage, WalletDescriptor,
@@ -36,7 +37,10 @@ use frost::{
 use lightning_support::invoice::{Invoice, InvoiceError, Sha256};
 use miniscript::{descriptor::DescriptorSecretKey, DescriptorPublicKey};
 use std::str::FromStr;
-use wsm_integrity::{WsmContext, WsmIntegrityVerifier, WsmIntegrityVerifierError};
+use wsm_integrity::{
+    IntegrityKey, KeyRotationStatement, KeySelector, WsmContext, WsmIntegrityVerifier,
+    WsmIntegrityVerifierError,
+};
 
 pub fn extract_xpub_chaincode(xpub: &str) -> Result<Vec<u8>, XpubChaincodeError> {
     let extended_pubkey =
@@ -3,7 +3,7 @@ use std::{fmt::Display, str::FromStr};
 use crate::UniffiCustomTypeConverter;
 use bitcoin::bip32::{Fingerprint, Xpriv as ExtendedPrivKey, Xpub as ExtendedPubKey};
 use bitcoin::{psbt::Psbt, secp256k1::ecdsa::Signature, Network};
-use crypto::frost::FrostShare;
+use crypto::frost::{FrostShare, ParticipantIndex};
 use crypto::signature_utils::{CompactSignature, DERSignature};
 use miniscript::{descriptor::DescriptorSecretKey, DescriptorPublicKey};
 use std::convert::TryFrom;
@@ -72,6 +72,18 @@ impl UniffiCustomTypeConverter for FrostShare {
     }
 }
 
+impl UniffiCustomTypeConverter for ParticipantIndex {
+    type Builtin = u8;
+
+    fn into_custom(val: Self::Builtin) -> uniffi::Result<Self> {
+        Ok(ParticipantIndex(val))
+    }
+
+    fn from_custom(obj: Self) -> Self::Builtin {
+        obj.0
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FfiNetwork {
     Bitcoin,
@@ -6,39 +6,41 @@ use bitcoin::{bip32::Fingerprint, secp256k1::PublicKey};
 use teltra::{TelemetryIdentifiers, Teltra, TeltraError};
 use wca::attestation::{Attestation, AttestationError};
 use wca::command_interface::{Command, State};
-use wca::commands::{assemble_psbt_signatures, decompose_psbt};
+use wca::commands::{assemble_psbt_signatures, decompose_psbt, decompose_psbt_v2};
 use wca::commands::{
-    compute_commitment_hash, serialize_stream_payload, BioMatchStats, BtcDisplayUnit, BtcNetwork,
-    CancelFingerprintEnrollment, ConfirmedCommandResult, CoredumpFragment,
-    DecomposedPsbt, DeleteFingerprint, DescriptorPublicKey, DeviceIdentifiers, DeviceInfo,
-    DeviceInfoMcu, EekRestorationUnseal, EekRestorationUnsealResult, KeysetRepairRotateHwKey,
-    KeysetRepairRotateHwKeyResult, KeysetRepairUnseal, KeysetRepairUnsealResult, EnrolledFingerprints,
-    EnrollmentDiagnostics, EventFragment, FingerprintEnrollmentResult, FingerprintEnrollmentStatus,
-    FingerprintResetFinalize, FingerprintResetRequest, FirmwareFeatureFlag, FirmwareFeatureFlagCfg,
-    FirmwareMetadata, FirmwareSlot, FullAccountCloudBackupRestoration,
-    FullAccountCloudBackupRestorationContinue, FullAccountCloudBackupRestorationContinueResult,
-    FullAccountCloudBackupRestorationResult, FwupFinish, FwupFinishRspStatus, FwupMode, FwupStart,
-    FwupStartResult, FwupTransfer, GetAddress, GetAddressResult, GetAuthenticationKey,
-    GetCert, GetConfirmationResult,
+    compute_commitment_hash, serialize_stream_payload, serialize_stream_payload_v2, BioMatchStats,
+    BtcDisplayUnit, BtcNetwork, CancelFingerprintEnrollment, ConfirmedCommandResult,
+    CoredumpFragment, DecomposedPsbt, DecomposedPsbtV2, DeleteFingerprint, DescriptorPublicKey,
+    DeviceIdentifiers, DeviceInfo, DeviceInfoMcu, EekRestorationUnseal, EekRestorationUnsealResult,
+    EnrolledFingerprints, EnrollmentDiagnostics, EventFragment, FingerprintEnrollmentResult,
+    FingerprintEnrollmentStatus, FingerprintResetFinalize, FingerprintResetRequest,
+    FirmwareFeatureFlag, FirmwareFeatureFlagCfg, FirmwareMetadata, FirmwareSlot,
+    FullAccountCloudBackupRestoration, FullAccountCloudBackupRestorationContinue,
+    FullAccountCloudBackupRestorationContinueResult, FullAccountCloudBackupRestorationResult,
+    FwupFinish, FwupFinishRspStatus, FwupMode, FwupStart, FwupStartResult, FwupTransfer,
+    GetAddress, GetAddressResult, GetAuthenticationKey, GetCert, GetConfirmationResult,
     GetCoredumpCount, GetCoredumpFragment, GetDeviceIdentifiers, GetDeviceInfo,
     GetEnrolledFingerprints, GetEvents, GetFingerprintEnrollmentStatus, GetFirmwareFeatureFlags,
     GetFirmwareMetadata, GetInitialSpendingKey, GetNextSpendingKey, GetTelemetryIdentifiers,
-    GetTxSignature, GetTxSignaturesBatch, GetUnlockMethod, InputSignatureTuple, LockDevice,
-    LostAppRecovery, LostAppRecoveryContinue, LostAppRecoveryContinueResult, LostAppRecoveryResult,
-    LostAppRecoverySignChallenge, LostAppRecoverySignChallengeResult, McuInfo, McuName, McuRole,
-    PartiallySignedTransaction, ProvisionAppAuthKey, QueryAuthentication, RecoveryAuthorizeLostApp,
+    GetTxSignature, GetTxSignaturesBatch, GetUnlockMethod, InputSignatureTuple,
+    KeysetRepairRotateHwKey, KeysetRepairRotateHwKeyResult, KeysetRepairUnseal,
+    KeysetRepairUnsealResult, LockDevice, LostAppRecovery, LostAppRecoveryContinue,
+    LostAppRecoveryContinueResult, LostAppRecoveryResult, LostAppRecoverySignChallenge,
+    LostAppRecoverySignChallengeResult, McuInfo, McuName, McuRole, PartiallySignedTransaction,
+    ProvisionAppAuthKey, QueryAuthentication, RecoveryAuthorizeLostApp,
     RecoveryAuthorizeLostAppResult, RecoveryAuthorizeLostHw, RecoveryAuthorizeLostHwResult,
     RotateAppAuthKeys, RotateAppAuthKeysResult, SecureBootConfig, SetFingerprintLabel,
-    UpgradeRotateAppAuthKeys, UpgradeRotateAppAuthKeysResult,
     SetFirmwareFeatureFlags, ShowConfirmationScreen, SignActionProof, SignActionProofResult,
-    SignChallenge, SignChallengeAndSealSeks, SignChallengeAndSealSeksResult,
-    SignStart, SignStartResult, SignStreamFinalize, SignStreamFinalizeResult, SignStreamStart,
-    SignStreamStartResult, SignStreamTransfer, SignStreamTransferResult, SignTransaction,
-    SignTransfer, SignTransferResult, SignTxInputData, SignTxOutputData, SignTxRequest,
-    SignTxRequestResult, SignVerifyAttestationChallenge, Signature, StartFingerprintEnrollment,
-    SweepSignRequest, SweepSignStreamStart, SweepSignStreamStartResult, SweepXpub,
-    TemplateMatchStats, TxSignature, UnlockInfo, UpgradeAuthorizeW3, UpgradeAuthorizeW3Result,
-    VerifyKeysAndBuildDescriptor, Version, WipeState, WipeStateResult,
+    SignChallenge, SignChallengeAndSealSeks, SignChallengeAndSealSeksResult, SignStart,
+    SignStartResult, SignStreamFinalize, SignStreamFinalizeResult, SignStreamInputData,
+    SignStreamStart, SignStreamStartResult, SignStreamTransfer, SignStreamTransferResult,
+    SignTransaction, SignTransfer, SignTransferResult, SignTxInputData, SignTxOutputData,
+    SignTxRequest, SignTxRequestResult, SignVerifyAttestationChallenge, Signature, SignatureType,
+    StartFingerprintEnrollment, SweepSignRequest, SweepSignStreamStart, SweepSignStreamStartResult,
+    SweepXpub, TemplateMatchStats, TransactionVerificationCheck, TransactionVerificationGrant,
+    TxSignature, UnlockInfo, UpgradeAuthorizeW3, UpgradeAuthorizeW3Result,
+    UpgradeRotateAppAuthKeys, UpgradeRotateAppAuthKeysResult, VerifyKeysAndBuildDescriptor,
+    Version, WipeState, WipeStateResult,
 };
 use wca::errors::CommandError;
 use wca::fwpb::cert_get_cmd::CertType;
@@ -47,7 +49,10 @@ use wca::fwpb::FingerprintHandle;
 use wca::log_buffer::{
     disable_proto_exchange_logging, enable_proto_exchange_logging, get_proto_exchange_logs,
 };
-use wca::{EllipticCurve, KeyEncoding, PublicKeyHandle, PublicKeyMetadata, SignatureContext, SpendingKeyResult};
+use wca::{
+    EllipticCurve, KeyEncoding, PublicKeyHandle, PublicKeyMetadata, SignatureContext,
+    SpendingKeyResult,
+};
 
 type BooleanState = State<bool>;
 type U16State = State<u16>;
@@ -125,4 +130,22 @@ pub fn serialize_sign_stream_payload(
     })
 }
 
+/// Serializes transaction inputs/outputs into the version 2 binary format,
+/// which supports taproot key-path inputs, for the streaming signing protocol.
+pub fn serialize_sign_stream_payload_v2(
+    version: u32,
+    lock_time: u32,
+    inputs: Vec<SignStreamInputData>,
+    outputs: Vec<SignTxOutputData>,
+) -> Result<StreamPayload, CommandError> {
+    let data = serialize_stream_payload_v2(version, lock_time, &inputs, &outputs)?;
+    let commitment_hash = compute_commitment_hash(&data);
+    let payload_size = data.len() as u32;
+    Ok(StreamPayload {
+        data,
+        commitment_hash,
+        payload_size,
+    })
+}
+
 uniffi::include_scaffolding!("firmware");
@@ -0,0 +1,438 @@
+//! Async counterparts of [`Transactor`](crate::pcsc::Transactor) and
+//! [`Performer`](crate::pcsc::Performer).
+//!
+//! Every APDU of a command can be bounded by a timeout and interrupted by a
+//! [`CancellationToken`], e.g. when the NFC tag is lost or the user cancels. An interrupted
+//! command can't be resumed, since its generator is left waiting for the response that never
+//! came. Exchanges that span many commands, like streaming signing or FWUP, go through
+//! [`ChunkedTransfer`], which checkpoints after every acknowledged chunk so that they resume
+//! where they stopped instead of restarting.
+
+use std::{
+    future::Future,
+    pin::pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use tokio::sync::Notify;
+
+use crate::{
+    command_interface::{Command, State},
+    errors::CommandError,
+};
+
+/// How long to wait for the hardware to answer a single APDU by default.
+pub const DEFAULT_APDU_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub trait AsyncTransactor: Send + Sync {
+    type Error: std::error::Error + Send + Sync + 'static;
+
+    fn transmit(&self, buffer: &[u8]) -> impl Future<Output = Result<Vec<u8>, Self::Error>> + Send;
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum AsyncTransactorError {
+    #[error("transport error")]
+    Transport(#[source] Box<dyn std::error::Error + Send + Sync>),
+    #[error("no response within {0:?}")]
+    Timeout(Duration),
+    #[error("cancelled")]
+    Cancelled,
+    #[error("command error")]
+    CommandError(#[from] CommandError),
+}
+
+/// Signals an in-flight exchange to stop. Clones share the same state, so one can be handed to
+/// whatever detects the interruption, e.g. a UI cancel button or an NFC session callback.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(Arc<CancellationState>);
+
+#[derive(Debug, Default)]
+struct CancellationState {
+    cancelled: AtomicBool,
+    notify: Notify,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.cancelled.store(true, Ordering::SeqCst);
+        self.0.notify.notify_waiters();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Completes once the token is cancelled.
+    pub async fn cancelled(&self) {
+        loop {
+            // Register for the notification before checking the flag, so a cancel in between
+            // isn't missed.
+            let mut notified = pin!(self.0.notify.notified());
+            notified.as_mut().enable();
+            if self.is_cancelled() {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PerformOptions {
+    /// Applied to each APDU separately, so long multi-APDU commands aren't cut short. `None`
+    /// waits indefinitely.
+    pub apdu_timeout: Option<Duration>,
+    pub cancellation: CancellationToken,
+}
+
+impl Default for PerformOptions {
+    fn default() -> Self {
+        Self {
+            apdu_timeout: Some(DEFAULT_APDU_TIMEOUT),
+            cancellation: CancellationToken::new(),
+        }
+    }
+}
+
+pub trait AsyncPerformer {
+    fn perform<V, E>(
+        &self,
+        command: impl Command<V, E> + Send,
+        options: &PerformOptions,
+    ) -> impl Future<Output = Result<V, AsyncTransactorError>> + Send
+    where
+        AsyncTransactorError: From<E>,
+        V: Send,
+        E: Send;
+}
+
+impl<T: AsyncTransactor> AsyncPerformer for T {
+    fn perform<V, E>(
+        &self,
+        command: impl Command<V, E> + Send,
+        options: &PerformOptions,
+    ) -> impl Future<Output = Result<V, AsyncTransactorError>> + Send
+    where
+        AsyncTransactorError: From<E>,
+        V: Send,
+        E: Send,
+    {
+        async move {
+            let mut response = vec![];
+            loop {
+                if options.cancellation.is_cancelled() {
+                    return Err(AsyncTransactorError::Cancelled);
+                }
+                response = match command.next(response)? {
+                    State::Data { response: cmd } => transmit(self, &cmd, options).await?,
+                    State::Result { value } => break Ok(value),
+                }
+            }
+        }
+    }
+}
+
+async fn transmit<T: AsyncTransactor>(
+    transactor: &T,
+    buffer: &[u8],
+    options: &PerformOptions,
+) -> Result<Vec<u8>, AsyncTransactorError> {
+    let transmit = async {
+        let transmit = transactor.transmit(buffer);
+        let response = match options.apdu_timeout {
+            Some(timeout) => tokio::time::timeout(timeout, transmit)
+                .await
+                .map_err(|_| AsyncTransactorError::Timeout(timeout))?,
+            None => transmit.await,
+        };
+        response.map_err(|e| AsyncTransactorError::Transport(Box::new(e)))
+    };
+
+    tokio::select! {
+        biased;
+        _ = options.cancellation.cancelled() => Err(AsyncTransactorError::Cancelled),
+        response = transmit => response,
+    }
+}
+
+/// Runs a blocking [`Transactor`](crate::pcsc::Transactor) on tokio's blocking thread pool.
+///
+/// A timed out or cancelled APDU stops being waited on, but the reader still finishes
+/// transmitting it in the background.
+#[cfg(feature = "pcsc")]
+pub struct BlockingTransactor<T>(Arc<T>);
+
+#[cfg(feature = "pcsc")]
+impl<T> BlockingTransactor<T> {
+    pub fn new(transactor: T) -> Self {
+        Self(Arc::new(transactor))
+    }
+}
+
+#[cfg(feature = "pcsc")]
+impl<T: crate::pcsc::Transactor + 'static> AsyncTransactor for BlockingTransactor<T> {
+    type Error = pcsc::Error;
+
+    fn transmit(&self, buffer: &[u8]) -> impl Future<Output = Result<Vec<u8>, Self::Error>> + Send {
+        let transactor = self.0.clone();
+        let buffer = buffer.to_vec();
+        async move {
+            match tokio::task::spawn_blocking(move || transactor.transmit(&buffer)).await {
+                Ok(response) => response,
+                Err(e) => std::panic::resume_unwind(e.into_panic()),
+            }
+        }
+    }
+}
+
+/// One chunk of a [`ChunkedTransfer`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TransferChunk {
+    pub sequence_id: u32,
+    pub data: Vec<u8>,
+}
+
+/// Sends data as a sequence of chunk commands, e.g. `SignStreamTransfer` or `FwupTransfer`,
+/// keeping count of the chunks the hardware acknowledged.
+///
+/// If [`perform`](Self::perform) is interrupted, performing it again picks up at the first
+/// unacknowledged chunk. The checkpoint can also outlive the transfer: persist
+/// [`acknowledged`](Self::acknowledged) and restore it with [`resume_from`](Self::resume_from).
+pub struct ChunkedTransfer<F> {
+    chunks: Vec<Vec<u8>>,
+    acknowledged: usize,
+    command: F,
+}
+
+impl<F> ChunkedTransfer<F> {
+    /// `command` builds the command sending a chunk.
+    pub fn new(chunks: Vec<Vec<u8>>, command: F) -> Self {
+        Self {
+            chunks,
+            acknowledged: 0,
+            command,
+        }
+    }
+
+    /// Skips the first `acknowledged` chunks, which the hardware already has.
+    pub fn resume_from(mut self, acknowledged: usize) -> Self {
+        self.acknowledged = acknowledged.min(self.chunks.len());
+        self
+    }
+
+    pub fn acknowledged(&self) -> usize {
+        self.acknowledged
+    }
+
+    pub fn total(&self) -> usize {
+        self.chunks.len()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.acknowledged == self.chunks.len()
+    }
+
+    pub async fn perform<T, C, V>(
+        &mut self,
+        transactor: &T,
+        options: &PerformOptions,
+    ) -> Result<(), AsyncTransactorError>
+    where
+        T: AsyncTransactor,
+        F: Fn(TransferChunk) -> C,
+        C: Command<V, CommandError> + Send,
+        V: Send,
+    {
+        while let Some(data) = self.chunks.get(self.acknowledged) {
+            let chunk = TransferChunk {
+                sequence_id: self
+                    .acknowledged
+                    .try_into()
+                    .map_err(|_| CommandError::InvalidArguments)?,
+                data: data.clone(),
+            };
+            transactor.perform((self.command)(chunk), options).await?;
+            self.acknowledged += 1;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        future::Future,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Mutex,
+        },
+        time::Duration,
+    };
+
+    use prost::Message;
+
+    use crate::{
+        commands::{chunk_payload, SignStreamTransfer, Version, CHUNK_SIZE},
+        fwpb::{self, wallet_cmd, wallet_rsp::Msg, SignStreamTransferRsp, Status, WalletRsp},
+    };
+
+    use super::{
+        AsyncPerformer, AsyncTransactor, AsyncTransactorError, CancellationToken, ChunkedTransfer,
+        PerformOptions,
+    };
+
+    /// Answers sign stream transfers, fails the APDU numbered `fail_at` once, and stalls on the
+    /// one numbered `stall_at`.
+    #[derive(Default)]
+    struct FakeTransactor {
+        transmitted: AtomicUsize,
+        fail_at: Option<usize>,
+        stall_at: Option<usize>,
+        sequence_ids: Mutex<Vec<u32>>,
+    }
+
+    impl AsyncTransactor for FakeTransactor {
+        type Error = std::io::Error;
+
+        fn transmit(
+            &self,
+            buffer: &[u8],
+        ) -> impl Future<Output = Result<Vec<u8>, Self::Error>> + Send {
+            let index = self.transmitted.fetch_add(1, Ordering::SeqCst);
+            let fail = self.fail_at == Some(index);
+            let stall = self.stall_at == Some(index);
+            let buffer = buffer.to_vec();
+            async move {
+                if stall {
+                    tokio::time::sleep(Duration::from_secs(60)).await;
+                }
+                if fail {
+                    return Err(std::io::Error::other("tag lost"));
+                }
+
+                let data = match buffer[4] {
+                    0 => &buffer[7..],
+                    _ => &buffer[5..],
+                };
+                let cmd = fwpb::WalletCmd::decode(data).unwrap();
+                let msg = match cmd.msg {
+                    Some(wallet_cmd::Msg::SignStreamTransferCmd(transfer)) => {
+                        self.sequence_ids.lock().unwrap().push(transfer.sequence_id);
+                        Some(Msg::SignStreamTransferRsp(SignStreamTransferRsp {}))
+                    }
+                    _ => None,
+                };
+                let mut response = WalletRsp {
+                    status: Status::Success.into(),
+                    msg,
+                    ..Default::default()
+                }
+                .encode_to_vec();
+                response.extend_from_slice(&[0x90, 0x00]);
+                Ok(response)
+            }
+        }
+    }
+
+    fn payload() -> Vec<u8> {
+        (0..CHUNK_SIZE * 4 + 10).map(|i| i as u8).collect()
+    }
+
+    #[tokio::test]
+    async fn perform_times_out_stalled_apdu() {
+        let transactor = FakeTransactor {
+            stall_at: Some(0),
+            ..Default::default()
+        };
+        let options = PerformOptions {
+            apdu_timeout: Some(Duration::from_millis(50)),
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            transactor.perform(Version::new(), &options).await,
+            Err(AsyncTransactorError::Timeout(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn perform_is_cancellable() {
+        let transactor = FakeTransactor {
+            stall_at: Some(0),
+            ..Default::default()
+        };
+        let options = PerformOptions {
+            apdu_timeout: None,
+            cancellation: CancellationToken::new(),
+        };
+
+        let cancellation = options.cancellation.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            cancellation.cancel();
+        });
+        assert!(matches!(
+            transactor.perform(Version::new(), &options).await,
+            Err(AsyncTransactorError::Cancelled)
+        ));
+
+        // Nothing more is sent once cancelled.
+        assert!(matches!(
+            transactor.perform(Version::new(), &options).await,
+            Err(AsyncTransactorError::Cancelled)
+        ));
+        assert_eq!(transactor.transmitted.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn chunked_transfer_resumes_after_interruption() {
+        let transactor = FakeTransactor {
+            fail_at: Some(2),
+            ..Default::default()
+        };
+        let options = PerformOptions::default();
+        let mut transfer = ChunkedTransfer::new(chunk_payload(&payload()), |chunk| {
+            SignStreamTransfer::new(chunk.sequence_id, chunk.data)
+        });
+        assert_eq!(transfer.total(), 5);
+
+        assert!(matches!(
+            transfer.perform(&transactor, &options).await,
+            Err(AsyncTransactorError::Transport(_))
+        ));
+        assert_eq!(transfer.acknowledged(), 2);
+        assert!(!transfer.is_complete());
+
+        transfer.perform(&transactor, &options).await.unwrap();
+        assert!(transfer.is_complete());
+        assert_eq!(
+            *transactor.sequence_ids.lock().unwrap(),
+            vec![0, 1, 2, 3, 4]
+        );
+    }
+
+    #[tokio::test]
+    async fn chunked_transfer_resumes_from_checkpoint() {
+        let transactor = FakeTransactor::default();
+        let mut transfer = ChunkedTransfer::new(chunk_payload(&payload()), |chunk| {
+            SignStreamTransfer::new(chunk.sequence_id, chunk.data)
+        })
+        .resume_from(3);
+
+        transfer
+            .perform(&transactor, &PerformOptions::default())
+            .await
+            .unwrap();
+        assert!(transfer.is_complete());
+        assert_eq!(*transactor.sequence_ids.lock().unwrap(), vec![3, 4]);
+    }
+}
@@ -3,6 +3,9 @@
 //! intact while delegating the cert-chain and signature-verification
 //! logic to the shared crate.
 
+use std::sync::RwLock;
+
+use device_attestation::TrustStore;
 use rand_core::{OsRng, RngCore};
 
 pub use device_attestation::AttestationError;
@@ -26,7 +29,9 @@ fn spending_key_attestation_payload(compressed_spending_pubkey: &[u8]) -> Vec<u8
     out
 }
 
-pub struct Attestation {}
+pub struct Attestation {
+    trust_store: RwLock<TrustStore>,
+}
 
 impl Default for Attestation {
     fn default() -> Self {
@@ -36,7 +41,31 @@ impl Default for Attestation {
 
 impl Attestation {
     pub fn new() -> Attestation {
-        Self {}
+        Self {
+            trust_store: RwLock::new(TrustStore::builtin()),
+        }
+    }
+
+    /// Replace the baked-in roots, intermediates and denylist with a trust
+    /// store bundle signed by a pinned bundle signing key, returning its
+    /// sequence. The caller persists that
+    /// sequence and passes it back as `last_accepted_sequence`; bundles that
+    /// aren't newer than it, or than the one in use, are rejected so a
+    /// replayed bundle can't un-revoke a device.
+    pub fn load_trust_store_bundle(
+        &self,
+        bundle: Vec<u8>,
+        signature: Vec<u8>,
+        last_accepted_sequence: u64,
+    ) -> Result<u64, AttestationError> {
+        let trust_store = TrustStore::from_signed_bundle(&bundle, &signature)?;
+        let mut current = self.trust_store.write().unwrap();
+        if trust_store.sequence <= last_accepted_sequence.max(current.sequence) {
+            return Err(AttestationError::InvalidTrustStore);
+        }
+        let sequence = trust_store.sequence;
+        *current = trust_store;
+        Ok(sequence)
     }
 
     /// Verify a certificate chain for a Bitkey. Returns the manufacturer
@@ -46,8 +75,12 @@ impl Attestation {
         identity_cert_der: Vec<u8>,
         batch_cert_der: Vec<u8>,
     ) -> Result<String, AttestationError> {
-        device_attestation::verify_device_identity_chain(&identity_cert_der, &batch_cert_der)
-            .map(|cert| cert.serial().to_string())
+        device_attestation::verify_device_identity_chain(
+            &self.trust_store.read().unwrap(),
+            &identity_cert_der,
+            &batch_cert_der,
+        )
+        .map(|cert| cert.serial().to_string())
     }
 
     /// Verify a hardware-attestation challenge response. The caller is
@@ -80,8 +113,11 @@ impl Attestation {
             return Err(AttestationError::ParseFailure);
         }
 
-        let device =
-            device_attestation::verify_device_identity_chain(&identity_cert_der, &batch_cert_der)?;
+        let device = device_attestation::verify_device_identity_chain(
+            &self.trust_store.read().unwrap(),
+            &identity_cert_der,
+            &batch_cert_der,
+        )?;
 
         let payload = spending_key_attestation_payload(&compressed_spending_pubkey);
         device.verify_signature(&payload, &signature)
@@ -118,13 +154,13 @@ mod tests {
 
     #[test]
     fn test_verify_valid_cert_chain_w3() {
-        let dev_result = Attestation {}.verify_device_identity_cert_chain(
+        let dev_result = Attestation::new().verify_device_identity_cert_chain(
             decode_hex(W3_DEV_MCU_CERT_HEX).unwrap(),
             decode_hex(W3_DEV_BATCH_CERT_HEX).unwrap(),
         );
         assert_eq!(dev_result.unwrap(), "6CA042FFFE3C65EF".to_string());
 
-        let prod_result = Attestation {}.verify_device_identity_cert_chain(
+        let prod_result = Attestation::new().verify_device_identity_cert_chain(
             decode_hex(W3_PROD_MCU_CERT_HEX).unwrap(),
             decode_hex(W3_PROD_BATCH_CERT_HEX).unwrap(),
         );
@@ -136,7 +172,7 @@ mod tests {
         let identity_cert_der = decode_hex(W1_IDENTITY_CERT_HEX).unwrap();
         let batch_cert_der = decode_hex(W1_BATCH_CERT_HEX).unwrap();
         let result =
-            Attestation {}.verify_device_identity_cert_chain(identity_cert_der, batch_cert_der);
+            Attestation::new().verify_device_identity_cert_chain(identity_cert_der, batch_cert_der);
         assert!(result.is_ok());
         assert_eq!(result.unwrap(), "38398FFFFED081B6".to_string());
     }
@@ -158,8 +194,8 @@ mod tests {
             ),
         ] {
             // Swap the batch and identity certs.
-            let result =
-                Attestation {}.verify_device_identity_cert_chain(batch_cert_der, identity_cert_der);
+            let result = Attestation::new()
+                .verify_device_identity_cert_chain(batch_cert_der, identity_cert_der);
             assert_eq!(result.unwrap_err(), AttestationError::NotForBlock);
         }
     }
@@ -170,7 +206,7 @@ mod tests {
         let challenge = decode_hex("0b05c5ef411f36354219036afa3e3a02").unwrap();
         let signature = decode_hex("ff3be827b5e8e0bd1b55d24c94783992ab05b7f5dcf110ad0eccb2054b83987e3910a9013c72fbbb6300e6f96e255b7e06c74483ccf5f27a1ffa338ea93a7a82").unwrap();
         assert_eq!(
-            Attestation {}
+            Attestation::new()
                 .verify_challenge_response(challenge, identity_cert_der, signature)
                 .unwrap(),
             ()
@@ -183,7 +219,7 @@ mod tests {
         let challenge = decode_hex("0b05c5ef411f36354219036afa3e3a02").unwrap();
         let signature = decode_hex("0f3be827b5e8e0bd1b55d24c94783992ab05b7f5dcf110ad0eccb2054b83987e3910a9013c72fbbb6300e6f96e255b7e06c74483ccf5f27a1ffa338ea93a7a82").unwrap();
         assert_eq!(
-            Attestation {}
+            Attestation::new()
                 .verify_challenge_response(challenge, identity_cert_der, signature)
                 .unwrap_err(),
             AttestationError::VerificationFailure
@@ -216,7 +252,7 @@ mod tests {
         let bad_pubkey = vec![0u8; 32];
         let signature = vec![0u8; 64];
 
-        let result = Attestation {}.verify_spending_key_attestation(
+        let result = Attestation::new().verify_spending_key_attestation(
             identity_cert_der,
             batch_cert_der,
             bad_pubkey,
@@ -230,8 +266,72 @@ mod tests {
         let mut set = HashSet::new();
         // lightweight check: ensure each result is different
         for _ in 0..100 {
-            let challenge = Attestation {}.generate_challenge().unwrap();
+            let challenge = Attestation::new().generate_challenge().unwrap();
             assert!(set.insert(challenge));
         }
     }
+
+    #[test]
+    fn load_trust_store_bundle_applies_denylist_and_rejects_stale_bundles() {
+        use device_attestation::test_signing_key;
+
+        let signed_bundle = |trust_store: &TrustStore| {
+            let bundle = trust_store.serialize_bundle().unwrap();
+            let signature = test_signing_key::sign_bundle(&bundle);
+            (bundle, signature)
+        };
+
+        let mut denying = TrustStore::builtin();
+        denying.sequence = 2;
+        denying
+            .denylist
+            .device_serials
+            .insert("38398FFFFED081B6".to_string());
+
+        let attestation = Attestation::new();
+        let (bundle, signature) = signed_bundle(&denying);
+        assert_eq!(
+            attestation
+                .load_trust_store_bundle(bundle.clone(), signature.clone(), 0)
+                .unwrap(),
+            2
+        );
+        assert_eq!(
+            attestation
+                .verify_device_identity_cert_chain(
+                    decode_hex(W1_IDENTITY_CERT_HEX).unwrap(),
+                    decode_hex(W1_BATCH_CERT_HEX).unwrap(),
+                )
+                .unwrap_err(),
+            AttestationError::Revoked
+        );
+
+        // Replaying the bundle in use, or an older one, is rejected.
+        assert_eq!(
+            attestation
+                .load_trust_store_bundle(bundle.clone(), signature.clone(), 0)
+                .unwrap_err(),
+            AttestationError::InvalidTrustStore
+        );
+        let stale = TrustStore {
+            sequence: 1,
+            ..TrustStore::builtin()
+        };
+        let (stale_bundle, stale_signature) = signed_bundle(&stale);
+        assert_eq!(
+            attestation
+                .load_trust_store_bundle(stale_bundle, stale_signature, 0)
+                .unwrap_err(),
+            AttestationError::InvalidTrustStore
+        );
+
+        // A fresh instance, e.g. after a restart, relies on the sequence the
+        // caller persisted.
+        assert_eq!(
+            Attestation::new()
+                .load_trust_store_bundle(bundle, signature, 2)
+                .unwrap_err(),
+            AttestationError::InvalidTrustStore
+        );
+    }
 }
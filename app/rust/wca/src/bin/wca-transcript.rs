@@ -0,0 +1,39 @@
+//! Inspects recorded wca transcripts.
+//!
+//! `wca-transcript show <file>` prints a transcript with its proto messages decoded.
+//! `wca-transcript diff <old> <new>` prints how two transcripts differ, exiting 1 if they do.
+
+use std::{env, process::ExitCode};
+
+use wca::transcript::{Transcript, TranscriptError};
+
+const USAGE: &str = "usage: wca-transcript show <file>\n       wca-transcript diff <old> <new>";
+
+fn load(path: &str) -> Result<Transcript, String> {
+    Transcript::load(path).map_err(|e: TranscriptError| format!("{path}: {e}"))
+}
+
+fn run(args: &[String]) -> Result<ExitCode, String> {
+    match args {
+        [command, path] if command == "show" => {
+            print!("{}", load(path)?.render());
+            Ok(ExitCode::SUCCESS)
+        }
+        [command, old, new] if command == "diff" => match load(old)?.diff(&load(new)?) {
+            Some(diff) => {
+                print!("{diff}");
+                Ok(ExitCode::from(1))
+            }
+            None => Ok(ExitCode::SUCCESS),
+        },
+        _ => Err(USAGE.to_string()),
+    }
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    run(&args).unwrap_or_else(|error| {
+        eprintln!("{error}");
+        ExitCode::from(2)
+    })
+}
@@ -1,8 +1,87 @@
-use std::pin::Pin;
+use std::{
+    pin::Pin,
+    sync::{mpsc, Mutex},
+    thread,
+};
+
+use next_gen::generator::GeneratorState;
+
+use crate::errors::CommandError;
 
 pub type CommandFn<T, E> =
     Pin<Box<dyn next_gen::prelude::Generator<Vec<u8>, Yield = Vec<u8>, Return = Result<T, E>>>>;
 
+/// Creates a command's generator on the thread that drives it.
+pub type CreateCommandFn<T, E> = Box<dyn FnOnce() -> CommandFn<T, E> + Send>;
+
+/// The generator backing one command instance, created on the first call to `next` and kept
+/// until the command is dropped, so each instance runs independently of any other.
+///
+/// A generator is !Send: next_gen passes values in and out of it through a `Cell` that the
+/// generator borrows from its own boxed allocation. So it lives on a thread of its own, and the
+/// command only holds channels to that thread, which can move between threads like any other
+/// value. The thread exits once the generator returns or the command is dropped.
+pub struct CommandGenerator<T, E> {
+    generator: Mutex<Option<GeneratorThread<T, E>>>,
+}
+
+struct GeneratorThread<T, E> {
+    responses: mpsc::Sender<Vec<u8>>,
+    states: mpsc::Receiver<GeneratorState<Vec<u8>, Result<T, E>>>,
+}
+
+impl<T: Send + 'static, E: Send + 'static> GeneratorThread<T, E> {
+    fn spawn(create: CreateCommandFn<T, E>) -> Self {
+        let (responses, response_receiver) = mpsc::channel::<Vec<u8>>();
+        let (state_sender, states) = mpsc::channel();
+        thread::spawn(move || {
+            let mut generator = create();
+            for response in response_receiver {
+                let state = generator.as_mut().resume(response);
+                let returned = matches!(state, GeneratorState::Returned(_));
+                if state_sender.send(state).is_err() || returned {
+                    break;
+                }
+            }
+        });
+        Self { responses, states }
+    }
+}
+
+impl<T: Send + 'static, E: Send + 'static> CommandGenerator<T, E> {
+    pub fn new() -> Self {
+        Self {
+            generator: Mutex::new(None),
+        }
+    }
+
+    /// Resumes the generator with `response`, creating it with `create` on the first call.
+    ///
+    /// Fails with [`CommandError::GeneralCommandError`] once the generator has returned.
+    pub fn resume(
+        &self,
+        create: impl FnOnce() -> CreateCommandFn<T, E>,
+        response: Vec<u8>,
+    ) -> Result<GeneratorState<Vec<u8>, Result<T, E>>, CommandError> {
+        let mut generator = self.generator.lock()?;
+        let generator = generator.get_or_insert_with(|| GeneratorThread::spawn(create()));
+        generator
+            .responses
+            .send(response)
+            .map_err(|_| CommandError::GeneralCommandError)?;
+        generator
+            .states
+            .recv()
+            .map_err(|_| CommandError::GeneralCommandError)
+    }
+}
+
+impl<T: Send + 'static, E: Send + 'static> Default for CommandGenerator<T, E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[derive(Debug, PartialEq, Eq)]
 pub enum State<T> {
     Data { response: Vec<u8> },
@@ -44,36 +123,20 @@ pub trait Command<T, E> {
 macro_rules! command {
     ($struct_name:ident = $generator_name:ident -> $generator_return_type:ty) => {
         pub struct $struct_name {
-            is_first_call: std::sync::atomic::AtomicBool,
+            generator: $crate::command_interface::CommandGenerator<$generator_return_type, CommandError>,
         }
 
         impl $struct_name {
             pub fn new() -> Self {
-                // New is called when the command is created, so is_first_call is reset to true on creation.
                 Self {
-                    is_first_call: std::sync::atomic::AtomicBool::new(true),
+                    generator: $crate::command_interface::CommandGenerator::new(),
                 }
             }
 
-            fn generator<'a>(&self) -> &'a mut Option<$crate::command_interface::CommandFn<$generator_return_type, CommandError>> {
-                #![allow(static_mut_refs)]
-                static mut GENERATOR: Option<$crate::command_interface::CommandFn<$generator_return_type, CommandError>> = None;
-                // We have to use unsafe because of Cell<TransferBox> (https://docs.rs/next-gen/latest/src/next_gen/generator_fn.rs.html#88)
-                // and Cell is !Sync https://doc.rust-lang.org/std/cell/struct.Cell.html#impl-Sync-for-Cell%3CT%3E
-                // However, this is safe in practice AS LONG AS a command is only used by one thread at a time and NOT moved
-                // across threads.
-                unsafe {
-                    // Reset generator if it's the first call
-                    if self.is_first_call.swap(false, std::sync::atomic::Ordering::SeqCst) {  // Swap to false, and if it was true, reset the generator
-                        GENERATOR = None;
-                    }
-
-                    if GENERATOR.is_none() {
-                        // Create the generator when it's not already set
-                        GENERATOR = Some(next_gen::generator_fn::CallBoxed::call_boxed($generator_name, ()));
-                    }
-                    std::ptr::addr_of_mut!(GENERATOR).as_mut().unwrap()
-                }
+            fn create_generator(&self) -> $crate::command_interface::CreateCommandFn<$generator_return_type, CommandError> {
+                Box::new(|| -> $crate::command_interface::CommandFn<$generator_return_type, CommandError> {
+                    next_gen::generator_fn::CallBoxed::call_boxed($generator_name, ())
+                })
             }
         }
 
@@ -91,32 +154,22 @@ macro_rules! command {
     ($struct_name:ident = $generator_name:ident -> $generator_return_type:ty, $($argname:ident: $type:ty),*) => {
         pub struct $struct_name {
             $( $argname: $type, )*
-            is_first_call: std::sync::atomic::AtomicBool,  // Track first call with AtomicBool
+            generator: $crate::command_interface::CommandGenerator<$generator_return_type, CommandError>,
         }
 
         impl $struct_name {
             pub fn new($($argname: $type),*) -> Self {
                 Self {
                     $( $argname ),*,
-                    is_first_call: std::sync::atomic::AtomicBool::new(true),
+                    generator: $crate::command_interface::CommandGenerator::new(),
                 }
             }
 
-            fn generator<'a>(&self) -> &'a mut Option<$crate::command_interface::CommandFn<$generator_return_type, CommandError>> {
-                #![allow(static_mut_refs)]
-                static mut GENERATOR: Option<$crate::command_interface::CommandFn<$generator_return_type, CommandError>> = None;
-                unsafe {
-                    // Reset generator if it's the first call
-                    if self.is_first_call.swap(false, std::sync::atomic::Ordering::SeqCst) {
-                        GENERATOR = None;
-                    }
-
-                    if GENERATOR.is_none() {
-                        // Create the generator with arguments when it's not already set
-                        GENERATOR = Some(next_gen::generator_fn::CallBoxed::call_boxed($generator_name, ($(self.$argname.to_owned()),*,)));
-                    }
-                    std::ptr::addr_of_mut!(GENERATOR).as_mut().unwrap()
-                }
+            fn create_generator(&self) -> $crate::command_interface::CreateCommandFn<$generator_return_type, CommandError> {
+                let args = ($(self.$argname.to_owned()),*,);
+                Box::new(move || -> $crate::command_interface::CommandFn<$generator_return_type, CommandError> {
+                    next_gen::generator_fn::CallBoxed::call_boxed($generator_name, args)
+                })
             }
         }
 
@@ -127,8 +180,7 @@ macro_rules! command {
 
     (next_impl $generator_return_type:ty) => {
         fn next(&self, response: Vec<u8>) -> std::result::Result<$crate::command_interface::State<$generator_return_type>, $crate::errors::CommandError> {
-            // Acquire the statically allocated generator. We statically allocate here to to ensure that the generator's state
-            // is preserved across calls to next.
+            // Each instance owns its generator, which preserves its state across calls to next.
             // Previously, this code created a new generator per call to next, and we cached the responses from the generator, and
             // then replayed them into the generator with resume. The code was like this:
             //      let mut generator = self.generator();
@@ -142,8 +194,7 @@ macro_rules! command {
             // This was problematic: the call to `call_boxed()` invokes the generator function, for example `sign_transaction()`.
             // If that function had side effects, then those re-evaluated lines of code would trigger them multiple times.
             // This didn't cause any problems because the generator functions were pure, but it was easy to misuse.
-            let generator_opt = self.generator();
-            let response = generator_opt.as_mut().expect("generator must be set").as_mut().resume(response);
+            let response = self.generator.resume(|| self.create_generator(), response)?;
 
             match response {
                 next_gen::generator::GeneratorState::Yielded(response) => Ok($crate::command_interface::State::Data { response }),
@@ -235,4 +286,113 @@ mod tests {
         );
         Ok(())
     }
+
+    #[generator(yield(Vec<u8>), resume(Vec<u8>))]
+    fn echo_generator(prefix: String) -> Result<Vec<u8>, CommandError> {
+        let first = yield_!(prefix.clone().into());
+        let second = yield_!(first.clone());
+        Ok([prefix.into_bytes(), first, second].concat())
+    }
+    command!(Echo = echo_generator -> Vec<u8>, prefix: String);
+
+    fn run_echo(command: &Echo, responses: [&str; 3]) -> Result<Vec<u8>, CommandError> {
+        for response in &responses[..2] {
+            command.next(response.as_bytes().to_vec())?;
+        }
+        match command.next(responses[2].as_bytes().to_vec())? {
+            State::Result { value } => Ok(value),
+            State::Data { .. } => Err(CommandError::InvalidResponse),
+        }
+    }
+
+    #[test]
+    fn interleaved_instances() -> Result<(), CommandError> {
+        let a = Echo::new("a".into());
+        let b = Echo::new("b".into());
+
+        assert_eq!(
+            a.next(vec![])?,
+            State::Data {
+                response: "a".into()
+            }
+        );
+        assert_eq!(
+            b.next(vec![])?,
+            State::Data {
+                response: "b".into()
+            }
+        );
+        assert_eq!(
+            a.next("1".into())?,
+            State::Data {
+                response: "1".into()
+            }
+        );
+        assert_eq!(
+            b.next("2".into())?,
+            State::Data {
+                response: "2".into()
+            }
+        );
+        assert_eq!(
+            a.next("3".into())?,
+            State::Result {
+                value: "a13".into()
+            }
+        );
+        assert_eq!(
+            b.next("4".into())?,
+            State::Result {
+                value: "b24".into()
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn concurrent_instances() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Echo>();
+
+        let handles: Vec<_> = (0..8)
+            .map(|i| {
+                std::thread::spawn(move || run_echo(&Echo::new(i.to_string()), ["", "x", "y"]))
+            })
+            .collect();
+        for (i, handle) in handles.into_iter().enumerate() {
+            assert_eq!(
+                handle.join().unwrap().unwrap(),
+                format!("{i}xy").into_bytes()
+            );
+        }
+    }
+
+    #[test]
+    fn next_after_result_fails() -> Result<(), CommandError> {
+        let command = Nullary::new();
+        command.next(vec![])?;
+        assert!(matches!(command.next(vec![])?, State::Result { .. }));
+        assert!(matches!(
+            command.next(vec![]),
+            Err(CommandError::GeneralCommandError)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn instance_moved_between_threads() -> Result<(), CommandError> {
+        let command = Echo::new("moved".into());
+        command.next(vec![])?;
+
+        let command = std::thread::spawn(move || command.next("1".into()).map(|_| command))
+            .join()
+            .unwrap()?;
+        assert_eq!(
+            command.next("2".into())?,
+            State::Result {
+                value: "moved12".into()
+            }
+        );
+        Ok(())
+    }
 }
@@ -2,18 +2,25 @@ use bitcoin::{
     ecdsa::Signature as EcdsaSig,
     psbt::Psbt as PartiallySignedTransaction,
     secp256k1::{PublicKey, Secp256k1},
+    taproot::Signature as SchnorrSig,
 };
 use miniscript::psbt::PsbtExt;
 
-use super::sign_tx_request::InputSignatureTuple;
+use super::{sign_stream_serializer_v2::SignatureType, sign_tx_request::InputSignatureTuple};
 use crate::errors::CommandError;
 
 /// Inserts hardware-produced signatures into a PSBT and finalizes it.
 ///
-/// For each `InputSignatureTuple`, inserts the signature as a `partial_sig`
-/// on the corresponding PSBT input. Then attempts to finalize the PSBT
-/// (converting `partial_sigs` → `final_script_witness`) so the transaction
-/// can be extracted and broadcast.
+/// For each `InputSignatureTuple`, inserts the signature on the corresponding
+/// PSBT input, choosing the signature type from the prevout scriptPubKey as
+/// [`decompose_psbt_v2`](super::decompose_psbt_v2) does:
+/// - P2TR inputs are signed through the key path, so the Schnorr signature
+///   (64 bytes, plus a sighash type byte unless SIGHASH_DEFAULT) becomes the
+///   input's `tap_key_sig`
+/// - any other input gets the ECDSA signature as a `partial_sig`
+///
+/// Then attempts to finalize the PSBT (converting the signatures to a
+/// `final_script_witness`) so the transaction can be extracted and broadcast.
 ///
 /// # Finalization behavior
 ///
@@ -59,14 +66,26 @@ pub fn assemble_psbt_signatures(
         let public_key = PublicKey::from_slice(&sig_tuple.public_key)
             .map_err(|_| CommandError::InvalidArguments)?;
 
-        // The hardware returns DER-encoded signature + sighash type byte.
-        let ecdsa_sig = EcdsaSig::from_slice(&sig_tuple.signature)
-            .map_err(|_| CommandError::InvalidArguments)?;
-
         let input = &mut psbt.inputs[input_index];
-        input
-            .partial_sigs
-            .insert(bitcoin::PublicKey::new(public_key), ecdsa_sig);
+        let signature_type = match &input.witness_utxo {
+            Some(utxo) if utxo.script_pubkey.is_p2tr() => SignatureType::Schnorr,
+            _ => SignatureType::Ecdsa,
+        };
+        match signature_type {
+            SignatureType::Ecdsa => {
+                // The hardware returns DER-encoded signature + sighash type byte.
+                let ecdsa_sig = EcdsaSig::from_slice(&sig_tuple.signature)
+                    .map_err(|_| CommandError::InvalidArguments)?;
+                input
+                    .partial_sigs
+                    .insert(bitcoin::PublicKey::new(public_key), ecdsa_sig);
+            }
+            SignatureType::Schnorr => {
+                let schnorr_sig = SchnorrSig::from_slice(&sig_tuple.signature)
+                    .map_err(|_| CommandError::InvalidArguments)?;
+                input.tap_key_sig = Some(schnorr_sig);
+            }
+        }
     }
 
     let finalize_result = psbt.finalize_mut(&Secp256k1::verification_only());
@@ -80,14 +99,17 @@ pub fn assemble_psbt_signatures(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::commands::{decompose_psbt_v2, DecomposedPsbtV2};
     use bitcoin::{
         bip32::{ChildNumber, DerivationPath, Fingerprint},
+        consensus::encode::deserialize,
         hashes::Hash,
+        key::{Keypair, TapTweak},
         psbt::Input as PsbtInput,
         psbt::Psbt,
-        secp256k1::{Secp256k1, SecretKey},
-        sighash::{EcdsaSighashType, SighashCache},
-        Amount, ScriptBuf, Sequence, Transaction, TxIn, TxOut, Txid, Witness,
+        secp256k1::{Message, Secp256k1, SecretKey},
+        sighash::{EcdsaSighashType, Prevouts, SighashCache, TapSighashType},
+        Amount, ScriptBuf, Sequence, TapNodeHash, Transaction, TxIn, TxOut, Txid, Witness,
     };
     use std::collections::BTreeMap;
 
@@ -228,4 +250,208 @@ mod tests {
         assert!(psbt.inputs[0].final_script_witness.is_none());
         assert!(psbt.inputs[0].partial_sigs.is_empty());
     }
+
+    /// A PSBT spending a P2WPKH output of `keys[0]` and a P2TR key-path
+    /// output of `keys[1]`, whose output key commits to a script tree.
+    fn make_taproot_signable_psbt(keys: &[SecretKey; 2]) -> Psbt {
+        let secp = Secp256k1::new();
+        let fingerprint = Fingerprint::from([0x96, 0xae, 0x19, 0x27]);
+        let path = |purpose: u32, index: u32| {
+            DerivationPath::from(vec![
+                ChildNumber::from_hardened_idx(purpose).unwrap(),
+                ChildNumber::from_hardened_idx(0).unwrap(),
+                ChildNumber::from_hardened_idx(0).unwrap(),
+                ChildNumber::from_normal_idx(0).unwrap(),
+                ChildNumber::from_normal_idx(index).unwrap(),
+            ])
+        };
+
+        let ecdsa_key = PublicKey::from_secret_key(&secp, &keys[0]);
+        let (internal_key, _) = PublicKey::from_secret_key(&secp, &keys[1]).x_only_public_key();
+        let merkle_root = TapNodeHash::from_byte_array([0x77; 32]);
+
+        let tx_in = |txid: u8| TxIn {
+            previous_output: bitcoin::OutPoint {
+                txid: Txid::from_slice(&[txid; 32]).unwrap(),
+                vout: 1,
+            },
+            script_sig: ScriptBuf::new(),
+            sequence: Sequence(0xFFFFFFFD),
+            witness: Witness::default(),
+        };
+        let unsigned_tx = Transaction {
+            version: bitcoin::transaction::Version::TWO,
+            lock_time: bitcoin::blockdata::locktime::absolute::LockTime::ZERO,
+            input: vec![tx_in(0xab), tx_in(0xcd)],
+            output: vec![TxOut {
+                value: Amount::from_sat(140_000),
+                script_pubkey: ScriptBuf::new_p2tr(&secp, internal_key, None),
+            }],
+        };
+
+        Psbt {
+            unsigned_tx,
+            version: 0,
+            xpub: Default::default(),
+            proprietary: Default::default(),
+            unknown: Default::default(),
+            inputs: vec![
+                PsbtInput {
+                    witness_utxo: Some(TxOut {
+                        value: Amount::from_sat(100_000),
+                        script_pubkey: ScriptBuf::new_p2wpkh(
+                            &bitcoin::PublicKey::new(ecdsa_key).wpubkey_hash().unwrap(),
+                        ),
+                    }),
+                    bip32_derivation: BTreeMap::from([(ecdsa_key, (fingerprint, path(84, 7)))]),
+                    ..Default::default()
+                },
+                PsbtInput {
+                    witness_utxo: Some(TxOut {
+                        value: Amount::from_sat(50_000),
+                        script_pubkey: ScriptBuf::new_p2tr(&secp, internal_key, Some(merkle_root)),
+                    }),
+                    tap_internal_key: Some(internal_key),
+                    tap_merkle_root: Some(merkle_root),
+                    tap_key_origins: BTreeMap::from([(
+                        internal_key,
+                        (vec![], (fingerprint, path(86, 3))),
+                    )]),
+                    ..Default::default()
+                },
+            ],
+            outputs: vec![Default::default()],
+        }
+    }
+
+    /// Plays the hardware's part: rebuilds the transaction from the
+    /// decomposed fields alone and signs each input with `keys[input_index]`.
+    fn sign_decomposed(
+        decomposed: &DecomposedPsbtV2,
+        keys: &[SecretKey],
+    ) -> Vec<InputSignatureTuple> {
+        let secp = Secp256k1::new();
+        let tx = Transaction {
+            version: bitcoin::transaction::Version(decomposed.version as i32),
+            lock_time: bitcoin::blockdata::locktime::absolute::LockTime::from_consensus(
+                decomposed.lock_time,
+            ),
+            input: decomposed
+                .inputs
+                .iter()
+                .map(|input| TxIn {
+                    previous_output: bitcoin::OutPoint {
+                        txid: deserialize(&input.prev_txid).unwrap(),
+                        vout: input.prev_index,
+                    },
+                    script_sig: ScriptBuf::new(),
+                    sequence: Sequence(input.sequence),
+                    witness: Witness::default(),
+                })
+                .collect(),
+            output: decomposed
+                .outputs
+                .iter()
+                .map(|output| TxOut {
+                    value: Amount::from_sat(output.amount),
+                    script_pubkey: ScriptBuf::from(output.destination_spk.clone()),
+                })
+                .collect(),
+        };
+        let prevouts: Vec<TxOut> = decomposed
+            .inputs
+            .iter()
+            .map(|input| TxOut {
+                value: Amount::from_sat(input.amount),
+                script_pubkey: ScriptBuf::from(input.script_pubkey.clone()),
+            })
+            .collect();
+
+        let mut cache = SighashCache::new(&tx);
+        decomposed
+            .inputs
+            .iter()
+            .zip(keys)
+            .enumerate()
+            .map(|(index, (input, secret_key))| {
+                let keypair = Keypair::from_secret_key(&secp, secret_key);
+                let signature = match input.signature_type {
+                    SignatureType::Ecdsa => {
+                        let sighash = cache
+                            .p2wpkh_signature_hash(
+                                index,
+                                &prevouts[index].script_pubkey,
+                                prevouts[index].value,
+                                EcdsaSighashType::All,
+                            )
+                            .unwrap();
+                        let msg = Message::from_digest(sighash.to_byte_array());
+                        EcdsaSig::sighash_all(secp.sign_ecdsa(&msg, secret_key)).to_vec()
+                    }
+                    SignatureType::Schnorr => {
+                        let sighash = cache
+                            .taproot_key_spend_signature_hash(
+                                index,
+                                &Prevouts::All(&prevouts),
+                                TapSighashType::Default,
+                            )
+                            .unwrap();
+                        let merkle_root = (!input.tap_merkle_root.is_empty())
+                            .then(|| TapNodeHash::from_slice(&input.tap_merkle_root).unwrap());
+                        let tweaked = keypair.tap_tweak(&secp, merkle_root).to_inner();
+                        let msg = Message::from_digest(sighash.to_byte_array());
+                        SchnorrSig {
+                            signature: secp.sign_schnorr_no_aux_rand(&msg, &tweaked),
+                            sighash_type: TapSighashType::Default,
+                        }
+                        .to_vec()
+                    }
+                };
+                InputSignatureTuple {
+                    input_index: index as u32,
+                    public_key: keypair.public_key().serialize().to_vec(),
+                    signature,
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn decompose_sign_assemble_round_trip() {
+        let keys = [
+            SecretKey::from_slice(&[0x01; 32]).expect("32 bytes, within curve order"),
+            SecretKey::from_slice(&[0x02; 32]).expect("32 bytes, within curve order"),
+        ];
+        let base64 = make_taproot_signable_psbt(&keys).to_string();
+
+        let decomposed = decompose_psbt_v2(base64.clone(), "96ae1927".to_string()).unwrap();
+        let signatures = sign_decomposed(&decomposed, &keys);
+        let result = assemble_psbt_signatures(base64, signatures, false).unwrap();
+
+        // finalize_mut checks every witness against its prevout, so a
+        // signature over the wrong sighash or key would have failed above.
+        let psbt: Psbt = result.parse().unwrap();
+        assert!(psbt.inputs[0].final_script_witness.is_some());
+        let taproot_witness = psbt.inputs[1].final_script_witness.as_ref().unwrap();
+        assert_eq!(taproot_witness.len(), 1);
+        assert_eq!(taproot_witness.nth(0).unwrap().len(), 64);
+    }
+
+    #[test]
+    fn assemble_rejects_ecdsa_signature_for_taproot_input() {
+        let keys = [
+            SecretKey::from_slice(&[0x01; 32]).expect("32 bytes, within curve order"),
+            SecretKey::from_slice(&[0x02; 32]).expect("32 bytes, within curve order"),
+        ];
+        let base64 = make_taproot_signable_psbt(&keys).to_string();
+        let (_, public_key, der_sig) = make_signable_psbt();
+
+        let signatures = vec![InputSignatureTuple {
+            input_index: 1,
+            public_key: public_key.serialize().to_vec(),
+            signature: der_sig,
+        }];
+        let result = assemble_psbt_signatures(base64, signatures, true);
+        assert!(matches!(result, Err(CommandError::InvalidArguments)));
+    }
 }
@@ -1,3 +1,5 @@
+use std::sync::Arc;
+
 use next_gen::generator;
 
 use crate::attestation::{Attestation, AttestationError};
@@ -45,6 +47,7 @@ fn get_cert(kind: CertType) -> Result<Vec<u8>, CommandError> {
 fn sign_verify_attestation_challenge(
     device_identity_der: Vec<u8>,
     challenge: Vec<u8>,
+    attestation: Arc<Attestation>,
 ) -> Result<bool, CommandError> {
     let apdu: apdu::Command = HardwareAttestationCmd {
         nonce: challenge.clone(),
@@ -58,7 +61,7 @@ fn sign_verify_attestation_challenge(
         .ok_or(CommandError::MissingMessage)?;
 
     if let Msg::HardwareAttestationRsp(HardwareAttestationRsp { signature }) = message {
-        Attestation {}
+        attestation
             .verify_challenge_response(challenge.clone(), device_identity_der, signature)
             .map_err(|e| match e {
                 AttestationError::VerificationFailure => CommandError::SignatureInvalid,
@@ -77,5 +80,6 @@ command!(GetCert = get_cert -> Vec<u8>,
 
 command!(SignVerifyAttestationChallenge = sign_verify_attestation_challenge -> bool,
     device_identity_der: Vec<u8>,
-    challenge: Vec<u8>
+    challenge: Vec<u8>,
+    attestation: Arc<Attestation>
 );
@@ -1,8 +1,15 @@
 use bitcoin::{
-    bip32::Fingerprint, consensus::encode::serialize, psbt::Psbt as PartiallySignedTransaction,
+    bip32::{DerivationPath, Fingerprint},
+    consensus::encode::serialize,
+    hashes::Hash,
+    psbt::{Output as PsbtOutput, Psbt as PartiallySignedTransaction},
+    sighash::{EcdsaSighashType, TapSighashType},
 };
 
-use super::sign_tx_request::{SignTxInputData, SignTxOutputData};
+use super::{
+    sign_stream_serializer_v2::{SignStreamInputData, SignatureType},
+    sign_tx_request::{SignTxInputData, SignTxOutputData},
+};
 use crate::errors::CommandError;
 
 /// Decomposed PSBT data ready for the non-PSBT signing protocol.
@@ -146,6 +153,182 @@ pub fn decompose_psbt(
     })
 }
 
+/// Decomposed PSBT data ready for the version 2 streaming payload.
+///
+/// Unlike [`DecomposedPsbt`], inputs carry their prevout scriptPubKey, signature
+/// type and taproot fields, so P2TR key-path inputs can be signed.
+#[derive(Debug, Clone)]
+pub struct DecomposedPsbtV2 {
+    pub version: u32,
+    pub lock_time: u32,
+    pub inputs: Vec<SignStreamInputData>,
+    pub outputs: Vec<SignTxOutputData>,
+}
+
+fn path_elements(path: &DerivationPath) -> Vec<u32> {
+    path.into_iter().map(|child| u32::from(*child)).collect()
+}
+
+/// Finds an output's derivation path matching the hardware fingerprint, from
+/// either its BIP32 derivations or, for P2TR outputs, the origin of its
+/// internal key.
+fn output_derivation_path(output: &PsbtOutput, fingerprint: Fingerprint) -> Vec<u32> {
+    let bip32 = output
+        .bip32_derivation
+        .values()
+        .find(|(fp, _)| *fp == fingerprint)
+        .map(|(_, path)| path);
+    let taproot = output
+        .tap_internal_key
+        .and_then(|key| output.tap_key_origins.get(&key))
+        .filter(|(_, (fp, _))| *fp == fingerprint)
+        .map(|(_, (_, path))| path);
+    bip32.or(taproot).map(path_elements).unwrap_or_default()
+}
+
+/// Decomposes a base64-encoded PSBT into the fields of the version 2 streaming
+/// payload.
+///
+/// For each input, extracts the same fields as [`decompose_psbt`], plus the
+/// prevout scriptPubKey, which selects how the input is signed:
+/// - P2WPKH inputs are signed with ECDSA, using the BIP32 derivation path
+///   matching the hardware fingerprint
+/// - P2TR inputs are signed with Schnorr through the key path, using the
+///   `tap_internal_key`, its `tap_key_origins` entry matching the hardware
+///   fingerprint, and `tap_merkle_root` if present
+///
+/// The input's `sighash_type` is passed through, defaulting to SIGHASH_ALL for
+/// ECDSA and SIGHASH_DEFAULT for Schnorr. Whether the firmware supports it is
+/// checked by [`serialize_stream_payload_v2`](super::serialize_stream_payload_v2).
+///
+/// Outputs are decomposed as in [`decompose_psbt`], except that a P2TR change
+/// output's derivation path is taken from the origin of its internal key.
+///
+/// # Errors
+/// Returns `CommandError::InvalidArguments` if the PSBT cannot be parsed, an
+/// input spends anything but P2WPKH or P2TR, or an input is missing the data
+/// needed to sign it.
+pub fn decompose_psbt_v2(
+    psbt_base64: String,
+    origin_fingerprint: String,
+) -> Result<DecomposedPsbtV2, CommandError> {
+    let psbt: PartiallySignedTransaction = psbt_base64
+        .parse()
+        .map_err(|_| CommandError::InvalidArguments)?;
+
+    let fingerprint: Fingerprint = origin_fingerprint
+        .parse()
+        .map_err(|_| CommandError::InvalidArguments)?;
+
+    let mut inputs = Vec::with_capacity(psbt.unsigned_tx.input.len());
+    for (i, tx_in) in psbt.unsigned_tx.input.iter().enumerate() {
+        let psbt_input = psbt.inputs.get(i).ok_or(CommandError::InvalidArguments)?;
+
+        // Both sighash algorithms commit to the amount, and BIP-341 to the
+        // scriptPubKey, of the output being spent.
+        let utxo = psbt_input
+            .witness_utxo
+            .as_ref()
+            .ok_or(CommandError::InvalidArguments)?;
+
+        let (signature_type, sighash_type, derivation_path, tap_internal_key, tap_merkle_root) =
+            if utxo.script_pubkey.is_p2tr() {
+                let internal_key = psbt_input
+                    .tap_internal_key
+                    .ok_or(CommandError::InvalidArguments)?;
+                let derivation_path = psbt_input
+                    .tap_key_origins
+                    .get(&internal_key)
+                    .filter(|(_, (fp, _))| *fp == fingerprint)
+                    .map(|(_, (_, path))| path_elements(path))
+                    .ok_or(CommandError::InvalidArguments)?;
+                let sighash_type = match psbt_input.sighash_type {
+                    Some(ty) => ty
+                        .taproot_hash_ty()
+                        .map_err(|_| CommandError::InvalidArguments)?,
+                    None => TapSighashType::Default,
+                };
+                (
+                    SignatureType::Schnorr,
+                    sighash_type as u8,
+                    derivation_path,
+                    internal_key.serialize().to_vec(),
+                    psbt_input
+                        .tap_merkle_root
+                        .map(|root| root.to_byte_array().to_vec())
+                        .unwrap_or_default(),
+                )
+            } else if utxo.script_pubkey.is_p2wpkh() {
+                let derivation_path = psbt_input
+                    .bip32_derivation
+                    .values()
+                    .find(|(fp, _)| *fp == fingerprint)
+                    .map(|(_, path)| path_elements(path))
+                    .ok_or(CommandError::InvalidArguments)?;
+                let sighash_type = match psbt_input.sighash_type {
+                    Some(ty) => ty
+                        .ecdsa_hash_ty()
+                        .map_err(|_| CommandError::InvalidArguments)?,
+                    None => EcdsaSighashType::All,
+                };
+                (
+                    SignatureType::Ecdsa,
+                    sighash_type.to_u32() as u8,
+                    derivation_path,
+                    vec![],
+                    vec![],
+                )
+            } else {
+                return Err(CommandError::InvalidArguments);
+            };
+
+        if derivation_path.len() > 5 {
+            return Err(CommandError::InvalidArguments);
+        }
+
+        inputs.push(SignStreamInputData {
+            prev_txid: serialize(&tx_in.previous_output.txid),
+            prev_index: tx_in.previous_output.vout,
+            sequence: tx_in.sequence.0,
+            amount: utxo.value.to_sat(),
+            script_pubkey: utxo.script_pubkey.to_bytes(),
+            signature_type,
+            sighash_type,
+            derivation_path,
+            tap_internal_key,
+            tap_merkle_root,
+        });
+    }
+
+    let mut outputs = Vec::with_capacity(psbt.unsigned_tx.output.len());
+    for (i, tx_out) in psbt.unsigned_tx.output.iter().enumerate() {
+        let derivation_path = psbt
+            .outputs
+            .get(i)
+            .map(|psbt_output| output_derivation_path(psbt_output, fingerprint))
+            .unwrap_or_default();
+
+        let destination_spk = tx_out.script_pubkey.to_bytes();
+        if destination_spk.len() > 35 || derivation_path.len() > 5 {
+            return Err(CommandError::InvalidArguments);
+        }
+
+        outputs.push(SignTxOutputData {
+            amount: tx_out.value.to_sat(),
+            destination_spk,
+            has_derivation_path: !derivation_path.is_empty(),
+            derivation_path,
+        });
+    }
+
+    Ok(DecomposedPsbtV2 {
+        version: psbt.unsigned_tx.version.0 as u32,
+        lock_time: psbt.unsigned_tx.lock_time.to_consensus_u32(),
+        inputs,
+        outputs,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -156,7 +339,7 @@ mod tests {
         psbt::Output as PsbtOutput,
         psbt::Psbt,
         secp256k1::{PublicKey, Secp256k1, SecretKey},
-        Amount, ScriptBuf, Sequence, Transaction, TxIn, TxOut, Txid, Witness,
+        Amount, ScriptBuf, Sequence, TapNodeHash, Transaction, TxIn, TxOut, Txid, Witness,
     };
     use std::collections::BTreeMap;
 
@@ -371,4 +554,159 @@ mod tests {
         let result = decompose_psbt(base64, "deadbeef".to_string());
         assert!(result.is_err());
     }
+
+    fn taproot_path(index: u32) -> DerivationPath {
+        DerivationPath::from(vec![
+            ChildNumber::from_hardened_idx(86).unwrap(),
+            ChildNumber::from_hardened_idx(0).unwrap(),
+            ChildNumber::from_hardened_idx(0).unwrap(),
+            ChildNumber::from_normal_idx(0).unwrap(),
+            ChildNumber::from_normal_idx(index).unwrap(),
+        ])
+    }
+
+    /// A PSBT spending a P2WPKH and a P2TR output, paying to a destination and
+    /// to a P2TR change output.
+    fn make_taproot_test_psbt() -> Psbt {
+        let secp = Secp256k1::new();
+        let secret_key = SecretKey::from_slice(&[0x02; 32]).expect("32 bytes, within curve order");
+        let (internal_key, _) = PublicKey::from_secret_key(&secp, &secret_key).x_only_public_key();
+        let fingerprint = Fingerprint::from([0x96, 0xae, 0x19, 0x27]);
+        let merkle_root = TapNodeHash::from_byte_array([0x77; 32]);
+
+        // Start from the P2WPKH test PSBT and add a P2TR input and change output.
+        let (base64, _) = make_test_psbt();
+        let mut psbt: Psbt = base64.parse().unwrap();
+
+        psbt.unsigned_tx.input.push(TxIn {
+            previous_output: bitcoin::OutPoint {
+                txid: Txid::from_slice(&[0xcd; 32]).unwrap(),
+                vout: 2,
+            },
+            script_sig: ScriptBuf::new(),
+            sequence: Sequence(0xFFFFFFFE),
+            witness: Witness::default(),
+        });
+        psbt.inputs.push(PsbtInput {
+            witness_utxo: Some(TxOut {
+                value: Amount::from_sat(50_000),
+                script_pubkey: ScriptBuf::new_p2tr(&secp, internal_key, Some(merkle_root)),
+            }),
+            tap_internal_key: Some(internal_key),
+            tap_merkle_root: Some(merkle_root),
+            tap_key_origins: BTreeMap::from([(
+                internal_key,
+                (vec![], (fingerprint, taproot_path(3))),
+            )]),
+            ..Default::default()
+        });
+
+        psbt.unsigned_tx.output[1].script_pubkey = ScriptBuf::new_p2tr(&secp, internal_key, None);
+        psbt.outputs[1] = PsbtOutput {
+            tap_internal_key: Some(internal_key),
+            tap_key_origins: BTreeMap::from([(
+                internal_key,
+                (vec![], (fingerprint, taproot_path(4))),
+            )]),
+            ..Default::default()
+        };
+
+        psbt
+    }
+
+    #[test]
+    fn decompose_psbt_v2_extracts_taproot_input() {
+        let psbt = make_taproot_test_psbt();
+        let result = decompose_psbt_v2(psbt.to_string(), "96ae1927".to_string()).unwrap();
+        assert_eq!(result.inputs.len(), 2);
+
+        let p2wpkh = &result.inputs[0];
+        assert_eq!(p2wpkh.signature_type, SignatureType::Ecdsa);
+        assert_eq!(p2wpkh.sighash_type, 0x01);
+        assert_eq!(p2wpkh.amount, 100_000);
+        assert_eq!(p2wpkh.script_pubkey.len(), 22);
+        assert!(p2wpkh.tap_internal_key.is_empty());
+
+        let p2tr = &result.inputs[1];
+        assert_eq!(p2tr.signature_type, SignatureType::Schnorr);
+        assert_eq!(p2tr.sighash_type, 0x00);
+        assert_eq!(p2tr.prev_index, 2);
+        assert_eq!(p2tr.amount, 50_000);
+        assert_eq!(
+            p2tr.script_pubkey,
+            psbt.inputs[1]
+                .witness_utxo
+                .as_ref()
+                .unwrap()
+                .script_pubkey
+                .to_bytes()
+        );
+        assert_eq!(
+            p2tr.tap_internal_key,
+            psbt.inputs[1].tap_internal_key.unwrap().serialize()
+        );
+        assert_eq!(p2tr.tap_merkle_root, vec![0x77; 32]);
+        assert_eq!(p2tr.derivation_path[0], 86 | (1 << 31));
+        assert_eq!(p2tr.derivation_path[4], 3);
+    }
+
+    #[test]
+    fn decompose_psbt_v2_extracts_taproot_change() {
+        let psbt = make_taproot_test_psbt();
+        let result = decompose_psbt_v2(psbt.to_string(), "96ae1927".to_string()).unwrap();
+
+        assert!(!result.outputs[0].has_derivation_path);
+        let change = &result.outputs[1];
+        assert!(change.has_derivation_path);
+        assert_eq!(change.destination_spk.len(), 34);
+        assert_eq!(change.derivation_path[4], 4);
+    }
+
+    #[test]
+    fn decompose_psbt_v2_serializes() {
+        let psbt = make_taproot_test_psbt();
+        let result = decompose_psbt_v2(psbt.to_string(), "96ae1927".to_string()).unwrap();
+        let payload = crate::commands::serialize_stream_payload_v2(
+            result.version,
+            result.lock_time,
+            &result.inputs,
+            &result.outputs,
+        )
+        .unwrap();
+        assert_eq!(
+            payload.len(),
+            crate::commands::payload_size_v2(result.inputs.len(), result.outputs.len())
+        );
+    }
+
+    #[test]
+    fn decompose_psbt_v2_rejects_missing_internal_key() {
+        let mut psbt = make_taproot_test_psbt();
+        psbt.inputs[1].tap_internal_key = None;
+        let result = decompose_psbt_v2(psbt.to_string(), "96ae1927".to_string());
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn decompose_psbt_v2_rejects_missing_taproot_origin() {
+        // Drop the P2WPKH input, so only the taproot origin is looked up.
+        let mut taproot_only = make_taproot_test_psbt();
+        taproot_only.unsigned_tx.input.remove(0);
+        taproot_only.inputs.remove(0);
+        assert!(decompose_psbt_v2(taproot_only.to_string(), "96ae1927".to_string()).is_ok());
+        assert!(decompose_psbt_v2(taproot_only.to_string(), "deadbeef".to_string()).is_err());
+    }
+
+    #[test]
+    fn decompose_psbt_v2_rejects_unsupported_script() {
+        let mut psbt = make_taproot_test_psbt();
+        psbt.inputs[0].witness_utxo.as_mut().unwrap().script_pubkey = ScriptBuf::from(
+            vec![0x00, 0x20]
+                .into_iter()
+                .chain([0xcc; 32])
+                .collect::<Vec<_>>(),
+        );
+        let result = decompose_psbt_v2(psbt.to_string(), "96ae1927".to_string());
+        assert!(result.is_err());
+    }
 }
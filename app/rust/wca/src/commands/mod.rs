@@ -32,11 +32,13 @@ mod sign_challenge_and_seal_seks;
 mod sign_sighash;
 mod sign_stream;
 mod sign_stream_serializer;
+mod sign_stream_serializer_v2;
 mod sign_transaction;
 mod sign_transaction_chunked;
 mod sign_tx_request;
 mod sweep_sign;
 mod telemetry;
+mod tx_verification;
 mod unseal_key;
 mod upgrade_authorize_w3;
 mod upgrade_rotate_app_auth_keys;
@@ -58,7 +60,7 @@ pub use authentication::{GetAuthenticationKey, GetUnlockMethod, UnlockInfo};
 pub use coredump::CoredumpFragment;
 pub use coredump::GetCoredumpCount;
 pub use coredump::GetCoredumpFragment;
-pub use decompose_psbt::{decompose_psbt, DecomposedPsbt};
+pub use decompose_psbt::{decompose_psbt, decompose_psbt_v2, DecomposedPsbt, DecomposedPsbtV2};
 pub use device_id::DeviceIdentifiers;
 pub use device_id::DeviceInfo;
 pub use device_id::GetDeviceIdentifiers;
@@ -113,7 +115,7 @@ pub use recovery_authorize_lost_hw::{RecoveryAuthorizeLostHw, RecoveryAuthorizeL
 pub use rotate_app_auth_keys::{RotateAppAuthKeys, RotateAppAuthKeysResult};
 pub use seal_key::SealKey;
 pub use show_confirmation_screen::ShowConfirmationScreen;
-pub use sign_action_proof::{SignActionProof, SignActionProofResult};
+pub use sign_action_proof::{sign_action_proof_display, SignActionProof, SignActionProofResult};
 pub use sign_challenge_and_seal_seks::{SignChallengeAndSealSeks, SignChallengeAndSealSeksResult};
 pub use sign_sighash::SignedSighash;
 pub use sign_stream::{
@@ -124,6 +126,9 @@ pub use sign_stream::{
 pub use sign_stream_serializer::{
     chunk_payload, compute_commitment_hash, payload_size, serialize_stream_payload, CHUNK_SIZE,
 };
+pub use sign_stream_serializer_v2::{
+    payload_size_v2, serialize_stream_payload_v2, SignStreamInputData, SignatureType,
+};
 pub use sign_transaction::SignTransaction;
 pub use sign_transaction_chunked::{SignStart, SignStartResult, SignTransfer, SignTransferResult};
 pub use sign_tx_request::{
@@ -134,6 +139,10 @@ pub use sweep_sign::{
 };
 pub use telemetry::EventFragment;
 pub use telemetry::GetEvents;
+pub use tx_verification::{
+    TransactionVerificationCheck, TransactionVerificationGrant, TxVerificationExpectations,
+    TX_VERIFICATION_V1, TX_VERIFICATION_V1_ACCEPTED_UNTIL, TX_VERIFICATION_V2,
+};
 pub use unseal_key::UnsealKey;
 pub use upgrade_authorize_w3::{UpgradeAuthorizeW3, UpgradeAuthorizeW3Result};
 pub use upgrade_rotate_app_auth_keys::{UpgradeRotateAppAuthKeys, UpgradeRotateAppAuthKeysResult};
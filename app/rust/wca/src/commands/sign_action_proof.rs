@@ -1,3 +1,4 @@
+use action_proof::{RenderedPayload, CANONICAL_MAGIC, UNIT_SEPARATOR, W3_RENDER_POLICY};
 use next_gen::generator;
 
 use crate::{
@@ -19,6 +20,31 @@ pub enum SignActionProofResult {
     },
 }
 
+/// Renders the confirmation screen for a `SignActionProof` request.
+///
+/// The hardware rebuilds the canonical payload from these same fields, so the
+/// app can show byte-identical text alongside the device prompt.
+pub fn sign_action_proof_display(
+    version: u32,
+    action: &str,
+    value: Option<&str>,
+    bindings: &str,
+) -> Result<RenderedPayload, CommandError> {
+    let version = version.to_string();
+    let payload = [
+        CANONICAL_MAGIC,
+        version.as_str(),
+        action,
+        value.unwrap_or_default(),
+        bindings,
+    ]
+    .join(&char::from(UNIT_SEPARATOR).to_string());
+
+    let parsed = action_proof::parse_payload(payload.as_bytes())
+        .map_err(|_| CommandError::InvalidArguments)?;
+    Ok(action_proof::render_payload(&parsed, &W3_RENDER_POLICY))
+}
+
 #[generator(yield(Vec<u8>), resume(Vec<u8>))]
 fn sign_action_proof(
     version: u32,
@@ -71,7 +97,17 @@ mod tests {
         fwpb::{wallet_rsp::Msg, SignActionProofRsp, Status, WalletRsp},
     };
 
-    use super::{SignActionProof, SignActionProofResult};
+    use super::{sign_action_proof_display, SignActionProof, SignActionProofResult};
+
+    const RENDER_GOLDEN: &str =
+        include_str!("../../../../../core/action-proof/testdata/render.golden");
+
+    fn golden_entry(name: &str) -> &'static str {
+        let header = format!("== {name}\n");
+        let start = RENDER_GOLDEN.find(&header).expect("golden entry") + header.len();
+        let entry = &RENDER_GOLDEN[start..];
+        &entry[..entry.find("\ntruncated:").expect("golden entry end")]
+    }
 
     fn make_response(wallet_rsp: WalletRsp) -> Vec<u8> {
         let mut buf = wallet_rsp.encode_to_vec();
@@ -111,6 +147,24 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn sign_action_proof_display_matches_golden() -> Result<(), CommandError> {
+        let rendered =
+            sign_action_proof_display(1, "SetRecoveryEmail", Some("alice@example.com"), "tb=59dc")?;
+        assert_eq!(rendered.to_text(), golden_entry("v1 email"));
+
+        let rendered =
+            sign_action_proof_display(2, "SetDelayNotifyPeriod", Some("days=7"), "tb=59dc")?;
+        assert_eq!(rendered.to_text(), golden_entry("v2 delay days"));
+
+        assert!(matches!(
+            sign_action_proof_display(1, "NotAnAction", None, "tb=59dc"),
+            Err(CommandError::InvalidArguments)
+        ));
+
+        Ok(())
+    }
+
     #[test]
     fn sign_action_proof_confirmation_pending() -> Result<(), CommandError> {
         let command = SignActionProof::new(
@@ -93,37 +93,47 @@ pub fn serialize_stream_payload(
 
     // Outputs
     for output in outputs {
-        // amount: u64 LE
-        buf.extend_from_slice(&output.amount.to_le_bytes());
-        // spk_len: u8 (must be ≤ 35 to fit the fixed-size record)
-        if output.destination_spk.len() > 35 {
-            return Err(CommandError::InvalidArguments);
-        }
-        let spk_len = output.destination_spk.len() as u8;
-        buf.push(spk_len);
-        // spk: always 35 bytes, zero-padded
-        let mut spk_padded = [0u8; 35];
-        spk_padded[..output.destination_spk.len()].copy_from_slice(&output.destination_spk);
-        buf.extend_from_slice(&spk_padded);
-        // has_path: u8
-        buf.push(output.has_derivation_path as u8);
-        // path_len: u8 (must be ≤ 5 to fit the fixed-size record)
-        if output.derivation_path.len() > 5 {
-            return Err(CommandError::InvalidArguments);
-        }
-        let path_len = output.derivation_path.len() as u8;
-        buf.push(path_len);
-        // path: always 5 × u32 LE = 20 bytes, zero-padded
-        for i in 0..5 {
-            let val = output.derivation_path.get(i).copied().unwrap_or(0);
-            buf.extend_from_slice(&val.to_le_bytes());
-        }
+        write_output_record(&mut buf, output)?;
     }
 
     debug_assert_eq!(buf.len(), total_size);
     Ok(buf)
 }
 
+/// Appends the fixed-size record for `output`, shared by every version of the format.
+pub(super) fn write_output_record(
+    buf: &mut Vec<u8>,
+    output: &SignTxOutputData,
+) -> Result<(), CommandError> {
+    // amount: u64 LE
+    buf.extend_from_slice(&output.amount.to_le_bytes());
+    // spk_len: u8 (must be ≤ 35 to fit the fixed-size record)
+    if output.destination_spk.len() > 35 {
+        return Err(CommandError::InvalidArguments);
+    }
+    let spk_len = output.destination_spk.len() as u8;
+    buf.push(spk_len);
+    // spk: always 35 bytes, zero-padded
+    let mut spk_padded = [0u8; 35];
+    spk_padded[..output.destination_spk.len()].copy_from_slice(&output.destination_spk);
+    buf.extend_from_slice(&spk_padded);
+    // has_path: u8
+    buf.push(output.has_derivation_path as u8);
+    // path_len: u8 (must be ≤ 5 to fit the fixed-size record)
+    if output.derivation_path.len() > 5 {
+        return Err(CommandError::InvalidArguments);
+    }
+    let path_len = output.derivation_path.len() as u8;
+    buf.push(path_len);
+    // path: always 5 × u32 LE = 20 bytes, zero-padded
+    for i in 0..5 {
+        let val = output.derivation_path.get(i).copied().unwrap_or(0);
+        buf.extend_from_slice(&val.to_le_bytes());
+    }
+
+    Ok(())
+}
+
 /// Computes the SHA256 commitment hash over the canonical payload.
 ///
 /// This is the value sent in `sign_stream_finalize_cmd.commitment_hash`.
@@ -0,0 +1,527 @@
+//! Version 2 of the canonical binary format for the streaming transaction signing protocol.
+//!
+//! Version 1 only describes P2WPKH spends: its input records carry neither the
+//! prevout's scriptPubKey nor any taproot data. BIP-341 sighashes commit to the
+//! amount and scriptPubKey of *every* prevout, so version 2 input records carry
+//! both, along with how the input is to be signed and, for taproot key-path
+//! spends, the internal key and script tree root needed to check the output key.
+//! Output records are unchanged.
+//!
+//! ## Wire Format
+//!
+//! ```text
+//! Header (20 bytes):
+//!   magic       : [u8; 4]   ("SSP2", distinguishes v2 from a v1 header)
+//!   version     : u32 LE
+//!   lock_time   : u32 LE
+//!   num_inputs  : u32 LE
+//!   num_outputs : u32 LE
+//!
+//! Per-input (172 bytes each):
+//!   prev_txid       : [u8; 32]
+//!   prev_index      : u32 LE
+//!   sequence        : u32 LE
+//!   amount          : u64 LE
+//!   spk_len         : u8        (22 for P2WPKH, 34 for P2TR)
+//!   spk             : [u8; 35]  (prevout scriptPubKey, zero-padded)
+//!   signature_type  : u8        (0 = ECDSA / BIP-143, 1 = Schnorr / BIP-341 key path)
+//!   sighash_type    : u8        (0x01 for ECDSA; 0x00 or 0x01 for Schnorr)
+//!   path_len        : u8        (0..=5)
+//!   path            : [u32 LE; 5]  (always 20 bytes, zero-padded)
+//!   internal_key    : [u8; 32]  (x-only taproot internal key; zero for ECDSA)
+//!   has_merkle_root : u8        (0 or 1)
+//!   merkle_root     : [u8; 32]  (taproot script tree root, zero if absent)
+//!
+//! Per-output (66 bytes each): as in version 1.
+//! ```
+//!
+//! Only sighash types committing to every input and output are accepted, so
+//! firmware can accumulate the BIP-143 and BIP-341 intermediate hashes
+//! (`sha_prevouts`, `sha_amounts`, `sha_scriptpubkeys`, `sha_sequences`,
+//! `sha_outputs`) in a single pass over the stream.
+
+use super::{
+    sign_stream_serializer::{write_output_record, OUTPUT_RECORD_SIZE},
+    sign_tx_request::SignTxOutputData,
+};
+use crate::errors::CommandError;
+
+/// Leading bytes of a version 2 payload.
+pub const MAGIC: [u8; 4] = *b"SSP2";
+/// Size of the fixed header.
+pub const HEADER_SIZE: usize = 20;
+/// Size of each input record in the canonical encoding.
+pub const INPUT_RECORD_SIZE: usize = 172;
+
+const MAX_SPK_LEN: usize = 35;
+const MAX_DERIVATION_PATH: usize = 5;
+
+const SIGHASH_DEFAULT: u8 = 0x00;
+const SIGHASH_ALL: u8 = 0x01;
+
+/// How the hardware signs an input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignatureType {
+    /// ECDSA over a BIP-143 sighash, for P2WPKH inputs.
+    Ecdsa,
+    /// Schnorr over a BIP-341 sighash, for P2TR key-path inputs.
+    Schnorr,
+}
+
+/// Per-input data for the version 2 streaming payload.
+#[derive(Debug, Clone)]
+pub struct SignStreamInputData {
+    pub prev_txid: Vec<u8>,
+    pub prev_index: u32,
+    pub sequence: u32,
+    pub amount: u64,
+    /// The scriptPubKey of the output being spent.
+    pub script_pubkey: Vec<u8>,
+    pub signature_type: SignatureType,
+    pub sighash_type: u8,
+    pub derivation_path: Vec<u32>,
+    /// The x-only taproot internal key; empty for ECDSA inputs.
+    pub tap_internal_key: Vec<u8>,
+    /// The taproot script tree root; empty if the output commits to no scripts.
+    pub tap_merkle_root: Vec<u8>,
+}
+
+fn is_p2wpkh(spk: &[u8]) -> bool {
+    matches!(spk, [0x00, 0x14, rest @ ..] if rest.len() == 20)
+}
+
+fn is_p2tr(spk: &[u8]) -> bool {
+    matches!(spk, [0x51, 0x20, rest @ ..] if rest.len() == 32)
+}
+
+/// Appends the fixed-size record for `input`, rejecting any field the firmware couldn't sign.
+fn write_input_record(buf: &mut Vec<u8>, input: &SignStreamInputData) -> Result<(), CommandError> {
+    let valid = match input.signature_type {
+        SignatureType::Ecdsa => {
+            is_p2wpkh(&input.script_pubkey)
+                && input.sighash_type == SIGHASH_ALL
+                && input.tap_internal_key.is_empty()
+                && input.tap_merkle_root.is_empty()
+        }
+        SignatureType::Schnorr => {
+            is_p2tr(&input.script_pubkey)
+                && matches!(input.sighash_type, SIGHASH_DEFAULT | SIGHASH_ALL)
+                && input.tap_internal_key.len() == 32
+                && matches!(input.tap_merkle_root.len(), 0 | 32)
+        }
+    };
+    if !valid || input.prev_txid.len() != 32 || input.derivation_path.len() > MAX_DERIVATION_PATH {
+        return Err(CommandError::InvalidArguments);
+    }
+
+    buf.extend_from_slice(&input.prev_txid);
+    buf.extend_from_slice(&input.prev_index.to_le_bytes());
+    buf.extend_from_slice(&input.sequence.to_le_bytes());
+    buf.extend_from_slice(&input.amount.to_le_bytes());
+
+    // spk: always 35 bytes, zero-padded
+    let mut spk_padded = [0u8; MAX_SPK_LEN];
+    spk_padded[..input.script_pubkey.len()].copy_from_slice(&input.script_pubkey);
+    buf.push(input.script_pubkey.len() as u8);
+    buf.extend_from_slice(&spk_padded);
+
+    buf.push(match input.signature_type {
+        SignatureType::Ecdsa => 0,
+        SignatureType::Schnorr => 1,
+    });
+    buf.push(input.sighash_type);
+
+    // path: always 5 × u32 LE = 20 bytes, zero-padded
+    buf.push(input.derivation_path.len() as u8);
+    for i in 0..MAX_DERIVATION_PATH {
+        let val = input.derivation_path.get(i).copied().unwrap_or(0);
+        buf.extend_from_slice(&val.to_le_bytes());
+    }
+
+    let mut internal_key = [0u8; 32];
+    internal_key[..input.tap_internal_key.len()].copy_from_slice(&input.tap_internal_key);
+    buf.extend_from_slice(&internal_key);
+
+    let mut merkle_root = [0u8; 32];
+    merkle_root[..input.tap_merkle_root.len()].copy_from_slice(&input.tap_merkle_root);
+    buf.push(!input.tap_merkle_root.is_empty() as u8);
+    buf.extend_from_slice(&merkle_root);
+
+    Ok(())
+}
+
+/// Serializes transaction data into the version 2 binary format for streaming.
+///
+/// Returns the complete payload bytes, which are chunked, streamed and
+/// committed to exactly like a version 1 payload.
+pub fn serialize_stream_payload_v2(
+    version: u32,
+    lock_time: u32,
+    inputs: &[SignStreamInputData],
+    outputs: &[SignTxOutputData],
+) -> Result<Vec<u8>, CommandError> {
+    let total_size = payload_size_v2(inputs.len(), outputs.len());
+    let mut buf = Vec::with_capacity(total_size);
+
+    // Header
+    buf.extend_from_slice(&MAGIC);
+    buf.extend_from_slice(&version.to_le_bytes());
+    buf.extend_from_slice(&lock_time.to_le_bytes());
+    buf.extend_from_slice(&(inputs.len() as u32).to_le_bytes());
+    buf.extend_from_slice(&(outputs.len() as u32).to_le_bytes());
+
+    for input in inputs {
+        write_input_record(&mut buf, input)?;
+    }
+    for output in outputs {
+        write_output_record(&mut buf, output)?;
+    }
+
+    debug_assert_eq!(buf.len(), total_size);
+    Ok(buf)
+}
+
+/// Computes the total version 2 payload size for given input/output counts.
+pub fn payload_size_v2(num_inputs: usize, num_outputs: usize) -> usize {
+    HEADER_SIZE + num_inputs * INPUT_RECORD_SIZE + num_outputs * OUTPUT_RECORD_SIZE
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::{
+        consensus::encode::serialize,
+        hashes::{sha256, sha256d, Hash},
+        sighash::{EcdsaSighashType, Prevouts, SighashCache, TapSighashType},
+        Amount, OutPoint, ScriptBuf, Sequence, Transaction, TxIn, TxOut, Txid, Witness,
+    };
+
+    use super::*;
+
+    fn p2wpkh_spk(byte: u8) -> Vec<u8> {
+        [&[0x00, 0x14][..], &[byte; 20]].concat()
+    }
+
+    fn p2tr_spk(byte: u8) -> Vec<u8> {
+        [&[0x51, 0x20][..], &[byte; 32]].concat()
+    }
+
+    fn ecdsa_input(index: u8) -> SignStreamInputData {
+        SignStreamInputData {
+            prev_txid: vec![index; 32],
+            prev_index: index.into(),
+            sequence: 0xFFFFFFFD,
+            amount: 100_000,
+            script_pubkey: p2wpkh_spk(0xcc),
+            signature_type: SignatureType::Ecdsa,
+            sighash_type: SIGHASH_ALL,
+            derivation_path: vec![84 | (1 << 31), 1 << 31, 1 << 31, 0, index.into()],
+            tap_internal_key: vec![],
+            tap_merkle_root: vec![],
+        }
+    }
+
+    fn schnorr_input(index: u8) -> SignStreamInputData {
+        SignStreamInputData {
+            prev_txid: vec![index; 32],
+            prev_index: index.into(),
+            sequence: 0xFFFFFFFE,
+            amount: 250_000,
+            script_pubkey: p2tr_spk(0xdd),
+            signature_type: SignatureType::Schnorr,
+            sighash_type: SIGHASH_DEFAULT,
+            derivation_path: vec![86 | (1 << 31), 1 << 31, 1 << 31, 0, index.into()],
+            tap_internal_key: vec![0xee; 32],
+            tap_merkle_root: vec![],
+        }
+    }
+
+    fn output(amount: u64, destination_spk: Vec<u8>) -> SignTxOutputData {
+        SignTxOutputData {
+            amount,
+            destination_spk,
+            derivation_path: vec![],
+            has_derivation_path: false,
+        }
+    }
+
+    /// The serialized input fields that sighashes commit to.
+    struct ParsedInput {
+        outpoint: [u8; 36],
+        sequence: [u8; 4],
+        amount: [u8; 8],
+        script_pubkey: Vec<u8>,
+        sighash_type: u8,
+    }
+
+    /// The fields of a version 2 payload that sighashes commit to, read back as firmware would.
+    struct Parsed {
+        version: u32,
+        lock_time: u32,
+        inputs: Vec<ParsedInput>,
+        /// (amount, scriptPubKey) per output.
+        outputs: Vec<([u8; 8], Vec<u8>)>,
+    }
+
+    fn parse(payload: &[u8]) -> Parsed {
+        let u32_at = |at: usize| u32::from_le_bytes(payload[at..at + 4].try_into().unwrap());
+        assert_eq!(payload[..4], MAGIC);
+        let (num_inputs, num_outputs) = (u32_at(12) as usize, u32_at(16) as usize);
+
+        let inputs = (0..num_inputs)
+            .map(|i| {
+                let r = &payload[HEADER_SIZE + i * INPUT_RECORD_SIZE..][..INPUT_RECORD_SIZE];
+                ParsedInput {
+                    outpoint: r[0..36].try_into().unwrap(),
+                    sequence: r[36..40].try_into().unwrap(),
+                    amount: r[40..48].try_into().unwrap(),
+                    script_pubkey: r[49..49 + r[48] as usize].to_vec(),
+                    sighash_type: r[85],
+                }
+            })
+            .collect();
+        let outputs_start = HEADER_SIZE + num_inputs * INPUT_RECORD_SIZE;
+        let outputs = (0..num_outputs)
+            .map(|i| {
+                let r = &payload[outputs_start + i * OUTPUT_RECORD_SIZE..][..OUTPUT_RECORD_SIZE];
+                (
+                    r[0..8].try_into().unwrap(),
+                    r[9..9 + r[8] as usize].to_vec(),
+                )
+            })
+            .collect();
+
+        Parsed {
+            version: u32_at(4),
+            lock_time: u32_at(8),
+            inputs,
+            outputs,
+        }
+    }
+
+    /// Serializes a script with its compact-size length prefix (all test scripts are short).
+    fn script(spk: &[u8]) -> Vec<u8> {
+        [&[spk.len() as u8][..], spk].concat()
+    }
+
+    impl Parsed {
+        fn serialized_outputs(&self) -> Vec<u8> {
+            self.outputs
+                .iter()
+                .flat_map(|(amount, spk)| [&amount[..], &script(spk)].concat())
+                .collect()
+        }
+
+        fn field(&self, f: impl Fn(&ParsedInput) -> Vec<u8>) -> Vec<u8> {
+            self.inputs.iter().flat_map(f).collect()
+        }
+
+        /// BIP-143 sighash for a P2WPKH input with SIGHASH_ALL.
+        fn bip143(&self, index: usize) -> [u8; 32] {
+            let input = &self.inputs[index];
+            let pubkey_hash = &input.script_pubkey[2..];
+            let script_code = [&[0x76, 0xa9, 0x14][..], pubkey_hash, &[0x88, 0xac]].concat();
+            let preimage = [
+                &self.version.to_le_bytes()[..],
+                sha256d::Hash::hash(&self.field(|i| i.outpoint.to_vec())).as_byte_array(),
+                sha256d::Hash::hash(&self.field(|i| i.sequence.to_vec())).as_byte_array(),
+                &input.outpoint,
+                &script(&script_code),
+                &input.amount,
+                &input.sequence,
+                sha256d::Hash::hash(&self.serialized_outputs()).as_byte_array(),
+                &self.lock_time.to_le_bytes(),
+                &u32::from(input.sighash_type).to_le_bytes(),
+            ]
+            .concat();
+            sha256d::Hash::hash(&preimage).to_byte_array()
+        }
+
+        /// BIP-341 key-path sighash, with no annex, for SIGHASH_DEFAULT or SIGHASH_ALL.
+        fn bip341(&self, index: usize) -> [u8; 32] {
+            let sha = |data: Vec<u8>| sha256::Hash::hash(&data).to_byte_array();
+            let message = [
+                &[0x00, self.inputs[index].sighash_type][..],
+                &self.version.to_le_bytes(),
+                &self.lock_time.to_le_bytes(),
+                &sha(self.field(|i| i.outpoint.to_vec())),
+                &sha(self.field(|i| i.amount.to_vec())),
+                &sha(self.field(|i| script(&i.script_pubkey))),
+                &sha(self.field(|i| i.sequence.to_vec())),
+                &sha(self.serialized_outputs()),
+                &[0x00],
+                &(index as u32).to_le_bytes(),
+            ]
+            .concat();
+            let tag = sha(b"TapSighash".to_vec());
+            sha([&tag[..], &tag, &message].concat())
+        }
+    }
+
+    fn transaction(
+        inputs: &[SignStreamInputData],
+        outputs: &[SignTxOutputData],
+    ) -> (Transaction, Vec<TxOut>) {
+        let tx = Transaction {
+            version: bitcoin::transaction::Version::TWO,
+            lock_time: bitcoin::absolute::LockTime::from_consensus(800_000),
+            input: inputs
+                .iter()
+                .map(|input| TxIn {
+                    previous_output: OutPoint {
+                        txid: Txid::from_slice(&input.prev_txid).unwrap(),
+                        vout: input.prev_index,
+                    },
+                    script_sig: ScriptBuf::new(),
+                    sequence: Sequence(input.sequence),
+                    witness: Witness::default(),
+                })
+                .collect(),
+            output: outputs
+                .iter()
+                .map(|output| TxOut {
+                    value: Amount::from_sat(output.amount),
+                    script_pubkey: ScriptBuf::from(output.destination_spk.clone()),
+                })
+                .collect(),
+        };
+        let prevouts = inputs
+            .iter()
+            .map(|input| TxOut {
+                value: Amount::from_sat(input.amount),
+                script_pubkey: ScriptBuf::from(input.script_pubkey.clone()),
+            })
+            .collect();
+        (tx, prevouts)
+    }
+
+    #[test]
+    fn serialize_correct_size() {
+        let inputs = vec![ecdsa_input(0), schnorr_input(1)];
+        let outputs = vec![output(90_000, p2tr_spk(0xaa))];
+        let payload = serialize_stream_payload_v2(2, 0, &inputs, &outputs).unwrap();
+        assert_eq!(payload.len(), payload_size_v2(2, 1));
+        assert_eq!(payload.len(), 20 + 2 * 172 + 66);
+    }
+
+    #[test]
+    fn serialize_taproot_input_record() {
+        let input = SignStreamInputData {
+            tap_merkle_root: vec![0x77; 32],
+            ..schnorr_input(3)
+        };
+        let outputs = vec![output(90_000, p2wpkh_spk(0xaa))];
+        let payload = serialize_stream_payload_v2(2, 0, &[input], &outputs).unwrap();
+        let record = &payload[HEADER_SIZE..HEADER_SIZE + INPUT_RECORD_SIZE];
+
+        assert_eq!(&record[0..32], &[3u8; 32]);
+        assert_eq!(record[48], 34);
+        assert_eq!(record[49..83], p2tr_spk(0xdd)[..]);
+        assert_eq!(record[83..84], [0]); // padding
+        assert_eq!(record[84], 1); // Schnorr
+        assert_eq!(record[85], SIGHASH_DEFAULT);
+        assert_eq!(record[86], 5);
+        assert_eq!(
+            u32::from_le_bytes(record[87..91].try_into().unwrap()),
+            86 | (1 << 31)
+        );
+        assert_eq!(&record[107..139], &[0xee; 32]);
+        assert_eq!(record[139], 1);
+        assert_eq!(&record[140..172], &[0x77; 32]);
+    }
+
+    #[test]
+    fn payload_reproduces_sighashes() {
+        let inputs = vec![
+            ecdsa_input(0),
+            schnorr_input(1),
+            SignStreamInputData {
+                sighash_type: SIGHASH_ALL,
+                amount: 1_234,
+                ..schnorr_input(2)
+            },
+        ];
+        let outputs = vec![
+            output(90_000, p2tr_spk(0xaa)),
+            output(9_000, p2wpkh_spk(0xbb)),
+        ];
+        let payload = serialize_stream_payload_v2(2, 800_000, &inputs, &outputs).unwrap();
+        let parsed = parse(&payload);
+
+        let (tx, prevouts) = transaction(&inputs, &outputs);
+        assert_eq!(
+            serialize(&tx.input[0].previous_output),
+            parsed.inputs[0].outpoint
+        );
+        let mut cache = SighashCache::new(&tx);
+
+        let expected = cache
+            .p2wpkh_signature_hash(
+                0,
+                &prevouts[0].script_pubkey,
+                prevouts[0].value,
+                EcdsaSighashType::All,
+            )
+            .unwrap();
+        assert_eq!(parsed.bip143(0), expected.to_byte_array());
+
+        for (index, sighash_type) in [(1, TapSighashType::Default), (2, TapSighashType::All)] {
+            let expected = cache
+                .taproot_key_spend_signature_hash(index, &Prevouts::All(&prevouts), sighash_type)
+                .unwrap();
+            assert_eq!(parsed.bip341(index), expected.to_byte_array());
+        }
+    }
+
+    #[test]
+    fn rejects_mismatched_signature_type() {
+        let outputs = vec![output(90_000, p2wpkh_spk(0xaa))];
+        let inputs = [
+            SignStreamInputData {
+                signature_type: SignatureType::Schnorr,
+                ..ecdsa_input(0)
+            },
+            SignStreamInputData {
+                signature_type: SignatureType::Ecdsa,
+                sighash_type: SIGHASH_ALL,
+                ..schnorr_input(0)
+            },
+        ];
+        for input in inputs {
+            assert!(serialize_stream_payload_v2(2, 0, &[input], &outputs).is_err());
+        }
+    }
+
+    #[test]
+    fn rejects_partial_sighash_types() {
+        let outputs = vec![output(90_000, p2wpkh_spk(0xaa))];
+        for sighash_type in [0x02, 0x03, 0x81] {
+            let input = SignStreamInputData {
+                sighash_type,
+                ..schnorr_input(0)
+            };
+            assert!(serialize_stream_payload_v2(2, 0, &[input], &outputs).is_err());
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_taproot_fields() {
+        let outputs = vec![output(90_000, p2wpkh_spk(0xaa))];
+        let inputs = [
+            SignStreamInputData {
+                tap_internal_key: vec![0xee; 33],
+                ..schnorr_input(0)
+            },
+            SignStreamInputData {
+                tap_merkle_root: vec![0x77; 31],
+                ..schnorr_input(0)
+            },
+            SignStreamInputData {
+                derivation_path: vec![0; 6],
+                ..schnorr_input(0)
+            },
+        ];
+        for input in inputs {
+            assert!(serialize_stream_payload_v2(2, 0, &[input], &outputs).is_err());
+        }
+    }
+}
@@ -5,7 +5,7 @@ use std::result::Result;
 
 use crate::{
     command_interface::command,
-    commands::SignedSighash,
+    commands::{SignedSighash, TransactionVerificationCheck},
     errors::CommandError,
     signing::{derived::DerivedKeySigner, sign, Signer},
     yield_from_,
@@ -18,7 +18,13 @@ fn sign_transaction(
     mut psbt: PartiallySignedTransaction,
     origin_fingerprint: Fingerprint,
     async_sign: bool,
+    verification: Option<TransactionVerificationCheck>,
 ) -> Result<PartiallySignedTransaction, CommandError> {
+    // Check the grant before the hardware sees anything, so a bad grant never reaches it.
+    if let Some(verification) = &verification {
+        verification.check(&psbt)?;
+    }
+
     let derived_signables = DerivedKeySigner::new(origin_fingerprint).signables_for(&mut psbt)?;
     if derived_signables.is_empty() {
         return Err(CommandError::InvalidArguments);
@@ -42,5 +48,80 @@ fn sign_transaction(
 command!(SignTransaction = sign_transaction -> PartiallySignedTransaction,
     psbt: PartiallySignedTransaction,
     origin_fingerprint: Fingerprint,
-    async_sign: bool
+    async_sign: bool,
+    verification: Option<TransactionVerificationCheck>
 );
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::str::FromStr;
+
+    use bitcoin::secp256k1::{Message, PublicKey, SecretKey};
+    use bitcoin::{absolute, transaction, Transaction};
+    use wsm_grant::tx_verification::calculate_chained_sighashes;
+
+    use super::*;
+    use crate::command_interface::Command;
+    use crate::commands::{TransactionVerificationGrant, TX_VERIFICATION_V1};
+    use crate::fwpb::BtcNetwork;
+
+    fn cosigned_psbt() -> PartiallySignedTransaction {
+        let secp = Secp256k1::new();
+        let secret_key = SecretKey::from_slice(&[9u8; 32]).unwrap();
+        let public_key = bitcoin::PublicKey::new(secret_key.public_key(&secp));
+        let signature = bitcoin::ecdsa::Signature::sighash_all(
+            secp.sign_ecdsa(&Message::from_digest([5u8; 32]), &secret_key),
+        );
+
+        let mut psbt = PartiallySignedTransaction::from_unsigned_tx(Transaction {
+            version: transaction::Version::TWO,
+            lock_time: absolute::LockTime::ZERO,
+            input: vec![Default::default()],
+            output: vec![],
+        })
+        .unwrap();
+        psbt.inputs[0].partial_sigs = BTreeMap::from([(public_key, signature)]);
+        psbt
+    }
+
+    #[test]
+    fn bad_grant_is_rejected_before_signing() {
+        let secp = Secp256k1::new();
+        let psbt = cosigned_psbt();
+        let reverse_hash_chain =
+            calculate_chained_sighashes(psbt.inputs.clone(), [3u8; 32]).unwrap();
+        let hw_auth_public_key = PublicKey::from_str(
+            "0326cb04015410966e715a14da549bacbf12acb823fe1247540b6123b2daea0164",
+        )
+        .unwrap();
+
+        // Signed by a key other than the WSM integrity key
+        let forger = SecretKey::from_slice(&[2u8; 32]).unwrap();
+        let message = wsm_grant::tx_verification::generate_message(
+            hw_auth_public_key,
+            reverse_hash_chain[0].clone(),
+        )
+        .unwrap();
+        let verification = TransactionVerificationCheck {
+            grant: TransactionVerificationGrant {
+                version: TX_VERIFICATION_V1,
+                hw_auth_public_key,
+                commitment: reverse_hash_chain[0].clone(),
+                reverse_hash_chain,
+                signature: secp.sign_ecdsa(&message, &forger),
+                envelope: None,
+            },
+            wik_public_key: SecretKey::from_slice(&[1u8; 32]).unwrap().public_key(&secp),
+            hardware_serial: "000WS27100000000".to_string(),
+            network: BtcNetwork::Bitcoin,
+            now: 1_700_000_000,
+        };
+
+        let command = SignTransaction::new(psbt, Fingerprint::default(), false, Some(verification));
+        assert!(matches!(
+            command.next(Vec::default()),
+            Err(CommandError::SignatureInvalid)
+        ));
+    }
+}
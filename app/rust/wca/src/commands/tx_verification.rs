@@ -0,0 +1,244 @@
+use bitcoin::psbt::Psbt;
+use bitcoin::secp256k1::{ecdsa::Signature, PublicKey};
+use bitcoin::Network;
+use wsm_grant::tx_verification::{calculate_chained_sighashes, TxVerificationApproval};
+
+use crate::errors::CommandError;
+use crate::fwpb::BtcNetwork;
+
+pub use wsm_grant::tx_verification::{
+    TxVerificationExpectations, TX_VERIFICATION_V1, TX_VERIFICATION_V1_ACCEPTED_UNTIL,
+    TX_VERIFICATION_V2,
+};
+
+/// A transaction verification grant issued by the WSM for a PSBT, as handed to the app.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransactionVerificationGrant {
+    pub version: u8,
+    pub hw_auth_public_key: PublicKey,
+    pub commitment: Vec<u8>,
+    pub reverse_hash_chain: Vec<Vec<u8>>,
+    pub signature: Signature,
+    /// The serialized grant envelope the signature covers. `None` for version 1 grants.
+    pub envelope: Option<Vec<u8>>,
+}
+
+impl TransactionVerificationGrant {
+    fn approval(&self) -> Result<TxVerificationApproval, CommandError> {
+        TxVerificationApproval::from_parts(
+            self.version,
+            self.hw_auth_public_key,
+            &self.commitment,
+            self.envelope.as_deref(),
+        )
+        .map_err(|_| CommandError::InvalidArguments)
+    }
+
+    /// The digest the WSM integrity key signed.
+    pub fn message(&self) -> Result<[u8; 32], CommandError> {
+        let message = self
+            .approval()?
+            .message()
+            .map_err(|_| CommandError::InvalidArguments)?;
+        Ok(*message.as_ref())
+    }
+
+    /// Checks that this grant covers `psbt` and was signed by the WSM integrity key for the
+    /// expected hardware, before the PSBT is streamed to the hardware for signing.
+    pub fn check(
+        &self,
+        psbt: &Psbt,
+        wik_public_key: &PublicKey,
+        expected: &TxVerificationExpectations,
+    ) -> Result<(), CommandError> {
+        let approval = self.approval()?;
+
+        let init: [u8; 32] = self
+            .reverse_hash_chain
+            .last()
+            .and_then(|init| init.as_slice().try_into().ok())
+            .ok_or(CommandError::InvalidArguments)?;
+        let reverse_hash_chain = calculate_chained_sighashes(psbt.inputs.clone(), init)
+            .map_err(|_| CommandError::InvalidArguments)?;
+        if reverse_hash_chain.first() != Some(&self.commitment) {
+            return Err(CommandError::SignatureInvalid);
+        }
+
+        approval
+            .verify(&self.signature, wik_public_key, expected)
+            .map_err(|_| CommandError::SignatureInvalid)
+    }
+}
+
+/// A grant to check before signing, and what it must be bound to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransactionVerificationCheck {
+    pub grant: TransactionVerificationGrant,
+    pub wik_public_key: PublicKey,
+    /// The attested serial of the hardware about to sign.
+    pub hardware_serial: String,
+    pub network: BtcNetwork,
+    /// Seconds since the Unix epoch.
+    pub now: u64,
+}
+
+impl TransactionVerificationCheck {
+    pub fn check(&self, psbt: &Psbt) -> Result<(), CommandError> {
+        let expected = TxVerificationExpectations {
+            hardware_serial: &self.hardware_serial,
+            network: Network::from(self.network),
+            now: self.now,
+            v1_accepted_until: TX_VERIFICATION_V1_ACCEPTED_UNTIL,
+        };
+        self.grant.check(psbt, &self.wik_public_key, &expected)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::str::FromStr;
+
+    use bitcoin::secp256k1::{Message, Secp256k1, SecretKey};
+    use bitcoin::{absolute, transaction, Network, Transaction};
+
+    use super::*;
+
+    const HARDWARE_SERIAL: &str = "000WS27100000000";
+    const NOW: u64 = 1_700_000_000;
+
+    fn signed_psbt() -> Psbt {
+        let secp = Secp256k1::new();
+        let secret_key = SecretKey::from_slice(&[9u8; 32]).unwrap();
+        let public_key = bitcoin::PublicKey::new(secret_key.public_key(&secp));
+        let signature = bitcoin::ecdsa::Signature::sighash_all(
+            secp.sign_ecdsa(&Message::from_digest([5u8; 32]), &secret_key),
+        );
+
+        let mut psbt = Psbt::from_unsigned_tx(Transaction {
+            version: transaction::Version::TWO,
+            lock_time: absolute::LockTime::ZERO,
+            input: vec![Default::default()],
+            output: vec![],
+        })
+        .unwrap();
+        psbt.inputs[0].partial_sigs = BTreeMap::from([(public_key, signature)]);
+        psbt
+    }
+
+    fn grant_for(psbt: &Psbt, wik_private_key: &SecretKey) -> TransactionVerificationGrant {
+        let hw_auth_public_key = PublicKey::from_str(
+            "0326cb04015410966e715a14da549bacbf12acb823fe1247540b6123b2daea0164",
+        )
+        .unwrap();
+        let reverse_hash_chain =
+            calculate_chained_sighashes(psbt.inputs.clone(), [3u8; 32]).unwrap();
+        let approval = TxVerificationApproval::v2(
+            hw_auth_public_key,
+            &reverse_hash_chain[0],
+            HARDWARE_SERIAL,
+            Network::Bitcoin,
+            [4u8; 16],
+            NOW + 60,
+        )
+        .unwrap();
+        TransactionVerificationGrant {
+            version: approval.version(),
+            hw_auth_public_key,
+            commitment: approval.commitment(),
+            reverse_hash_chain,
+            signature: approval.sign(wik_private_key).unwrap(),
+            envelope: approval.envelope().unwrap(),
+        }
+    }
+
+    fn expectations() -> TxVerificationExpectations<'static> {
+        TxVerificationExpectations {
+            hardware_serial: HARDWARE_SERIAL,
+            network: Network::Bitcoin,
+            now: NOW,
+            v1_accepted_until: TX_VERIFICATION_V1_ACCEPTED_UNTIL,
+        }
+    }
+
+    #[test]
+    fn check_accepts_grant_for_psbt() {
+        let secp = Secp256k1::new();
+        let wik_private_key = SecretKey::from_slice(&[1u8; 32]).unwrap();
+        let wik_public_key = wik_private_key.public_key(&secp);
+        let psbt = signed_psbt();
+        let grant = grant_for(&psbt, &wik_private_key);
+
+        assert_eq!(grant.version, TX_VERIFICATION_V2);
+        assert!(grant.check(&psbt, &wik_public_key, &expectations()).is_ok());
+    }
+
+    #[test]
+    fn check_rejects_grant_for_other_hardware_or_psbt() {
+        let secp = Secp256k1::new();
+        let wik_private_key = SecretKey::from_slice(&[1u8; 32]).unwrap();
+        let wik_public_key = wik_private_key.public_key(&secp);
+        let psbt = signed_psbt();
+        let grant = grant_for(&psbt, &wik_private_key);
+
+        let other_hardware = TxVerificationExpectations {
+            hardware_serial: "000WS27100000001",
+            ..expectations()
+        };
+        assert!(matches!(
+            grant.check(&psbt, &wik_public_key, &other_hardware),
+            Err(CommandError::SignatureInvalid)
+        ));
+
+        let expired = TxVerificationExpectations {
+            now: NOW + 60,
+            ..expectations()
+        };
+        assert!(matches!(
+            grant.check(&psbt, &wik_public_key, &expired),
+            Err(CommandError::SignatureInvalid)
+        ));
+
+        let mut other_psbt = psbt.clone();
+        let (public_key, _) = psbt.inputs[0].partial_sigs.first_key_value().unwrap();
+        let other_signature = bitcoin::ecdsa::Signature::sighash_all(secp.sign_ecdsa(
+            &Message::from_digest([6u8; 32]),
+            &SecretKey::from_slice(&[9u8; 32]).unwrap(),
+        ));
+        other_psbt.inputs[0].partial_sigs = BTreeMap::from([(*public_key, other_signature)]);
+        assert!(matches!(
+            grant.check(&other_psbt, &wik_public_key, &expectations()),
+            Err(CommandError::SignatureInvalid)
+        ));
+    }
+
+    #[test]
+    fn check_rejects_grant_without_matching_envelope() {
+        let secp = Secp256k1::new();
+        let wik_private_key = SecretKey::from_slice(&[1u8; 32]).unwrap();
+        let wik_public_key = wik_private_key.public_key(&secp);
+        let psbt = signed_psbt();
+        let grant = grant_for(&psbt, &wik_private_key);
+
+        let downgraded = TransactionVerificationGrant {
+            version: TX_VERIFICATION_V1,
+            envelope: None,
+            ..grant.clone()
+        };
+        assert!(matches!(
+            downgraded.check(&psbt, &wik_public_key, &expectations()),
+            Err(CommandError::SignatureInvalid)
+        ));
+
+        let mut envelope = grant.envelope.clone().unwrap();
+        let last = envelope.len() - 1;
+        envelope[last] ^= 1;
+        let tampered = TransactionVerificationGrant {
+            envelope: Some(envelope),
+            ..grant
+        };
+        assert!(tampered
+            .check(&psbt, &wik_public_key, &expectations())
+            .is_err());
+    }
+}
@@ -1,3 +1,5 @@
+#[cfg(feature = "async")]
+pub mod async_transactor;
 pub mod attestation;
 pub mod command_interface;
 pub mod commands;
@@ -8,6 +10,10 @@ pub mod secure_channel;
 #[cfg(feature = "pcsc")]
 pub mod pcsc;
 pub mod signing;
+#[cfg(feature = "socket")]
+pub mod socket;
+#[cfg(feature = "transcript")]
+pub mod transcript;
 mod wca;
 
 use std::{
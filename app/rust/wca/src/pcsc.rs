@@ -9,6 +9,16 @@ pub trait Transactor: Send + Sync {
     fn reset(&mut self) -> Result<(), pcsc::Error>;
 }
 
+impl<T: Transactor + ?Sized> Transactor for Box<T> {
+    fn transmit(&self, buffer: &[u8]) -> Result<Vec<u8>, pcsc::Error> {
+        (**self).transmit(buffer)
+    }
+
+    fn reset(&mut self) -> Result<(), pcsc::Error> {
+        (**self).reset()
+    }
+}
+
 pub trait Performer<T: Transactor + ?Sized> {
     fn perform<V, E>(&self, command: impl Command<V, E>) -> Result<V, TransactorError>
     where
@@ -52,6 +62,8 @@ pub enum TransactorError {
     ReaderNotFound,
     #[error("reader error")]
     ReaderError(#[from] pcsc::Error),
+    #[error("socket error")]
+    SocketError(#[from] std::io::Error),
     #[error("command error")]
     CommandError(#[from] CommandError),
 }
@@ -0,0 +1,219 @@
+//! A transactor that drives the firmware emulator (core-sim) through the emulator launcher's WCA
+//! proxy, so commands can run on machines without a card reader.
+//!
+//! The proxy forwards bytes verbatim between one TCP client at a time and core-sim's stdin/stdout,
+//! which speak typed frames: `[1-byte type][4-byte BE length][payload]`. WCA frames carry a raw
+//! APDU and its response, status word included. Control frames drive emulator state that has no
+//! APDU equivalent, like a finger touching the sensor.
+
+use std::{
+    io::{self, Read, Write},
+    net::{Shutdown, SocketAddr, TcpStream, ToSocketAddrs},
+    sync::Mutex,
+    time::Duration,
+};
+
+use crate::pcsc::{Transactor, TransactorError};
+
+/// Where the emulator launcher listens for WCA traffic.
+pub const DEFAULT_EMULATOR_ADDR: &str = "127.0.0.1:5000";
+
+/// How long to wait on the emulator before failing a transmission.
+const IO_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Upper bound on an incoming frame, well above core-sim's response buffer, so a desynchronised
+/// stream fails fast instead of allocating whatever length it happens to read.
+const MAX_FRAME_LEN: usize = 64 * 1024;
+
+const MSG_TYPE_WCA: u8 = 0x00;
+const MSG_TYPE_CONTROL: u8 = 0x01;
+
+// Control commands, as defined in core-sim's handler_emulator.h.
+const CONTROL_SET_AUTHENTICATED: u8 = 0x06;
+const CONTROL_RESET_EMULATOR: u8 = 0x07;
+const CONTROL_SIMULATE_FINGER_TOUCH: u8 = 0x20;
+
+pub struct SocketTransactor {
+    address: SocketAddr,
+    stream: Mutex<TcpStream>,
+}
+
+impl SocketTransactor {
+    pub fn connect(address: impl ToSocketAddrs) -> Result<Self, TransactorError> {
+        let stream = connect(address)?;
+        Ok(Self {
+            address: stream.peer_addr()?,
+            stream: Mutex::new(stream),
+        })
+    }
+
+    /// Marks the emulated device as unlocked (or locked), as if the user had authenticated.
+    pub fn set_authenticated(&self, authenticated: bool) -> Result<(), pcsc::Error> {
+        self.control(CONTROL_SET_AUTHENTICATED, &[authenticated.into()])
+    }
+
+    /// Deletes enrolled fingerprints and unlock state, and returns the emulator to instant auth.
+    pub fn reset_emulator(&self) -> Result<(), pcsc::Error> {
+        self.control(CONTROL_RESET_EMULATOR, &[])
+    }
+
+    /// Places a finger on the emulated sensor and lifts it again.
+    pub fn simulate_finger_touch(&self) -> Result<(), pcsc::Error> {
+        self.control(CONTROL_SIMULATE_FINGER_TOUCH, &[])
+    }
+
+    fn control(&self, command: u8, payload: &[u8]) -> Result<(), pcsc::Error> {
+        let request = [&[command][..], payload].concat();
+        match self.exchange(MSG_TYPE_CONTROL, &request)?.as_slice() {
+            [1] => Ok(()),
+            _ => Err(pcsc::Error::CommError),
+        }
+    }
+
+    fn exchange(&self, msg_type: u8, payload: &[u8]) -> Result<Vec<u8>, pcsc::Error> {
+        let mut stream = self.stream.lock().map_err(|_| pcsc::Error::InternalError)?;
+        write_frame(&mut *stream, msg_type, payload).map_err(to_pcsc_error)?;
+        match read_frame(&mut *stream).map_err(to_pcsc_error)? {
+            (response_type, response) if response_type == msg_type => Ok(response),
+            _ => Err(pcsc::Error::CommError),
+        }
+    }
+}
+
+impl Transactor for SocketTransactor {
+    fn transmit(&self, buffer: &[u8]) -> Result<Vec<u8>, pcsc::Error> {
+        self.exchange(MSG_TYPE_WCA, buffer)
+    }
+
+    fn reset(&mut self) -> Result<(), pcsc::Error> {
+        let stream = self
+            .stream
+            .get_mut()
+            .map_err(|_| pcsc::Error::InternalError)?;
+        // The proxy serves one client at a time, so hang up before reconnecting.
+        let _ = stream.shutdown(Shutdown::Both);
+        *stream = connect(self.address).map_err(to_pcsc_error)?;
+        Ok(())
+    }
+}
+
+fn connect(address: impl ToSocketAddrs) -> io::Result<TcpStream> {
+    let stream = TcpStream::connect(address)?;
+    stream.set_nodelay(true)?;
+    stream.set_read_timeout(Some(IO_TIMEOUT))?;
+    stream.set_write_timeout(Some(IO_TIMEOUT))?;
+    Ok(stream)
+}
+
+fn write_frame(writer: &mut impl Write, msg_type: u8, payload: &[u8]) -> io::Result<()> {
+    let len = u32::try_from(payload.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "frame too large"))?;
+    writer.write_all(&[&[msg_type][..], &len.to_be_bytes()[..], payload].concat())?;
+    writer.flush()
+}
+
+fn read_frame(reader: &mut impl Read) -> io::Result<(u8, Vec<u8>)> {
+    let mut header = [0; 5];
+    reader.read_exact(&mut header)?;
+    let [msg_type, len @ ..] = header;
+    let len = u32::from_be_bytes(len) as usize;
+    if len > MAX_FRAME_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("frame of {len} bytes exceeds {MAX_FRAME_LEN}"),
+        ));
+    }
+
+    let mut payload = vec![0; len];
+    reader.read_exact(&mut payload)?;
+    Ok((msg_type, payload))
+}
+
+fn to_pcsc_error(error: io::Error) -> pcsc::Error {
+    match error.kind() {
+        io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => pcsc::Error::Timeout,
+        io::ErrorKind::ConnectionRefused => pcsc::Error::NoService,
+        io::ErrorKind::UnexpectedEof
+        | io::ErrorKind::ConnectionReset
+        | io::ErrorKind::ConnectionAborted
+        | io::ErrorKind::BrokenPipe => pcsc::Error::ReaderUnavailable,
+        _ => pcsc::Error::CommError,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Cursor, net::TcpListener, thread};
+
+    use super::*;
+    use crate::{commands::Version, pcsc::Performer};
+
+    /// Serves `connections` clients in turn, answering every WCA version request with version 1
+    /// and acknowledging every control command.
+    fn fake_emulator(connections: usize) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            for stream in listener.incoming().take(connections) {
+                let mut stream = stream.unwrap();
+                while let Ok((msg_type, payload)) = read_frame(&mut stream) {
+                    let response = match (msg_type, payload.as_slice()) {
+                        (MSG_TYPE_WCA, [0x87, 0x74, ..]) => vec![0x00, 0x01, 0x90, 0x00],
+                        (MSG_TYPE_CONTROL, [_, ..]) => vec![1],
+                        _ => vec![0],
+                    };
+                    write_frame(&mut stream, msg_type, &response).unwrap();
+                }
+            }
+        });
+        address
+    }
+
+    #[test]
+    fn frame_round_trip() {
+        let mut buffer = vec![];
+        write_frame(&mut buffer, MSG_TYPE_WCA, &[0x87, 0x74, 0x00, 0x00]).unwrap();
+        assert_eq!(buffer, [0x00, 0, 0, 0, 4, 0x87, 0x74, 0x00, 0x00]);
+        assert_eq!(
+            read_frame(&mut Cursor::new(buffer)).unwrap(),
+            (MSG_TYPE_WCA, vec![0x87, 0x74, 0x00, 0x00])
+        );
+    }
+
+    #[test]
+    fn oversized_frame() {
+        let header = [MSG_TYPE_WCA, 0xff, 0xff, 0xff, 0xff];
+        assert_eq!(
+            read_frame(&mut Cursor::new(header)).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+    }
+
+    #[test]
+    fn perform_and_control() {
+        let transactor = SocketTransactor::connect(fake_emulator(1)).unwrap();
+        assert_eq!(transactor.perform(Version::new()).unwrap(), 1);
+        transactor.set_authenticated(true).unwrap();
+        transactor.simulate_finger_touch().unwrap();
+    }
+
+    #[test]
+    fn reset_reconnects() {
+        let mut transactor = SocketTransactor::connect(fake_emulator(2)).unwrap();
+        assert_eq!(transactor.perform(Version::new()).unwrap(), 1);
+        transactor.reset().unwrap();
+        assert_eq!(transactor.perform(Version::new()).unwrap(), 1);
+    }
+
+    #[test]
+    fn emulator_gone() {
+        let transactor = SocketTransactor::connect(fake_emulator(1)).unwrap();
+        transactor
+            .stream
+            .lock()
+            .unwrap()
+            .shutdown(Shutdown::Both)
+            .unwrap();
+        assert!(transactor.transmit(&[0x87, 0x74, 0x00, 0x00]).is_err());
+    }
+}
@@ -0,0 +1,311 @@
+use std::{fmt::Debug, fmt::Write, ops::Range};
+
+use prost::Message as _;
+
+use super::Exchange;
+use crate::{
+    fwpb::{
+        get_confirmation_result_rsp::Result as ConfirmationResult, wallet_cmd, wallet_rsp,
+        WalletCmd, WalletRsp,
+    },
+    wca::{WCA_INS_PROTO, WCA_INS_PROTO_CONTINUATION, WCA_INS_VERSION},
+};
+
+/// One protocol message: the exchanges carrying it, and the request reassembled from them.
+struct Message {
+    exchanges: Range<usize>,
+    request: Request,
+}
+
+enum Request {
+    Version,
+    Proto(Vec<u8>),
+    /// A proto command whose fragments stop before reaching its declared size.
+    Partial,
+    Other,
+}
+
+/// The instruction, P1/P2 and data field of a serialized command APDU.
+struct Apdu {
+    ins: u8,
+    p1p2: u16,
+    data: Range<usize>,
+}
+
+fn parse_apdu(apdu: &[u8]) -> Option<Apdu> {
+    let [_cla, ins, p1, p2, rest @ ..] = apdu else {
+        return None;
+    };
+    let data = match rest {
+        [] => apdu.len()..apdu.len(),
+        [0, _, _, ..] => 7..apdu.len(),
+        _ => 5..apdu.len(),
+    };
+    Some(Apdu {
+        ins: *ins,
+        p1p2: u16::from_be_bytes([*p1, *p2]),
+        data,
+    })
+}
+
+/// Groups commands into messages, joining proto fragments: the first carries the total size in
+/// P1/P2 and continuations follow until it is reached.
+fn messages<'a>(commands: impl IntoIterator<Item = &'a [u8]>) -> Vec<Message> {
+    let commands: Vec<&[u8]> = commands.into_iter().collect();
+    let mut messages = vec![];
+    // The first exchange, declared size and data so far of a proto command still being sent.
+    let mut pending: Option<(usize, usize, Vec<u8>)> = None;
+
+    for (index, command) in commands.iter().enumerate() {
+        let apdu = parse_apdu(command);
+        let fragment = match (&apdu, pending.take()) {
+            (Some(apdu), Some((start, size, mut data)))
+                if apdu.ins == WCA_INS_PROTO_CONTINUATION =>
+            {
+                data.extend_from_slice(&command[apdu.data.clone()]);
+                Some((start, size, data))
+            }
+            (_, partial) => {
+                if let Some((start, ..)) = partial {
+                    messages.push(Message {
+                        exchanges: start..index,
+                        request: Request::Partial,
+                    });
+                }
+                match &apdu {
+                    Some(apdu) if apdu.ins == WCA_INS_PROTO => {
+                        let data = command[apdu.data.clone()].to_vec();
+                        Some((index, apdu.p1p2.into(), data))
+                    }
+                    Some(apdu) if apdu.ins == WCA_INS_VERSION => {
+                        messages.push(Message {
+                            exchanges: index..index + 1,
+                            request: Request::Version,
+                        });
+                        None
+                    }
+                    _ => {
+                        messages.push(Message {
+                            exchanges: index..index + 1,
+                            request: Request::Other,
+                        });
+                        None
+                    }
+                }
+            }
+        };
+
+        match fragment {
+            Some((start, size, data)) if data.len() >= size => messages.push(Message {
+                exchanges: start..index + 1,
+                request: Request::Proto(data),
+            }),
+            fragment => pending = fragment,
+        }
+    }
+
+    if let Some((start, ..)) = pending {
+        messages.push(Message {
+            exchanges: start..commands.len(),
+            request: Request::Partial,
+        });
+    }
+    messages
+}
+
+/// Whether the last of `exchanges` completes a message, so the group can be redacted as a whole.
+pub(super) fn ends_message(exchanges: &[Exchange]) -> bool {
+    !matches!(
+        messages(exchanges.iter().map(|e| e.command.as_slice())).last(),
+        Some(Message {
+            request: Request::Partial,
+            ..
+        })
+    )
+}
+
+fn command_secrets(command: &WalletCmd) -> Vec<&[u8]> {
+    match &command.msg {
+        Some(wallet_cmd::Msg::SealCsekCmd(cmd)) => vec![cmd.unsealed_csek.as_slice()],
+        Some(wallet_cmd::Msg::SignChallengeAndSealSeksCmd(cmd)) => {
+            vec![cmd.unsealed_csek.as_slice(), cmd.unsealed_ssek.as_slice()]
+        }
+        Some(wallet_cmd::Msg::RecoveryAuthorizeLostHwCmd(cmd)) => {
+            vec![cmd.ddk_private_key.as_slice()]
+        }
+        Some(wallet_cmd::Msg::UpgradeAuthorizeW3Cmd(cmd)) => vec![cmd.ddk_private_key.as_slice()],
+        _ => vec![],
+    }
+}
+
+fn response_secrets(response: &WalletRsp) -> Vec<&[u8]> {
+    match &response.msg {
+        Some(wallet_rsp::Msg::UnsealCsekRsp(rsp)) => vec![rsp.unsealed_csek.as_slice()],
+        Some(wallet_rsp::Msg::EekRestorationUnsealSymmetricKeyRsp(rsp)) => {
+            vec![rsp.unsealed_key.as_slice()]
+        }
+        Some(wallet_rsp::Msg::FullAccountCloudBackupRestorationContinueRsp(rsp)) => {
+            vec![rsp.unsealed_csek.as_slice()]
+        }
+        Some(wallet_rsp::Msg::GetConfirmationResultRsp(rsp)) => match &rsp.result {
+            Some(ConfirmationResult::LostAppRecoverySsekRsp(rsp)) => {
+                vec![rsp.unsealed_ssek.as_slice()]
+            }
+            Some(ConfirmationResult::RecoveryAuthorizeLostAppResult(rsp)) => {
+                vec![
+                    rsp.unsealed_ddk_data.as_slice(),
+                    rsp.unsealed_ssek.as_slice(),
+                ]
+            }
+            Some(ConfirmationResult::UpgradeAuthorizeW3Result(rsp)) => {
+                vec![rsp.unsealed_ssek.as_slice()]
+            }
+            Some(ConfirmationResult::EekRestorationUnsealSymmetricKeyResult(rsp)) => {
+                vec![rsp.unsealed_key.as_slice()]
+            }
+            Some(ConfirmationResult::KeysetRepairUnsealSymmetricKeyResult(rsp)) => {
+                vec![rsp.unsealed_key.as_slice()]
+            }
+            _ => vec![],
+        },
+        _ => vec![],
+    }
+}
+
+/// Zeroes every occurrence of each secret in `bytes`. Zeroing in place keeps every length intact,
+/// so a redacted message still splits into the same APDUs and decodes to the same shape.
+fn scrub(bytes: &mut [u8], secrets: &[&[u8]]) -> bool {
+    let mut scrubbed = false;
+    for secret in secrets.iter().filter(|s| !s.is_empty()) {
+        let mut start = 0;
+        while let Some(offset) = bytes[start..]
+            .windows(secret.len())
+            .position(|window| window == *secret)
+        {
+            let at = start + offset;
+            bytes[at..at + secret.len()].fill(0);
+            start = at + secret.len();
+            scrubbed = true;
+        }
+    }
+    scrubbed
+}
+
+/// Redacts the secret fields of every command and response in `exchanges`.
+pub(super) fn redact(exchanges: &mut [Exchange]) {
+    for message in messages(exchanges.iter().map(|e| e.command.as_slice())) {
+        let Request::Proto(mut data) = message.request else {
+            continue;
+        };
+
+        if let Ok(command) = WalletCmd::decode(data.as_slice()) {
+            if scrub(&mut data, &command_secrets(&command)) {
+                // Write the scrubbed message back over the fragments it was reassembled from.
+                let mut rest = data.as_slice();
+                for exchange in &mut exchanges[message.exchanges.clone()] {
+                    if let Some(apdu) = parse_apdu(&exchange.command) {
+                        let (fragment, tail) = rest.split_at(apdu.data.len().min(rest.len()));
+                        exchange.command[apdu.data.start..apdu.data.start + fragment.len()]
+                            .copy_from_slice(fragment);
+                        rest = tail;
+                    }
+                }
+            }
+        }
+
+        // Earlier fragments are only acknowledged; the last one gets the response.
+        let response = &mut exchanges[message.exchanges.end - 1].response;
+        let data_len = response.len().saturating_sub(2);
+        let data = &mut response[..data_len];
+        if let Ok(decoded) = WalletRsp::decode(&*data) {
+            scrub(data, &response_secrets(&decoded));
+        }
+    }
+}
+
+/// Renders `exchanges` one message at a time, decoding proto commands and responses.
+///
+/// Command timestamps are left out, since they change with every recording.
+pub(super) fn render(exchanges: &[Exchange]) -> String {
+    let mut out = String::new();
+    for message in messages(exchanges.iter().map(|e| e.command.as_slice())) {
+        let group = &exchanges[message.exchanges.clone()];
+        let response = apdu::Response::from(group[group.len() - 1].response.clone());
+        let status = format!("{:02x}{:02x}", response.sw1, response.sw2);
+
+        match message.request {
+            Request::Version => {
+                writeln!(out, "> version").unwrap();
+                writeln!(out, "< {status} {}", hex::encode(&response.data)).unwrap();
+            }
+            Request::Proto(data) => {
+                let fragments = match group.len() {
+                    1 => String::new(),
+                    n => format!("[{n} APDUs] "),
+                };
+                match WalletCmd::decode(data.as_slice()) {
+                    Ok(command) => {
+                        let command = WalletCmd {
+                            timestamp: 0,
+                            ..command
+                        };
+                        writeln!(out, "> {fragments}{}", pretty(&command)).unwrap()
+                    }
+                    Err(_) => writeln!(out, "> {fragments}{}", hex::encode(&data)).unwrap(),
+                }
+                match WalletRsp::decode(response.data.as_slice()) {
+                    Ok(decoded) if !response.data.is_empty() => {
+                        writeln!(out, "< {status} {}", pretty(&decoded)).unwrap()
+                    }
+                    _ => writeln!(out, "< {status} {}", hex::encode(&response.data)).unwrap(),
+                }
+            }
+            Request::Partial | Request::Other => {
+                for exchange in group {
+                    writeln!(out, "> {}", hex::encode(&exchange.command)).unwrap();
+                    writeln!(out, "< {}", hex::encode(&exchange.response)).unwrap();
+                }
+            }
+        }
+    }
+    out
+}
+
+/// Pretty-prints `value`, keeping lists of numbers (mostly byte strings) on one line, where `{:#?}`
+/// would put every element on its own.
+fn pretty(value: &impl Debug) -> String {
+    let pretty = format!("{value:#?}");
+    let lines: Vec<&str> = pretty.lines().collect();
+    let is_number = |line: &str| {
+        line.trim()
+            .strip_suffix(',')
+            .is_some_and(|n| n.parse::<i64>().is_ok())
+    };
+
+    let mut out = vec![];
+    let mut index = 0;
+    while index < lines.len() {
+        let line = lines[index];
+        let items = lines[index + 1..]
+            .iter()
+            .take_while(|line| is_number(line))
+            .map(|item| item.trim().trim_end_matches(','))
+            .collect::<Vec<_>>();
+        let close = index + 1 + items.len();
+        match lines.get(close) {
+            Some(end)
+                if line.ends_with('[')
+                    && !items.is_empty()
+                    && end.trim_start().starts_with(']') =>
+            {
+                out.push(format!("{line}{}{}", items.join(", "), end.trim_start()));
+                index = close + 1;
+            }
+            _ => {
+                out.push(line.to_string());
+                index += 1;
+            }
+        }
+    }
+    out.join("\n")
+}
@@ -0,0 +1,91 @@
+/// Lines of unchanged context kept around each change.
+const CONTEXT: usize = 3;
+
+enum Line<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// A unified-style diff of `old` and `new`, or `None` if they are identical.
+pub(super) fn lines(old: &str, new: &str) -> Option<String> {
+    if old == new {
+        return None;
+    }
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    // lengths[i][j] is the length of the longest common subsequence of old[i..] and new[j..].
+    let mut lengths = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i][j] = match old[i] == new[j] {
+                true => lengths[i + 1][j + 1] + 1,
+                false => lengths[i + 1][j].max(lengths[i][j + 1]),
+            };
+        }
+    }
+
+    let mut edits = vec![];
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            edits.push(Line::Same(old[i]));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lengths[i + 1][j] >= lengths[i][j + 1]) {
+            edits.push(Line::Removed(old[i]));
+            i += 1;
+        } else {
+            edits.push(Line::Added(new[j]));
+            j += 1;
+        }
+    }
+
+    let changed: Vec<usize> = (0..edits.len())
+        .filter(|&index| !matches!(edits[index], Line::Same(_)))
+        .collect();
+    let near_change = |index: usize| {
+        changed
+            .iter()
+            .any(|&change| index + CONTEXT >= change && index <= change + CONTEXT)
+    };
+
+    let mut out = String::new();
+    let mut skipped = false;
+    for (index, edit) in edits.iter().enumerate() {
+        if !near_change(index) {
+            skipped = true;
+            continue;
+        }
+        if skipped && !out.is_empty() {
+            out.push_str("...\n");
+        }
+        skipped = false;
+        let (prefix, line) = match edit {
+            Line::Same(line) => (' ', line),
+            Line::Removed(line) => ('-', line),
+            Line::Added(line) => ('+', line),
+        };
+        out.push(prefix);
+        out.push_str(line);
+        out.push('\n');
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::lines;
+
+    #[test]
+    fn context_around_changes() {
+        let old = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\nk\n";
+        let new = "a\nB\nc\nd\ne\nf\ng\nh\ni\nj\nK\n";
+        assert_eq!(lines(old, old), None);
+        assert_eq!(
+            lines(old, new).unwrap(),
+            " a\n-b\n+B\n c\n d\n e\n...\n h\n i\n j\n-k\n+K\n"
+        );
+    }
+}
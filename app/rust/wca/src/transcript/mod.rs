@@ -0,0 +1,341 @@
+//! Recording and replaying of the APDUs exchanged while performing commands.
+//!
+//! A [`Recorder`] wraps a real or emulated transactor and keeps every exchange that passes through
+//! it. The resulting [`Transcript`] is saved as a versioned JSON file, with secret fields zeroed,
+//! and later served back by a [`Replayer`], which checks that each command sent matches the
+//! recording. Transcripts are compared by decoding the protobuf messages they carry, so a protocol
+//! change shows up as a diff of `fwpb` fields rather than of raw bytes.
+
+mod decode;
+mod diff;
+
+use std::{
+    fs, io,
+    path::Path,
+    sync::{Mutex, PoisonError},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::pcsc::Transactor;
+
+/// The transcript format written by this version of the crate.
+pub const TRANSCRIPT_VERSION: u32 = 1;
+
+#[derive(Debug, thiserror::Error)]
+pub enum TranscriptError {
+    #[error("could not access the transcript file")]
+    Io(#[from] io::Error),
+    #[error("malformed transcript")]
+    Malformed(#[from] serde_json::Error),
+    #[error("unsupported transcript version {0} (expected {TRANSCRIPT_VERSION})")]
+    UnsupportedVersion(u32),
+}
+
+/// One command APDU and the response it got, status word included.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Exchange {
+    #[serde(with = "hex_bytes")]
+    pub command: Vec<u8>,
+    #[serde(with = "hex_bytes")]
+    pub response: Vec<u8>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Transcript {
+    version: u32,
+    exchanges: Vec<Exchange>,
+}
+
+#[derive(Deserialize)]
+struct Header {
+    version: u32,
+}
+
+impl Transcript {
+    /// Builds a transcript from `exchanges`, redacting any secret they carry.
+    pub fn new(mut exchanges: Vec<Exchange>) -> Self {
+        decode::redact(&mut exchanges);
+        Self {
+            version: TRANSCRIPT_VERSION,
+            exchanges,
+        }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, TranscriptError> {
+        let contents = fs::read_to_string(path)?;
+        // Check the version first, so a future format is reported as such rather than as garbage.
+        let Header { version } = serde_json::from_str(&contents)?;
+        if version != TRANSCRIPT_VERSION {
+            return Err(TranscriptError::UnsupportedVersion(version));
+        }
+        Ok(serde_json::from_str(&contents)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), TranscriptError> {
+        let mut contents = serde_json::to_string_pretty(self)?;
+        contents.push('\n');
+        Ok(fs::write(path, contents)?)
+    }
+
+    pub fn exchanges(&self) -> &[Exchange] {
+        &self.exchanges
+    }
+
+    /// Renders the transcript one message per line pair, with proto messages decoded.
+    pub fn render(&self) -> String {
+        decode::render(&self.exchanges)
+    }
+
+    /// Describes how `newer` differs from this transcript, or `None` if they decode the same.
+    pub fn diff(&self, newer: &Transcript) -> Option<String> {
+        diff::lines(&self.render(), &newer.render())
+    }
+}
+
+/// Passes APDUs through to `transactor`, keeping a copy of every exchange.
+pub struct Recorder<T: Transactor> {
+    transactor: T,
+    exchanges: Mutex<Vec<Exchange>>,
+}
+
+impl<T: Transactor> Recorder<T> {
+    pub fn new(transactor: T) -> Self {
+        Self {
+            transactor,
+            exchanges: Mutex::new(vec![]),
+        }
+    }
+
+    /// The exchanges recorded so far, redacted.
+    pub fn transcript(&self) -> Transcript {
+        let exchanges = self
+            .exchanges
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        Transcript::new(exchanges.clone())
+    }
+}
+
+impl<T: Transactor> Transactor for Recorder<T> {
+    fn transmit(&self, buffer: &[u8]) -> Result<Vec<u8>, pcsc::Error> {
+        let response = self.transactor.transmit(buffer)?;
+        self.exchanges
+            .lock()
+            .map_err(|_| pcsc::Error::InternalError)?
+            .push(Exchange {
+                command: buffer.to_vec(),
+                response: response.clone(),
+            });
+        Ok(response)
+    }
+
+    fn reset(&mut self) -> Result<(), pcsc::Error> {
+        self.transactor.reset()
+    }
+}
+
+/// Answers APDUs from a transcript instead of a device.
+///
+/// Every command must match the recording: a mismatch, or running past the end of the transcript,
+/// panics with a decoded diff. Fragments of a proto command are checked together once the whole
+/// message is sent, since that is the unit secrets are redacted in.
+pub struct Replayer {
+    transcript: Transcript,
+    state: Mutex<ReplayState>,
+}
+
+#[derive(Default)]
+struct ReplayState {
+    /// Index of the first exchange not yet checked against the transcript.
+    position: usize,
+    /// Exchanges of the message being sent, with the live commands and the recorded responses.
+    pending: Vec<Exchange>,
+}
+
+impl Replayer {
+    pub fn new(transcript: Transcript) -> Self {
+        Self {
+            transcript,
+            state: Mutex::default(),
+        }
+    }
+
+    /// How many recorded exchanges have not been replayed yet.
+    pub fn remaining(&self) -> usize {
+        let state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        self.transcript.exchanges.len() - state.position - state.pending.len()
+    }
+}
+
+impl Transactor for Replayer {
+    fn transmit(&self, buffer: &[u8]) -> Result<Vec<u8>, pcsc::Error> {
+        let mut state = self.state.lock().map_err(|_| pcsc::Error::InternalError)?;
+        let ReplayState { position, pending } = &mut *state;
+
+        let recorded = &self.transcript.exchanges[*position..];
+        let Some(next) = recorded.get(pending.len()) else {
+            panic!(
+                "transcript exhausted after {} exchanges; unexpected command:\n{}",
+                self.transcript.exchanges.len(),
+                Transcript::new(vec![Exchange {
+                    command: buffer.to_vec(),
+                    response: vec![],
+                }])
+                .render()
+            );
+        };
+        pending.push(Exchange {
+            command: buffer.to_vec(),
+            response: next.response.clone(),
+        });
+
+        if decode::ends_message(pending) {
+            let expected = Transcript::new(recorded[..pending.len()].to_vec());
+            let actual = Transcript::new(pending.clone());
+            if let Some(diff) = expected.diff(&actual) {
+                panic!("command does not match the transcript at exchange {position}:\n{diff}");
+            }
+            *position += pending.len();
+            pending.clear();
+        }
+        Ok(next.response.clone())
+    }
+
+    fn reset(&mut self) -> Result<(), pcsc::Error> {
+        Ok(())
+    }
+}
+
+/// Serializes bytes as a hex string, which keeps transcripts compact and readable.
+mod hex_bytes {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&hex::encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        hex::decode(String::deserialize(deserializer)?).map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use prost::Message as _;
+
+    use super::*;
+    use crate::{
+        commands::{GetAuthenticationKey, Version},
+        fwpb::{wallet_cmd, SealCsekCmd, WalletCmd},
+        pcsc::Performer,
+    };
+
+    fn version() -> Exchange {
+        Exchange {
+            command: vec![0x87, 0x74, 0x00, 0x00],
+            response: vec![0x00, 0x01, 0x90, 0x00],
+        }
+    }
+
+    /// A proto command APDU carrying `payload`, split as the wca encoder would.
+    fn proto(payload: &[u8], chunk: usize) -> Vec<Vec<u8>> {
+        let size = (payload.len() as u16).to_be_bytes();
+        payload
+            .chunks(chunk)
+            .enumerate()
+            .map(|(index, fragment)| {
+                let ins = if index == 0 { 0x75 } else { 0x77 };
+                let header = [0x87, ins, size[0], size[1], fragment.len() as u8];
+                [&header[..], fragment].concat()
+            })
+            .collect()
+    }
+
+    fn seal_csek(csek: &[u8]) -> Vec<u8> {
+        WalletCmd {
+            timestamp: 0,
+            msg: Some(wallet_cmd::Msg::SealCsekCmd(SealCsekCmd {
+                unsealed_csek: csek.to_vec(),
+                ..Default::default()
+            })),
+        }
+        .encode_to_vec()
+    }
+
+    #[test]
+    fn redacts_fragmented_secrets() {
+        let secret = [0xa5; 32];
+        let fragments = proto(&seal_csek(&secret), 16);
+        assert!(fragments.len() > 1);
+
+        let exchanges: Vec<_> = fragments
+            .into_iter()
+            .map(|command| Exchange {
+                command,
+                response: vec![0x90, 0x00],
+            })
+            .collect();
+        let transcript = Transcript::new(exchanges.clone());
+
+        let joined = |exchanges: &[Exchange]| {
+            exchanges
+                .iter()
+                .flat_map(|e| e.command[5..].to_vec())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(joined(transcript.exchanges()), seal_csek(&[0; 32]));
+        assert_eq!(
+            transcript.exchanges().len(),
+            exchanges.len(),
+            "redaction must keep the APDU framing"
+        );
+        assert!(transcript.render().contains("[3 APDUs]"));
+    }
+
+    #[test]
+    fn diff_decodes_messages() {
+        let old = Transcript::new(vec![version()]);
+        let mut changed = version();
+        changed.response = vec![0x00, 0x02, 0x90, 0x00];
+        let new = Transcript::new(vec![changed]);
+
+        assert_eq!(old.diff(&old.clone()), None);
+        let diff = old.diff(&new).unwrap();
+        assert!(diff.contains("-< 9000 0001"), "{diff}");
+        assert!(diff.contains("+< 9000 0002"), "{diff}");
+    }
+
+    #[test]
+    fn save_and_load() {
+        let path = std::env::temp_dir().join(format!("wca-transcript-{}.json", std::process::id()));
+        let transcript = Transcript::new(vec![version()]);
+        transcript.save(&path).unwrap();
+        assert_eq!(Transcript::load(&path).unwrap(), transcript);
+
+        fs::write(&path, r#"{"version": 99, "exchanges": {}}"#).unwrap();
+        assert!(matches!(
+            Transcript::load(&path),
+            Err(TranscriptError::UnsupportedVersion(99))
+        ));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn record_then_replay() {
+        let recorder = Recorder::new(Replayer::new(Transcript::new(vec![version()])));
+        assert_eq!(recorder.perform(Version::new()).unwrap(), 1);
+
+        let replayer = Replayer::new(recorder.transcript());
+        assert_eq!(replayer.remaining(), 1);
+        assert_eq!(replayer.perform(Version::new()).unwrap(), 1);
+        assert_eq!(replayer.remaining(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "does not match the transcript")]
+    fn replay_mismatch() {
+        let replayer = Replayer::new(Transcript::new(vec![version()]));
+        let _ = replayer.perform(GetAuthenticationKey::new());
+    }
+}
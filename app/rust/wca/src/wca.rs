@@ -5,9 +5,9 @@ use std::time::SystemTime;
 use crate::{errors::EncodeError, log_buffer::LogBuffer};
 
 const WCA_CLA: u8 = 0x87;
-const WCA_INS_VERSION: u8 = 0x74;
-const WCA_INS_PROTO: u8 = 0x75;
-const WCA_INS_PROTO_CONTINUATION: u8 = 0x77;
+pub(crate) const WCA_INS_VERSION: u8 = 0x74;
+pub(crate) const WCA_INS_PROTO: u8 = 0x75;
+pub(crate) const WCA_INS_PROTO_CONTINUATION: u8 = 0x77;
 const WCA_INS_GET_RESPONSE: u8 = 0x78;
 
 const MAX_WCA_BUFFER_SIZE: usize = 512;
@@ -1,3 +1,6 @@
-pub mod expectations;
+#[cfg(feature = "socket")]
+pub mod socket;
+#[cfg(feature = "transcript")]
+pub mod transcript;
 #[cfg(feature = "pcsc")]
-pub mod pcsc;
+pub mod wallet;
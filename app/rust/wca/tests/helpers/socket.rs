@@ -0,0 +1,22 @@
+use std::env;
+
+use wca::socket::SocketTransactor;
+
+/// Connects to the emulator at `WCA_EMULATOR_ADDR` (e.g. `127.0.0.1:5000`), reset and unlocked.
+/// Returns `None` when the variable is unset.
+pub fn try_emulator() -> Option<SocketTransactor> {
+    let address = env::var("WCA_EMULATOR_ADDR").ok()?;
+    let transactor = SocketTransactor::connect(address.as_str())
+        .unwrap_or_else(|e| panic!("Could not connect to the emulator at {address}: {e}"));
+    transactor.reset_emulator().expect("reset emulator");
+    transactor
+        .set_authenticated(true)
+        .expect("authenticate emulator");
+    Some(transactor)
+}
+
+/// Like [`try_emulator`], but panics when `WCA_EMULATOR_ADDR` is unset, so an emulator test run
+/// without an emulator fails instead of passing without checking anything.
+pub fn emulator() -> SocketTransactor {
+    try_emulator().expect("WCA_EMULATOR_ADDR must be set to run the emulator tests")
+}
@@ -0,0 +1,108 @@
+use std::{env, path::PathBuf, thread};
+
+use wca::{
+    pcsc::{PCSCTransactor, Transactor},
+    transcript::{Recorder, Replayer, Transcript},
+};
+
+/// Performs commands against `tests/transcripts/<name>.json`.
+///
+/// With `UPDATE_EXPECT` set, commands go to the emulator at `WCA_EMULATOR_ADDR` if there is one,
+/// or else to a PC/SC reader, and the transcript is rewritten when the transactor is dropped,
+/// printing how it changed. Otherwise the recorded transcript is replayed, and must be used up.
+pub enum TranscriptTransactor {
+    Recording {
+        path: PathBuf,
+        recorder: Recorder<Box<dyn Transactor>>,
+    },
+    Replaying {
+        path: PathBuf,
+        replayer: Replayer,
+    },
+}
+
+impl TranscriptTransactor {
+    pub fn new(name: &str) -> Self {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/transcripts")
+            .join(format!("{name}.json"));
+
+        if env::var_os("UPDATE_EXPECT").is_some() {
+            return Self::Recording {
+                path,
+                recorder: Recorder::new(device()),
+            };
+        }
+
+        let transcript = Transcript::load(&path).unwrap_or_else(|e| {
+            panic!(
+                "Could not load {}: {e}. Did you record it via `env UPDATE_EXPECT=1 cargo test`?",
+                path.display()
+            )
+        });
+        Self::Replaying {
+            path,
+            replayer: Replayer::new(transcript),
+        }
+    }
+}
+
+#[cfg(feature = "socket")]
+fn device() -> Box<dyn Transactor> {
+    match super::socket::try_emulator() {
+        Some(emulator) => Box::new(emulator),
+        None => Box::new(PCSCTransactor::new().expect("connect to a PC/SC reader")),
+    }
+}
+
+#[cfg(not(feature = "socket"))]
+fn device() -> Box<dyn Transactor> {
+    Box::new(PCSCTransactor::new().expect("connect to a PC/SC reader"))
+}
+
+impl Transactor for TranscriptTransactor {
+    fn transmit(&self, buffer: &[u8]) -> Result<Vec<u8>, pcsc::Error> {
+        match self {
+            Self::Recording { recorder, .. } => recorder.transmit(buffer),
+            Self::Replaying { replayer, .. } => replayer.transmit(buffer),
+        }
+    }
+
+    fn reset(&mut self) -> Result<(), pcsc::Error> {
+        match self {
+            Self::Recording { recorder, .. } => recorder.reset(),
+            Self::Replaying { replayer, .. } => replayer.reset(),
+        }
+    }
+}
+
+impl Drop for TranscriptTransactor {
+    fn drop(&mut self) {
+        // A failed test leaves a partial recording behind, and should not panic twice.
+        if thread::panicking() {
+            return;
+        }
+
+        match self {
+            Self::Recording { path, recorder } => {
+                let transcript = recorder.transcript();
+                match Transcript::load(&*path) {
+                    Ok(old) => match old.diff(&transcript) {
+                        Some(diff) => println!("{} changed:\n{diff}", path.display()),
+                        None => println!("{} is unchanged", path.display()),
+                    },
+                    Err(_) => println!("{} is new", path.display()),
+                }
+                transcript
+                    .save(&*path)
+                    .unwrap_or_else(|e| panic!("Could not save {}: {e}", path.display()));
+            }
+            Self::Replaying { path, replayer } => assert_eq!(
+                replayer.remaining(),
+                0,
+                "{} has exchanges the test did not perform",
+                path.display()
+            ),
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use std::str::FromStr;
+
+use bdk_wallet::{KeychainKind, Wallet};
+use bitcoin::{bip32::ChildNumber, psbt::Psbt as PartiallySignedTransaction, Amount};
+use miniscript::{descriptor::DescriptorXKey, Descriptor, DescriptorPublicKey};
+use wca::{
+    fwpb::BtcNetwork::Signet,
+    pcsc::{Performer, Transactor},
+};
+
+fn extend_descriptor_public_key(
+    origin: &DescriptorPublicKey,
+    path: &[ChildNumber],
+) -> DescriptorPublicKey {
+    match origin {
+        DescriptorPublicKey::Single(_) => unimplemented!(),
+        DescriptorPublicKey::MultiXPub(_) => unimplemented!(),
+        DescriptorPublicKey::XPub(xpub) => DescriptorPublicKey::XPub(DescriptorXKey {
+            derivation_path: xpub.derivation_path.extend(path),
+            origin: xpub.origin.clone(),
+            ..*xpub
+        }),
+    }
+}
+
+pub fn get_funded_wallet(base: &DescriptorPublicKey) -> Wallet {
+    let spending: DescriptorPublicKey =
+        extend_descriptor_public_key(base, &[ChildNumber::Normal { index: 0 }]);
+    let change: DescriptorPublicKey =
+        extend_descriptor_public_key(base, &[ChildNumber::Normal { index: 1 }]);
+    let descriptor = Descriptor::<DescriptorPublicKey>::new_wpkh(spending).unwrap();
+    let change_descriptor = Descriptor::<DescriptorPublicKey>::new_wpkh(change).unwrap();
+    let (wallet, _) = bdk_wallet::test_utils::get_funded_wallet(
+        &descriptor.to_string(),
+        &change_descriptor.to_string(),
+    );
+    wallet
+}
+
+pub fn normal_transaction(
+    from: &mut Wallet,
+    to: &mut Wallet,
+    amount: u64,
+) -> PartiallySignedTransaction {
+    let destination = to.reveal_next_address(KeychainKind::External);
+
+    let mut builder = from.build_tx();
+    builder
+        .add_recipient(destination.script_pubkey(), Amount::from_sat(amount))
+        .ordering(bdk_wallet::TxOrdering::Untouched); // A deterministic PSBT is nice for testing purposes.
+    builder.finish().unwrap()
+}
+
+pub fn drain_wallet(from: &mut Wallet, to: &mut Wallet) -> PartiallySignedTransaction {
+    let destination = to.reveal_next_address(KeychainKind::External);
+
+    let mut builder = from.build_tx();
+    builder.drain_wallet().drain_to(destination.script_pubkey());
+    builder.finish().unwrap()
+}
+
+fn is_finalized(psbt: &PartiallySignedTransaction) -> bool {
+    psbt.inputs
+        .iter()
+        .all(|input| input.final_script_sig.is_some() || input.final_script_witness.is_some())
+}
+
+/// Derives a source and a destination spending key, checking that each derivation is stable.
+pub fn derive_spending_keys(rt: &impl Transactor) -> (DescriptorPublicKey, DescriptorPublicKey) {
+    let source = {
+        let a = rt
+            .perform(wca::commands::GetInitialSpendingKey::new(Signet))
+            .unwrap();
+        let b = rt
+            .perform(wca::commands::GetInitialSpendingKey::new(Signet))
+            .unwrap();
+        assert_eq!(a, b);
+        DescriptorPublicKey::from_str(&a.dpub).unwrap()
+    };
+
+    let destination = {
+        let a = rt
+            .perform(wca::commands::GetNextSpendingKey::new(
+                vec![source.clone()],
+                Signet,
+            ))
+            .unwrap();
+        let b = rt
+            .perform(wca::commands::GetNextSpendingKey::new(
+                vec![source.clone()],
+                Signet,
+            ))
+            .unwrap();
+        assert_eq!(a, b);
+        DescriptorPublicKey::from_str(&a.dpub).unwrap()
+    };
+
+    assert_ne!(source, destination);
+    (source, destination)
+}
+
+/// Has the hardware sign `unsigned`, spending from `source_wallet`, and checks the result finalizes.
+pub fn sign_and_finalize(
+    rt: &impl Transactor,
+    source: &DescriptorPublicKey,
+    source_wallet: &Wallet,
+    unsigned: PartiallySignedTransaction,
+) {
+    let mut signed = rt
+        .perform(wca::commands::SignTransaction::new(
+            unsigned,
+            source.master_fingerprint(),
+            false,
+            None,
+        ))
+        .unwrap();
+    assert!(is_finalized(&signed));
+    let finalized = source_wallet
+        .finalize_psbt(&mut signed, Default::default())
+        .unwrap();
+    assert!(finalized);
+}
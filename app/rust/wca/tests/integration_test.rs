@@ -1,26 +1,30 @@
 mod helpers;
 
-#[cfg(feature = "pcsc")]
+#[cfg(feature = "transcript")]
 mod recordings {
-    use std::str::FromStr;
-
-    use bdk_wallet::{KeychainKind, Wallet};
     use bitcoin::{
-        bip32::ChildNumber,
         hashes::{sha256, Hash as _},
-        psbt::Psbt as PartiallySignedTransaction,
         secp256k1::{Message, Secp256k1},
-        Amount,
     };
-    use miniscript::{descriptor::DescriptorXKey, Descriptor, DescriptorPublicKey};
-    use wca::{fwpb::BtcNetwork::Signet, pcsc::Performer};
+    use wca::pcsc::Performer;
+
+    use crate::helpers::{
+        transcript::TranscriptTransactor,
+        wallet::{
+            derive_spending_keys, drain_wallet, get_funded_wallet, normal_transaction,
+            sign_and_finalize,
+        },
+    };
 
-    use crate::helpers::{expectations::Expectations, pcsc::RecordingTransactor};
+    #[test]
+    fn test_version() {
+        let rt = TranscriptTransactor::new("version");
+        rt.perform(wca::commands::Version::new()).unwrap();
+    }
 
     #[test]
     fn test_authentication() {
-        let expectations = Expectations::new("authentication");
-        let rt = RecordingTransactor::new(&expectations).unwrap();
+        let rt = TranscriptTransactor::new("authentication");
 
         let challenge = "0123456789abcdef".as_bytes();
         let message = Message::from_digest(sha256::Hash::hash(challenge).to_byte_array());
@@ -37,166 +41,175 @@ mod recordings {
             .unwrap();
     }
 
-    fn extend_descriptor_public_key(
-        origin: &DescriptorPublicKey,
-        path: &[ChildNumber],
-    ) -> DescriptorPublicKey {
-        match origin {
-            DescriptorPublicKey::Single(_) => unimplemented!(),
-            DescriptorPublicKey::MultiXPub(_) => unimplemented!(),
-            DescriptorPublicKey::XPub(xpub) => DescriptorPublicKey::XPub(DescriptorXKey {
-                derivation_path: xpub.derivation_path.extend(path),
-                origin: xpub.origin.clone(),
-                ..*xpub
-            }),
-        }
-    }
+    #[ignore]
+    #[test]
+    fn test_spending_derive() {
+        let rt = TranscriptTransactor::new("spending-derive");
 
-    fn get_funded_wallet(base: &DescriptorPublicKey) -> Wallet {
-        let spending: DescriptorPublicKey =
-            extend_descriptor_public_key(base, &[ChildNumber::Normal { index: 0 }]);
-        let change: DescriptorPublicKey =
-            extend_descriptor_public_key(base, &[ChildNumber::Normal { index: 1 }]);
-        let descriptor = Descriptor::<DescriptorPublicKey>::new_wpkh(spending).unwrap();
-        let change_descriptor = Descriptor::<DescriptorPublicKey>::new_wpkh(change).unwrap();
-        let (wallet, _) = bdk_wallet::test_utils::get_funded_wallet(
-            &descriptor.to_string(),
-            &change_descriptor.to_string(),
-        );
-        wallet
+        let (source, destination) = derive_spending_keys(&rt);
+        let mut source_wallet = get_funded_wallet(&source);
+        let mut destination_wallet = get_funded_wallet(&destination);
+        let unsigned = normal_transaction(&mut source_wallet, &mut destination_wallet, 5000);
+        sign_and_finalize(&rt, &source, &source_wallet, unsigned);
     }
 
-    fn normal_transaction(
-        from: &mut Wallet,
-        to: &mut Wallet,
-        amount: u64,
-    ) -> PartiallySignedTransaction {
-        let destination = to.reveal_next_address(KeychainKind::External);
-
-        let mut builder = from.build_tx();
-        builder
-            .add_recipient(destination.script_pubkey(), Amount::from_sat(amount))
-            .ordering(bdk_wallet::TxOrdering::Untouched); // A deterministic PSBT is nice for testing purposes.
-        builder.finish().unwrap()
+    #[ignore]
+    #[test]
+    fn test_drain_derive() {
+        let rt = TranscriptTransactor::new("drain-derive");
+
+        let (source, destination) = derive_spending_keys(&rt);
+        let mut source_wallet = get_funded_wallet(&source);
+        let mut destination_wallet = get_funded_wallet(&destination);
+        let unsigned = drain_wallet(&mut source_wallet, &mut destination_wallet);
+        sign_and_finalize(&rt, &source, &source_wallet, unsigned);
     }
+}
 
-    fn drain_wallet(from: &mut Wallet, to: &mut Wallet) -> PartiallySignedTransaction {
-        let destination = to.reveal_next_address(KeychainKind::External);
+#[cfg(feature = "socket")]
+mod emulator {
+    use std::{thread, time::Duration};
 
-        let mut builder = from.build_tx();
-        builder.drain_wallet().drain_to(destination.script_pubkey());
-        builder.finish().unwrap()
-    }
+    use bitcoin::{
+        hashes::{sha256, Hash as _},
+        secp256k1::{Message, Secp256k1},
+    };
+    use serial_test::serial;
+    use wca::{
+        commands::{
+            FingerprintEnrollmentStatus, FwupFinish, FwupFinishRspStatus, FwupMode, FwupStart,
+            FwupStartResult, FwupTransfer, GetEnrolledFingerprints, GetFingerprintEnrollmentStatus,
+            McuRole, StartFingerprintEnrollment, Version,
+        },
+        pcsc::Performer,
+    };
+
+    use crate::helpers::{
+        socket::emulator,
+        wallet::{
+            derive_spending_keys, drain_wallet, get_funded_wallet, normal_transaction,
+            sign_and_finalize,
+        },
+    };
+
+    // Divisible by both the EFR32 (4) and STM32U5 (8) write granularity.
+    const FWUP_CHUNK_SIZE: usize = 448;
+
+    #[test]
+    #[ignore = "needs an emulator at WCA_EMULATOR_ADDR"]
+    #[serial(emulator)]
+    fn test_version() {
+        let rt = emulator();
 
-    fn is_finalized(psbt: &PartiallySignedTransaction) -> bool {
-        psbt.inputs
-            .iter()
-            .all(|input| input.final_script_sig.is_some() || input.final_script_witness.is_some())
+        assert_eq!(rt.perform(Version::new()).unwrap(), 1);
     }
 
-    #[ignore]
     #[test]
-    fn test_spending_derive() {
-        let expectations = Expectations::new("spending-derive");
-        let rt = RecordingTransactor::new(&expectations).unwrap();
+    #[ignore = "needs an emulator at WCA_EMULATOR_ADDR"]
+    #[serial(emulator)]
+    fn test_authentication() {
+        let rt = emulator();
 
-        let source = {
-            let a = rt
-                .perform(wca::commands::GetInitialSpendingKey::new(Signet))
-                .unwrap();
-            let b = rt
-                .perform(wca::commands::GetInitialSpendingKey::new(Signet))
-                .unwrap();
-            assert_eq!(a, b);
-            miniscript::DescriptorPublicKey::from_str(&a.dpub).unwrap()
-        };
-
-        let destination = {
-            let a = rt
-                .perform(wca::commands::GetNextSpendingKey::new(
-                    vec![source.clone()],
-                    Signet,
-                ))
-                .unwrap();
-            let b = rt
-                .perform(wca::commands::GetNextSpendingKey::new(
-                    vec![source.clone()],
-                    Signet,
-                ))
-                .unwrap();
-            assert_eq!(a, b);
-            DescriptorPublicKey::from_str(&a.dpub).unwrap()
-        };
+        let challenge = "0123456789abcdef".as_bytes();
+        let message = Message::from_digest(sha256::Hash::hash(challenge).to_byte_array());
+
+        let authentication_key = rt
+            .perform(wca::commands::GetAuthenticationKey::new())
+            .unwrap();
+        let signature = rt
+            .perform(wca::commands::SignChallenge::new(challenge.to_vec(), false))
+            .unwrap();
+
+        Secp256k1::new()
+            .verify_ecdsa(&message, &signature, &authentication_key)
+            .unwrap();
+    }
 
-        assert_ne!(source, destination);
+    #[test]
+    #[ignore = "needs an emulator at WCA_EMULATOR_ADDR"]
+    #[serial(emulator)]
+    fn test_spending_derive() {
+        let rt = emulator();
 
+        let (source, destination) = derive_spending_keys(&rt);
         let mut source_wallet = get_funded_wallet(&source);
         let mut destination_wallet = get_funded_wallet(&destination);
         let unsigned = normal_transaction(&mut source_wallet, &mut destination_wallet, 5000);
-        let mut signed = rt
-            .perform(wca::commands::SignTransaction::new(
-                unsigned,
-                source.master_fingerprint(),
-                false,
-            ))
-            .unwrap();
-        assert!(is_finalized(&signed));
-        let finalized = source_wallet
-            .finalize_psbt(&mut signed, Default::default())
-            .unwrap();
-        assert!(finalized);
+        sign_and_finalize(&rt, &source, &source_wallet, unsigned);
     }
 
-    #[ignore]
     #[test]
+    #[ignore = "needs an emulator at WCA_EMULATOR_ADDR"]
+    #[serial(emulator)]
     fn test_drain_derive() {
-        let expectations = Expectations::new("drain-derive");
-        let rt = RecordingTransactor::new(&expectations).unwrap();
-
-        let source = {
-            let a = rt
-                .perform(wca::commands::GetInitialSpendingKey::new(Signet))
-                .unwrap();
-            let b = rt
-                .perform(wca::commands::GetInitialSpendingKey::new(Signet))
-                .unwrap();
-            assert_eq!(a, b);
-            miniscript::DescriptorPublicKey::from_str(&a.dpub).unwrap()
-        };
-
-        let destination = {
-            let a = rt
-                .perform(wca::commands::GetNextSpendingKey::new(
-                    vec![source.clone()],
-                    Signet,
-                ))
-                .unwrap();
-            let b = rt
-                .perform(wca::commands::GetNextSpendingKey::new(
-                    vec![source.clone()],
-                    Signet,
-                ))
-                .unwrap();
-            assert_eq!(a, b);
-            DescriptorPublicKey::from_str(&a.dpub).unwrap()
-        };
-
-        assert_ne!(source, destination);
+        let rt = emulator();
 
+        let (source, destination) = derive_spending_keys(&rt);
         let mut source_wallet = get_funded_wallet(&source);
         let mut destination_wallet = get_funded_wallet(&destination);
         let unsigned = drain_wallet(&mut source_wallet, &mut destination_wallet);
-        let mut signed = rt
-            .perform(wca::commands::SignTransaction::new(
-                unsigned,
-                source.master_fingerprint(),
+        sign_and_finalize(&rt, &source, &source_wallet, unsigned);
+    }
+
+    #[test]
+    #[ignore = "needs an emulator at WCA_EMULATOR_ADDR"]
+    #[serial(emulator)]
+    fn test_fingerprint_enrollment() {
+        let rt = emulator();
+
+        assert!(rt
+            .perform(StartFingerprintEnrollment::new(0, "test".to_string()))
+            .unwrap());
+
+        // The sensor runs on its own thread, so give each touch a moment to register.
+        let complete = (0..50).any(|_| {
+            rt.simulate_finger_touch().unwrap();
+            thread::sleep(Duration::from_millis(50));
+            let result = rt
+                .perform(GetFingerprintEnrollmentStatus::new(true))
+                .unwrap();
+            result.status == FingerprintEnrollmentStatus::Complete
+        });
+        assert!(complete, "enrollment did not complete");
+
+        let enrolled = rt.perform(GetEnrolledFingerprints::new()).unwrap();
+        assert!(enrolled.fingerprints.iter().any(|f| f.index == 0));
+    }
+
+    #[test]
+    #[ignore = "needs an emulator at WCA_EMULATOR_ADDR"]
+    #[serial(emulator)]
+    fn test_fwup() {
+        let rt = emulator();
+
+        let start = rt
+            .perform(FwupStart::new(
+                None,
+                FwupMode::Normal,
+                McuRole::Core,
+                "1.0.0".to_string(),
                 false,
             ))
             .unwrap();
-        assert!(is_finalized(&signed));
-        let finalized = source_wallet
-            .finalize_psbt(&mut signed, Default::default())
+        assert!(matches!(start, FwupStartResult::Success { value: true }));
+
+        let image = vec![0xa5; FWUP_CHUNK_SIZE * 3 + 16];
+        for (sequence_id, chunk) in image.chunks(FWUP_CHUNK_SIZE).enumerate() {
+            assert!(rt
+                .perform(FwupTransfer::new(
+                    sequence_id as u32,
+                    chunk.to_vec(),
+                    0,
+                    FwupMode::Normal,
+                    McuRole::Core,
+                ))
+                .unwrap());
+        }
+
+        // The image is unsigned, so the emulator has to refuse to apply it.
+        let status = rt
+            .perform(FwupFinish::new(0, 0, FwupMode::Normal, McuRole::Core))
             .unwrap();
-        assert!(finalized);
+        assert_ne!(status, FwupFinishRspStatus::Success);
     }
 }
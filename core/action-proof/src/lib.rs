@@ -2,6 +2,9 @@
 //!
 //! Provides canonical payload format for actions requiring cryptographic authorization.
 //! The canonical bytes (0x1F delimited) are signed by app and/or hardware keys.
+//! Version 2 payloads carry typed fields declared per action by [`Action::schema`].
+//! Each action also declares the signers it needs via [`Action::signer_policy`];
+//! [`ActionProofBundle`] checks a set of role-tagged signatures against it.
 //!
 //! # Example
 //!
@@ -70,14 +73,24 @@ macro_rules! define_enum {
 pub mod action;
 pub mod binding;
 pub mod payload;
+pub mod policy;
+pub mod render;
+pub mod schema;
 pub mod validation;
 
 pub use action::{Action, ContextBinding, ParseActionError, ValueFormat};
 pub use binding::compute_token_binding;
 pub use payload::{
-    build_payload, parse_payload, BuildError, ParseError, ParsedPayload, CANONICAL_MAGIC,
-    CANONICAL_VERSION, UNIT_SEPARATOR,
+    build_payload, build_payload_v2, parse_payload, BuildError, ParseError, ParsedPayload,
+    CANONICAL_MAGIC, CANONICAL_VERSION, CANONICAL_VERSION_V2, SUPPORTED_VERSIONS, UNIT_SEPARATOR,
 };
+pub use policy::{
+    ActionProofBundle, KeyRole, ParseKeyRoleError, PolicyError, RoleSignature, SignerPolicy,
+};
+pub use render::{
+    render_payload, DisplayLine, RenderPolicy, RenderedPayload, Truncation, W3_RENDER_POLICY,
+};
+pub use schema::{FieldSpec, FieldType, SchemaError};
 pub use validation::{
     is_valid_value, validate_if_present, validate_value, ValidationError, MAX_VALUE_LENGTH,
 };
@@ -1,14 +1,25 @@
 //! Canonical payload: `ACTIONPROOF␟1␟Action␟Value␟key1=val1,key2=val2`
+//!
+//! Version 2 replaces the value with typed fields declared by [`Action::schema`]:
+//! `ACTIONPROOF␟2␟Action␟name1=val1,name2=val2␟key1=val1,key2=val2`
+//!
+//! Free-text fields are prefixed with their length in bytes, so they may contain
+//! `=` and `,`: `name=12:Smith, Alice`.
 
 use crate::action::Action;
+use crate::schema::{fields_from_value, validate_fields, value_from_fields, SchemaError};
 use crate::validation::{validate_if_present, ValidationError};
 use thiserror::Error;
 
 pub const UNIT_SEPARATOR: u8 = 0x1F;
 pub const BINDING_KEY_VALUE_SEPARATOR: u8 = b'=';
 pub const BINDING_PAIR_SEPARATOR: u8 = b',';
+pub const FIELD_LENGTH_SEPARATOR: u8 = b':';
 pub const CANONICAL_VERSION: u8 = 1;
 pub const CANONICAL_VERSION_STR: &str = "1";
+pub const CANONICAL_VERSION_V2: u8 = 2;
+pub const CANONICAL_VERSION_V2_STR: &str = "2";
+pub const SUPPORTED_VERSIONS: &[u8] = &[CANONICAL_VERSION, CANONICAL_VERSION_V2];
 pub const CANONICAL_MAGIC: &str = "ACTIONPROOF";
 
 #[derive(Debug, Clone, PartialEq, Eq, Error)]
@@ -68,6 +79,10 @@ pub enum BuildError {
     DuplicateBindingKey(String),
     #[error("invalid value: {0}")]
     InvalidValue(#[from] ValidationError),
+    #[error("invalid fields: {0}")]
+    InvalidFields(#[from] SchemaError),
+    #[error("unsupported version: {0}")]
+    UnsupportedVersion(u8),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Error)]
@@ -76,7 +91,7 @@ pub enum ParseError {
     InvalidPartCount(usize),
     #[error("invalid magic: expected '{CANONICAL_MAGIC}', got '{0}'")]
     InvalidMagic(String),
-    #[error("unsupported version: expected {CANONICAL_VERSION}, got {0}")]
+    #[error("unsupported version: {0}")]
     UnsupportedVersion(u8),
     #[error("invalid action: '{0}'")]
     InvalidAction(String),
@@ -94,6 +109,8 @@ pub enum ParseError {
     NonCanonicalVersion(String),
     #[error("invalid value: {0}")]
     InvalidValue(String),
+    #[error("invalid fields: {0}")]
+    InvalidFields(#[from] SchemaError),
 }
 
 /// Bindings must be sorted alphabetically by key.
@@ -106,22 +123,55 @@ pub fn build_payload(
 
     validate_bindings(bindings)?;
 
+    let mut payload = start_payload(CANONICAL_VERSION_STR, action);
+
+    if let Some(v) = value {
+        payload.extend_from_slice(v.as_bytes());
+    }
+    payload.push(UNIT_SEPARATOR);
+
+    write_pairs(&mut payload, bindings);
+
+    Ok(payload)
+}
+
+/// Builds a version-2 payload. Fields must match [`Action::schema`] exactly and
+/// appear in schema order; bindings must be sorted alphabetically by key.
+pub fn build_payload_v2(
+    action: Action,
+    fields: &[(&str, &str)],
+    bindings: &[(&str, &str)],
+) -> Result<Vec<u8>, BuildError> {
+    validate_fields(action, fields)?;
+
+    validate_bindings(bindings)?;
+
+    let mut payload = start_payload(CANONICAL_VERSION_V2_STR, action);
+
+    write_fields(&mut payload, action, fields);
+    payload.push(UNIT_SEPARATOR);
+
+    write_pairs(&mut payload, bindings);
+
+    Ok(payload)
+}
+
+fn start_payload(version: &str, action: Action) -> Vec<u8> {
     let mut payload = Vec::with_capacity(256);
 
     payload.extend_from_slice(CANONICAL_MAGIC.as_bytes());
     payload.push(UNIT_SEPARATOR);
-    payload.extend_from_slice(CANONICAL_VERSION_STR.as_bytes());
+    payload.extend_from_slice(version.as_bytes());
     payload.push(UNIT_SEPARATOR);
 
     payload.extend_from_slice(action.as_str().as_bytes());
     payload.push(UNIT_SEPARATOR);
 
-    if let Some(v) = value {
-        payload.extend_from_slice(v.as_bytes());
-    }
-    payload.push(UNIT_SEPARATOR);
+    payload
+}
 
-    for (i, (key, val)) in bindings.iter().enumerate() {
+fn write_pairs(payload: &mut Vec<u8>, pairs: &[(&str, &str)]) {
+    for (i, (key, val)) in pairs.iter().enumerate() {
         if i > 0 {
             payload.push(BINDING_PAIR_SEPARATOR);
         }
@@ -129,8 +179,23 @@ pub fn build_payload(
         payload.push(BINDING_KEY_VALUE_SEPARATOR);
         payload.extend_from_slice(val.as_bytes());
     }
+}
 
-    Ok(payload)
+/// Like [`write_pairs`], but length-prefixes the values of free-text fields.
+/// `fields` must already be validated against the action's schema.
+fn write_fields(payload: &mut Vec<u8>, action: Action, fields: &[(&str, &str)]) {
+    for (i, ((name, val), spec)) in fields.iter().zip(action.schema()).enumerate() {
+        if i > 0 {
+            payload.push(BINDING_PAIR_SEPARATOR);
+        }
+        payload.extend_from_slice(name.as_bytes());
+        payload.push(BINDING_KEY_VALUE_SEPARATOR);
+        if spec.field_type.is_length_prefixed() {
+            payload.extend_from_slice(val.len().to_string().as_bytes());
+            payload.push(FIELD_LENGTH_SEPARATOR);
+        }
+        payload.extend_from_slice(val.as_bytes());
+    }
 }
 
 pub fn parse_payload(payload: &[u8]) -> Result<ParsedPayload<'_>, ParseError> {
@@ -148,15 +213,19 @@ pub fn parse_payload(payload: &[u8]) -> Result<ParsedPayload<'_>, ParseError> {
     let version_str =
         std::str::from_utf8(parts[1]).map_err(|_| ParseError::InvalidUtf8("version"))?;
     // Require exact canonical format: single ASCII digit, no leading zeros or whitespace
-    if version_str != CANONICAL_VERSION_STR {
-        return match version_str.parse::<u8>() {
-            Ok(v) if v == CANONICAL_VERSION => {
-                Err(ParseError::NonCanonicalVersion(version_str.to_string()))
-            }
-            Ok(v) => Err(ParseError::UnsupportedVersion(v)),
-            Err(_) => Err(ParseError::InvalidVersionFormat),
-        };
-    }
+    let version = match version_str {
+        CANONICAL_VERSION_STR => CANONICAL_VERSION,
+        CANONICAL_VERSION_V2_STR => CANONICAL_VERSION_V2,
+        _ => {
+            return match version_str.parse::<u8>() {
+                Ok(v) if SUPPORTED_VERSIONS.contains(&v) => {
+                    Err(ParseError::NonCanonicalVersion(version_str.to_string()))
+                }
+                Ok(v) => Err(ParseError::UnsupportedVersion(v)),
+                Err(_) => Err(ParseError::InvalidVersionFormat),
+            };
+        }
+    };
 
     let action_str =
         std::str::from_utf8(parts[2]).map_err(|_| ParseError::InvalidUtf8("action"))?;
@@ -165,31 +234,125 @@ pub fn parse_payload(payload: &[u8]) -> Result<ParsedPayload<'_>, ParseError> {
         .map_err(|_| ParseError::InvalidAction(action_str.to_string()))?;
 
     let value = std::str::from_utf8(parts[3]).map_err(|_| ParseError::InvalidUtf8("value"))?;
-    let value = (!value.is_empty()).then_some(value);
-
-    if let Some(v) = value {
-        crate::validation::validate_value(v)
-            .map_err(|e| ParseError::InvalidValue(e.to_string()))?;
-    }
+    let (value, fields) = if version == CANONICAL_VERSION {
+        let value = (!value.is_empty()).then_some(value);
+        if let Some(v) = value {
+            crate::validation::validate_value(v)
+                .map_err(|e| ParseError::InvalidValue(e.to_string()))?;
+        }
+        (value, Vec::new())
+    } else {
+        let fields = parse_fields(action, value)?;
+        validate_fields(action, &fields)?;
+        (None, fields)
+    };
 
     let bindings_str =
         std::str::from_utf8(parts[4]).map_err(|_| ParseError::InvalidUtf8("bindings"))?;
     let bindings = parse_bindings(bindings_str)?;
 
     Ok(ParsedPayload {
+        version,
         action,
         value,
+        fields,
         bindings,
     })
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ParsedPayload<'a> {
+    pub version: u8,
     pub action: Action,
+    /// Free-form value; only set for v1 payloads.
     pub value: Option<&'a str>,
+    /// Typed fields in schema order; only set for v2 payloads.
+    pub fields: Vec<(&'a str, &'a str)>,
     pub bindings: Vec<(&'a str, &'a str)>,
 }
 
+impl ParsedPayload<'_> {
+    /// Re-encodes this payload in the given canonical version.
+    ///
+    /// A v1 value converts to v2 only if it splits into the action's schema
+    /// (see [`fields_from_value`]); v2 always converts to v1.
+    pub fn to_version(&self, version: u8) -> Result<Vec<u8>, BuildError> {
+        match version {
+            CANONICAL_VERSION => {
+                let value = match self.version {
+                    CANONICAL_VERSION => self.value.map(str::to_string),
+                    _ => value_from_fields(&self.fields),
+                };
+                build_payload(self.action, value.as_deref(), &self.bindings)
+            }
+            CANONICAL_VERSION_V2 => match self.version {
+                CANONICAL_VERSION => {
+                    let fields = fields_from_value(self.action, self.value)?;
+                    build_payload_v2(self.action, &fields, &self.bindings)
+                }
+                _ => build_payload_v2(self.action, &self.fields, &self.bindings),
+            },
+            other => Err(BuildError::UnsupportedVersion(other)),
+        }
+    }
+}
+
+fn parse_fields(action: Action, s: &str) -> Result<Vec<(&str, &str)>, ParseError> {
+    let mut fields = Vec::new();
+    if s.is_empty() {
+        return Ok(fields);
+    }
+
+    let mut rest = s;
+    loop {
+        let invalid = || {
+            let pair = rest.split(BINDING_PAIR_SEPARATOR as char).next();
+            SchemaError::InvalidFieldFormat(pair.unwrap_or_default().to_string())
+        };
+        let (name, tail) = rest
+            .split_once(BINDING_KEY_VALUE_SEPARATOR as char)
+            .filter(|(name, _)| !name.contains(BINDING_PAIR_SEPARATOR as char))
+            .ok_or_else(invalid)?;
+
+        let length_prefixed = action
+            .schema()
+            .iter()
+            .any(|spec| spec.name == name && spec.field_type.is_length_prefixed());
+        let (value, next) = if length_prefixed {
+            split_length_prefixed(tail).ok_or_else(invalid)?
+        } else {
+            match tail.split_once(BINDING_PAIR_SEPARATOR as char) {
+                Some((value, next)) => (value, Some(next)),
+                None => (tail, None),
+            }
+        };
+        fields.push((name, value));
+
+        match next {
+            Some(next) => rest = next,
+            None => return Ok(fields),
+        }
+    }
+}
+
+/// Splits `len:value[,rest]`, where `len` is the value's length in bytes
+/// without leading zeros, into the value and whatever follows the separator.
+fn split_length_prefixed(s: &str) -> Option<(&str, Option<&str>)> {
+    let (len, s) = s.split_once(FIELD_LENGTH_SEPARATOR as char)?;
+    if !len.bytes().all(|b| b.is_ascii_digit()) || (len.len() > 1 && len.starts_with('0')) {
+        return None;
+    }
+    let len: usize = len.parse().ok()?;
+    let value = s.get(..len)?;
+    match &s[len..] {
+        "" => Some((value, None)),
+        rest => Some((
+            value,
+            Some(rest.strip_prefix(BINDING_PAIR_SEPARATOR as char)?),
+        )),
+    }
+}
+
 fn parse_bindings(s: &str) -> Result<Vec<(&str, &str)>, ParseError> {
     if s.is_empty() {
         return Ok(Vec::new());
@@ -344,6 +507,10 @@ mod tests {
                 b"ACTIONPROOF\x1f+1\x1fAddRecoveryContact\x1fAlice\x1ftb=X",
                 "NonCanonicalVersion",
             ),
+            (
+                b"ACTIONPROOF\x1f02\x1fAddRecoveryContact\x1fname=Alice\x1ftb=X",
+                "NonCanonicalVersion",
+            ),
             (
                 b"ACTIONPROOF\x1f 1\x1fAddRecoveryContact\x1fAlice\x1ftb=X",
                 "InvalidVersionFormat",
@@ -361,6 +528,160 @@ mod tests {
         }
     }
 
+    #[test]
+    fn build_payload_v2_formats() {
+        let p = build_payload_v2(
+            Action::SetSpendWithoutHardware,
+            &[("amount", "50000"), ("currency", "USD")],
+            &[("n", "0a"), ("tb", "XYZ")],
+        )
+        .unwrap();
+        assert_eq!(
+            p,
+            b"ACTIONPROOF\x1f2\x1fSetSpendWithoutHardware\x1famount=50000,currency=USD\x1fn=0a,tb=XYZ"
+        );
+
+        // Actions without a schema have an empty field section
+        let p = build_payload_v2(Action::DeleteAccount, &[], &[("tb", "XYZ")]).unwrap();
+        assert_eq!(p, b"ACTIONPROOF\x1f2\x1fDeleteAccount\x1f\x1ftb=XYZ");
+    }
+
+    #[test]
+    fn build_v2_enforces_schema() {
+        for fields in [
+            &[("amount", "50000")][..],
+            &[("currency", "USD"), ("amount", "50000")],
+            &[("amount", "500.00"), ("currency", "USD")],
+            &[("amount", "50000"), ("currency", "USD"), ("extra", "1")],
+        ] {
+            assert!(matches!(
+                build_payload_v2(Action::SetSpendWithoutHardware, fields, &[("tb", "X")]),
+                Err(BuildError::InvalidFields(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn parse_v2_roundtrip() {
+        let original = build_payload_v2(
+            Action::SetDelayNotifyPeriod,
+            &[("days", "7")],
+            &[("n", "0a"), ("tb", "XYZ")],
+        )
+        .unwrap();
+        let parsed = parse_payload(&original).expect("should parse");
+        assert_eq!(parsed.version, CANONICAL_VERSION_V2);
+        assert_eq!(parsed.action, Action::SetDelayNotifyPeriod);
+        assert_eq!(parsed.value, None);
+        assert_eq!(parsed.fields, vec![("days", "7")]);
+        assert_eq!(parsed.bindings, vec![("n", "0a"), ("tb", "XYZ")]);
+    }
+
+    #[test]
+    fn contact_names_may_contain_separators() {
+        let p = build_payload_v2(
+            Action::AddRecoveryContact,
+            &[("name", "Smith, Alice=1")],
+            &[("tb", "XYZ")],
+        )
+        .unwrap();
+        assert_eq!(
+            p,
+            b"ACTIONPROOF\x1f2\x1fAddRecoveryContact\x1fname=14:Smith, Alice=1\x1ftb=XYZ"
+        );
+
+        let parsed = parse_payload(&p).unwrap();
+        assert_eq!(parsed.fields, vec![("name", "Smith, Alice=1")]);
+        assert_eq!(parsed.to_version(CANONICAL_VERSION_V2).unwrap(), p);
+        assert_eq!(
+            parsed.to_version(CANONICAL_VERSION).unwrap(),
+            b"ACTIONPROOF\x1f1\x1fAddRecoveryContact\x1fSmith, Alice=1\x1ftb=XYZ"
+        );
+
+        // The length must be canonical and cover the value exactly
+        for fields in [
+            &b"name=Alice"[..],
+            b"name=05:Alice",
+            b"name=+5:Alice",
+            b"name=4:Alice",
+            b"name=6:Alice",
+            b"name=5:Alice,",
+        ] {
+            let payload = [
+                &b"ACTIONPROOF\x1f2\x1fAddRecoveryContact\x1f"[..],
+                fields,
+                b"\x1ftb=X",
+            ]
+            .concat();
+            assert!(
+                matches!(parse_payload(&payload), Err(ParseError::InvalidFields(_))),
+                "{fields:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn parse_v2_rejects_schema_violations() {
+        for payload in [
+            &b"ACTIONPROOF\x1f2\x1fSetDelayNotifyPeriod\x1f\x1ftb=X"[..],
+            b"ACTIONPROOF\x1f2\x1fSetDelayNotifyPeriod\x1fdays=seven\x1ftb=X",
+            b"ACTIONPROOF\x1f2\x1fSetDelayNotifyPeriod\x1fdays\x1ftb=X",
+            b"ACTIONPROOF\x1f2\x1fDeleteAccount\x1fdays=7\x1ftb=X",
+        ] {
+            assert!(
+                matches!(parse_payload(payload), Err(ParseError::InvalidFields(_))),
+                "{payload:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn version_conversion_roundtrip() {
+        let cases: &[(Action, Option<&str>)] = &[
+            (Action::SetSpendWithoutHardware, Some("50000 USD")),
+            (Action::SetRecoveryEmail, Some("alice@example.com")),
+            (Action::SetRecoveryPhone, Some("+15551234567")),
+            (Action::AddRecoveryContact, Some("Alice Smith")),
+            (Action::AddRecoveryContact, Some("Smith, Alice")),
+            (Action::SetVerificationThreshold, Some("50000 USD")),
+            (Action::SetVerificationThreshold, Some("Never")),
+            (Action::DisableSpendWithoutHardware, None),
+            (Action::RotateSpendingKeyset, Some("urn:wallet-keyset:01HQ")),
+            (Action::SetDelayNotifyPeriod, Some("7")),
+            (Action::DeleteAccount, None),
+        ];
+        for (action, value) in cases {
+            let v1 = build_payload(*action, *value, &[("n", "0a"), ("tb", "XYZ")]).unwrap();
+            let v2 = parse_payload(&v1)
+                .unwrap()
+                .to_version(CANONICAL_VERSION_V2)
+                .unwrap();
+
+            let parsed_v2 = parse_payload(&v2).unwrap();
+            assert_eq!(parsed_v2.version, CANONICAL_VERSION_V2);
+            assert_eq!(parsed_v2.fields.len(), action.schema().len());
+
+            assert_eq!(parsed_v2.to_version(CANONICAL_VERSION).unwrap(), v1);
+            assert_eq!(parsed_v2.to_version(CANONICAL_VERSION_V2).unwrap(), v2);
+        }
+    }
+
+    #[test]
+    fn version_conversion_errors() {
+        // Display-formatted v1 money can't be expressed as typed fields
+        let v1 = build_payload(Action::SetSpendWithoutHardware, Some("5.00 USD"), &[]).unwrap();
+        assert!(matches!(
+            parse_payload(&v1).unwrap().to_version(CANONICAL_VERSION_V2),
+            Err(BuildError::InvalidFields(_))
+        ));
+
+        let v1 = build_payload(Action::DeleteAccount, None, &[]).unwrap();
+        assert_eq!(
+            parse_payload(&v1).unwrap().to_version(3),
+            Err(BuildError::UnsupportedVersion(3))
+        );
+    }
+
     #[test]
     fn all_action_variants_roundtrip() {
         for action in Action::all() {
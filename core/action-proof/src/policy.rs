@@ -0,0 +1,348 @@
+//! Signer policies and multi-signer proof bundles.
+//!
+//! Every [`Action`] declares which key roles must sign its canonical payload via
+//! [`Action::signer_policy`]. An [`ActionProofBundle`] carries the payload together
+//! with signatures tagged by [`KeyRole`], and checks them against that policy.
+//!
+//! This crate does no signature cryptography itself: callers pass a verifier
+//! closure, so the same evaluation runs on the server, in the app and in tooling.
+
+use crate::action::Action;
+use crate::payload::{parse_payload, ParseError, ParsedPayload};
+use thiserror::Error;
+
+define_enum!(
+    /// Role of the key that produced a signature.
+    KeyRole, ParseKeyRoleError, "invalid key role: {0}" {
+        App,
+        Hardware,
+        Recovery,
+        TrustedContact
+    }
+);
+
+impl KeyRole {
+    /// Lowercase name used in error messages, e.g. `"hardware"`.
+    pub const fn label(&self) -> &'static str {
+        match self {
+            Self::App => "app",
+            Self::Hardware => "hardware",
+            Self::Recovery => "recovery",
+            Self::TrustedContact => "trusted contact",
+        }
+    }
+}
+
+/// Which signers an action requires.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignerPolicy {
+    /// Every listed role must sign.
+    All(&'static [KeyRole]),
+    /// At least `threshold` distinct roles from `of` must sign.
+    Threshold {
+        threshold: usize,
+        of: &'static [KeyRole],
+    },
+}
+
+impl SignerPolicy {
+    /// Hardware AND app. The default for privileged actions.
+    pub const HARDWARE_AND_APP: Self = Self::All(&[KeyRole::Hardware, KeyRole::App]);
+
+    /// Either factor alone. Used in recovery flows where one factor has been lost.
+    pub const ANY_FACTOR: Self = Self::Threshold {
+        threshold: 1,
+        of: &[KeyRole::App, KeyRole::Hardware],
+    };
+
+    /// Roles that count towards this policy.
+    pub const fn roles(&self) -> &'static [KeyRole] {
+        match self {
+            Self::All(roles) => roles,
+            Self::Threshold { of, .. } => of,
+        }
+    }
+
+    /// Checks a set of verified signer roles against the policy.
+    ///
+    /// Signers outside [`roles`](Self::roles) are ignored rather than rejected.
+    pub fn evaluate(&self, signers: &[KeyRole]) -> Result<(), PolicyError> {
+        match self {
+            Self::All(roles) => match roles.iter().find(|role| !signers.contains(role)) {
+                Some(missing) => Err(PolicyError::MissingSigner(*missing)),
+                None => Ok(()),
+            },
+            Self::Threshold { threshold, of } => {
+                let present = of.iter().filter(|role| signers.contains(role)).count();
+                if present < *threshold {
+                    return Err(PolicyError::BelowThreshold {
+                        required: *threshold,
+                        present,
+                    });
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Action {
+    /// Signers required to authorize this action.
+    pub const fn signer_policy(&self) -> SignerPolicy {
+        match self {
+            Self::CreateLostAppRecovery
+            | Self::CreateLostHardwareRecovery
+            | Self::CancelLostAppRecovery
+            | Self::CancelLostHardwareRecovery
+            | Self::CancelConflictingRecovery
+            | Self::SendRecoveryVerificationCode
+            | Self::VerifyRecoveryVerificationCode => SignerPolicy::ANY_FACTOR,
+            Self::SetSpendWithoutHardware
+            | Self::DisableSpendWithoutHardware
+            | Self::SetVerificationThreshold
+            | Self::SetRecoveryEmail
+            | Self::DisableRecoveryEmail
+            | Self::SetRecoveryPhone
+            | Self::DisableRecoveryPhone
+            | Self::SetRecoveryPushNotifications
+            | Self::DisableRecoveryPushNotifications
+            | Self::AddRecoveryContact
+            | Self::RemoveRecoveryContact
+            | Self::RemoveRecoveryCustomer
+            | Self::AddBeneficiary
+            | Self::RemoveBeneficiary
+            | Self::RemoveBenefactor
+            | Self::CreateSpendingKeyset
+            | Self::RotateSpendingKeyset
+            | Self::DeleteAccount
+            | Self::UpdateDescriptorBackups
+            | Self::RotateAppAuthKeys
+            | Self::SetDelayNotifyPeriod => SignerPolicy::HARDWARE_AND_APP,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum PolicyError {
+    #[error("{} signature required", .0.label())]
+    MissingSigner(KeyRole),
+    #[error("at least {required} signature(s) required, got {present}")]
+    BelowThreshold { required: usize, present: usize },
+    #[error("duplicate {} signature", .0.label())]
+    DuplicateSigner(KeyRole),
+    #[error("invalid {} signature", .0.label())]
+    InvalidSignature(KeyRole),
+    #[error("invalid payload: {0}")]
+    InvalidPayload(#[from] ParseError),
+}
+
+/// A signature tagged with the role of the key that produced it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RoleSignature {
+    pub role: KeyRole,
+    pub signature: Vec<u8>,
+}
+
+/// A canonical payload and the signatures collected over it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActionProofBundle {
+    pub payload: Vec<u8>,
+    pub signatures: Vec<RoleSignature>,
+}
+
+impl ActionProofBundle {
+    pub fn new(payload: Vec<u8>) -> Self {
+        Self {
+            payload,
+            signatures: vec![],
+        }
+    }
+
+    /// Adds a signature. Each role may sign at most once.
+    pub fn add_signature(&mut self, role: KeyRole, signature: Vec<u8>) -> Result<(), PolicyError> {
+        if self.signatures.iter().any(|s| s.role == role) {
+            return Err(PolicyError::DuplicateSigner(role));
+        }
+        self.signatures.push(RoleSignature { role, signature });
+        Ok(())
+    }
+
+    pub fn parse(&self) -> Result<ParsedPayload<'_>, ParseError> {
+        parse_payload(&self.payload)
+    }
+
+    /// Roles that have signed, in the order they were added.
+    pub fn signers(&self) -> Vec<KeyRole> {
+        self.signatures.iter().map(|s| s.role).collect()
+    }
+
+    /// Verifies every signature and evaluates the payload action's signer policy.
+    ///
+    /// `verify` is called as `verify(role, payload, signature)` and returns whether
+    /// the signature is valid for that role's registered key. Returns the verified
+    /// signer roles on success.
+    pub fn verify<F>(&self, mut verify: F) -> Result<Vec<KeyRole>, PolicyError>
+    where
+        F: FnMut(KeyRole, &[u8], &[u8]) -> bool,
+    {
+        let parsed = self.parse()?;
+        let mut signers = Vec::with_capacity(self.signatures.len());
+        for RoleSignature { role, signature } in &self.signatures {
+            if signers.contains(role) {
+                return Err(PolicyError::DuplicateSigner(*role));
+            }
+            if !verify(*role, &self.payload, signature) {
+                return Err(PolicyError::InvalidSignature(*role));
+            }
+            signers.push(*role);
+        }
+        parsed.action.signer_policy().evaluate(&signers)?;
+        Ok(signers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::payload::build_payload;
+
+    const TRIO: SignerPolicy = SignerPolicy::Threshold {
+        threshold: 2,
+        of: &[KeyRole::App, KeyRole::Hardware, KeyRole::Recovery],
+    };
+
+    fn bundle(action: Action, roles: &[KeyRole]) -> ActionProofBundle {
+        let payload = build_payload(action, None, &[("tb", "abc")]).unwrap();
+        let mut bundle = ActionProofBundle::new(payload);
+        for role in roles {
+            bundle
+                .add_signature(*role, role.as_str().as_bytes().to_vec())
+                .unwrap();
+        }
+        bundle
+    }
+
+    /// Accepts a signature iff it is the role's name, standing in for real crypto.
+    fn fake_verify(role: KeyRole, _payload: &[u8], sig: &[u8]) -> bool {
+        sig == role.as_str().as_bytes()
+    }
+
+    #[test]
+    fn key_role_roundtrip() {
+        for role in KeyRole::all() {
+            assert_eq!(role.as_str().parse::<KeyRole>().unwrap(), *role);
+        }
+        assert!("hardware".parse::<KeyRole>().is_err());
+    }
+
+    #[test]
+    fn all_policy() {
+        let policy = SignerPolicy::HARDWARE_AND_APP;
+        assert!(policy.evaluate(&[KeyRole::App, KeyRole::Hardware]).is_ok());
+        assert_eq!(
+            policy.evaluate(&[KeyRole::App]),
+            Err(PolicyError::MissingSigner(KeyRole::Hardware))
+        );
+        assert_eq!(
+            policy.evaluate(&[KeyRole::Hardware, KeyRole::Recovery]),
+            Err(PolicyError::MissingSigner(KeyRole::App))
+        );
+        // Hardware is reported first when both are missing.
+        assert_eq!(
+            policy.evaluate(&[]),
+            Err(PolicyError::MissingSigner(KeyRole::Hardware))
+        );
+    }
+
+    #[test]
+    fn threshold_policy() {
+        assert!(TRIO.evaluate(&[KeyRole::App, KeyRole::Recovery]).is_ok());
+        assert!(TRIO
+            .evaluate(&[KeyRole::App, KeyRole::Hardware, KeyRole::Recovery])
+            .is_ok());
+        assert_eq!(
+            TRIO.evaluate(&[KeyRole::App, KeyRole::TrustedContact]),
+            Err(PolicyError::BelowThreshold {
+                required: 2,
+                present: 1
+            })
+        );
+        assert!(SignerPolicy::ANY_FACTOR.evaluate(&[KeyRole::App]).is_ok());
+        assert_eq!(
+            SignerPolicy::ANY_FACTOR.evaluate(&[]),
+            Err(PolicyError::BelowThreshold {
+                required: 1,
+                present: 0
+            })
+        );
+    }
+
+    #[test]
+    fn every_action_policy_is_satisfiable() {
+        for action in Action::all() {
+            let policy = action.signer_policy();
+            assert!(policy.evaluate(policy.roles()).is_ok(), "{action}");
+        }
+    }
+
+    #[test]
+    fn bundle_verifies_against_action_policy() {
+        let b = bundle(Action::SetRecoveryEmail, &[KeyRole::Hardware, KeyRole::App]);
+        assert_eq!(
+            b.verify(fake_verify),
+            Ok(vec![KeyRole::Hardware, KeyRole::App])
+        );
+
+        let b = bundle(Action::SetRecoveryEmail, &[KeyRole::App]);
+        assert_eq!(
+            b.verify(fake_verify),
+            Err(PolicyError::MissingSigner(KeyRole::Hardware))
+        );
+
+        let b = bundle(Action::CreateLostHardwareRecovery, &[KeyRole::App]);
+        assert_eq!(b.verify(fake_verify), Ok(vec![KeyRole::App]));
+    }
+
+    #[test]
+    fn bundle_rejects_bad_signatures() {
+        let mut b = bundle(Action::DeleteAccount, &[KeyRole::Hardware]);
+        b.add_signature(KeyRole::App, b"forged".to_vec()).unwrap();
+        assert_eq!(
+            b.verify(fake_verify),
+            Err(PolicyError::InvalidSignature(KeyRole::App))
+        );
+
+        let mut b = bundle(Action::DeleteAccount, &[KeyRole::App]);
+        assert_eq!(
+            b.add_signature(KeyRole::App, vec![]),
+            Err(PolicyError::DuplicateSigner(KeyRole::App))
+        );
+        // Duplicates pushed directly are caught at verification.
+        b.signatures.push(b.signatures[0].clone());
+        assert_eq!(
+            b.verify(fake_verify),
+            Err(PolicyError::DuplicateSigner(KeyRole::App))
+        );
+    }
+
+    #[test]
+    fn bundle_rejects_invalid_payload() {
+        let b = ActionProofBundle::new(b"garbage".to_vec());
+        assert!(matches!(
+            b.verify(fake_verify),
+            Err(PolicyError::InvalidPayload(_))
+        ));
+    }
+
+    #[test]
+    fn error_messages() {
+        assert_eq!(
+            PolicyError::MissingSigner(KeyRole::Hardware).to_string(),
+            "hardware signature required"
+        );
+        assert_eq!(
+            PolicyError::DuplicateSigner(KeyRole::TrustedContact).to_string(),
+            "duplicate trusted contact signature"
+        );
+    }
+}
@@ -0,0 +1,460 @@
+//! Confirmation-screen rendering for parsed payloads.
+//!
+//! Turns a [`ParsedPayload`] into the title and value lines shown on the W3
+//! privileged-action screen and in the app's confirmation sheet. Output depends
+//! only on the payload and the [`RenderPolicy`], so every surface that renders
+//! the same payload shows byte-identical text.
+
+use crate::action::{Action, ValueFormat};
+use crate::payload::{ParsedPayload, CANONICAL_VERSION};
+use crate::schema::{FieldType, V1_VALUE_FIELD_SEPARATOR};
+
+/// Marker inserted where a value was shortened. ASCII so firmware fonts can draw it.
+pub const ELLIPSIS: &str = "...";
+
+/// Where characters are dropped when a line exceeds the policy's length bound.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Truncation {
+    /// Keep the start and end, joined by [`ELLIPSIS`], so email domains and id
+    /// suffixes stay visible.
+    Middle,
+    /// Keep the start, followed by [`ELLIPSIS`].
+    End,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RenderPolicy {
+    /// Maximum number of value lines; extra lines are dropped.
+    pub max_lines: usize,
+    /// Maximum title length in bytes.
+    pub max_title_len: usize,
+    /// Maximum value length in bytes.
+    pub max_value_len: usize,
+    pub truncation: Truncation,
+}
+
+/// Bounds of the W3 privileged-action screen: 5 pages, and the nanopb string
+/// sizes of `display_params_privileged_action` (less the NUL terminator).
+pub const W3_RENDER_POLICY: RenderPolicy = RenderPolicy {
+    max_lines: 5,
+    max_title_len: 31,
+    max_value_len: 127,
+    truncation: Truncation::Middle,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DisplayLine {
+    pub title: String,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenderedPayload {
+    /// Screen title, e.g. `Set Recovery Email`.
+    pub title: String,
+    pub lines: Vec<DisplayLine>,
+    /// Whether any text was shortened or any line dropped under `policy`.
+    pub truncated: bool,
+    pub policy: RenderPolicy,
+}
+
+impl RenderedPayload {
+    /// Plain-text form, one `title: value` pair per line after the title.
+    pub fn to_text(&self) -> String {
+        let mut text = self.title.clone();
+        for line in &self.lines {
+            text.push('\n');
+            text.push_str(&line.title);
+            text.push_str(": ");
+            text.push_str(&line.value);
+        }
+        text
+    }
+}
+
+/// Renders a payload for confirmation. Context bindings are never shown.
+///
+/// v1 values are shown verbatim, exactly as the hardware receives them. v2
+/// fields are formatted by type: amounts in major units with their currency,
+/// NANP phone numbers grouped, and delays in days.
+pub fn render_payload(payload: &ParsedPayload<'_>, policy: &RenderPolicy) -> RenderedPayload {
+    let mut truncated = false;
+
+    let lines = if payload.version == CANONICAL_VERSION {
+        payload
+            .value
+            .map(|value| DisplayLine {
+                title: value_label(payload.action).to_string(),
+                value: value.to_string(),
+            })
+            .into_iter()
+            .collect()
+    } else {
+        field_lines(payload.action, &payload.fields)
+    };
+
+    let mut lines: Vec<DisplayLine> = lines
+        .into_iter()
+        .map(|line| DisplayLine {
+            title: fit(
+                &line.title,
+                policy.max_title_len,
+                Truncation::End,
+                &mut truncated,
+            ),
+            value: fit(
+                &line.value,
+                policy.max_value_len,
+                policy.truncation,
+                &mut truncated,
+            ),
+        })
+        .collect();
+
+    if lines.len() > policy.max_lines {
+        lines.truncate(policy.max_lines);
+        truncated = true;
+    }
+
+    RenderedPayload {
+        title: fit(
+            payload.action.display_name(),
+            policy.max_title_len,
+            Truncation::End,
+            &mut truncated,
+        ),
+        lines,
+        truncated,
+        policy: *policy,
+    }
+}
+
+fn value_label(action: Action) -> &'static str {
+    match action.value_format() {
+        Some(ValueFormat::Money) => "Amount",
+        Some(ValueFormat::VerificationPolicy) => "Threshold",
+        Some(ValueFormat::Email) => "Email",
+        Some(ValueFormat::Phone) => "Phone",
+        Some(ValueFormat::ContactName) => "Name",
+        None => "Value",
+    }
+}
+
+fn field_label(field_type: FieldType) -> &'static str {
+    match field_type {
+        FieldType::Amount => "Amount",
+        FieldType::Currency => "Currency",
+        FieldType::Days => "Delay",
+        FieldType::KeysetId => "Keyset",
+        FieldType::Email => "Email",
+        FieldType::Phone => "Phone",
+        FieldType::ContactName => "Name",
+        FieldType::VerificationPolicy => "Threshold",
+    }
+}
+
+fn field_lines(action: Action, fields: &[(&str, &str)]) -> Vec<DisplayLine> {
+    let types: Vec<FieldType> = action.schema().iter().map(|s| s.field_type).collect();
+    let mut lines = Vec::with_capacity(fields.len());
+
+    let mut i = 0;
+    while i < fields.len() {
+        let field_type = types.get(i).copied();
+        let value = fields[i].1;
+
+        // An amount followed by its currency renders as a single money line.
+        if field_type == Some(FieldType::Amount) && types.get(i + 1) == Some(&FieldType::Currency) {
+            if let Some(&(_, currency)) = fields.get(i + 1) {
+                lines.push(DisplayLine {
+                    title: field_label(FieldType::Amount).to_string(),
+                    value: format_money(value, currency),
+                });
+                i += 2;
+                continue;
+            }
+        }
+
+        lines.push(DisplayLine {
+            title: field_type.map_or("Value", field_label).to_string(),
+            value: match field_type {
+                Some(FieldType::Days) => format_days(value),
+                Some(FieldType::Phone) => format_phone(value),
+                Some(FieldType::VerificationPolicy) => {
+                    match value.split_once(V1_VALUE_FIELD_SEPARATOR) {
+                        Some((amount, currency)) => format_money(amount, currency),
+                        None => value.to_string(),
+                    }
+                }
+                _ => value.to_string(),
+            },
+        });
+        i += 1;
+    }
+
+    lines
+}
+
+/// Number of minor-unit digits for a currency code.
+fn fractional_digits(currency: &str) -> usize {
+    match currency {
+        "BTC" => 8,
+        "JPY" | "KRW" | "VND" | "CLP" | "ISK" => 0,
+        _ => 2,
+    }
+}
+
+/// Formats a minor-unit amount with `,` grouping and `.` decimals, e.g.
+/// `123456` USD → `1,234.56 USD`. BTC drops trailing fractional zeros.
+fn format_money(amount: &str, currency: &str) -> String {
+    let digits = fractional_digits(currency);
+    let padded = format!("{amount:0>width$}", width = digits + 1);
+    let (whole, frac) = padded.split_at(padded.len() - digits);
+
+    let mut grouped = String::with_capacity(whole.len() + whole.len() / 3);
+    for (i, c) in whole.chars().enumerate() {
+        if i > 0 && (whole.len() - i) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(c);
+    }
+
+    let frac = if currency == "BTC" {
+        frac.trim_end_matches('0')
+    } else {
+        frac
+    };
+
+    if frac.is_empty() {
+        format!("{grouped} {currency}")
+    } else {
+        format!("{grouped}.{frac} {currency}")
+    }
+}
+
+fn format_days(days: &str) -> String {
+    match days {
+        "1" => "1 day".to_string(),
+        _ => format!("{days} days"),
+    }
+}
+
+/// Groups NANP numbers as `+1 (555) 123-4567`; other numbers are shown as sent.
+fn format_phone(phone: &str) -> String {
+    match phone.strip_prefix("+1") {
+        Some(n) if n.len() == 10 && n.bytes().all(|b| b.is_ascii_digit()) => {
+            format!("+1 ({}) {}-{}", &n[..3], &n[3..6], &n[6..])
+        }
+        _ => phone.to_string(),
+    }
+}
+
+/// Shortens `s` to at most `max` bytes on char boundaries, inserting [`ELLIPSIS`].
+fn fit(s: &str, max: usize, truncation: Truncation, truncated: &mut bool) -> String {
+    if s.len() <= max {
+        return s.to_string();
+    }
+    *truncated = true;
+
+    let budget = max.saturating_sub(ELLIPSIS.len());
+    match truncation {
+        Truncation::End => {
+            format!("{}{ELLIPSIS}", prefix_within(s, budget))
+        }
+        Truncation::Middle => {
+            let head = prefix_within(s, budget - budget / 2);
+            let tail = suffix_within(s, budget / 2);
+            format!("{head}{ELLIPSIS}{tail}")
+        }
+    }
+}
+
+fn prefix_within(s: &str, max: usize) -> &str {
+    let mut end = max.min(s.len());
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    &s[..end]
+}
+
+fn suffix_within(s: &str, max: usize) -> &str {
+    let mut start = s.len() - max.min(s.len());
+    while !s.is_char_boundary(start) {
+        start += 1;
+    }
+    &s[start..]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::payload::{build_payload, build_payload_v2, parse_payload};
+
+    const GOLDEN_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/testdata/render.golden");
+
+    /// Payloads pinned by the golden file. Keep in sync with firmware and app
+    /// confirmation screens; regenerate with `UPDATE_GOLDEN=1 cargo test`.
+    fn golden_payloads() -> Vec<(&'static str, Vec<u8>)> {
+        let tb = &[("tb", "59dc")];
+        let long_name = "Bartholomew ".repeat(10) + "Fitzgerald-Worthington";
+        let v1 = |action, value| build_payload(action, value, tb).unwrap();
+        let v2 = |action, fields: &[(&str, &str)]| build_payload_v2(action, fields, tb).unwrap();
+        vec![
+            (
+                "v1 email",
+                v1(Action::SetRecoveryEmail, Some("alice@example.com")),
+            ),
+            (
+                "v1 money",
+                v1(Action::SetSpendWithoutHardware, Some("500 USD")),
+            ),
+            ("v1 no value", v1(Action::DeleteAccount, None)),
+            (
+                "v2 money usd",
+                v2(
+                    Action::SetSpendWithoutHardware,
+                    &[("amount", "123456789"), ("currency", "USD")],
+                ),
+            ),
+            (
+                "v2 money btc",
+                v2(
+                    Action::SetSpendWithoutHardware,
+                    &[("amount", "50000"), ("currency", "BTC")],
+                ),
+            ),
+            (
+                "v2 money jpy",
+                v2(
+                    Action::SetSpendWithoutHardware,
+                    &[("amount", "1000"), ("currency", "JPY")],
+                ),
+            ),
+            (
+                "v2 threshold",
+                v2(Action::SetVerificationThreshold, &[("policy", "50000 BTC")]),
+            ),
+            (
+                "v2 threshold always",
+                v2(Action::SetVerificationThreshold, &[("policy", "Always")]),
+            ),
+            ("v2 no fields", v2(Action::DisableSpendWithoutHardware, &[])),
+            (
+                "v2 money sub-unit",
+                v2(
+                    Action::SetSpendWithoutHardware,
+                    &[("amount", "5"), ("currency", "EUR")],
+                ),
+            ),
+            (
+                "v2 phone nanp",
+                v2(Action::SetRecoveryPhone, &[("phone", "+15551234567")]),
+            ),
+            (
+                "v2 phone intl",
+                v2(Action::SetRecoveryPhone, &[("phone", "+442071234567")]),
+            ),
+            (
+                "v2 email",
+                v2(Action::SetRecoveryEmail, &[("email", "alice@example.com")]),
+            ),
+            (
+                "v2 delay one day",
+                v2(Action::SetDelayNotifyPeriod, &[("days", "1")]),
+            ),
+            (
+                "v2 delay days",
+                v2(Action::SetDelayNotifyPeriod, &[("days", "7")]),
+            ),
+            (
+                "v2 keyset",
+                v2(
+                    Action::RotateSpendingKeyset,
+                    &[("keyset_id", "urn:wallet-keyset:01HQXYZ123")],
+                ),
+            ),
+            (
+                "v2 long name",
+                v2(Action::AddRecoveryContact, &[("name", &long_name[..128])]),
+            ),
+            (
+                "v2 long title",
+                v2(Action::DisableRecoveryPushNotifications, &[]),
+            ),
+        ]
+    }
+
+    fn render_golden() -> String {
+        let mut out = String::new();
+        for (name, payload) in golden_payloads() {
+            let parsed = parse_payload(&payload).unwrap();
+            let rendered = render_payload(&parsed, &W3_RENDER_POLICY);
+            out.push_str(&format!("== {name}\n{}\n", rendered.to_text()));
+            out.push_str(&format!("truncated: {}\n\n", rendered.truncated));
+        }
+        out
+    }
+
+    #[test]
+    fn golden_file() {
+        let actual = render_golden();
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            std::fs::write(GOLDEN_PATH, &actual).unwrap();
+        }
+        let expected = std::fs::read_to_string(GOLDEN_PATH).unwrap();
+        assert_eq!(
+            actual, expected,
+            "rendering changed; run with UPDATE_GOLDEN=1 to update"
+        );
+    }
+
+    #[test]
+    fn lines_respect_policy_bounds() {
+        let policy = RenderPolicy {
+            max_lines: 1,
+            max_title_len: 10,
+            max_value_len: 12,
+            truncation: Truncation::End,
+        };
+        let payload = build_payload_v2(
+            Action::SetRecoveryEmail,
+            &[("email", "alice@example.com")],
+            &[],
+        )
+        .unwrap();
+        let rendered = render_payload(&parse_payload(&payload).unwrap(), &policy);
+
+        assert!(rendered.truncated);
+        assert_eq!(rendered.title, "Set Rec...");
+        assert_eq!(rendered.lines[0].value, "alice@exa...");
+        for line in &rendered.lines {
+            assert!(line.title.len() <= policy.max_title_len);
+            assert!(line.value.len() <= policy.max_value_len);
+        }
+    }
+
+    #[test]
+    fn truncation_keeps_char_boundaries() {
+        let mut truncated = false;
+        let s = "田中田中田中田中";
+        for max in 3..s.len() {
+            for truncation in [Truncation::Middle, Truncation::End] {
+                let out = fit(s, max, truncation, &mut truncated);
+                assert!(out.len() <= max, "{out:?} exceeds {max}");
+            }
+        }
+        assert!(truncated);
+    }
+
+    #[test]
+    fn money_formatting() {
+        for (amount, currency, expected) in [
+            ("0", "USD", "0.00 USD"),
+            ("100", "USD", "1.00 USD"),
+            ("100000000", "BTC", "1 BTC"),
+            ("1", "BTC", "0.00000001 BTC"),
+            ("1234567", "KRW", "1,234,567 KRW"),
+        ] {
+            assert_eq!(format_money(amount, currency), expected);
+        }
+    }
+}
@@ -0,0 +1,443 @@
+//! Typed field schemas for version-2 payloads.
+//!
+//! A v2 payload replaces the free-form value with `name=value` fields, listed in
+//! the order declared by [`Action::schema`]. Each field is checked against its
+//! [`FieldType`], so clients sign exactly the structured data the server acts on.
+
+use crate::action::Action;
+use crate::payload::{BINDING_KEY_VALUE_SEPARATOR, BINDING_PAIR_SEPARATOR, UNIT_SEPARATOR};
+use crate::validation::{validate_value, ValidationError};
+use thiserror::Error;
+
+/// Separator between fields when flattened into a v1 value, e.g. `"500 USD"`.
+pub const V1_VALUE_FIELD_SEPARATOR: char = ' ';
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FieldType {
+    /// Integer amount in the currency's minor unit (cents, sats, ...).
+    Amount,
+    /// Three-letter uppercase currency code, e.g. `USD` or `BTC`.
+    Currency,
+    /// Whole number of days.
+    Days,
+    /// Server-assigned keyset identifier.
+    KeysetId,
+    Email,
+    /// E.164 phone number, e.g. `+15551234567`.
+    Phone,
+    /// Free text; length-prefixed in the payload so it may contain `=` and `,`.
+    ContactName,
+    /// `Never`, `Always`, or a threshold amount and currency, e.g. `50000 USD`.
+    VerificationPolicy,
+}
+
+impl FieldType {
+    pub const fn description(&self) -> &'static str {
+        match self {
+            Self::Amount => "amount in minor units",
+            Self::Currency => "currency code",
+            Self::Days => "number of days",
+            Self::KeysetId => "keyset id",
+            Self::Email => "email address",
+            Self::Phone => "E.164 phone number",
+            Self::ContactName => "contact name",
+            Self::VerificationPolicy => "verification policy",
+        }
+    }
+
+    /// Whether values of this type are written as `len:value` rather than
+    /// verbatim, which lets them contain the payload's reserved characters.
+    pub const fn is_length_prefixed(&self) -> bool {
+        matches!(self, Self::ContactName)
+    }
+
+    fn accepts(&self, value: &str) -> bool {
+        match self {
+            Self::Amount => is_canonical_integer::<u64>(value),
+            Self::Currency => value.len() == 3 && value.bytes().all(|b| b.is_ascii_uppercase()),
+            Self::Days => is_canonical_integer::<u32>(value),
+            Self::KeysetId => value
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b':')),
+            Self::Email => match value.split_once('@') {
+                Some((local, domain)) => {
+                    !local.is_empty()
+                        && domain.contains('.')
+                        && !domain.starts_with('.')
+                        && !domain.ends_with('.')
+                        && !domain.contains('@')
+                        && !value.contains(' ')
+                }
+                None => false,
+            },
+            Self::Phone => value.strip_prefix('+').is_some_and(|digits| {
+                (8..=15).contains(&digits.len()) && digits.bytes().all(|b| b.is_ascii_digit())
+            }),
+            Self::ContactName => true,
+            Self::VerificationPolicy => {
+                match value {
+                    "Never" | "Always" => true,
+                    _ => value.split_once(V1_VALUE_FIELD_SEPARATOR).is_some_and(
+                        |(amount, currency)| {
+                            Self::Amount.accepts(amount) && Self::Currency.accepts(currency)
+                        },
+                    ),
+                }
+            }
+        }
+    }
+}
+
+/// Decimal digits only, no leading zeros, and within range of `T`.
+fn is_canonical_integer<T: core::str::FromStr>(value: &str) -> bool {
+    value.bytes().all(|b| b.is_ascii_digit())
+        && (value == "0" || !value.starts_with('0'))
+        && value.parse::<T>().is_ok()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldSpec {
+    pub name: &'static str,
+    pub field_type: FieldType,
+}
+
+const fn field(name: &'static str, field_type: FieldType) -> FieldSpec {
+    FieldSpec { name, field_type }
+}
+
+const MONEY: &[FieldSpec] = &[
+    field("amount", FieldType::Amount),
+    field("currency", FieldType::Currency),
+];
+const EMAIL: &[FieldSpec] = &[field("email", FieldType::Email)];
+const PHONE: &[FieldSpec] = &[field("phone", FieldType::Phone)];
+const CONTACT: &[FieldSpec] = &[field("name", FieldType::ContactName)];
+const KEYSET: &[FieldSpec] = &[field("keyset_id", FieldType::KeysetId)];
+const DELAY: &[FieldSpec] = &[field("days", FieldType::Days)];
+const POLICY: &[FieldSpec] = &[field("policy", FieldType::VerificationPolicy)];
+
+impl Action {
+    /// Fields carried by a v2 payload for this action, in canonical order.
+    pub const fn schema(&self) -> &'static [FieldSpec] {
+        match self {
+            Self::SetSpendWithoutHardware => MONEY,
+            Self::SetVerificationThreshold => POLICY,
+            Self::SetRecoveryEmail | Self::DisableRecoveryEmail => EMAIL,
+            Self::SetRecoveryPhone | Self::DisableRecoveryPhone => PHONE,
+            Self::AddRecoveryContact
+            | Self::RemoveRecoveryContact
+            | Self::RemoveRecoveryCustomer
+            | Self::AddBeneficiary
+            | Self::RemoveBeneficiary
+            | Self::RemoveBenefactor => CONTACT,
+            Self::RotateSpendingKeyset => KEYSET,
+            Self::SetDelayNotifyPeriod => DELAY,
+            Self::DisableSpendWithoutHardware
+            | Self::SetRecoveryPushNotifications
+            | Self::DisableRecoveryPushNotifications
+            | Self::CreateSpendingKeyset
+            | Self::DeleteAccount
+            | Self::UpdateDescriptorBackups
+            | Self::CreateLostAppRecovery
+            | Self::CreateLostHardwareRecovery
+            | Self::CancelLostAppRecovery
+            | Self::CancelLostHardwareRecovery
+            | Self::CancelConflictingRecovery
+            | Self::SendRecoveryVerificationCode
+            | Self::VerifyRecoveryVerificationCode
+            | Self::RotateAppAuthKeys => &[],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum SchemaError {
+    #[error("missing field '{0}'")]
+    MissingField(&'static str),
+    #[error("unexpected field '{0}'")]
+    UnexpectedField(String),
+    #[error("field '{0}' is out of order")]
+    FieldOutOfOrder(String),
+    #[error("invalid field format: '{0}'")]
+    InvalidFieldFormat(String),
+    #[error("field '{0}' contains reserved character")]
+    ReservedCharacter(&'static str),
+    #[error("field '{name}' is not a valid {expected}")]
+    TypeMismatch {
+        name: &'static str,
+        expected: &'static str,
+    },
+    #[error("field '{name}': {source}")]
+    InvalidValue {
+        name: &'static str,
+        source: ValidationError,
+    },
+}
+
+/// Checks that `fields` match the action's schema exactly: same names, same
+/// order, and every value well-formed for its type.
+pub fn validate_fields(action: Action, fields: &[(&str, &str)]) -> Result<(), SchemaError> {
+    let schema = action.schema();
+
+    for (i, spec) in schema.iter().enumerate() {
+        let Some(&(name, value)) = fields.get(i) else {
+            return Err(SchemaError::MissingField(spec.name));
+        };
+        if name != spec.name {
+            return Err(if schema.iter().any(|s| s.name == name) {
+                SchemaError::FieldOutOfOrder(name.to_string())
+            } else if fields.iter().any(|(n, _)| *n == spec.name) {
+                SchemaError::UnexpectedField(name.to_string())
+            } else {
+                SchemaError::MissingField(spec.name)
+            });
+        }
+        validate_field(spec, value)?;
+    }
+
+    if let Some((name, _)) = fields.get(schema.len()) {
+        return Err(SchemaError::UnexpectedField(name.to_string()));
+    }
+
+    Ok(())
+}
+
+fn validate_field(spec: &FieldSpec, value: &str) -> Result<(), SchemaError> {
+    validate_value(value).map_err(|source| SchemaError::InvalidValue {
+        name: spec.name,
+        source,
+    })?;
+
+    // Length-prefixed values are delimited by their length, not by separators.
+    // The unit separator is already rejected by `validate_value`.
+    if !spec.field_type.is_length_prefixed()
+        && value.bytes().any(|b| {
+            b == UNIT_SEPARATOR || b == BINDING_KEY_VALUE_SEPARATOR || b == BINDING_PAIR_SEPARATOR
+        })
+    {
+        return Err(SchemaError::ReservedCharacter(spec.name));
+    }
+
+    if !spec.field_type.accepts(value) {
+        return Err(SchemaError::TypeMismatch {
+            name: spec.name,
+            expected: spec.field_type.description(),
+        });
+    }
+
+    Ok(())
+}
+
+/// Splits a v1 value into the action's v2 fields.
+///
+/// Fields are separated by a single space in schema order; the last field takes
+/// the remainder, so contact names may contain spaces. The result is validated
+/// against the schema.
+pub fn fields_from_value(
+    action: Action,
+    value: Option<&str>,
+) -> Result<Vec<(&'static str, &str)>, SchemaError> {
+    let schema = action.schema();
+    let value = value.filter(|v| !v.is_empty());
+
+    let fields: Vec<(&'static str, &str)> = match value {
+        None => Vec::new(),
+        Some(v) => {
+            if schema.is_empty() {
+                return Err(SchemaError::UnexpectedField(v.to_string()));
+            }
+            schema
+                .iter()
+                .map(|spec| spec.name)
+                .zip(v.splitn(schema.len(), V1_VALUE_FIELD_SEPARATOR))
+                .collect()
+        }
+    };
+
+    validate_fields(action, &fields)?;
+    Ok(fields)
+}
+
+/// Flattens v2 fields into a v1 value. Returns `None` when there are no fields.
+pub fn value_from_fields(fields: &[(&str, &str)]) -> Option<String> {
+    if fields.is_empty() {
+        return None;
+    }
+    let values: Vec<&str> = fields.iter().map(|(_, v)| *v).collect();
+    Some(values.join(&V1_VALUE_FIELD_SEPARATOR.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn schema_names_are_unique_and_safe() {
+        for action in Action::all() {
+            let schema = action.schema();
+            for (i, spec) in schema.iter().enumerate() {
+                assert!(!spec.name.is_empty());
+                assert!(spec
+                    .name
+                    .bytes()
+                    .all(|b| b.is_ascii_lowercase() || b == b'_'));
+                assert!(
+                    schema[i + 1..].iter().all(|s| s.name != spec.name),
+                    "duplicate field {} for {action:?}",
+                    spec.name
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn field_types() {
+        let cases: &[(FieldType, &str, bool)] = &[
+            (FieldType::Amount, "0", true),
+            (FieldType::Amount, "50000", true),
+            (FieldType::Amount, "050", false),
+            (FieldType::Amount, "5.00", false),
+            (FieldType::Amount, "-1", false),
+            (FieldType::Amount, "18446744073709551616", false),
+            (FieldType::Currency, "USD", true),
+            (FieldType::Currency, "BTC", true),
+            (FieldType::Currency, "usd", false),
+            (FieldType::Currency, "USDT", false),
+            (FieldType::Days, "7", true),
+            (FieldType::Days, "07", false),
+            (FieldType::KeysetId, "urn:wallet-keyset:01HQXYZ", true),
+            (FieldType::KeysetId, "keyset id", false),
+            (FieldType::Email, "alice@example.com", true),
+            (FieldType::Email, "alice@example", false),
+            (FieldType::Email, "alice@@example.com", false),
+            (FieldType::Email, "@example.com", false),
+            (FieldType::Phone, "+15551234567", true),
+            (FieldType::Phone, "+1 (555) 123-4567", false),
+            (FieldType::Phone, "15551234567", false),
+            (FieldType::ContactName, "Alice Smith", true),
+            (FieldType::ContactName, "Smith, Alice", true),
+            (FieldType::VerificationPolicy, "Never", true),
+            (FieldType::VerificationPolicy, "Always", true),
+            (FieldType::VerificationPolicy, "50000 USD", true),
+            (FieldType::VerificationPolicy, "never", false),
+            (FieldType::VerificationPolicy, "50000", false),
+            (FieldType::VerificationPolicy, "500.00 USD", false),
+        ];
+        for (field_type, value, ok) in cases {
+            assert_eq!(
+                field_type.accepts(value),
+                *ok,
+                "{field_type:?} accepts {value:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn validate_fields_errors() {
+        let action = Action::SetSpendWithoutHardware;
+        assert!(validate_fields(action, &[("amount", "500"), ("currency", "USD")]).is_ok());
+
+        let cases: &[(&[(&str, &str)], SchemaError)] = &[
+            (&[("amount", "500")], SchemaError::MissingField("currency")),
+            (
+                &[("currency", "USD"), ("amount", "500")],
+                SchemaError::FieldOutOfOrder("currency".to_string()),
+            ),
+            (
+                &[("amount", "500"), ("currency", "USD"), ("memo", "x")],
+                SchemaError::UnexpectedField("memo".to_string()),
+            ),
+            (
+                &[("amount", "5.00"), ("currency", "USD")],
+                SchemaError::TypeMismatch {
+                    name: "amount",
+                    expected: "amount in minor units",
+                },
+            ),
+            (
+                &[("amount", "500"), ("currency", "U,S")],
+                SchemaError::ReservedCharacter("currency"),
+            ),
+        ];
+        for (fields, expected) in cases {
+            assert_eq!(validate_fields(action, fields).unwrap_err(), *expected);
+        }
+
+        assert!(matches!(
+            validate_fields(Action::AddRecoveryContact, &[("name", "p\u{0430}ypal")]),
+            Err(SchemaError::InvalidValue {
+                name: "name",
+                source: ValidationError::MixedScripts
+            })
+        ));
+        assert!(validate_fields(Action::DeleteAccount, &[]).is_ok());
+        assert!(validate_fields(Action::AddRecoveryContact, &[("name", "Smith, A=B")]).is_ok());
+        assert_eq!(
+            validate_fields(Action::SetRecoveryEmail, &[("email", "a,b@example.com")]),
+            Err(SchemaError::ReservedCharacter("email"))
+        );
+    }
+
+    #[test]
+    fn policy_actions_do_not_carry_money() {
+        assert_eq!(
+            Action::SetVerificationThreshold.schema(),
+            &[field("policy", FieldType::VerificationPolicy)]
+        );
+        assert!(Action::DisableSpendWithoutHardware.schema().is_empty());
+        assert!(matches!(
+            validate_fields(
+                Action::SetVerificationThreshold,
+                &[("amount", "500"), ("currency", "USD")]
+            ),
+            Err(SchemaError::MissingField("policy"))
+        ));
+        assert!(matches!(
+            validate_fields(
+                Action::DisableSpendWithoutHardware,
+                &[("amount", "500"), ("currency", "USD")]
+            ),
+            Err(SchemaError::UnexpectedField(_))
+        ));
+    }
+
+    #[test]
+    fn v1_value_conversion() {
+        assert_eq!(
+            fields_from_value(Action::SetSpendWithoutHardware, Some("500 USD")).unwrap(),
+            vec![("amount", "500"), ("currency", "USD")]
+        );
+        assert_eq!(
+            fields_from_value(Action::AddRecoveryContact, Some("Alice Smith")).unwrap(),
+            vec![("name", "Alice Smith")]
+        );
+        assert_eq!(
+            fields_from_value(Action::SetVerificationThreshold, Some("500 USD")).unwrap(),
+            vec![("policy", "500 USD")]
+        );
+        assert_eq!(
+            fields_from_value(Action::DeleteAccount, None).unwrap(),
+            vec![]
+        );
+
+        // Display-formatted money is not a canonical amount
+        assert!(matches!(
+            fields_from_value(Action::SetSpendWithoutHardware, Some("5.00 USD")),
+            Err(SchemaError::TypeMismatch { name: "amount", .. })
+        ));
+        assert_eq!(
+            fields_from_value(Action::SetDelayNotifyPeriod, None),
+            Err(SchemaError::MissingField("days"))
+        );
+        assert!(matches!(
+            fields_from_value(Action::DeleteAccount, Some("x")),
+            Err(SchemaError::UnexpectedField(_))
+        ));
+
+        assert_eq!(
+            value_from_fields(&[("amount", "500"), ("currency", "USD")]).as_deref(),
+            Some("500 USD")
+        );
+        assert_eq!(value_from_fields(&[]), None);
+    }
+}
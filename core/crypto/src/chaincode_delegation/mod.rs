@@ -86,16 +86,38 @@ impl UntweakedPsbt {
     /// # Arguments
     /// * `source_keyset` - The keyset that will be used to tweak the PSBT's inputs.
     pub fn with_source_wallet_tweaks(
-        mut self,
+        self,
         source_keyset: &Keyset,
+    ) -> Result<WithSourceWalletTweaksPsbt> {
+        self.with_source_wallets_tweaks(std::slice::from_ref(source_keyset))
+    }
+
+    /// Like [`UntweakedPsbt::with_source_wallet_tweaks`], for PSBTs spending from several
+    /// keysets at once, e.g. sweeping every inactive keyset of an account that has rotated keys
+    /// more than once.
+    ///
+    /// Each BIP32 derivation entry is matched to a keyset by master fingerprint. Keysets can share
+    /// a fingerprint (the HW's, across an App key rotation), so an entry only matches a keyset if
+    /// deriving along its path also yields its public key.
+    ///
+    /// # Arguments
+    /// * `source_keysets` - The keysets the PSBT's inputs may belong to.
+    ///
+    /// # Errors
+    /// * [`ChaincodeDelegationError::UnknownKey`] if an entry's fingerprint matches no keyset.
+    /// * [`ChaincodeDelegationError::KeyMismatch`] if it matches a keyset's fingerprint, but
+    ///   not its key.
+    pub fn with_source_wallets_tweaks(
+        mut self,
+        source_keysets: &[Keyset],
     ) -> Result<WithSourceWalletTweaksPsbt> {
         let secp = Secp256k1::new();
         for input in self.0.inputs.iter_mut() {
             for (final_pk, (master_fingerprint, path_from_parent)) in input.bip32_derivation.iter()
             {
-                process_psbt_entry_tweaks(
+                process_psbt_entry_tweaks_for_keysets(
                     &secp,
-                    source_keyset,
+                    source_keysets,
                     master_fingerprint,
                     path_from_parent,
                     final_pk,
@@ -106,9 +128,9 @@ impl UntweakedPsbt {
         for output in self.0.outputs.iter_mut() {
             for (final_pk, (master_fingerprint, path_from_parent)) in output.bip32_derivation.iter()
             {
-                process_psbt_entry_tweaks(
+                process_psbt_entry_tweaks_for_keysets(
                     &secp,
-                    source_keyset,
+                    source_keysets,
                     master_fingerprint,
                     path_from_parent,
                     final_pk,
@@ -367,6 +389,39 @@ pub struct Keyset {
     pub app_account_xpub_with_origin: XpubWithOrigin,
 }
 
+// Tries each keyset in turn, keeping the tweaks from the first one the entry belongs to.
+fn process_psbt_entry_tweaks_for_keysets(
+    secp: &Secp256k1<All>,
+    keysets: &[Keyset],
+    master_fingerprint: &Fingerprint,
+    path_from_parent: &DerivationPath,
+    final_pk: &PublicKey,
+    proprietary_map: &mut BTreeMap<ProprietaryKey, Vec<u8>>,
+) -> Result<()> {
+    let mut mismatch = None;
+    for keyset in keysets {
+        match process_psbt_entry_tweaks(
+            secp,
+            keyset,
+            master_fingerprint,
+            path_from_parent,
+            final_pk,
+            proprietary_map,
+        ) {
+            Ok(()) => return Ok(()),
+            Err(ChaincodeDelegationError::UnknownKey { .. }) => {}
+            Err(e @ ChaincodeDelegationError::KeyMismatch { .. }) => {
+                mismatch.get_or_insert(e);
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
+    Err(mismatch.unwrap_or(ChaincodeDelegationError::UnknownKey {
+        fingerprint: *master_fingerprint,
+    }))
+}
+
 // Helper function to process BIP32 derivation and add tweaks to a PSBT entry's proprietary map.
 fn process_psbt_entry_tweaks(
     secp: &Secp256k1<All>,
@@ -794,6 +849,126 @@ mod tests {
         ));
     }
 
+    fn create_rotated_test_keyset(app_seed: u8) -> (Keyset, ExtendedPrivKey) {
+        let secp = Secp256k1::new();
+        let (keyset, _, _) = create_test_keyset();
+        let app_root_xprv = ExtendedPrivKey::new_master(Network::Testnet, &[app_seed; 32]).unwrap();
+        let account_path = DerivationPath::from_str("m/84'/0'/0'").unwrap();
+        let app_account_level_xpub = ExtendedPubKey::from_priv(
+            &secp,
+            &app_root_xprv.derive_priv(&secp, &account_path).unwrap(),
+        );
+
+        // Same HW and server, new App key.
+        let keyset = Keyset {
+            app_account_xpub_with_origin: XpubWithOrigin {
+                fingerprint: app_root_xprv.fingerprint(&secp),
+                xpub: app_account_level_xpub,
+            },
+            ..keyset
+        };
+        (keyset, app_root_xprv)
+    }
+
+    #[test]
+    fn test_psbt_with_tweaks_multiple_source_keysets() {
+        let secp = Secp256k1::new();
+        let (keyset, hw_root_xprv, _) = create_test_keyset();
+        let (rotated_keyset, rotated_app_root_xprv) = create_rotated_test_keyset(4);
+
+        let mut psbt = create_default_psbt();
+        psbt.unsigned_tx.input.push(bitcoin::TxIn::default());
+        psbt.inputs.push(Default::default());
+
+        // First input: HW key, whose fingerprint both keysets share.
+        let hw_path = DerivationPath::from_str("m/84'/0'/0'/0/1").unwrap();
+        let hw_derived = hw_root_xprv.derive_priv(&secp, &hw_path).unwrap();
+        psbt.inputs[0].bip32_derivation.insert(
+            hw_derived.to_priv().public_key(&secp).inner,
+            (keyset.hw_descriptor_public_keys.root_fingerprint(), hw_path),
+        );
+
+        // Second input: App key of the rotated keyset only.
+        let app_path = DerivationPath::from_str("m/84'/0'/0'/1/7").unwrap();
+        let app_derived = rotated_app_root_xprv.derive_priv(&secp, &app_path).unwrap();
+        psbt.inputs[1].bip32_derivation.insert(
+            app_derived.to_priv().public_key(&secp).inner,
+            (rotated_app_root_xprv.fingerprint(&secp), app_path),
+        );
+
+        assert!(matches!(
+            UntweakedPsbt::new(psbt.clone()).with_source_wallet_tweaks(&keyset),
+            Err(ChaincodeDelegationError::UnknownKey { .. })
+        ));
+
+        let psbt = UntweakedPsbt::new(psbt)
+            .with_source_wallets_tweaks(&[keyset.clone(), rotated_keyset.clone()])
+            .unwrap()
+            .into_psbt();
+
+        let tweak_key = |xpub: Xpub| ProprietaryKey {
+            prefix: PROPRIETARY_KEY_PREFIX.to_vec(),
+            subtype: PROPRIETARY_KEY_SUBTYPE,
+            key: xpub.public_key.serialize().to_vec(),
+        };
+        assert!(psbt.inputs[0]
+            .proprietary
+            .contains_key(&tweak_key(keyset.hw_descriptor_public_keys.account_xpub())));
+        assert!(psbt.inputs[1]
+            .proprietary
+            .contains_key(&tweak_key(rotated_keyset.app_account_xpub_with_origin.xpub)));
+    }
+
+    #[test]
+    fn test_psbt_with_tweaks_multiple_source_keysets_key_mismatch_error() {
+        let secp = Secp256k1::new();
+        let (keyset, _, _) = create_test_keyset();
+        let (rotated_keyset, _) = create_rotated_test_keyset(4);
+
+        // Claims the HW fingerprint, but isn't derived from the HW key.
+        let unknown_xprv = ExtendedPrivKey::new_master(Network::Testnet, &[94; 32]).unwrap();
+        let derivation_path = DerivationPath::from_str("m/84'/0'/0'/0/1").unwrap();
+        let derived_key = unknown_xprv.derive_priv(&secp, &derivation_path).unwrap();
+
+        let mut psbt = create_default_psbt();
+        psbt.inputs[0].bip32_derivation.insert(
+            derived_key.to_priv().public_key(&secp).inner,
+            (
+                keyset.hw_descriptor_public_keys.root_fingerprint(),
+                derivation_path,
+            ),
+        );
+
+        assert!(matches!(
+            UntweakedPsbt::new(psbt).with_source_wallets_tweaks(&[keyset, rotated_keyset]),
+            Err(ChaincodeDelegationError::KeyMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_psbt_with_tweaks_multiple_source_keysets_unknown_key_error() {
+        let secp = Secp256k1::new();
+        let (keyset, _, _) = create_test_keyset();
+        let (rotated_keyset, _) = create_rotated_test_keyset(4);
+
+        let unknown_xprv = ExtendedPrivKey::new_master(Network::Testnet, &[94; 32]).unwrap();
+        let derivation_path = DerivationPath::from_str("m/84'/0'/0'/0/1").unwrap();
+        let derived_key = unknown_xprv.derive_priv(&secp, &derivation_path).unwrap();
+
+        let mut psbt = create_default_psbt();
+        psbt.inputs[0].bip32_derivation.insert(
+            derived_key.to_priv().public_key(&secp).inner,
+            (unknown_xprv.fingerprint(&secp), derivation_path),
+        );
+
+        assert_eq!(
+            UntweakedPsbt::new(psbt).with_source_wallets_tweaks(&[keyset, rotated_keyset]),
+            Err(ChaincodeDelegationError::UnknownKey {
+                fingerprint: unknown_xprv.fingerprint(&secp),
+            })
+        );
+    }
+
     #[test]
     fn test_sweep_psbt_with_tweaks_no_sweep_outputs() {
         let (source_keyset, _, _) = create_test_keyset();
@@ -5,31 +5,91 @@ use bitcoin::secp256k1::{
 use rand::{rngs::StdRng, RngCore, SeedableRng};
 use secp256k1_zkp::{
     self as zkp,
-    frost::{
-        generate_frost_shares, CoefficientCommitment, FrostPublicKey, FrostShare, VerificationShare,
-    },
+    frost::{generate_frost_shares, CoefficientCommitment, FrostShare},
 };
 use thiserror::Error;
 
-use super::{KeyCommitments, Participant, ShareDetails, ZkpPublicKey};
+use super::{
+    frost_public_key, KeyCommitments, Participant, ParticipantIndex, ShareDetails, ZkpPublicKey,
+};
+
+/// The threshold and participant set for a DKG run.
+///
+/// Every participant generates one share package per participant, and aggregates the packages
+/// addressed to it from every participant, so all of them must agree on the same config.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DkgConfig {
+    threshold: usize,
+    participants: Vec<Participant>,
+}
+
+impl DkgConfig {
+    /// Creates a `threshold`-of-`participants.len()` config.
+    ///
+    /// Participants are sorted by index, so the order they are given in doesn't matter.
+    pub fn new(threshold: usize, participants: &[Participant]) -> Result<Self, KeygenError> {
+        let count = participants.len();
+        let mut participants = participants.to_vec();
+        participants.sort_by_key(|participant| ParticipantIndex::from(*participant));
+        participants.dedup();
+
+        if participants.len() != count || count < 2 || threshold < 1 || threshold > count {
+            return Err(KeygenError::InvalidParticipants);
+        }
+
+        Ok(Self {
+            threshold,
+            participants,
+        })
+    }
+
+    pub fn threshold(&self) -> usize {
+        self.threshold
+    }
+
+    pub fn participants(&self) -> &[Participant] {
+        &self.participants
+    }
 
-static DKG_THRESHOLD: usize = 2;
-static DKG_PARTICIPANTS: usize = 2;
+    fn participant_indices(&self) -> Vec<ParticipantIndex> {
+        self.participants
+            .iter()
+            .map(|participant| (*participant).into())
+            .collect()
+    }
+}
 
+/// The 2-of-2 App/Server config used for every wallet before t-of-n support.
+impl Default for DkgConfig {
+    fn default() -> Self {
+        Self {
+            threshold: 2,
+            participants: vec![Participant::App, Participant::Server],
+        }
+    }
+}
+
+/// Generates one share package per participant of the default 2-of-2 App/Server config.
 pub fn generate_share_packages() -> Result<Vec<SharePackage>, KeygenError> {
+    generate_share_packages_for(&DkgConfig::default())
+}
+
+/// Generates one share package per participant in `config`, in the config's participant order.
+pub fn generate_share_packages_for(config: &DkgConfig) -> Result<Vec<SharePackage>, KeygenError> {
     let mut seed = [0u8; 32];
     let mut rng = StdRng::from_entropy();
     rng.fill_bytes(&mut seed);
 
-    let participants = [Participant::App, Participant::Server]
+    let participants = config
+        .participants
         .iter()
         .map(|participant| (*participant).into())
         .collect::<Vec<zkp::PublicKey>>();
     let participants_refs = participants.iter().collect::<Vec<&zkp::PublicKey>>();
 
     let (shares, commitments, pok) =
-        generate_frost_shares(zkp::SECP256K1, &seed, DKG_THRESHOLD, &participants_refs)
-            .expect("Should always succeed since we hardcode the threshold.");
+        generate_frost_shares(zkp::SECP256K1, &seed, config.threshold, &participants_refs)
+            .map_err(|_| KeygenError::InvalidParticipants)?;
 
     let share_packages = shares
         .into_iter()
@@ -45,15 +105,41 @@ pub fn generate_share_packages() -> Result<Vec<SharePackage>, KeygenError> {
     Ok(share_packages)
 }
 
-/// Aggregate the shares and generates key commitments.
+/// Aggregate the shares and generates key commitments for the default 2-of-2 App/Server config.
 ///
-/// package – The participant's share package.
-/// peer_package – The peer's share package.
-/// share_agg_params – The participant's share aggregation parameters.
+/// participant – The participant aggregating its shares.
+/// share_packages – The share packages addressed to the participant, one from each participant.
 pub fn aggregate_shares(
     participant: Participant,
     share_packages: &[&SharePackage],
 ) -> Result<ShareDetails, KeygenError> {
+    aggregate_shares_for(&DkgConfig::default(), participant, share_packages)
+}
+
+/// Aggregate the shares and generates key commitments.
+///
+/// config – The DKG config every participant generated its share packages with.
+/// participant – The participant aggregating its shares.
+/// share_packages – The share packages addressed to the participant, one from each participant.
+pub fn aggregate_shares_for(
+    config: &DkgConfig,
+    participant: Participant,
+    share_packages: &[&SharePackage],
+) -> Result<ShareDetails, KeygenError> {
+    if !config.participants.contains(&participant) {
+        return Err(KeygenError::InvalidParticipants);
+    }
+    if share_packages.len() != config.participants.len() {
+        return Err(KeygenError::MissingSharePackage);
+    }
+
+    let identity: zkp::PublicKey = participant.into();
+    if share_packages.iter().any(|package| {
+        package.index != identity || package.coefficient_commitments.len() != config.threshold
+    }) {
+        return Err(KeygenError::InvalidParticipants);
+    }
+
     let intermediate_shares = share_packages
         .iter()
         .map(|package| &package.intermediate_share)
@@ -78,31 +164,14 @@ pub fn aggregate_shares(
         &intermediate_shares,
         &vss_commitment_refs,
         &poks,
-        &participant.into(),
-        DKG_THRESHOLD,
+        &identity,
+        config.threshold,
     )
     .map_err(|_| KeygenError::ShareAggregationFailed)?;
 
-    let app_verification_share = VerificationShare::new(
-        zkp::SECP256K1,
-        &vss_commitments,
-        &Participant::App.into(),
-        DKG_PARTICIPANTS,
-    )
-    .map_err(|_| KeygenError::VerificationShareGenerationFailed)?;
-    let server_verification_share = VerificationShare::new(
-        zkp::SECP256K1,
-        &vss_commitments,
-        &Participant::Server.into(),
-        DKG_PARTICIPANTS,
-    )
-    .map_err(|_| KeygenError::VerificationShareGenerationFailed)?;
-
-    let aggregate_public_key = FrostPublicKey::from_verification_shares(
-        zkp::SECP256K1,
-        &[&app_verification_share, &server_verification_share],
-        &[&Participant::App.into(), &Participant::Server.into()],
-    );
+    let participants = config.participant_indices();
+    let aggregate_public_key = frost_public_key(&vss_commitments, &participants)
+        .ok_or(KeygenError::VerificationShareGenerationFailed)?;
 
     Ok(ShareDetails {
         key_commitments: KeyCommitments {
@@ -113,6 +182,7 @@ pub fn aggregate_shares(
                 .into_iter()
                 .map(|zkp_public_key| ZkpPublicKey(zkp_public_key).into())
                 .collect::<Vec<PublicKey>>(),
+            participants,
         },
         secret_share,
     })
@@ -251,9 +321,16 @@ mod tests {
     use secp256k1_zkp::{new_frost_nonce_pair, Message, Scalar};
 
     use crate::frost::dkg::{equality_check, generate_share_packages};
-    use crate::frost::Participant::{self, App, Server};
+    use crate::frost::tests::{run_dkg, sign_and_verify};
+    use crate::frost::Participant::{self, App, Hardware, Recovery, Server};
+    use crate::frost::{KeyCommitments, APP_PARTICIPANT_INDEX, SERVER_PARTICIPANT_INDEX};
 
-    use super::{aggregate_shares, app, server, DKG_PARTICIPANTS};
+    use super::{
+        aggregate_shares, aggregate_shares_for, app, generate_share_packages_for, server,
+        DkgConfig, KeygenError,
+    };
+
+    const DKG_PARTICIPANTS: usize = 2;
 
     #[test]
     fn test_equality_check() {
@@ -425,4 +502,101 @@ mod tests {
             .verify_schnorr(&app_agg_sig, &msg, &final_pubkey)
             .unwrap()
     }
+
+    #[test]
+    fn test_dkg_config() {
+        let config = DkgConfig::new(2, &[Hardware, Server, App]).unwrap();
+        assert_eq!(config.participants(), &[App, Server, Hardware]);
+        assert_eq!(config.threshold(), 2);
+        assert_eq!(
+            DkgConfig::default(),
+            DkgConfig::new(2, &[App, Server]).unwrap()
+        );
+
+        for (threshold, participants) in [
+            (0, &[App, Server][..]),
+            (3, &[App, Server][..]),
+            (1, &[App][..]),
+            (2, &[App, App, Server][..]),
+        ] {
+            assert_eq!(
+                DkgConfig::new(threshold, participants),
+                Err(KeygenError::InvalidParticipants)
+            );
+        }
+    }
+
+    #[test]
+    fn test_two_of_three_dkg_and_sign() {
+        let config = DkgConfig::new(2, &[App, Server, Recovery]).unwrap();
+        let share_details = run_dkg(&config);
+
+        for details in &share_details[1..] {
+            assert!(equality_check(&share_details[0].key_commitments, details.clone()).is_ok());
+        }
+        let key_commitments = &share_details[0].key_commitments;
+        assert_eq!(key_commitments.threshold(), 2);
+        assert_eq!(key_commitments.participants.len(), 3);
+
+        // Any two of the three participants can sign.
+        for (a, b) in [(0, 1), (0, 2), (1, 2)] {
+            assert!(sign_and_verify(&[
+                (config.participants()[a], &share_details[a]),
+                (config.participants()[b], &share_details[b]),
+            ]));
+        }
+    }
+
+    #[test]
+    fn test_aggregate_rejects_mismatched_packages() {
+        let config = DkgConfig::new(2, &[App, Server, Hardware]).unwrap();
+        let app_packages = generate_share_packages_for(&config).unwrap();
+        let server_packages = generate_share_packages_for(&config).unwrap();
+        let hardware_packages = generate_share_packages_for(&config).unwrap();
+
+        // Not a participant.
+        assert_eq!(
+            aggregate_shares_for(
+                &config,
+                Recovery,
+                &[&app_packages[0], &server_packages[0], &hardware_packages[0]],
+            ),
+            Err(KeygenError::InvalidParticipants)
+        );
+        // One package short.
+        assert_eq!(
+            aggregate_shares_for(&config, App, &[&app_packages[0], &server_packages[0]]),
+            Err(KeygenError::MissingSharePackage)
+        );
+        // A package addressed to someone else.
+        assert_eq!(
+            aggregate_shares_for(
+                &config,
+                App,
+                &[&app_packages[0], &server_packages[1], &hardware_packages[0]],
+            ),
+            Err(KeygenError::InvalidParticipants)
+        );
+        // Packages from a 2-of-3 run don't aggregate as 2-of-2.
+        assert_eq!(
+            aggregate_shares(App, &[&app_packages[0], &server_packages[0]]),
+            Err(KeygenError::InvalidParticipants)
+        );
+    }
+
+    #[test]
+    fn test_legacy_key_commitments_load_as_two_of_two() {
+        let share_details = run_dkg(&DkgConfig::default()).remove(0);
+        let key_commitments = share_details.key_commitments;
+        assert_eq!(
+            key_commitments.participants,
+            vec![APP_PARTICIPANT_INDEX, SERVER_PARTICIPANT_INDEX]
+        );
+
+        // Strip the participants field, as serialized before t-of-n support.
+        let mut legacy = serde_json::to_value(&key_commitments).unwrap();
+        legacy.as_object_mut().unwrap().remove("participants");
+        let loaded: KeyCommitments = serde_json::from_value(legacy).unwrap();
+        assert_eq!(loaded, key_commitments);
+    }
 }
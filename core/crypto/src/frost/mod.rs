@@ -8,7 +8,7 @@ use bitcoin::{
     Network,
 };
 
-use secp256k1_zkp::frost::CoefficientCommitment;
+use secp256k1_zkp::frost::{CoefficientCommitment, FrostPublicKey, VerificationShare};
 pub use secp256k1_zkp::{
     self as zkp,
     constants::{GENERATOR_X, GENERATOR_Y},
@@ -16,6 +16,9 @@ pub use secp256k1_zkp::{
 };
 
 pub mod dkg;
+mod polynomial;
+pub mod refresh;
+pub mod repair;
 pub mod signing;
 
 #[cfg(test)]
@@ -34,48 +37,129 @@ pub struct ShareDetails {
 pub struct KeyCommitments {
     pub vss_commitments: Vec<PublicKey>,
     pub aggregate_public_key: PublicKey,
+    /// Every participant holding a share of the key, in ascending index order. Commitments
+    /// serialized before t-of-n support omit this field; they are always 2-of-2 App/Server.
+    #[serde(default = "legacy_participants")]
+    pub participants: Vec<ParticipantIndex>,
+}
+
+fn legacy_participants() -> Vec<ParticipantIndex> {
+    vec![APP_PARTICIPANT_INDEX, SERVER_PARTICIPANT_INDEX]
 }
 
 impl PartialEq for KeyCommitments {
     fn eq(&self, other: &Self) -> bool {
         self.vss_commitments == other.vss_commitments
             && self.aggregate_public_key == other.aggregate_public_key
+            && self.participants == other.participants
     }
 }
 
 impl KeyCommitments {
+    /// Minimum number of participants required to sign. The aggregate VSS commitment has one
+    /// coefficient per degree of the sharing polynomial, i.e. `threshold` of them.
+    pub fn threshold(&self) -> usize {
+        self.vss_commitments.len()
+    }
+
     fn aggregate_coefficient_commitment(&self) -> CoefficientCommitment {
-        CoefficientCommitment::from_public_keys(
-            self.vss_commitments
-                .iter()
-                .map(|public_key| {
-                    let zkp_public_key: ZkpPublicKey = (*public_key).into();
-                    zkp_public_key.0
-                })
-                .collect(),
-        )
+        CoefficientCommitment::from_public_keys(self.zkp_vss_commitments())
+    }
+
+    fn zkp_vss_commitments(&self) -> Vec<zkp::PublicKey> {
+        self.vss_commitments
+            .iter()
+            .map(|public_key| {
+                let zkp_public_key: ZkpPublicKey = (*public_key).into();
+                zkp_public_key.0
+            })
+            .collect()
+    }
+
+    fn frost_public_key(&self) -> Option<FrostPublicKey> {
+        frost_public_key(&self.aggregate_coefficient_commitment(), &self.participants)
     }
 }
 
+/// Interpolates the FROST public key from every participant's verification share.
+///
+/// Returns `None` if any verification share cannot be computed, e.g. for a zero index.
+fn frost_public_key(
+    aggregate_commitment: &CoefficientCommitment,
+    participants: &[ParticipantIndex],
+) -> Option<FrostPublicKey> {
+    if participants.contains(&ParticipantIndex(0)) {
+        return None;
+    }
+
+    let identities = participants
+        .iter()
+        .map(|index| (*index).into())
+        .collect::<Vec<zkp::PublicKey>>();
+    let verification_shares = identities
+        .iter()
+        .map(|identity| {
+            VerificationShare::new(
+                zkp::SECP256K1,
+                aggregate_commitment,
+                identity,
+                participants.len(),
+            )
+            .ok()
+        })
+        .collect::<Option<Vec<VerificationShare>>>()?;
+
+    Some(FrostPublicKey::from_verification_shares(
+        zkp::SECP256K1,
+        &verification_shares.iter().collect::<Vec<_>>(),
+        &identities.iter().collect::<Vec<_>>(),
+    ))
+}
+
 /// A participant in the DKG protocol.
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Participant {
     App,
     Server,
+    Hardware,
+    Recovery,
+    TrustedContact,
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
 #[serde(crate = "bitcoin::secp256k1::serde")]
 pub struct ParticipantIndex(pub u8);
 
+// Indices are baked into every share, so they must never be reassigned.
 pub const APP_PARTICIPANT_INDEX: ParticipantIndex = ParticipantIndex(1);
 pub const SERVER_PARTICIPANT_INDEX: ParticipantIndex = ParticipantIndex(2);
+pub const HARDWARE_PARTICIPANT_INDEX: ParticipantIndex = ParticipantIndex(3);
+pub const RECOVERY_PARTICIPANT_INDEX: ParticipantIndex = ParticipantIndex(4);
+pub const TRUSTED_CONTACT_PARTICIPANT_INDEX: ParticipantIndex = ParticipantIndex(5);
 
 impl From<Participant> for ParticipantIndex {
     fn from(participant: Participant) -> Self {
         match participant {
             Participant::App => APP_PARTICIPANT_INDEX,
             Participant::Server => SERVER_PARTICIPANT_INDEX,
+            Participant::Hardware => HARDWARE_PARTICIPANT_INDEX,
+            Participant::Recovery => RECOVERY_PARTICIPANT_INDEX,
+            Participant::TrustedContact => TRUSTED_CONTACT_PARTICIPANT_INDEX,
+        }
+    }
+}
+
+impl TryFrom<ParticipantIndex> for Participant {
+    type Error = ParticipantIndex;
+
+    fn try_from(index: ParticipantIndex) -> Result<Self, Self::Error> {
+        match index {
+            APP_PARTICIPANT_INDEX => Ok(Participant::App),
+            SERVER_PARTICIPANT_INDEX => Ok(Participant::Server),
+            HARDWARE_PARTICIPANT_INDEX => Ok(Participant::Hardware),
+            RECOVERY_PARTICIPANT_INDEX => Ok(Participant::Recovery),
+            TRUSTED_CONTACT_PARTICIPANT_INDEX => Ok(Participant::TrustedContact),
+            _ => Err(index),
         }
     }
 }
@@ -106,8 +190,13 @@ impl From<ZkpSchnorrSignature> for Signature {
 /// We use participant indices (1, 2, ...) to derive an identity public key.
 impl From<Participant> for zkp::PublicKey {
     fn from(participant: Participant) -> Self {
+        ParticipantIndex::from(participant).into()
+    }
+}
+
+impl From<ParticipantIndex> for zkp::PublicKey {
+    fn from(index: ParticipantIndex) -> Self {
         let generator_point = get_generator_point();
-        let index: ParticipantIndex = participant.into();
         let mut index_bytes = [0u8; 32];
         index_bytes[31] = index.0;
         let participant_index_tweak =
@@ -0,0 +1,156 @@
+//! Scalar and polynomial arithmetic over participants' evaluation points, for the share refresh
+//! and repair protocols which libsecp256k1-zkp doesn't implement itself.
+//!
+//! Scalars are non-zero `SecretKey`s, so any operation that would produce zero returns `None`.
+//! This only happens with negligible probability for honestly generated values.
+
+use rand::{rngs::StdRng, RngCore, SeedableRng};
+use secp256k1_zkp::{
+    self as zkp,
+    constants::{CURVE_ORDER, ONE},
+    frost::FrostShare,
+    Scalar, SecretKey,
+};
+use sha2::{Digest, Sha256};
+
+use super::{KeyCommitments, ParticipantIndex};
+
+const FROST_INDEX_TAG: &[u8] = b"FROST/index";
+
+/// The x-coordinate at which a participant's share is evaluated.
+///
+/// This mirrors libsecp256k1-zkp's `secp256k1_frost_compute_indexhash`: the `FROST/index` tagged
+/// hash of the participant's 33-byte identity public key.
+pub(super) fn evaluation_point(index: ParticipantIndex) -> Option<SecretKey> {
+    if index.0 == 0 {
+        return None;
+    }
+
+    let identity: zkp::PublicKey = index.into();
+    let tag = Sha256::digest(FROST_INDEX_TAG);
+    let hash = Sha256::new()
+        .chain_update(tag)
+        .chain_update(tag)
+        .chain_update(identity.serialize())
+        .finalize();
+    SecretKey::from_slice(&hash).ok()
+}
+
+pub(super) fn random_scalar() -> SecretKey {
+    let mut rng = StdRng::from_entropy();
+    loop {
+        let mut bytes = [0u8; 32];
+        rng.fill_bytes(&mut bytes);
+        if let Ok(scalar) = SecretKey::from_slice(&bytes) {
+            return scalar;
+        }
+    }
+}
+
+pub(super) fn add(a: SecretKey, b: &SecretKey) -> Option<SecretKey> {
+    a.add_tweak(&Scalar::from(*b)).ok()
+}
+
+pub(super) fn sub(a: SecretKey, b: &SecretKey) -> Option<SecretKey> {
+    add(a, &b.negate())
+}
+
+pub(super) fn mul(a: SecretKey, b: &SecretKey) -> Option<SecretKey> {
+    a.mul_tweak(&Scalar::from(*b)).ok()
+}
+
+pub(super) fn sum(values: impl IntoIterator<Item = SecretKey>) -> Option<SecretKey> {
+    let mut values = values.into_iter();
+    let first = values.next()?;
+    values.try_fold(first, |acc, value| add(acc, &value))
+}
+
+fn one() -> SecretKey {
+    SecretKey::from_slice(&ONE).expect("One is a valid scalar.")
+}
+
+/// Inverts a scalar as `a^(n - 2)`, by Fermat's little theorem.
+fn invert(a: &SecretKey) -> SecretKey {
+    let mut exponent = CURVE_ORDER;
+    exponent[31] -= 2;
+
+    let mut result = one();
+    for byte in exponent {
+        for bit in (0..8).rev() {
+            result = mul(result, &result).expect("Product of non-zero scalars is non-zero.");
+            if (byte >> bit) & 1 == 1 {
+                result = mul(result, a).expect("Product of non-zero scalars is non-zero.");
+            }
+        }
+    }
+    result
+}
+
+/// Evaluates `a_1 x + a_2 x^2 + ... + a_k x^k`, a polynomial with a zero constant term, at `x`.
+pub(super) fn evaluate_without_constant(
+    coefficients: &[SecretKey],
+    x: &SecretKey,
+) -> Option<SecretKey> {
+    let mut acc: Option<SecretKey> = None;
+    for coefficient in coefficients.iter().rev() {
+        acc = Some(match acc {
+            None => *coefficient,
+            Some(acc) => add(mul(acc, x)?, coefficient)?,
+        });
+    }
+    mul(acc?, x)
+}
+
+/// Evaluates `C_0 + C_1 x + ... + C_k x^k` for coefficient commitments `C_i`, i.e. computes the
+/// public counterpart of a polynomial evaluation.
+pub(super) fn evaluate_commitments(
+    commitments: &[zkp::PublicKey],
+    x: &SecretKey,
+) -> Option<zkp::PublicKey> {
+    let mut acc: Option<zkp::PublicKey> = None;
+    for commitment in commitments.iter().rev() {
+        acc = Some(match acc {
+            None => *commitment,
+            Some(acc) => acc
+                .mul_tweak(zkp::SECP256K1, &Scalar::from(*x))
+                .ok()?
+                .combine(commitment)
+                .ok()?,
+        });
+    }
+    acc
+}
+
+/// The Lagrange coefficient for `x_i` over the points `xs` (which include `x_i`), evaluated at
+/// `at`.
+pub(super) fn lagrange_coefficient(
+    x_i: &SecretKey,
+    xs: &[SecretKey],
+    at: &SecretKey,
+) -> Option<SecretKey> {
+    let mut numerator = one();
+    let mut denominator = one();
+    for x_k in xs.iter().filter(|x_k| *x_k != x_i) {
+        numerator = mul(numerator, &sub(*at, x_k)?)?;
+        denominator = mul(denominator, &sub(*x_i, x_k)?)?;
+    }
+    mul(numerator, &invert(&denominator))
+}
+
+/// Whether `share` is the evaluation at `x` of the polynomial committed to by `key_commitments`.
+pub(super) fn share_matches_commitments(
+    key_commitments: &KeyCommitments,
+    x: &SecretKey,
+    share: &SecretKey,
+) -> bool {
+    evaluate_commitments(&key_commitments.zkp_vss_commitments(), x)
+        == Some(zkp::PublicKey::from_secret_key(zkp::SECP256K1, share))
+}
+
+pub(super) fn share_to_scalar(share: &FrostShare) -> Option<SecretKey> {
+    SecretKey::from_slice(&share.serialize()).ok()
+}
+
+pub(super) fn scalar_to_share(scalar: &SecretKey) -> Option<FrostShare> {
+    FrostShare::from_slice(&scalar.secret_bytes()).ok()
+}
@@ -0,0 +1,424 @@
+//! Proactive share refresh.
+//!
+//! Every participant deals a random polynomial with a zero constant term and sends each
+//! participant its evaluation, along with commitments to the polynomial's coefficients. Adding the
+//! evaluations to a share re-randomizes it without changing the shared secret, so the aggregate
+//! public key (and every address derived from it) stays the same, while shares from before the
+//! refresh can no longer be combined with shares from after it.
+
+use bitcoin::secp256k1::{
+    serde::{Deserialize, Serialize},
+    PublicKey,
+};
+use secp256k1_zkp::{self as zkp, frost::FrostShare, Scalar};
+use thiserror::Error;
+
+use super::polynomial::{
+    evaluate_commitments, evaluate_without_constant, evaluation_point, random_scalar,
+    scalar_to_share, share_matches_commitments, share_to_scalar, sum,
+};
+use super::{KeyCommitments, Participant, ParticipantIndex, ShareDetails, ZkpPublicKey};
+
+/// Generates one refresh package per participant of the key, in the key's participant order.
+pub fn generate_refresh_packages(
+    key_commitments: &KeyCommitments,
+) -> Result<Vec<RefreshPackage>, RefreshError> {
+    // The constant term is fixed at zero, so a degree-0 polynomial can't re-randomize anything.
+    let threshold = key_commitments.threshold();
+    if threshold < 2 {
+        return Err(RefreshError::InvalidKeyCommitments);
+    }
+
+    let coefficients = (1..threshold).map(|_| random_scalar()).collect::<Vec<_>>();
+    let coefficient_commitments = coefficients
+        .iter()
+        .map(|coefficient| zkp::PublicKey::from_secret_key(zkp::SECP256K1, coefficient))
+        .collect::<Vec<zkp::PublicKey>>();
+
+    key_commitments
+        .participants
+        .iter()
+        .map(|index| {
+            let x = evaluation_point(*index).ok_or(RefreshError::InvalidParticipants)?;
+            let refresh_share = evaluate_without_constant(&coefficients, &x)
+                .and_then(|share| scalar_to_share(&share))
+                .ok_or(RefreshError::RefreshPackageGenerationFailed)?;
+
+            Ok(RefreshPackage {
+                index: (*index).into(),
+                coefficient_commitments: coefficient_commitments.clone(),
+                refresh_share,
+            })
+        })
+        .collect()
+}
+
+/// Applies the refresh packages addressed to a participant to its share.
+///
+/// participant – The participant refreshing its share.
+/// share_details – The participant's current share.
+/// refresh_packages – The refresh packages addressed to the participant, one from each participant.
+pub fn refresh_shares(
+    participant: Participant,
+    share_details: &ShareDetails,
+    refresh_packages: &[&RefreshPackage],
+) -> Result<ShareDetails, RefreshError> {
+    let key_commitments = &share_details.key_commitments;
+    let index: ParticipantIndex = participant.into();
+    if !key_commitments.participants.contains(&index) {
+        return Err(RefreshError::InvalidParticipants);
+    }
+    if refresh_packages.len() != key_commitments.participants.len() {
+        return Err(RefreshError::MissingRefreshPackage);
+    }
+
+    let x = evaluation_point(index).ok_or(RefreshError::InvalidParticipants)?;
+    let secret_share =
+        share_to_scalar(&share_details.secret_share).ok_or(RefreshError::InvalidShareDetails)?;
+    if !share_matches_commitments(key_commitments, &x, &secret_share) {
+        return Err(RefreshError::InvalidShareDetails);
+    }
+
+    let identity: zkp::PublicKey = participant.into();
+    let mut refresh_shares = Vec::with_capacity(refresh_packages.len());
+    for package in refresh_packages {
+        if package.index != identity
+            || package.coefficient_commitments.len() != key_commitments.threshold() - 1
+        {
+            return Err(RefreshError::InvalidRefreshPackage);
+        }
+
+        // The evaluation must match the dealer's commitments: x * (D_1 + D_2 x + ...).
+        let refresh_share =
+            share_to_scalar(&package.refresh_share).ok_or(RefreshError::InvalidRefreshPackage)?;
+        let expected = evaluate_commitments(&package.coefficient_commitments, &x)
+            .and_then(|point| point.mul_tweak(zkp::SECP256K1, &Scalar::from(x)).ok());
+        let actual = zkp::PublicKey::from_secret_key(zkp::SECP256K1, &refresh_share);
+        if expected != Some(actual) {
+            return Err(RefreshError::InvalidRefreshPackage);
+        }
+        refresh_shares.push(refresh_share);
+    }
+
+    // The constant term commitment is the group key and stays as is; every other coefficient
+    // commitment absorbs the dealers' commitments.
+    let mut vss_commitments = key_commitments.zkp_vss_commitments();
+    for package in refresh_packages {
+        for (commitment, dealer_commitment) in vss_commitments[1..]
+            .iter_mut()
+            .zip(&package.coefficient_commitments)
+        {
+            *commitment = commitment
+                .combine(dealer_commitment)
+                .map_err(|_| RefreshError::ShareRefreshFailed)?;
+        }
+    }
+
+    let refreshed_key_commitments = KeyCommitments {
+        vss_commitments: vss_commitments
+            .into_iter()
+            .map(|zkp_public_key| ZkpPublicKey(zkp_public_key).into())
+            .collect::<Vec<PublicKey>>(),
+        aggregate_public_key: key_commitments.aggregate_public_key,
+        participants: key_commitments.participants.clone(),
+    };
+    let refreshed_share = sum(std::iter::once(secret_share).chain(refresh_shares))
+        .ok_or(RefreshError::ShareRefreshFailed)?;
+    if !share_matches_commitments(&refreshed_key_commitments, &x, &refreshed_share) {
+        return Err(RefreshError::ShareRefreshFailed);
+    }
+
+    Ok(ShareDetails {
+        secret_share: scalar_to_share(&refreshed_share).ok_or(RefreshError::ShareRefreshFailed)?,
+        key_commitments: refreshed_key_commitments,
+    })
+}
+
+pub fn equality_check(
+    peer_key_commitments: &KeyCommitments,
+    share_details: ShareDetails,
+) -> Result<ShareDetails, RefreshError> {
+    if peer_key_commitments != &share_details.key_commitments {
+        return Err(RefreshError::InvalidKeyCommitments);
+    }
+
+    Ok(share_details)
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(crate = "bitcoin::secp256k1::serde")]
+pub struct RefreshPackage {
+    index: zkp::PublicKey,
+    coefficient_commitments: Vec<zkp::PublicKey>,
+    refresh_share: FrostShare,
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum RefreshError {
+    #[error("Missing a refresh package from one or more participants")]
+    MissingRefreshPackage,
+    #[error("Unable to refresh shares for the given participants.")]
+    InvalidParticipants,
+    #[error("Invalid refresh package")]
+    InvalidRefreshPackage,
+    #[error("Share doesn't match its key commitments")]
+    InvalidShareDetails,
+    #[error("Invalid key commitments")]
+    InvalidKeyCommitments,
+    #[error("Unable to generate refresh package")]
+    RefreshPackageGenerationFailed,
+    #[error("Unable to refresh share")]
+    ShareRefreshFailed,
+}
+
+fn package_for(
+    packages: &[RefreshPackage],
+    participant: Participant,
+) -> Result<RefreshPackage, RefreshError> {
+    let identity: zkp::PublicKey = participant.into();
+    packages
+        .iter()
+        .find(|package| package.index == identity)
+        .cloned()
+        .ok_or(RefreshError::InvalidParticipants)
+}
+
+pub mod server {
+    use crate::frost::{KeyCommitments, Participant, ShareDetails};
+    use bitcoin::secp256k1::serde::{Deserialize, Serialize};
+
+    use super::{
+        equality_check, generate_refresh_packages, package_for, refresh_shares, RefreshError,
+        RefreshPackage,
+    };
+
+    #[derive(Deserialize, Serialize)]
+    #[serde(crate = "bitcoin::secp256k1::serde")]
+    pub struct InitiateRefreshResult {
+        pub refresh_package: RefreshPackage,
+        pub share_details: ShareDetails,
+    }
+
+    pub fn initiate_refresh(
+        share_details: &ShareDetails,
+        peer_refresh_package: &RefreshPackage,
+    ) -> Result<InitiateRefreshResult, RefreshError> {
+        let refresh_packages = generate_refresh_packages(&share_details.key_commitments)?;
+
+        let refresh_package_for_app = package_for(&refresh_packages, Participant::App)?;
+        let refresh_package_for_server = package_for(&refresh_packages, Participant::Server)?;
+
+        let share_details = refresh_shares(
+            Participant::Server,
+            share_details,
+            &[peer_refresh_package, &refresh_package_for_server],
+        )?;
+
+        Ok(InitiateRefreshResult {
+            refresh_package: refresh_package_for_app,
+            share_details,
+        })
+    }
+
+    pub fn continue_refresh(
+        share_details: ShareDetails,
+        peer_key_commitments: &KeyCommitments,
+    ) -> Result<ShareDetails, RefreshError> {
+        equality_check(peer_key_commitments, share_details)
+    }
+}
+
+pub mod app {
+    use crate::frost::{KeyCommitments, Participant, ShareDetails};
+
+    use super::{
+        equality_check, generate_refresh_packages, package_for, refresh_shares, RefreshError,
+        RefreshPackage,
+    };
+
+    pub struct InitialRefreshPackage {
+        pub refresh_package: RefreshPackage,
+        pub refresh_package_for_peer: RefreshPackage,
+    }
+
+    pub fn initiate_refresh(
+        share_details: &ShareDetails,
+    ) -> Result<InitialRefreshPackage, RefreshError> {
+        let refresh_packages = generate_refresh_packages(&share_details.key_commitments)?;
+
+        let refresh_package = package_for(&refresh_packages, Participant::App)?;
+        let refresh_package_for_peer = package_for(&refresh_packages, Participant::Server)?;
+
+        Ok(InitialRefreshPackage {
+            refresh_package,
+            refresh_package_for_peer,
+        })
+    }
+
+    pub fn continue_refresh(
+        share_details: &ShareDetails,
+        refresh_package: &RefreshPackage,
+        peer_refresh_package: &RefreshPackage,
+        peer_key_commitments: &KeyCommitments,
+    ) -> Result<ShareDetails, RefreshError> {
+        let share_details = refresh_shares(
+            Participant::App,
+            share_details,
+            &[refresh_package, peer_refresh_package],
+        )?;
+        equality_check(peer_key_commitments, share_details)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::Network;
+
+    use crate::frost::compute_frost_master_xpub;
+    use crate::frost::dkg::DkgConfig;
+    use crate::frost::tests::{run_dkg, sign_and_verify};
+    use crate::frost::Participant::{App, Recovery, Server};
+    use crate::frost::ShareDetails;
+
+    use super::{
+        app, generate_refresh_packages, refresh_shares, server, RefreshError, RefreshPackage,
+    };
+
+    /// Runs a refresh for every participant of `config`, returning the refreshed shares in order.
+    fn run_refresh(config: &DkgConfig, share_details: &[ShareDetails]) -> Vec<ShareDetails> {
+        let refresh_packages = share_details
+            .iter()
+            .map(|details| generate_refresh_packages(&details.key_commitments).unwrap())
+            .collect::<Vec<_>>();
+
+        config
+            .participants()
+            .iter()
+            .enumerate()
+            .map(|(index, participant)| {
+                let packages = refresh_packages
+                    .iter()
+                    .map(|packages| &packages[index])
+                    .collect::<Vec<_>>();
+                refresh_shares(*participant, &share_details[index], &packages).unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_refresh_keeps_public_key() {
+        let config = DkgConfig::new(2, &[App, Server, Recovery]).unwrap();
+        let share_details = run_dkg(&config);
+        let refreshed = run_refresh(&config, &share_details);
+
+        let key_commitments = &share_details[0].key_commitments;
+        for (before, after) in share_details.iter().zip(&refreshed) {
+            assert_ne!(before.secret_share, after.secret_share);
+            assert_eq!(after.key_commitments, refreshed[0].key_commitments);
+            assert_eq!(
+                after.key_commitments.aggregate_public_key,
+                key_commitments.aggregate_public_key
+            );
+            assert_eq!(
+                after.key_commitments.vss_commitments[0],
+                key_commitments.vss_commitments[0]
+            );
+        }
+        assert_eq!(
+            compute_frost_master_xpub(
+                refreshed[0].key_commitments.aggregate_public_key,
+                Network::Bitcoin
+            ),
+            compute_frost_master_xpub(key_commitments.aggregate_public_key, Network::Bitcoin)
+        );
+
+        // Refreshed shares sign for the same key, but can't be mixed with stale ones.
+        assert!(sign_and_verify(&[
+            (App, &refreshed[0]),
+            (Recovery, &refreshed[2])
+        ]));
+        assert!(sign_and_verify(&[
+            (Server, &refreshed[1]),
+            (Recovery, &refreshed[2])
+        ]));
+        assert!(!sign_and_verify(&[
+            (App, &refreshed[0]),
+            (Server, &share_details[1])
+        ]));
+    }
+
+    #[test]
+    fn test_wrappers() {
+        let share_details = run_dkg(&DkgConfig::default());
+        let (app_share_details, server_share_details) = (&share_details[0], &share_details[1]);
+
+        let app_initiate_result = app::initiate_refresh(app_share_details).unwrap();
+        let server_initiate_result = server::initiate_refresh(
+            server_share_details,
+            &app_initiate_result.refresh_package_for_peer,
+        )
+        .unwrap();
+
+        let app_continue_result = app::continue_refresh(
+            app_share_details,
+            &app_initiate_result.refresh_package,
+            &server_initiate_result.refresh_package,
+            &server_initiate_result.share_details.key_commitments,
+        )
+        .unwrap();
+        let server_continue_result = server::continue_refresh(
+            server_initiate_result.share_details,
+            &app_continue_result.key_commitments,
+        )
+        .unwrap();
+
+        assert_eq!(
+            app_continue_result.key_commitments.aggregate_public_key,
+            app_share_details.key_commitments.aggregate_public_key
+        );
+        assert!(sign_and_verify(&[
+            (App, &app_continue_result),
+            (Server, &server_continue_result)
+        ]));
+    }
+
+    #[test]
+    fn test_refresh_rejects_invalid_packages() {
+        let config = DkgConfig::default();
+        let share_details = run_dkg(&config);
+        let app_packages = generate_refresh_packages(&share_details[0].key_commitments).unwrap();
+        let server_packages = generate_refresh_packages(&share_details[1].key_commitments).unwrap();
+
+        assert_eq!(
+            refresh_shares(App, &share_details[0], &[&app_packages[0]]),
+            Err(RefreshError::MissingRefreshPackage)
+        );
+        // A package addressed to the server.
+        assert_eq!(
+            refresh_shares(
+                App,
+                &share_details[0],
+                &[&app_packages[0], &server_packages[1]]
+            ),
+            Err(RefreshError::InvalidRefreshPackage)
+        );
+        // A refresh share that doesn't match the dealer's commitments.
+        let forged = RefreshPackage {
+            refresh_share: server_packages[1].refresh_share,
+            ..server_packages[0].clone()
+        };
+        assert_eq!(
+            refresh_shares(App, &share_details[0], &[&app_packages[0], &forged]),
+            Err(RefreshError::InvalidRefreshPackage)
+        );
+        // Someone else's share.
+        assert_eq!(
+            refresh_shares(
+                App,
+                &share_details[1],
+                &[&app_packages[0], &server_packages[0]]
+            ),
+            Err(RefreshError::InvalidShareDetails)
+        );
+    }
+}
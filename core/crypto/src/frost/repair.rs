@@ -0,0 +1,386 @@
+//! Share repair.
+//!
+//! Rebuilds a participant's lost share from the shares of at least `threshold` helpers, without
+//! any of them learning the share or the secret:
+//!
+//! 1. Each helper scales its share by its Lagrange coefficient at the lost participant's
+//!    evaluation point, splits the result into random additive pieces, and sends one piece to
+//!    each helper ([`generate_repair_packages`]).
+//! 2. Each helper sums the pieces it received and sends the sum to the lost participant
+//!    ([`aggregate_repair_packages`]).
+//! 3. The lost participant sums those and checks the result against the key commitments
+//!    ([`repair_share`]).
+
+use bitcoin::secp256k1::serde::{Deserialize, Serialize};
+use secp256k1_zkp::{frost::FrostShare, SecretKey};
+use thiserror::Error;
+
+use super::polynomial::{
+    evaluation_point, lagrange_coefficient, mul, random_scalar, scalar_to_share,
+    share_matches_commitments, share_to_scalar, sub, sum,
+};
+use super::{KeyCommitments, Participant, ParticipantIndex, ShareDetails};
+
+/// Generates a helper's repair packages, one per helper, in ascending participant index order.
+///
+/// helper – The participant generating the packages.
+/// share_details – The helper's share.
+/// helpers – Every participant helping with the repair, including `helper`.
+/// lost – The participant whose share is being repaired.
+pub fn generate_repair_packages(
+    helper: Participant,
+    share_details: &ShareDetails,
+    helpers: &[Participant],
+    lost: Participant,
+) -> Result<Vec<RepairPackage>, RepairError> {
+    let key_commitments = &share_details.key_commitments;
+    let helpers = validate_helpers(key_commitments, helpers, lost)?;
+    let sender: ParticipantIndex = helper.into();
+    let lost: ParticipantIndex = lost.into();
+    if !helpers.contains(&sender) {
+        return Err(RepairError::InvalidParticipants);
+    }
+
+    let x = evaluation_point(sender).ok_or(RepairError::InvalidParticipants)?;
+    let secret_share =
+        share_to_scalar(&share_details.secret_share).ok_or(RepairError::InvalidShareDetails)?;
+    if !share_matches_commitments(key_commitments, &x, &secret_share) {
+        return Err(RepairError::InvalidShareDetails);
+    }
+
+    let helper_points = helpers
+        .iter()
+        .map(|index| evaluation_point(*index).ok_or(RepairError::InvalidParticipants))
+        .collect::<Result<Vec<SecretKey>, RepairError>>()?;
+    let lost_point = evaluation_point(lost).ok_or(RepairError::InvalidParticipants)?;
+    let contribution = lagrange_coefficient(&x, &helper_points, &lost_point)
+        .and_then(|coefficient| mul(coefficient, &secret_share))
+        .ok_or(RepairError::RepairPackageGenerationFailed)?;
+
+    // Random pieces for every helper but the last, who gets whatever makes them sum up.
+    let mut pieces = (1..helpers.len())
+        .map(|_| random_scalar())
+        .collect::<Vec<_>>();
+    let last_piece = match sum(pieces.iter().copied()) {
+        Some(total) => sub(contribution, &total),
+        None => Some(contribution),
+    }
+    .ok_or(RepairError::RepairPackageGenerationFailed)?;
+    pieces.push(last_piece);
+
+    helpers
+        .iter()
+        .zip(pieces)
+        .map(|(recipient, piece)| {
+            Ok(RepairPackage {
+                sender,
+                recipient: *recipient,
+                lost,
+                piece: scalar_to_share(&piece).ok_or(RepairError::RepairPackageGenerationFailed)?,
+            })
+        })
+        .collect()
+}
+
+/// Sums the repair packages addressed to a helper into its repair share for the lost participant.
+///
+/// helper – The participant aggregating the packages.
+/// lost – The participant whose share is being repaired.
+/// repair_packages – The repair packages addressed to the helper, one from each helper.
+pub fn aggregate_repair_packages(
+    helper: Participant,
+    lost: Participant,
+    repair_packages: &[&RepairPackage],
+) -> Result<RepairShare, RepairError> {
+    let recipient: ParticipantIndex = helper.into();
+    let lost: ParticipantIndex = lost.into();
+    if repair_packages.is_empty() {
+        return Err(RepairError::MissingRepairPackage);
+    }
+
+    let mut senders = Vec::with_capacity(repair_packages.len());
+    let mut pieces = Vec::with_capacity(repair_packages.len());
+    for package in repair_packages {
+        if package.recipient != recipient
+            || package.lost != lost
+            || senders.contains(&package.sender)
+        {
+            return Err(RepairError::InvalidRepairPackage);
+        }
+        senders.push(package.sender);
+        pieces.push(share_to_scalar(&package.piece).ok_or(RepairError::InvalidRepairPackage)?);
+    }
+
+    let repair_share = sum(pieces)
+        .and_then(|total| scalar_to_share(&total))
+        .ok_or(RepairError::ShareRepairFailed)?;
+    Ok(RepairShare {
+        sender: recipient,
+        lost,
+        repair_share,
+    })
+}
+
+/// Rebuilds the lost participant's share from every helper's repair share.
+///
+/// lost – The participant whose share is being repaired.
+/// key_commitments – The key's commitments, as held by any participant.
+/// repair_shares – The repair shares addressed to the lost participant, one from each helper.
+pub fn repair_share(
+    lost: Participant,
+    key_commitments: &KeyCommitments,
+    repair_shares: &[&RepairShare],
+) -> Result<ShareDetails, RepairError> {
+    let lost: ParticipantIndex = lost.into();
+    if !key_commitments.participants.contains(&lost) {
+        return Err(RepairError::InvalidParticipants);
+    }
+    if repair_shares.len() < key_commitments.threshold() {
+        return Err(RepairError::MissingRepairPackage);
+    }
+
+    let mut senders = Vec::with_capacity(repair_shares.len());
+    let mut pieces = Vec::with_capacity(repair_shares.len());
+    for share in repair_shares {
+        if share.lost != lost
+            || senders.contains(&share.sender)
+            || !key_commitments.participants.contains(&share.sender)
+        {
+            return Err(RepairError::InvalidRepairPackage);
+        }
+        senders.push(share.sender);
+        pieces.push(share_to_scalar(&share.repair_share).ok_or(RepairError::InvalidRepairPackage)?);
+    }
+
+    let x = evaluation_point(lost).ok_or(RepairError::InvalidParticipants)?;
+    let secret_share = sum(pieces).ok_or(RepairError::ShareRepairFailed)?;
+    if !share_matches_commitments(key_commitments, &x, &secret_share) {
+        return Err(RepairError::ShareRepairFailed);
+    }
+
+    Ok(ShareDetails {
+        secret_share: scalar_to_share(&secret_share).ok_or(RepairError::ShareRepairFailed)?,
+        key_commitments: key_commitments.clone(),
+    })
+}
+
+/// Checks that `helpers` are enough distinct shareholders, excluding `lost`, and returns their
+/// indices in ascending order.
+fn validate_helpers(
+    key_commitments: &KeyCommitments,
+    helpers: &[Participant],
+    lost: Participant,
+) -> Result<Vec<ParticipantIndex>, RepairError> {
+    let lost: ParticipantIndex = lost.into();
+    let mut indices = helpers
+        .iter()
+        .map(|helper| ParticipantIndex::from(*helper))
+        .collect::<Vec<ParticipantIndex>>();
+    indices.sort();
+    indices.dedup();
+
+    if indices.len() != helpers.len()
+        || indices.len() < key_commitments.threshold()
+        || indices.contains(&lost)
+        || !key_commitments.participants.contains(&lost)
+        || indices
+            .iter()
+            .any(|index| !key_commitments.participants.contains(index))
+    {
+        return Err(RepairError::InvalidParticipants);
+    }
+
+    Ok(indices)
+}
+
+/// A piece of a helper's contribution to a repair, sent to another helper.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(crate = "bitcoin::secp256k1::serde")]
+pub struct RepairPackage {
+    sender: ParticipantIndex,
+    recipient: ParticipantIndex,
+    lost: ParticipantIndex,
+    piece: FrostShare,
+}
+
+/// A helper's sum of the pieces it received, sent to the lost participant.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(crate = "bitcoin::secp256k1::serde")]
+pub struct RepairShare {
+    sender: ParticipantIndex,
+    lost: ParticipantIndex,
+    repair_share: FrostShare,
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum RepairError {
+    #[error("Missing a repair package from one or more helpers")]
+    MissingRepairPackage,
+    #[error("Unable to repair a share with the given participants.")]
+    InvalidParticipants,
+    #[error("Invalid repair package")]
+    InvalidRepairPackage,
+    #[error("Share doesn't match its key commitments")]
+    InvalidShareDetails,
+    #[error("Unable to generate repair package")]
+    RepairPackageGenerationFailed,
+    #[error("Repaired share doesn't match the key commitments")]
+    ShareRepairFailed,
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::Network;
+
+    use crate::frost::compute_frost_master_xpub;
+    use crate::frost::dkg::DkgConfig;
+    use crate::frost::tests::{run_dkg, sign_and_verify};
+    use crate::frost::Participant::{self, App, Hardware, Server, TrustedContact};
+    use crate::frost::ShareDetails;
+
+    use super::{
+        aggregate_repair_packages, generate_repair_packages, repair_share, RepairError, RepairShare,
+    };
+
+    /// Runs a repair of `lost`'s share with the given helpers and their shares.
+    fn run_repair(
+        helpers: &[(Participant, &ShareDetails)],
+        lost: Participant,
+    ) -> Result<ShareDetails, RepairError> {
+        let helper_participants = helpers
+            .iter()
+            .map(|(participant, _)| *participant)
+            .collect::<Vec<_>>();
+        let repair_packages = helpers
+            .iter()
+            .map(|(participant, details)| {
+                generate_repair_packages(*participant, details, &helper_participants, lost)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let repair_shares = helpers
+            .iter()
+            .enumerate()
+            .map(|(index, (participant, _))| {
+                let packages = repair_packages
+                    .iter()
+                    .map(|packages| &packages[index])
+                    .collect::<Vec<_>>();
+                aggregate_repair_packages(*participant, lost, &packages)
+            })
+            .collect::<Result<Vec<RepairShare>, _>>()?;
+
+        repair_share(
+            lost,
+            &helpers[0].1.key_commitments,
+            &repair_shares.iter().collect::<Vec<_>>(),
+        )
+    }
+
+    #[test]
+    fn test_repair_restores_lost_share() {
+        let config = DkgConfig::new(2, &[App, Server, Hardware, TrustedContact]).unwrap();
+        let share_details = run_dkg(&config);
+
+        // The server and the trusted contact rebuild the app's share.
+        let repaired = run_repair(
+            &[
+                (Server, &share_details[1]),
+                (TrustedContact, &share_details[3]),
+            ],
+            App,
+        )
+        .unwrap();
+        assert_eq!(repaired, share_details[0]);
+        assert_eq!(
+            compute_frost_master_xpub(
+                repaired.key_commitments.aggregate_public_key,
+                Network::Bitcoin
+            ),
+            compute_frost_master_xpub(
+                share_details[0].key_commitments.aggregate_public_key,
+                Network::Bitcoin
+            )
+        );
+        assert!(sign_and_verify(&[
+            (App, &repaired),
+            (Hardware, &share_details[2])
+        ]));
+
+        // More helpers than the threshold works too.
+        let repaired = run_repair(
+            &[
+                (App, &share_details[0]),
+                (Server, &share_details[1]),
+                (TrustedContact, &share_details[3]),
+            ],
+            Hardware,
+        )
+        .unwrap();
+        assert_eq!(repaired, share_details[2]);
+    }
+
+    #[test]
+    fn test_repair_rejects_invalid_helpers() {
+        let config = DkgConfig::new(3, &[App, Server, Hardware]).unwrap();
+        let share_details = run_dkg(&config);
+
+        // Below the threshold.
+        assert_eq!(
+            run_repair(
+                &[(Server, &share_details[1]), (Hardware, &share_details[2])],
+                App
+            ),
+            Err(RepairError::InvalidParticipants)
+        );
+        // The lost participant can't help.
+        assert_eq!(
+            generate_repair_packages(App, &share_details[0], &[App, Server, Hardware], App),
+            Err(RepairError::InvalidParticipants)
+        );
+
+        // Helper shares must match their participant.
+        let config = DkgConfig::new(2, &[App, Server, Hardware]).unwrap();
+        let share_details = run_dkg(&config);
+        assert_eq!(
+            generate_repair_packages(Server, &share_details[2], &[Server, Hardware], App),
+            Err(RepairError::InvalidShareDetails)
+        );
+    }
+
+    #[test]
+    fn test_repair_rejects_wrong_repair_shares() {
+        let config = DkgConfig::new(2, &[App, Server, Hardware]).unwrap();
+        let share_details = run_dkg(&config);
+        let other_share_details = run_dkg(&config);
+
+        // Helpers from a different key produce a share that doesn't match the commitments.
+        let packages = [
+            generate_repair_packages(Server, &share_details[1], &[Server, Hardware], App).unwrap(),
+            generate_repair_packages(Hardware, &other_share_details[2], &[Server, Hardware], App)
+                .unwrap(),
+        ];
+        let repair_shares = [
+            aggregate_repair_packages(Server, App, &[&packages[0][0], &packages[1][0]]).unwrap(),
+            aggregate_repair_packages(Hardware, App, &[&packages[0][1], &packages[1][1]]).unwrap(),
+        ];
+        assert_eq!(
+            repair_share(
+                App,
+                &share_details[0].key_commitments,
+                &[&repair_shares[0], &repair_shares[1]]
+            ),
+            Err(RepairError::ShareRepairFailed)
+        );
+
+        // Packages addressed to another helper.
+        assert_eq!(
+            aggregate_repair_packages(Server, App, &[&packages[0][1]]),
+            Err(RepairError::InvalidRepairPackage)
+        );
+        assert_eq!(
+            repair_share(App, &share_details[0].key_commitments, &[&repair_shares[0]]),
+            Err(RepairError::MissingRepairPackage)
+        );
+    }
+}
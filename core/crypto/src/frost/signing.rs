@@ -3,34 +3,39 @@ use bitcoin::hashes::{sha512, Hash, HashEngine, Hmac, HmacEngine};
 use bitcoin::psbt::{Input, Psbt};
 use bitcoin::secp256k1::{
     serde::{Deserialize, Serialize},
-    PublicKey,
+    PublicKey, XOnlyPublicKey,
 };
-use bitcoin::sighash::SighashCache;
-use bitcoin::taproot::{TapNodeHash, TapTweakHash};
+use bitcoin::sighash::{SighashCache, TapSighashType};
+use bitcoin::taproot::{TapLeafHash, TapNodeHash, TapTweakHash};
 use miniscript::psbt::{PsbtExt, PsbtSighashMsg, SighashError};
 use secp256k1_zkp::frost::{
     FrostPartialSignature, FrostPublicKey, FrostPublicNonce, FrostSecretNonce, FrostSession,
-    FrostSessionId, VerificationShare,
+    FrostSessionId,
 };
+use std::collections::BTreeMap;
 
 use secp256k1_zkp::{new_frost_nonce_pair, Message};
 
 use thiserror::Error;
 
-use super::{Participant, ShareDetails, ZkpPublicKey, ZkpSchnorrSignature, FROST_CHAINCODE};
-
-static NUM_PARTICIPANTS: usize = 2;
+use super::{
+    Participant, ParticipantIndex, ShareDetails, ZkpPublicKey, ZkpSchnorrSignature, FROST_CHAINCODE,
+};
 
 /// A signer struct used to sign a PSBT.
 pub struct Signer {
     participant: Participant,
+    /// Every participant in this signing session, including `participant`, in ascending index
+    /// order.
+    signing_participants: Vec<ParticipantIndex>,
     pub psbt: Psbt,
     signables: Vec<Signable>,
     share_details: ShareDetails,
 }
 
 impl Signer {
-    /// Create a new signer.
+    /// Create a new signer, with every participant holding a share of the key in the signing
+    /// session. Use [`Signer::with_signing_participants`] to sign with a subset.
     ///
     /// # Arguments
     ///
@@ -46,9 +51,56 @@ impl Signer {
         psbt: Psbt,
         share_details: ShareDetails,
     ) -> Result<Self, SigningError> {
+        let signing_participants = share_details
+            .key_commitments
+            .participants
+            .iter()
+            .filter_map(|index| Participant::try_from(*index).ok())
+            .collect::<Vec<Participant>>();
+        Signer::with_signing_participants(participant, &signing_participants, psbt, share_details)
+    }
+
+    /// Create a new signer for a subset of the key's participants, e.g. two of the three
+    /// participants of a 2-of-3 key.
+    ///
+    /// # Arguments
+    ///
+    /// * `participant` - The participant to sign the PSBT
+    /// * `signing_participants` - Every participant in the signing session, including `participant`
+    /// * `psbt` - The PSBT to sign
+    /// * `share_details` - The FROST secret share to use for signing
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Result` containing the new `Signer` instance if successful, or a `SigningError` if
+    /// the signing participants don't meet the key's threshold or aren't all shareholders
+    pub fn with_signing_participants(
+        participant: Participant,
+        signing_participants: &[Participant],
+        psbt: Psbt,
+        share_details: ShareDetails,
+    ) -> Result<Self, SigningError> {
+        let key_participants = &share_details.key_commitments.participants;
+        let mut signing_participants = signing_participants
+            .iter()
+            .map(|participant| ParticipantIndex::from(*participant))
+            .collect::<Vec<ParticipantIndex>>();
+        signing_participants.sort();
+        signing_participants.dedup();
+
+        if !signing_participants.contains(&participant.into())
+            || signing_participants.len() < share_details.key_commitments.threshold()
+            || signing_participants
+                .iter()
+                .any(|index| !key_participants.contains(index))
+        {
+            return Err(SigningError::InvalidSigningParticipants);
+        }
+
         let signables = Signer::generate_signables(&psbt, &share_details)?;
         Ok(Self {
             participant,
+            signing_participants,
             psbt,
             signables,
             share_details,
@@ -69,6 +121,8 @@ impl Signer {
 
     /// Generate partial signatures for the PSBT using the signing commitments from the counterparty.
     ///
+    /// Only valid when there is exactly one other participant in the signing session.
+    ///
     /// # Arguments
     ///
     /// * `counterparty_commitments` - The signing commitments from the counterparty
@@ -80,39 +134,66 @@ impl Signer {
         &mut self,
         counterparty_commitments: Vec<SigningCommitment>,
     ) -> Result<Vec<FrostPartialSignature>, SigningError> {
-        if self.signables.len() != counterparty_commitments.len() {
+        let counterparty = self.counterparty()?;
+        self.generate_partial_signatures_with_counterparties(BTreeMap::from([(
+            counterparty,
+            counterparty_commitments,
+        )]))
+    }
+
+    /// Generate partial signatures for the PSBT using the signing commitments from every other
+    /// participant in the signing session.
+    ///
+    /// # Arguments
+    ///
+    /// * `counterparty_commitments` - The signing commitments from each counterparty, keyed by
+    ///   participant index
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Vec` of `FrostPartialSignature` instances if successful, or a `SigningError` if generation fails
+    pub fn generate_partial_signatures_with_counterparties(
+        &mut self,
+        counterparty_commitments: BTreeMap<ParticipantIndex, Vec<SigningCommitment>>,
+    ) -> Result<Vec<FrostPartialSignature>, SigningError> {
+        if !self.is_counterparty_set(counterparty_commitments.keys())
+            || counterparty_commitments
+                .values()
+                .any(|commitments| commitments.len() != self.signables.len())
+        {
             return Err(SigningError::InvalidCounterpartyCommitments);
         }
 
-        let zipped: Vec<_> = counterparty_commitments
+        let own_index: ParticipantIndex = self.participant.into();
+        let participant_identities = self
+            .signing_participants
             .iter()
-            .zip(self.signables.iter_mut())
-            .collect();
-
-        let mut partial_signatures = Vec::with_capacity(zipped.len());
-        for (counterparty_commitment, signable) in zipped {
-            // Verify that the counterparty commitments match the signables
-            if counterparty_commitment.signable_idx
-                != signable.signing_nonce_pair.signing_commitment.signable_idx
-            {
-                return Err(SigningError::CommitmentMismatch {
-                    expected: signable.signing_nonce_pair.signing_commitment.signable_idx,
-                    got: counterparty_commitment.signable_idx,
-                });
-            }
+            .map(|index| (*index).into())
+            .collect::<Vec<secp256k1_zkp::PublicKey>>();
+        let participant_identity_refs = participant_identities.iter().collect::<Vec<_>>();
+
+        let mut partial_signatures = Vec::with_capacity(self.signables.len());
+        for (idx, signable) in self.signables.iter_mut().enumerate() {
+            let own_commitment = &signable.signing_nonce_pair.signing_commitment;
 
             // Ordering of nonces should be based on the ordering of the participants
-            let public_nonces = if self.participant == Participant::App {
-                vec![
-                    &signable.signing_nonce_pair.signing_commitment.public_nonce,
-                    &counterparty_commitment.public_nonce,
-                ]
-            } else {
-                vec![
-                    &counterparty_commitment.public_nonce,
-                    &signable.signing_nonce_pair.signing_commitment.public_nonce,
-                ]
-            };
+            let mut public_nonces = Vec::with_capacity(self.signing_participants.len());
+            for index in &self.signing_participants {
+                let commitment = if *index == own_index {
+                    own_commitment
+                } else {
+                    &counterparty_commitments[index][idx]
+                };
+
+                // Verify that the counterparty commitments match the signables
+                if commitment.signable_idx != own_commitment.signable_idx {
+                    return Err(SigningError::CommitmentMismatch {
+                        expected: own_commitment.signable_idx,
+                        got: commitment.signable_idx,
+                    });
+                }
+                public_nonces.push(&commitment.public_nonce);
+            }
 
             let session = FrostSession::new(
                 secp256k1_zkp::SECP256K1,
@@ -120,7 +201,7 @@ impl Signer {
                 &signable.msg,
                 &signable.signing_public_key,
                 &self.participant.into(),
-                &[&Participant::App.into(), &Participant::Server.into()],
+                &participant_identity_refs,
                 None,
             );
 
@@ -146,6 +227,8 @@ impl Signer {
 
     /// A function that aggregates partial signatures and signs the PSBT.
     ///
+    /// Only valid when there is exactly one other participant in the signing session.
+    ///
     /// # Arguments
     ///
     /// * `partial_signatures` - The partial signatures to use for signing
@@ -159,27 +242,70 @@ impl Signer {
         partial_signatures: Vec<FrostPartialSignature>,
         counterparty_partial_signatures: Vec<FrostPartialSignature>,
     ) -> Result<Psbt, SigningError> {
+        let counterparty = self.counterparty()?;
+        self.sign_psbt_with_counterparties(
+            partial_signatures,
+            BTreeMap::from([(counterparty, counterparty_partial_signatures)]),
+        )
+    }
+
+    /// A function that aggregates the partial signatures of every participant in the signing
+    /// session and signs the PSBT.
+    ///
+    /// # Arguments
+    ///
+    /// * `partial_signatures` - The partial signatures to use for signing
+    /// * `counterparty_partial_signatures` - The partial signatures from each counterparty, keyed
+    ///   by participant index
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Psbt` instance if successful, or a `SigningError` if signing fails
+    pub fn sign_psbt_with_counterparties(
+        &mut self,
+        partial_signatures: Vec<FrostPartialSignature>,
+        counterparty_partial_signatures: BTreeMap<ParticipantIndex, Vec<FrostPartialSignature>>,
+    ) -> Result<Psbt, SigningError> {
+        if !self.is_counterparty_set(counterparty_partial_signatures.keys()) {
+            return Err(SigningError::InvalidCounterpartyPartialSignatures);
+        }
+
+        let own_index: ParticipantIndex = self.participant.into();
         for signable in self.signables.iter_mut() {
             let signable_idx = signable.signing_nonce_pair.signing_commitment.signable_idx as usize;
+            let sighash_type = signable.sighash_type;
             let session = signable
                 .frost_session
                 .as_ref()
                 .ok_or(SigningError::MissingCounterpartyNonces)?;
 
-            let partial_sig = partial_signatures[signable_idx];
-            let counterparty_partial_sig = counterparty_partial_signatures[signable_idx];
-            let final_sig = session.aggregate_partial_sigs(
-                secp256k1_zkp::SECP256K1,
-                &[&partial_sig, &counterparty_partial_sig],
-            );
-
-            let input = &mut self.psbt.inputs[signable_idx];
-            input.tap_key_sig = Some(bitcoin::taproot::Signature {
+            let partial_sigs = self
+                .signing_participants
+                .iter()
+                .map(|index| {
+                    let sigs = if *index == own_index {
+                        &partial_signatures
+                    } else {
+                        &counterparty_partial_signatures[index]
+                    };
+                    sigs.get(signable_idx)
+                        .ok_or(SigningError::InvalidCounterpartyPartialSignatures)
+                })
+                .collect::<Result<Vec<&FrostPartialSignature>, SigningError>>()?;
+            let final_sig = session.aggregate_partial_sigs(secp256k1_zkp::SECP256K1, &partial_sigs);
+
+            let signature = bitcoin::taproot::Signature {
                 signature: ZkpSchnorrSignature(final_sig).into(),
-                sighash_type: input
-                    .taproot_hash_ty()
-                    .map_err(|_| SigningError::InvalidPsbt)?,
-            });
+                sighash_type,
+            };
+
+            let input = &mut self.psbt.inputs[signable.input_idx];
+            match signable.spend_path {
+                SpendPath::KeyPath => input.tap_key_sig = Some(signature),
+                SpendPath::ScriptPath { x_only, leaf_hash } => {
+                    input.tap_script_sigs.insert((x_only, leaf_hash), signature);
+                }
+            }
 
             // Zero out the frost session to prevent reuse.
             signable.frost_session = None;
@@ -188,6 +314,32 @@ impl Signer {
         Ok(self.psbt.clone())
     }
 
+    /// The sole other participant in a two-party signing session.
+    fn counterparty(&self) -> Result<ParticipantIndex, SigningError> {
+        let own_index: ParticipantIndex = self.participant.into();
+        match self.signing_participants.as_slice() {
+            [a, b] if *a == own_index => Ok(*b),
+            [a, b] if *b == own_index => Ok(*a),
+            _ => Err(SigningError::InvalidSigningParticipants),
+        }
+    }
+
+    /// Whether `indices` is exactly the signing participants other than this signer.
+    fn is_counterparty_set<'a>(&self, indices: impl Iterator<Item = &'a ParticipantIndex>) -> bool {
+        let own_index: ParticipantIndex = self.participant.into();
+        indices.copied().eq(self
+            .signing_participants
+            .iter()
+            .copied()
+            .filter(|index| *index != own_index))
+    }
+
+    /// Generates a signable for every spend path of every input that the FROST key can sign for.
+    ///
+    /// An input is signed on the key path when its tap key origins include the FROST key as the
+    /// internal key, tweaked with the input's merkle root as in BIP 341 (or BIP 86 when there is
+    /// none). Otherwise, it is signed on the script path for every leaf the origins list the key
+    /// under. Signables are ordered by input, then by leaf.
     fn generate_signables(
         psbt: &Psbt,
         share_details: &ShareDetails,
@@ -195,89 +347,128 @@ impl Signer {
         let mut signables = Vec::with_capacity(psbt.inputs.len());
         let mut sighash_cache = SighashCache::new(&psbt.unsigned_tx);
 
-        let aggregate_coefficient_commitment = share_details
+        let frost_public_key = share_details
             .key_commitments
-            .aggregate_coefficient_commitment();
-        let app_verification_share: VerificationShare = VerificationShare::new(
-            secp256k1_zkp::SECP256K1,
-            &aggregate_coefficient_commitment,
-            &Participant::App.into(),
-            NUM_PARTICIPANTS,
-        )
-        .expect("Failed to generate verification share");
-        let server_verification_share = VerificationShare::new(
-            secp256k1_zkp::SECP256K1,
-            &aggregate_coefficient_commitment,
-            &Participant::Server.into(),
-            NUM_PARTICIPANTS,
-        )
-        .expect("Failed to generate verification share");
-
-        let frost_public_key = FrostPublicKey::from_verification_shares(
-            secp256k1_zkp::SECP256K1,
-            &[&app_verification_share, &server_verification_share],
-            &[&Participant::App.into(), &Participant::Server.into()],
-        );
-
-        for idx in 0..psbt.inputs.len() {
-            let sighash_msg = psbt
-                .sighash_msg(idx, &mut sighash_cache, None)
-                .map_err(SigningError::UnableToRetrieveSighash)?;
-            let msg = Message::from_digest(match sighash_msg {
-                PsbtSighashMsg::TapSighash(sighash) => sighash.to_raw_hash().to_byte_array(),
-                _ => return Err(SigningError::InvalidPsbt),
-            });
-
-            let mut frost_public_key = frost_public_key.clone();
+            .frost_public_key()
+            .ok_or(SigningError::InvalidShareDetails)?;
+
+        for (input_idx, input) in psbt.inputs.iter().enumerate() {
+            let sighash_type = input
+                .taproot_hash_ty()
+                .map_err(|_| SigningError::InvalidPsbt)?;
+
+            for (spend_path, signing_public_key) in Signer::spend_paths(input, &frost_public_key)? {
+                let leaf_hash = match spend_path {
+                    SpendPath::KeyPath => None,
+                    SpendPath::ScriptPath { leaf_hash, .. } => Some(leaf_hash),
+                };
+                let sighash_msg = psbt
+                    .sighash_msg(input_idx, &mut sighash_cache, leaf_hash)
+                    .map_err(SigningError::UnableToRetrieveSighash)?;
+                let msg = Message::from_digest(match sighash_msg {
+                    PsbtSighashMsg::TapSighash(sighash) => sighash.to_raw_hash().to_byte_array(),
+                    _ => return Err(SigningError::InvalidPsbt),
+                });
 
-            let input: &Input = &psbt.inputs[idx];
-            if input.tap_key_origins.len() != 1 {
-                return Err(SigningError::InvalidPsbt);
+                let (secret_nonce, public_nonce) = new_frost_nonce_pair(
+                    secp256k1_zkp::SECP256K1,
+                    FrostSessionId::random(),
+                    &share_details.secret_share,
+                    &signing_public_key,
+                    &msg,
+                    None,
+                );
+
+                signables.push(Signable {
+                    input_idx,
+                    spend_path,
+                    sighash_type,
+                    msg,
+                    signing_nonce_pair: SigningNoncePair {
+                        secret_nonce: Some(secret_nonce),
+                        signing_commitment: SigningCommitment {
+                            signable_idx: signables.len() as u32,
+                            public_nonce,
+                        },
+                    },
+                    signing_public_key,
+                    frost_session: None,
+                });
             }
+        }
 
-            // Get the derivation path from the tap key origin
-            let (_, (_, (_, derivation_path))) = input
-                .tap_key_origins
-                .first_key_value()
-                .expect("Length checked above");
+        Ok(signables)
+    }
 
-            derive_frost_bip32_tweak(&mut frost_public_key, derivation_path);
-            derive_frost_tap_tweak(&mut frost_public_key, None);
+    /// The spend paths of `input` the FROST key can sign for, along with the tweaked key to sign
+    /// each with. Inputs the FROST key can't sign for are rejected.
+    fn spend_paths(
+        input: &Input,
+        frost_public_key: &FrostPublicKey,
+    ) -> Result<Vec<(SpendPath, FrostPublicKey)>, SigningError> {
+        let mut script_paths = Vec::new();
+        for (x_only, (leaf_hashes, (_, derivation_path))) in &input.tap_key_origins {
+            let mut signing_public_key = frost_public_key.clone();
+            derive_frost_bip32_tweak(&mut signing_public_key, derivation_path);
+
+            let derived_key: PublicKey =
+                ZkpPublicKey(signing_public_key.public_key(secp256k1_zkp::SECP256K1)).into();
+            if derived_key.x_only_public_key().0 != *x_only {
+                continue;
+            }
 
-            let (secret_nonce, public_nonce) = new_frost_nonce_pair(
-                secp256k1_zkp::SECP256K1,
-                FrostSessionId::random(),
-                &share_details.secret_share,
-                &frost_public_key,
-                &msg,
-                None,
-            );
+            // PSBTs without an internal key predate script-path support, and are key-path spends.
+            let is_internal_key = input
+                .tap_internal_key
+                .map_or(leaf_hashes.is_empty(), |internal_key| {
+                    internal_key == *x_only
+                });
+            if is_internal_key {
+                // A single key-path signature is enough to spend the input.
+                derive_frost_tap_tweak(&mut signing_public_key, input.tap_merkle_root);
+                return Ok(vec![(SpendPath::KeyPath, signing_public_key)]);
+            }
 
-            signables.push(Signable {
-                msg,
-                signing_nonce_pair: SigningNoncePair {
-                    secret_nonce: Some(secret_nonce),
-                    signing_commitment: SigningCommitment {
-                        signable_idx: idx as u32,
-                        public_nonce,
+            for leaf_hash in leaf_hashes {
+                script_paths.push((
+                    SpendPath::ScriptPath {
+                        x_only: *x_only,
+                        leaf_hash: *leaf_hash,
                     },
-                },
-                signing_public_key: frost_public_key,
-                frost_session: None,
-            });
+                    signing_public_key.clone(),
+                ));
+            }
         }
 
-        Ok(signables)
+        if script_paths.is_empty() {
+            return Err(SigningError::InvalidPsbt);
+        }
+        Ok(script_paths)
     }
 }
 
 struct Signable {
+    input_idx: usize,
+    spend_path: SpendPath,
+    sighash_type: TapSighashType,
     msg: Message,
     signing_nonce_pair: SigningNoncePair,
     signing_public_key: FrostPublicKey,
     frost_session: Option<FrostSession>,
 }
 
+/// How a signable's input is spent.
+#[derive(Clone, Copy)]
+enum SpendPath {
+    /// With the output key, i.e. the FROST key tweaked by the input's merkle root.
+    KeyPath,
+    /// With the FROST key in the given leaf's script.
+    ScriptPath {
+        x_only: XOnlyPublicKey,
+        leaf_hash: TapLeafHash,
+    },
+}
+
 struct SigningNoncePair {
     // We can only use the secret nonce once, so we need to take it, hence the Option.
     secret_nonce: Option<FrostSecretNonce>,
@@ -288,7 +479,7 @@ struct SigningNoncePair {
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 #[serde(crate = "bitcoin::secp256k1::serde")]
 pub struct SigningCommitment {
-    /// The index of the signable in the PSBT.
+    /// The index of the signable in the PSBT, ordered by input and then by script leaf.
     signable_idx: u32,
     /// The public nonce.
     public_nonce: FrostPublicNonce,
@@ -349,6 +540,12 @@ pub enum SigningError {
     UnableToRetrieveSighash(SighashError),
     #[error("Invalid counterparty commitments")]
     InvalidCounterpartyCommitments,
+    #[error("Invalid counterparty partial signatures")]
+    InvalidCounterpartyPartialSignatures,
+    #[error("Signing participants must meet the key's threshold and include the signer")]
+    InvalidSigningParticipants,
+    #[error("Invalid share details")]
+    InvalidShareDetails,
     #[error("Nonce already used")]
     NonceAlreadyUsed,
     #[error("Commitment index mismatch: expected {expected}, got {got}")]
@@ -358,3 +555,276 @@ pub enum SigningError {
     #[error("Unable to finalize PSBT: {errors:?}")]
     UnableToFinalizePsbt { errors: Vec<String> },
 }
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use bitcoin::absolute::LockTime;
+    use bitcoin::bip32::KeySource;
+    use bitcoin::opcodes::all::OP_CHECKSIG;
+    use bitcoin::psbt::PsbtSighashType;
+    use bitcoin::secp256k1::{self, Secp256k1};
+    use bitcoin::sighash::Prevouts;
+    use bitcoin::taproot::{LeafVersion, TaprootBuilder};
+    use bitcoin::transaction::Version;
+    use bitcoin::{
+        Amount, Network, OutPoint, ScriptBuf, Sequence, Transaction, TxIn, TxOut, Txid, Witness,
+    };
+
+    use super::*;
+    use crate::frost::compute_frost_master_xpub;
+    use crate::frost::dkg::DkgConfig;
+    use crate::frost::tests::run_dkg;
+    use crate::frost::Participant::{App, Server};
+
+    /// The BIP 341 NUMS point, which has no known discrete logarithm.
+    const UNSPENDABLE_KEY: &str =
+        "50929b74c1a04954b78b4b6035e97a5e078a5a0f28ec96d547bfee9ace803ac0";
+
+    #[derive(Clone, Copy)]
+    enum Spend {
+        /// A BIP 86 output, with the FROST key as the internal key and no script tree.
+        Bip86,
+        /// The FROST key as the internal key, committing to a script tree.
+        KeyPathWithScriptTree,
+        /// An unspendable internal key, with the FROST key in a leaf.
+        ScriptPath,
+    }
+
+    fn pk_script(key: &XOnlyPublicKey) -> ScriptBuf {
+        ScriptBuf::builder()
+            .push_x_only_key(key)
+            .push_opcode(OP_CHECKSIG)
+            .into_script()
+    }
+
+    /// Builds a PSBT spending one input per entry of `spends`, each to a distinct FROST child key.
+    fn build_psbt(
+        aggregate_public_key: PublicKey,
+        spends: &[(Spend, Option<TapSighashType>)],
+    ) -> Psbt {
+        let secp = Secp256k1::new();
+        let master_xpub = compute_frost_master_xpub(aggregate_public_key, Network::Regtest);
+        let unspendable_key = XOnlyPublicKey::from_str(UNSPENDABLE_KEY).unwrap();
+
+        let tx = Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: (0..spends.len())
+                .map(|idx| TxIn {
+                    previous_output: OutPoint::new(Txid::from_byte_array([idx as u8; 32]), 0),
+                    script_sig: ScriptBuf::new(),
+                    sequence: Sequence::MAX,
+                    witness: Witness::new(),
+                })
+                .collect(),
+            output: vec![TxOut {
+                value: Amount::from_sat(40_000),
+                script_pubkey: ScriptBuf::new_p2tr(&secp, unspendable_key, None),
+            }],
+        };
+        let mut psbt = Psbt::from_unsigned_tx(tx).unwrap();
+
+        for (idx, (spend, sighash_type)) in spends.iter().enumerate() {
+            let path = DerivationPath::from_str(&format!("m/86/1/0/0/{idx}")).unwrap();
+            let frost_key = master_xpub
+                .derive_pub(&secp, &path)
+                .unwrap()
+                .public_key
+                .x_only_public_key()
+                .0;
+            let key_source: KeySource = (master_xpub.fingerprint(), path);
+
+            let input = &mut psbt.inputs[idx];
+            let script_pubkey = match spend {
+                Spend::Bip86 => {
+                    input.tap_internal_key = Some(frost_key);
+                    input
+                        .tap_key_origins
+                        .insert(frost_key, (vec![], key_source));
+                    ScriptBuf::new_p2tr(&secp, frost_key, None)
+                }
+                Spend::KeyPathWithScriptTree | Spend::ScriptPath => {
+                    let (internal_key, leaf_key) = match spend {
+                        Spend::ScriptPath => (unspendable_key, frost_key),
+                        _ => (frost_key, unspendable_key),
+                    };
+                    let leaf = (pk_script(&leaf_key), LeafVersion::TapScript);
+                    let spend_info = TaprootBuilder::new()
+                        .add_leaf(0, leaf.0.clone())
+                        .unwrap()
+                        .finalize(&secp, internal_key)
+                        .unwrap();
+
+                    let leaf_hashes = match spend {
+                        Spend::ScriptPath => vec![TapLeafHash::from_script(&leaf.0, leaf.1)],
+                        _ => vec![],
+                    };
+                    input.tap_internal_key = Some(internal_key);
+                    input.tap_merkle_root = spend_info.merkle_root();
+                    input
+                        .tap_scripts
+                        .insert(spend_info.control_block(&leaf).unwrap(), leaf);
+                    input
+                        .tap_key_origins
+                        .insert(frost_key, (leaf_hashes, key_source));
+                    ScriptBuf::new_p2tr_tweaked(spend_info.output_key())
+                }
+            };
+            input.witness_utxo = Some(TxOut {
+                value: Amount::from_sat(50_000),
+                script_pubkey,
+            });
+            input.sighash_type = sighash_type.map(PsbtSighashType::from);
+        }
+
+        psbt
+    }
+
+    fn sign(psbt: Psbt, share_details: &[ShareDetails]) -> Psbt {
+        let mut app_signer = Signer::new(App, psbt.clone(), share_details[0].clone()).unwrap();
+        let mut server_signer = Signer::new(Server, psbt, share_details[1].clone()).unwrap();
+
+        let server_partial_signatures = server_signer
+            .generate_partial_signatures(app_signer.public_signing_commitments())
+            .unwrap();
+        let app_partial_signatures = app_signer
+            .generate_partial_signatures(server_signer.public_signing_commitments())
+            .unwrap();
+        app_signer
+            .sign_psbt(app_partial_signatures, server_partial_signatures)
+            .unwrap()
+    }
+
+    /// Checks every signature in the PSBT against its BIP 341 sighash, independently of the
+    /// signer's own sighash computation.
+    fn verify_signatures(psbt: &Psbt) {
+        let secp = Secp256k1::verification_only();
+        let prevouts = psbt
+            .inputs
+            .iter()
+            .map(|input| input.witness_utxo.clone().unwrap())
+            .collect::<Vec<_>>();
+        let prevouts = Prevouts::All(&prevouts);
+        let mut sighash_cache = SighashCache::new(&psbt.unsigned_tx);
+
+        for (idx, input) in psbt.inputs.iter().enumerate() {
+            if let Some(signature) = input.tap_key_sig {
+                let sighash = sighash_cache
+                    .taproot_key_spend_signature_hash(idx, &prevouts, signature.sighash_type)
+                    .unwrap();
+                let output_key = XOnlyPublicKey::from_slice(
+                    &input
+                        .witness_utxo
+                        .as_ref()
+                        .unwrap()
+                        .script_pubkey
+                        .as_bytes()[2..],
+                )
+                .unwrap();
+                secp.verify_schnorr(
+                    &signature.signature,
+                    &secp256k1::Message::from_digest(sighash.to_byte_array()),
+                    &output_key,
+                )
+                .unwrap();
+            }
+            for ((x_only, leaf_hash), signature) in &input.tap_script_sigs {
+                let sighash = sighash_cache
+                    .taproot_script_spend_signature_hash(
+                        idx,
+                        &prevouts,
+                        *leaf_hash,
+                        signature.sighash_type,
+                    )
+                    .unwrap();
+                secp.verify_schnorr(
+                    &signature.signature,
+                    &secp256k1::Message::from_digest(sighash.to_byte_array()),
+                    x_only,
+                )
+                .unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn test_sign_psbt_taproot_spend_paths() {
+        let share_details = run_dkg(&DkgConfig::default());
+        let psbt = build_psbt(
+            share_details[0].key_commitments.aggregate_public_key,
+            &[
+                (Spend::Bip86, None),
+                (Spend::KeyPathWithScriptTree, Some(TapSighashType::All)),
+                (Spend::ScriptPath, Some(TapSighashType::AllPlusAnyoneCanPay)),
+                (Spend::Bip86, Some(TapSighashType::NonePlusAnyoneCanPay)),
+            ],
+        );
+
+        let mut psbt = sign(psbt, &share_details);
+
+        let key_sighash_types = psbt
+            .inputs
+            .iter()
+            .map(|input| input.tap_key_sig.map(|signature| signature.sighash_type))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            key_sighash_types,
+            vec![
+                Some(TapSighashType::Default),
+                Some(TapSighashType::All),
+                None,
+                Some(TapSighashType::NonePlusAnyoneCanPay),
+            ]
+        );
+        let script_sighash_types = psbt.inputs[2]
+            .tap_script_sigs
+            .values()
+            .map(|signature| signature.sighash_type)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            script_sighash_types,
+            vec![TapSighashType::AllPlusAnyoneCanPay]
+        );
+        assert!(psbt
+            .inputs
+            .iter()
+            .enumerate()
+            .all(|(idx, input)| idx == 2 || input.tap_script_sigs.is_empty()));
+
+        verify_signatures(&psbt);
+
+        // Extraction runs the finalized transaction through miniscript's interpreter, which
+        // validates each witness against its prevout.
+        let secp = Secp256k1::new();
+        psbt.finalize_mut(&secp).unwrap();
+        let tx = psbt.extract(&secp).unwrap();
+        assert_eq!(tx.input[2].witness.len(), 3);
+    }
+
+    #[test]
+    fn test_signer_rejects_unsignable_inputs() {
+        let share_details = run_dkg(&DkgConfig::default());
+        let aggregate_public_key = share_details[0].key_commitments.aggregate_public_key;
+
+        // The tap key origin doesn't derive from the FROST key.
+        let mut psbt = build_psbt(aggregate_public_key, &[(Spend::Bip86, None)]);
+        let (_, origin) = psbt.inputs[0].tap_key_origins.pop_first().unwrap();
+        psbt.inputs[0]
+            .tap_key_origins
+            .insert(XOnlyPublicKey::from_str(UNSPENDABLE_KEY).unwrap(), origin);
+        assert_eq!(
+            Signer::new(App, psbt, share_details[0].clone()).err(),
+            Some(SigningError::InvalidPsbt)
+        );
+
+        // The sighash type isn't a valid taproot sighash type.
+        let mut psbt = build_psbt(aggregate_public_key, &[(Spend::Bip86, None)]);
+        psbt.inputs[0].sighash_type = Some(PsbtSighashType::from_u32(0xff));
+        assert_eq!(
+            Signer::new(App, psbt, share_details[0].clone()).err(),
+            Some(SigningError::InvalidPsbt)
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use std::collections::BTreeMap;
 use std::env;
 use std::str::FromStr;
 
@@ -5,7 +6,7 @@ use bdk_bitcoind_rpc::bitcoincore_rpc::{Auth, Client, RpcApi};
 use bdk_bitcoind_rpc::{Emitter, NO_EXPECTED_MEMPOOL_TXS};
 use bdk_wallet::{KeychainKind, Wallet};
 use bitcoin::bip32::DerivationPath;
-use bitcoin::{secp256k1, Address, Amount, Network};
+use bitcoin::{secp256k1, Address, Amount, Network, Transaction};
 use miniscript::descriptor::{DescriptorXKey, Wildcard};
 use miniscript::psbt::PsbtExt;
 use miniscript::{Descriptor, DescriptorPublicKey};
@@ -14,13 +15,17 @@ use secp256k1_zkp::{
     frost::{FrostPublicKey, VerificationShare},
 };
 
-use crate::frost::Participant::{self, App, Server};
-use crate::frost::{compute_frost_master_xpub, ZkpPublicKey};
+use super::run_dkg;
+use crate::frost::Participant::{self, App, Hardware, Server};
+use crate::frost::{compute_frost_master_xpub, ShareDetails, ZkpPublicKey};
 use crate::frost::{
-    dkg::{aggregate_shares, generate_share_packages},
+    dkg::{aggregate_shares, generate_share_packages, DkgConfig},
     signing::Signer,
 };
 
+/// The BIP 341 NUMS point, which has no known discrete logarithm.
+const UNSPENDABLE_KEY: &str = "50929b74c1a04954b78b4b6035e97a5e078a5a0f28ec96d547bfee9ace803ac0";
+
 #[test]
 fn test_dkg_and_receive_funds() {
     let app_share_packages = generate_share_packages().unwrap();
@@ -166,6 +171,129 @@ fn test_sign_psbt() {
     println!("Successfully broadcast {}", signed_tx.compute_txid());
 }
 
+#[test]
+fn test_sign_psbt_two_of_three() {
+    let config = DkgConfig::new(2, &[App, Server, Hardware]).unwrap();
+    let mut share_details = run_dkg(&config);
+
+    let (mut wallet, rpc_client) =
+        get_wallet(share_details[0].key_commitments.aggregate_public_key).unwrap();
+    let funding_address = wallet.next_unused_address(KeychainKind::External);
+    treasury_fund_address(&funding_address);
+    sync_wallet_mempool(&mut wallet, &rpc_client);
+
+    let psbt = {
+        let recipient_spk = wallet
+            .peek_address(KeychainKind::External, 1337)
+            .address
+            .script_pubkey();
+        let mut builder = wallet.build_tx();
+        builder.add_recipient(recipient_spk, Amount::from_sat(20_000));
+        builder.finish().unwrap()
+    };
+
+    // App and Hardware sign without the Server.
+    let hardware_share_details = share_details.remove(2);
+    let app_share_details = share_details.remove(0);
+    let mut app_signer =
+        Signer::with_signing_participants(App, &[App, Hardware], psbt.clone(), app_share_details)
+            .unwrap();
+    let mut hardware_signer =
+        Signer::with_signing_participants(Hardware, &[App, Hardware], psbt, hardware_share_details)
+            .unwrap();
+
+    let app_partial_signatures = app_signer
+        .generate_partial_signatures_with_counterparties(BTreeMap::from([(
+            Hardware.into(),
+            hardware_signer.public_signing_commitments(),
+        )]))
+        .unwrap();
+    let hardware_partial_signatures = hardware_signer
+        .generate_partial_signatures(app_signer.public_signing_commitments())
+        .unwrap();
+
+    let mut psbt = app_signer
+        .sign_psbt_with_counterparties(
+            app_partial_signatures,
+            BTreeMap::from([(Hardware.into(), hardware_partial_signatures)]),
+        )
+        .unwrap();
+
+    psbt.finalize_mut(&secp256k1::Secp256k1::new()).unwrap();
+    let signed_tx = psbt.extract_tx().unwrap();
+    rpc_client.send_raw_transaction(&signed_tx).unwrap();
+}
+
+#[test]
+fn test_sign_psbt_key_path_with_script_tree() {
+    let share_details = run_dkg(&DkgConfig::default());
+
+    // The FROST key is the internal key, so the key path is tweaked by the tree's merkle root.
+    let (wallet, rpc_client) = get_wallet_with_template(
+        share_details[0].key_commitments.aggregate_public_key,
+        |frost_key| Descriptor::from_str(&format!("tr({frost_key},pk({UNSPENDABLE_KEY}))")),
+    )
+    .unwrap();
+    let signed_tx = fund_and_sign(wallet, &rpc_client, &share_details);
+
+    // A key-path witness is just the signature.
+    assert!(signed_tx.input.iter().all(|input| input.witness.len() == 1));
+    rpc_client.send_raw_transaction(&signed_tx).unwrap();
+}
+
+#[test]
+fn test_sign_psbt_script_path() {
+    let share_details = run_dkg(&DkgConfig::default());
+
+    // The internal key is unspendable, so the FROST key can only spend through its leaf.
+    let (wallet, rpc_client) = get_wallet_with_template(
+        share_details[0].key_commitments.aggregate_public_key,
+        |frost_key| Descriptor::from_str(&format!("tr({UNSPENDABLE_KEY},pk({frost_key}))")),
+    )
+    .unwrap();
+    let signed_tx = fund_and_sign(wallet, &rpc_client, &share_details);
+
+    // A script-path witness is the signature, the leaf script and its control block.
+    assert!(signed_tx.input.iter().all(|input| input.witness.len() == 3));
+    rpc_client.send_raw_transaction(&signed_tx).unwrap();
+}
+
+/// Funds the wallet, then has the App and Server sign and finalize a spend from it.
+fn fund_and_sign(
+    mut wallet: Wallet,
+    rpc_client: &Client,
+    share_details: &[ShareDetails],
+) -> Transaction {
+    let funding_address = wallet.next_unused_address(KeychainKind::External);
+    treasury_fund_address(&funding_address);
+    sync_wallet_mempool(&mut wallet, rpc_client);
+
+    let psbt = {
+        let recipient_spk = wallet
+            .peek_address(KeychainKind::External, 1337)
+            .address
+            .script_pubkey();
+        let mut builder = wallet.build_tx();
+        builder.add_recipient(recipient_spk, Amount::from_sat(20_000));
+        builder.finish().unwrap()
+    };
+
+    let mut app_signer = Signer::new(App, psbt.clone(), share_details[0].clone()).unwrap();
+    let mut server_signer = Signer::new(Server, psbt, share_details[1].clone()).unwrap();
+    let server_partial_signatures = server_signer
+        .generate_partial_signatures(app_signer.public_signing_commitments())
+        .unwrap();
+    let app_partial_signatures = app_signer
+        .generate_partial_signatures(server_signer.public_signing_commitments())
+        .unwrap();
+    let mut psbt = app_signer
+        .sign_psbt(app_partial_signatures, server_partial_signatures)
+        .unwrap();
+
+    psbt.finalize_mut(&secp256k1::Secp256k1::new()).unwrap();
+    psbt.extract_tx().unwrap()
+}
+
 fn treasury_fund_address(address: &Address) {
     let wallet_name = env::var("BITCOIND_RPC_WALLET_NAME").unwrap_or("testwallet".to_string());
     let treasury_rpc_client = generate_rpc_client(Some(wallet_name));
@@ -184,8 +312,18 @@ fn treasury_fund_address(address: &Address) {
 }
 
 fn get_wallet(aggregate_public_key: secp256k1::PublicKey) -> anyhow::Result<(Wallet, Client)> {
-    let external_descriptor = compute_wallet_descriptor(aggregate_public_key, false)?;
-    let internal_descriptor = compute_wallet_descriptor(aggregate_public_key, true)?;
+    get_wallet_with_template(aggregate_public_key, |frost_key| {
+        Descriptor::new_tr(frost_key, None)
+    })
+}
+
+/// Creates a wallet whose descriptors are built by `template` from the FROST descriptor key.
+fn get_wallet_with_template(
+    aggregate_public_key: secp256k1::PublicKey,
+    template: impl Fn(DescriptorPublicKey) -> Result<Descriptor<DescriptorPublicKey>, miniscript::Error>,
+) -> anyhow::Result<(Wallet, Client)> {
+    let external_descriptor = template(compute_descriptor_key(aggregate_public_key, false))?;
+    let internal_descriptor = template(compute_descriptor_key(aggregate_public_key, true))?;
 
     let wallet = Wallet::create(external_descriptor.clone(), internal_descriptor.clone())
         .network(Network::Regtest)
@@ -194,10 +332,10 @@ fn get_wallet(aggregate_public_key: secp256k1::PublicKey) -> anyhow::Result<(Wal
     Ok((wallet, generate_rpc_client(None)))
 }
 
-fn compute_wallet_descriptor(
+fn compute_descriptor_key(
     aggregate_public_key: secp256k1::PublicKey,
     is_internal: bool,
-) -> Result<Descriptor<DescriptorPublicKey>, miniscript::Error> {
+) -> DescriptorPublicKey {
     let master_xpub = compute_frost_master_xpub(aggregate_public_key, Network::Regtest);
     let secp = secp256k1::Secp256k1::new();
 
@@ -211,15 +349,12 @@ fn compute_wallet_descriptor(
         .derive_pub(&secp, &path)
         .expect("Derivation must be valid");
 
-    Descriptor::new_tr(
-        DescriptorPublicKey::XPub(DescriptorXKey {
-            origin: Some((master_xpub.fingerprint(), path)),
-            xkey: xpub,
-            derivation_path: DerivationPath::default(),
-            wildcard: Wildcard::Unhardened,
-        }),
-        None,
-    )
+    DescriptorPublicKey::XPub(DescriptorXKey {
+        origin: Some((master_xpub.fingerprint(), path)),
+        xkey: xpub,
+        derivation_path: DerivationPath::default(),
+        wildcard: Wildcard::Unhardened,
+    })
 }
 
 fn sync_wallet_mempool(wallet: &mut Wallet, rpc_client: &Client) {
@@ -1 +1,90 @@
 mod frost_integration_tests;
+
+use rand::{thread_rng, RngCore};
+use secp256k1_zkp::frost::{FrostSession, FrostSessionId};
+use secp256k1_zkp::{self as zkp, new_frost_nonce_pair, Message};
+
+use super::dkg::{aggregate_shares_for, generate_share_packages_for, DkgConfig};
+use super::{Participant, ShareDetails};
+
+/// Runs a full DKG for `config`, returning each participant's share details in config order.
+pub(crate) fn run_dkg(config: &DkgConfig) -> Vec<ShareDetails> {
+    let share_packages = config
+        .participants()
+        .iter()
+        .map(|_| generate_share_packages_for(config).unwrap())
+        .collect::<Vec<_>>();
+
+    config
+        .participants()
+        .iter()
+        .enumerate()
+        .map(|(index, participant)| {
+            let packages = share_packages
+                .iter()
+                .map(|packages| &packages[index])
+                .collect::<Vec<_>>();
+            aggregate_shares_for(config, *participant, &packages).unwrap()
+        })
+        .collect()
+}
+
+/// Signs a random message with the given participants' shares, and returns whether the signature
+/// verifies under the first signer's aggregate public key.
+pub(crate) fn sign_and_verify(signers: &[(Participant, &ShareDetails)]) -> bool {
+    let aggregate_pubkey = signers[0].1.key_commitments.frost_public_key().unwrap();
+    let (final_pubkey, _) = aggregate_pubkey
+        .public_key(zkp::SECP256K1)
+        .x_only_public_key();
+    let identities = signers
+        .iter()
+        .map(|(participant, _)| (*participant).into())
+        .collect::<Vec<zkp::PublicKey>>();
+    let identity_refs = identities.iter().collect::<Vec<_>>();
+
+    let mut msg = [0u8; 32];
+    thread_rng().fill_bytes(&mut msg[..]);
+    let msg = Message::from_digest(msg);
+
+    let nonces = signers
+        .iter()
+        .map(|(_, details)| {
+            new_frost_nonce_pair(
+                zkp::SECP256K1,
+                FrostSessionId::random(),
+                &details.secret_share,
+                &aggregate_pubkey,
+                &msg,
+                None,
+            )
+        })
+        .collect::<Vec<_>>();
+    let public_nonces = nonces.iter().map(|(_, public)| public).collect::<Vec<_>>();
+
+    let mut partial_sigs = vec![];
+    let mut sessions = vec![];
+    for ((participant, details), (secret_nonce, _)) in signers.iter().zip(nonces) {
+        let session = FrostSession::new(
+            zkp::SECP256K1,
+            &public_nonces,
+            &msg,
+            &aggregate_pubkey,
+            &(*participant).into(),
+            &identity_refs,
+            None,
+        );
+        partial_sigs.push(session.partial_sign(
+            zkp::SECP256K1,
+            secret_nonce,
+            &details.secret_share,
+            &aggregate_pubkey,
+        ));
+        sessions.push(session);
+    }
+
+    let agg_sig = sessions[0]
+        .aggregate_partial_sigs(zkp::SECP256K1, &partial_sigs.iter().collect::<Vec<_>>());
+    zkp::SECP256K1
+        .verify_schnorr(&agg_sig, &msg, &final_pubkey)
+        .is_ok()
+}
@@ -7,6 +7,7 @@ pub mod hmac;
 pub mod keys;
 pub mod signature_utils;
 pub mod signature_verifier;
+pub mod streaming_aead;
 
 #[cfg(feature = "chaincode_delegation")]
 pub mod chaincode_delegation;
@@ -1,6 +1,13 @@
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    AeadCore, XChaCha20Poly1305,
+};
+use hkdf::Hkdf;
 use p256::ecdh::diffie_hellman;
 use p256::elliptic_curve::sec1::{FromEncodedPoint, ToEncodedPoint};
 use p256::{EncodedPoint, PublicKey, SecretKey};
+use rand::RngCore;
+use sha2::Sha256;
 use snow::{
     params::{CipherChoice, DHChoice, HashChoice, NoiseParams},
     resolvers::{CryptoResolver, DefaultResolver},
@@ -8,8 +15,10 @@ use snow::{
     Builder, HandshakeState, TransportState,
 };
 use std::{
+    collections::HashMap,
     ops::Deref,
     sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 use thiserror::Error;
 
@@ -237,6 +246,9 @@ impl CryptoResolver for SoftwareP256Resolver {
 }
 
 const NOISE_PARAMS: &str = "Noise_IK_p256_ChaChaPoly_SHA256";
+// Resumed sessions authenticate with the PSK from a session ticket instead of static keys, so
+// they skip the static-key DH. The ephemeral DH keeps them forward secret.
+const RESUMPTION_NOISE_PARAMS: &str = "Noise_NNpsk0_p256_ChaChaPoly_SHA256";
 const NOISE_PROLOGUE: &[u8] = b"bitkey";
 
 fn create_params() -> NoiseParams {
@@ -250,6 +262,184 @@ fn create_params() -> NoiseParams {
     }
 }
 
+fn create_resumption_params() -> NoiseParams {
+    NoiseParams {
+        name: RESUMPTION_NOISE_PARAMS.to_string(),
+        handshake: "NNpsk0".parse().expect("Invalid handshake argument"),
+        ..create_params()
+    }
+}
+
+const TICKET_VERSION: u8 = 1;
+const TICKET_ID_LEN: usize = 16;
+const TICKET_NONCE_LEN: usize = 24;
+const RESUMPTION_SECRET_LEN: usize = 32;
+const RESUMPTION_SECRET_INFO: &[u8] = b"bitkey/noise/resumption";
+
+/// Derives the secret both parties of a finished handshake share for resuming the session.
+fn derive_resumption_secret(handshake_hash: &[u8]) -> [u8; RESUMPTION_SECRET_LEN] {
+    let mut secret = [0u8; RESUMPTION_SECRET_LEN];
+    Hkdf::<Sha256>::new(None, handshake_hash)
+        .expand(RESUMPTION_SECRET_INFO, &mut secret)
+        .expect("Resumption secret length is valid");
+    secret
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
+/// The contents of a session ticket, which only the responder that sealed it can read.
+struct SessionTicket {
+    id: [u8; TICKET_ID_LEN],
+    expires_at: u64,
+    resumption_secret: [u8; RESUMPTION_SECRET_LEN],
+}
+
+impl SessionTicket {
+    fn seal(&self, ticket_key: &[u8]) -> Result<Vec<u8>, NoiseWrapperError> {
+        let cipher = XChaCha20Poly1305::new_from_slice(ticket_key)
+            .map_err(|_| NoiseWrapperError::InvalidTicket)?;
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut rand::thread_rng());
+        let plaintext = [
+            &self.id[..],
+            &self.expires_at.to_be_bytes(),
+            &self.resumption_secret,
+        ]
+        .concat();
+        let ciphertext = cipher
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: &plaintext,
+                    aad: &[TICKET_VERSION],
+                },
+            )
+            .map_err(|_| NoiseWrapperError::InvalidTicket)?;
+
+        Ok([&[TICKET_VERSION][..], &nonce, &ciphertext].concat())
+    }
+
+    fn open(ticket_key: &[u8], ticket: &[u8]) -> Result<Self, NoiseWrapperError> {
+        let (version, rest) = ticket
+            .split_first()
+            .ok_or(NoiseWrapperError::InvalidTicket)?;
+        if *version != TICKET_VERSION || rest.len() < TICKET_NONCE_LEN {
+            return Err(NoiseWrapperError::InvalidTicket);
+        }
+        let (nonce, ciphertext) = rest.split_at(TICKET_NONCE_LEN);
+
+        let cipher = XChaCha20Poly1305::new_from_slice(ticket_key)
+            .map_err(|_| NoiseWrapperError::InvalidTicket)?;
+        let plaintext = cipher
+            .decrypt(
+                nonce.into(),
+                Payload {
+                    msg: ciphertext,
+                    aad: &[TICKET_VERSION],
+                },
+            )
+            .map_err(|_| NoiseWrapperError::InvalidTicket)?;
+        if plaintext.len() != TICKET_ID_LEN + 8 + RESUMPTION_SECRET_LEN {
+            return Err(NoiseWrapperError::InvalidTicket);
+        }
+
+        let (id, rest) = plaintext.split_at(TICKET_ID_LEN);
+        let (expires_at, resumption_secret) = rest.split_at(8);
+        Ok(Self {
+            id: id.try_into().expect("Length checked above"),
+            expires_at: u64::from_be_bytes(expires_at.try_into().expect("Length checked above")),
+            resumption_secret: resumption_secret.try_into().expect("Length checked above"),
+        })
+    }
+}
+
+/// Tracks the session tickets a responder has accepted, so that each can only resume a single
+/// session. Tickets are forgotten once they expire, since they're rejected from then on anyway.
+#[derive(Debug, Default)]
+pub struct ReplayGuard {
+    used_tickets: Mutex<HashMap<[u8; TICKET_ID_LEN], u64>>,
+}
+
+impl ReplayGuard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn redeem(&self, ticket: &SessionTicket, now: u64) -> Result<(), NoiseWrapperError> {
+        if ticket.expires_at <= now {
+            return Err(NoiseWrapperError::TicketExpired);
+        }
+
+        let mut used_tickets = self
+            .used_tickets
+            .lock()
+            .expect("Failed to lock used tickets");
+        used_tickets.retain(|_, expires_at| *expires_at > now);
+        if used_tickets.insert(ticket.id, ticket.expires_at).is_some() {
+            return Err(NoiseWrapperError::TicketReplayed);
+        }
+        Ok(())
+    }
+}
+
+/// When to replace a session's transport keys, per Section 11.3 of the Noise specification.
+///
+/// Once a policy is set, every transport message is prefixed with a byte flagging whether its
+/// sender rekeyed just before it, so both parties must set a policy before their first transport
+/// message. The flag isn't authenticated, so receivers only move to the next key once a message
+/// decrypts under it; a tampered flag fails that message alone and leaves the session intact.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RekeyPolicy {
+    /// Rekey after sending this many messages under one key. Receivers reject messages beyond it.
+    pub max_messages: u64,
+    /// Rekey before sending once this many seconds have passed since the last rekey.
+    pub max_duration_secs: u64,
+}
+
+#[derive(Debug)]
+struct RekeyState {
+    policy: RekeyPolicy,
+    sent: u64,
+    received: u64,
+    outgoing_key_created_at: Instant,
+    rekey_requested: bool,
+}
+
+impl RekeyState {
+    fn new(policy: RekeyPolicy) -> Self {
+        Self {
+            policy,
+            sent: 0,
+            received: 0,
+            outgoing_key_created_at: Instant::now(),
+            rekey_requested: false,
+        }
+    }
+
+    fn is_outgoing_rekey_due(&self) -> bool {
+        self.rekey_requested
+            || self.sent >= self.policy.max_messages
+            || self.outgoing_key_created_at.elapsed()
+                >= Duration::from_secs(self.policy.max_duration_secs)
+    }
+}
+
+/// How a context resumes a previous session rather than starting from static keys.
+#[derive(Debug)]
+enum Resumption {
+    /// An initiator, which presents the ticket with its first handshake message.
+    Initiator { ticket: Vec<u8> },
+    /// A responder, which can only build its handshake once it has opened the initiator's ticket.
+    Responder {
+        ticket_key: Vec<u8>,
+        replay_guard: Arc<ReplayGuard>,
+    },
+}
+
 #[derive(Debug)]
 pub enum NoiseRole {
     Initiator,
@@ -260,6 +450,26 @@ pub enum NoiseRole {
 struct NoiseContextState {
     handshake: Option<HandshakeState>,
     transport: Option<TransportState>,
+    resumption: Option<Resumption>,
+    // Set when the handshake is finalized, for deriving the resumption secret.
+    handshake_hash: Option<Vec<u8>>,
+    // The key of the incoming transport cipher, for trying a rekeyed message before committing
+    // to its key.
+    incoming_key: Option<[u8; CIPHER_KEY_LEN]>,
+    rekey: Option<RekeyState>,
+}
+
+impl NoiseContextState {
+    fn new(handshake: Option<HandshakeState>, resumption: Option<Resumption>) -> Self {
+        Self {
+            handshake,
+            transport: None,
+            resumption,
+            handshake_hash: None,
+            incoming_key: None,
+            rekey: None,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -281,9 +491,21 @@ pub enum NoiseWrapperError {
     Transport,
     #[error("Somebody wrote some bad code")]
     IllegalState,
+    #[error("Invalid session ticket")]
+    InvalidTicket,
+    #[error("Session ticket expired")]
+    TicketExpired,
+    #[error("Session ticket already used")]
+    TicketReplayed,
+    #[error("Peer didn't rekey as required by the rekey policy")]
+    RekeyRequired,
+    #[error("Rekeying requires a rekey policy")]
+    RekeyNotEnabled,
 }
 
 const NOISE_MAX_MESSAGE_SIZE: usize = 65535;
+const CIPHER_KEY_LEN: usize = 32;
+const CIPHER_TAG_LEN: usize = 16;
 
 impl NoiseContext {
     pub fn new(
@@ -326,14 +548,53 @@ impl NoiseContext {
                 .build_responder(),
         }?;
 
-        Ok(Self {
+        Ok(Self::with_state(
             role,
-            state: Arc::new(Mutex::new(NoiseContextState {
-                handshake: Some(handshake),
-                transport: None,
-            })),
+            NoiseContextState::new(Some(handshake), None),
+        ))
+    }
+
+    /// Creates an initiator that resumes a previous session with a ticket from its responder,
+    /// skipping the static-key DH. `resumption_secret` is the previous session's
+    /// [`NoiseContext::resumption_secret`]. Tickets are single use.
+    pub fn resume_initiator(
+        ticket: Vec<u8>,
+        resumption_secret: Vec<u8>,
+    ) -> Result<Self, NoiseWrapperError> {
+        let handshake =
+            build_resumption_handshake(NoiseRole::Initiator, &ticket, &resumption_secret)?;
+
+        Ok(Self::with_state(
+            NoiseRole::Initiator,
+            NoiseContextState::new(Some(handshake), Some(Resumption::Initiator { ticket })),
+        ))
+    }
+
+    /// Creates a responder for an initiator resuming a session with a ticket sealed under
+    /// `ticket_key`. Each ticket is only accepted once across every responder sharing
+    /// `replay_guard`.
+    pub fn resume_responder(
+        ticket_key: Vec<u8>,
+        replay_guard: Arc<ReplayGuard>,
+    ) -> Result<Self, NoiseWrapperError> {
+        Ok(Self::with_state(
+            NoiseRole::Responder,
+            NoiseContextState::new(
+                None,
+                Some(Resumption::Responder {
+                    ticket_key,
+                    replay_guard,
+                }),
+            ),
+        ))
+    }
+
+    fn with_state(role: NoiseRole, state: NoiseContextState) -> Self {
+        Self {
+            role,
+            state: Arc::new(Mutex::new(state)),
             scratch: Arc::new(Mutex::new(vec![0u8; NOISE_MAX_MESSAGE_SIZE])),
-        })
+        }
     }
 
     pub fn initiate_handshake(&self) -> Result<Vec<u8>, NoiseWrapperError> {
@@ -354,14 +615,45 @@ impl NoiseContext {
     ) -> Result<Option<Vec<u8>>, NoiseWrapperError> {
         let mut state = self.state.lock().expect("Failed to lock state");
         let mut scratch = self.scratch.lock().expect("Failed to lock scratch");
-        let mut handshake = state
-            .handshake
-            .take()
-            .ok_or(NoiseWrapperError::IllegalState)
-            .map_err(|_| NoiseWrapperError::IllegalState)?;
+
+        let is_first_message = peer_handshake_message.is_none();
+
+        let mut resumed_ticket = None;
+        let (mut handshake, peer_handshake_message) = match (
+            state.handshake.take(),
+            &state.resumption,
+            peer_handshake_message,
+        ) {
+            (Some(handshake), _, message) => (handshake, message),
+            // A resuming responder builds its handshake from the ticket in the first message.
+            (
+                None,
+                Some(Resumption::Responder {
+                    ticket_key,
+                    replay_guard,
+                }),
+                Some(message),
+            ) => {
+                let (sealed_ticket, message) = unframe_resumption_message(&message)?;
+                let ticket = SessionTicket::open(ticket_key, sealed_ticket)?;
+                let handshake = build_resumption_handshake(
+                    NoiseRole::Responder,
+                    sealed_ticket,
+                    &ticket.resumption_secret,
+                )?;
+                resumed_ticket = Some((ticket, replay_guard.clone()));
+                (handshake, Some(message.to_vec()))
+            }
+            _ => return Err(NoiseWrapperError::IllegalState),
+        };
 
         if let Some(message) = peer_handshake_message {
             let _ = handshake.read_message(&message, &mut scratch)?;
+            // Only redeem tickets once the initiator has proven it holds the resumption secret, so
+            // that eavesdroppers can't burn them.
+            if let Some((ticket, replay_guard)) = resumed_ticket {
+                replay_guard.redeem(&ticket, unix_time())?;
+            }
             if handshake.is_handshake_finished() {
                 state.handshake = Some(handshake);
                 return Ok(None);
@@ -369,7 +661,12 @@ impl NoiseContext {
         }
 
         let len = handshake.write_message(&[], &mut scratch)?;
-        let result = scratch[..len].to_vec();
+        let result = match &state.resumption {
+            Some(Resumption::Initiator { ticket }) if is_first_message => {
+                frame_resumption_message(ticket, &scratch[..len])?
+            }
+            _ => scratch[..len].to_vec(),
+        };
 
         state.handshake = Some(handshake);
         Ok(Some(result))
@@ -379,15 +676,81 @@ impl NoiseContext {
         let mut state = self.state.lock().expect("Failed to lock state");
 
         // Take the handshake state out of the Option
-        let handshake = state
+        let mut handshake = state
             .handshake
             .take()
             .ok_or(NoiseWrapperError::HandshakeNotFinished)?;
 
+        let handshake_hash = handshake.get_handshake_hash().to_vec();
+        let (initiator_key, responder_key) = handshake.dangerously_get_raw_split();
         let new_transport = handshake.into_transport_mode()?;
 
         state.transport = Some(new_transport);
+        state.handshake_hash = Some(handshake_hash);
+        state.incoming_key = Some(match self.role {
+            NoiseRole::Initiator => responder_key,
+            NoiseRole::Responder => initiator_key,
+        });
+
+        Ok(())
+    }
+
+    /// Returns the secret for resuming this session later, which the initiator should keep
+    /// alongside the ticket from [`NoiseContext::issue_session_ticket`].
+    pub fn resumption_secret(&self) -> Result<Vec<u8>, NoiseWrapperError> {
+        let state = self.state.lock().expect("Failed to lock state");
+        let handshake_hash = state
+            .handshake_hash
+            .as_ref()
+            .ok_or(NoiseWrapperError::HandshakeNotFinished)?;
+        Ok(derive_resumption_secret(handshake_hash).to_vec())
+    }
+
+    /// Seals a ticket the initiator can present within `lifetime` to resume this session. Only
+    /// the responder can issue tickets, since only it can open them again.
+    pub fn issue_session_ticket(
+        &self,
+        ticket_key: &[u8],
+        lifetime: Duration,
+    ) -> Result<Vec<u8>, NoiseWrapperError> {
+        if !matches!(self.role, NoiseRole::Responder) {
+            return Err(NoiseWrapperError::IllegalState);
+        }
+
+        let mut id = [0u8; TICKET_ID_LEN];
+        rand::thread_rng().fill_bytes(&mut id);
+        SessionTicket {
+            id,
+            expires_at: unix_time().saturating_add(lifetime.as_secs()),
+            resumption_secret: self
+                .resumption_secret()?
+                .try_into()
+                .expect("Resumption secrets are 32 bytes"),
+        }
+        .seal(ticket_key)
+    }
+
+    /// Enables rekeying under `policy`. Must be called on both sides before either sends a
+    /// transport message.
+    pub fn set_rekey_policy(&self, policy: RekeyPolicy) -> Result<(), NoiseWrapperError> {
+        let mut state = self.state.lock().expect("Failed to lock state");
+        if state.transport.as_ref().is_some_and(|transport| {
+            transport.sending_nonce() > 0 || transport.receiving_nonce() > 0
+        }) {
+            return Err(NoiseWrapperError::IllegalState);
+        }
+        state.rekey = Some(RekeyState::new(policy));
+        Ok(())
+    }
 
+    /// Rekeys before the next outgoing message, regardless of the rekey policy.
+    pub fn request_rekey(&self) -> Result<(), NoiseWrapperError> {
+        let mut state = self.state.lock().expect("Failed to lock state");
+        let rekey = state
+            .rekey
+            .as_mut()
+            .ok_or(NoiseWrapperError::RekeyNotEnabled)?;
+        rekey.rekey_requested = true;
         Ok(())
     }
 
@@ -402,33 +765,141 @@ impl NoiseContext {
 
     pub fn encrypt_message(&self, message: &[u8]) -> Result<Vec<u8>, NoiseWrapperError> {
         let mut state = self.state.lock().expect("Failed to lock state");
-        let transport_state = state
-            .transport
-            .as_mut()
-            .ok_or(NoiseWrapperError::Transport)?;
+        let NoiseContextState {
+            transport, rekey, ..
+        } = &mut *state;
+        let transport_state = transport.as_mut().ok_or(NoiseWrapperError::Transport)?;
         let mut scratch = self.scratch.lock().expect("Failed to lock scratch");
 
-        // Write the message using the transport state
+        let Some(rekey) = rekey else {
+            // Write the message using the transport state
+            let len = transport_state.write_message(message, &mut scratch)?;
+            return Ok(scratch[..len].to_vec());
+        };
+
+        let rekeyed = rekey.is_outgoing_rekey_due();
+        if rekeyed {
+            transport_state.rekey_outgoing();
+            *rekey = RekeyState {
+                received: rekey.received,
+                ..RekeyState::new(rekey.policy)
+            };
+        }
+
+        // The flag travels outside the ciphertext, so receivers only act on it once the message
+        // decrypts under the key it names.
         let len = transport_state.write_message(message, &mut scratch)?;
-        let result = scratch[..len].to_vec();
+        rekey.sent += 1;
 
-        Ok(result)
+        Ok([&[u8::from(rekeyed)][..], &scratch[..len]].concat())
     }
 
     pub fn decrypt_message(&self, message: &[u8]) -> Result<Vec<u8>, NoiseWrapperError> {
         let mut state = self.state.lock().expect("Failed to lock state");
-        let transport_state = state
-            .transport
-            .as_mut()
-            .ok_or(NoiseWrapperError::Transport)?;
+        let NoiseContextState {
+            transport,
+            incoming_key,
+            rekey,
+            ..
+        } = &mut *state;
+        let transport_state = transport.as_mut().ok_or(NoiseWrapperError::Transport)?;
         let mut scratch = self.scratch.lock().expect("Failed to lock scratch");
 
-        // Read the message using the transport state
+        let Some(rekey) = rekey else {
+            // Read the message using the transport state
+            let len = transport_state.read_message(message, &mut scratch)?;
+            return Ok(scratch[..len].to_vec());
+        };
+
+        let (rekeyed, message) = match message.split_first() {
+            Some((0, message)) => (false, message),
+            Some((1, message)) => (true, message),
+            _ => return Err(snow::Error::Decrypt.into()),
+        };
+        if rekeyed {
+            // The flag isn't authenticated, so try the next key on a scratch cipher and only
+            // commit to it once the message decrypts.
+            let incoming_key = incoming_key.as_mut().ok_or(NoiseWrapperError::Transport)?;
+            let next_key = next_cipher_key(incoming_key);
+            transport_cipher(&next_key).decrypt(
+                transport_state.receiving_nonce(),
+                &[],
+                message,
+                &mut scratch,
+            )?;
+            transport_state.rekey_incoming();
+            *incoming_key = next_key;
+            rekey.received = 0;
+        } else if rekey.received >= rekey.policy.max_messages {
+            return Err(NoiseWrapperError::RekeyRequired);
+        }
+
         let len = transport_state.read_message(message, &mut scratch)?;
-        let result = scratch[..len].to_vec();
+        rekey.received += 1;
+
+        Ok(scratch[..len].to_vec())
+    }
+}
+
+fn transport_cipher(key: &[u8; CIPHER_KEY_LEN]) -> Box<dyn Cipher> {
+    let mut cipher = DefaultResolver
+        .resolve_cipher(&create_params().cipher)
+        .expect("Transport cipher is supported");
+    cipher.set(key);
+    cipher
+}
+
+/// The key REKEY() moves a cipher to, per Section 4.2 of the Noise specification. Matches
+/// [`TransportState::rekey_incoming`] and [`TransportState::rekey_outgoing`].
+fn next_cipher_key(key: &[u8; CIPHER_KEY_LEN]) -> [u8; CIPHER_KEY_LEN] {
+    let mut ciphertext = [0u8; CIPHER_KEY_LEN + CIPHER_TAG_LEN];
+    transport_cipher(key).encrypt(u64::MAX, &[], &[0u8; CIPHER_KEY_LEN], &mut ciphertext);
+    ciphertext[..CIPHER_KEY_LEN]
+        .try_into()
+        .expect("Cipher keys are 32 bytes")
+}
+
+fn build_resumption_handshake(
+    role: NoiseRole,
+    sealed_ticket: &[u8],
+    resumption_secret: &[u8],
+) -> Result<HandshakeState, NoiseWrapperError> {
+    // Binding the ticket into the handshake keeps it from being swapped for another.
+    let prologue = [NOISE_PROLOGUE, sealed_ticket].concat();
+    let builder = Builder::with_resolver(
+        create_resumption_params(),
+        Box::new(SoftwareP256Resolver::default()),
+    )
+    .prologue(&prologue)
+    .psk(0, resumption_secret);
+
+    Ok(match role {
+        NoiseRole::Initiator => builder.build_initiator(),
+        NoiseRole::Responder => builder.build_responder(),
+    }?)
+}
+
+/// Prefixes the initiator's first resumption message with the ticket, as a u16 big-endian length
+/// followed by the ticket itself.
+fn frame_resumption_message(
+    sealed_ticket: &[u8],
+    message: &[u8],
+) -> Result<Vec<u8>, NoiseWrapperError> {
+    let ticket_len =
+        u16::try_from(sealed_ticket.len()).map_err(|_| NoiseWrapperError::InvalidTicket)?;
+    Ok([&ticket_len.to_be_bytes()[..], sealed_ticket, message].concat())
+}
 
-        Ok(result)
+fn unframe_resumption_message(message: &[u8]) -> Result<(&[u8], &[u8]), NoiseWrapperError> {
+    if message.len() < 2 {
+        return Err(NoiseWrapperError::InvalidTicket);
     }
+    let (ticket_len, rest) = message.split_at(2);
+    let ticket_len = u16::from_be_bytes([ticket_len[0], ticket_len[1]]) as usize;
+    if rest.len() < ticket_len {
+        return Err(NoiseWrapperError::InvalidTicket);
+    }
+    Ok(rest.split_at(ticket_len))
 }
 
 fn sec1_uncompressed_to_compressed(sec1_uncompressed: &[u8]) -> Result<Vec<u8>, DhError> {
@@ -630,6 +1101,284 @@ mod tests {
         assert_eq!(s2c_pt, b"Hello, client!");
     }
 
+    fn established_sessions() -> (NoiseContext, NoiseContext) {
+        let server_keypair = generate_keypair();
+        let client_keypair = generate_keypair();
+
+        let server = NoiseContext::new(
+            NoiseRole::Responder,
+            PrivateKey::InMemory {
+                secret_bytes: server_keypair.0,
+            },
+            None,
+            None,
+        )
+        .unwrap();
+        let client = NoiseContext::new(
+            NoiseRole::Initiator,
+            PrivateKey::InMemory {
+                secret_bytes: client_keypair.0,
+            },
+            Some(server_keypair.1),
+            None,
+        )
+        .unwrap();
+
+        let client_message = client.initiate_handshake().unwrap();
+        let server_message = server.advance_handshake(client_message).unwrap();
+        client.advance_handshake(server_message.unwrap()).unwrap();
+        client.finalize_handshake().unwrap();
+        server.finalize_handshake().unwrap();
+
+        (client, server)
+    }
+
+    fn resume(
+        ticket: Vec<u8>,
+        resumption_secret: Vec<u8>,
+        ticket_key: &[u8],
+        replay_guard: &Arc<ReplayGuard>,
+    ) -> Result<(NoiseContext, NoiseContext), NoiseWrapperError> {
+        let client = NoiseContext::resume_initiator(ticket, resumption_secret)?;
+        let server = NoiseContext::resume_responder(ticket_key.to_vec(), replay_guard.clone())?;
+
+        let client_message = client.initiate_handshake()?;
+        let server_message = server.advance_handshake(client_message)?;
+        assert!(server.is_handshake_finished());
+        client.advance_handshake(server_message.unwrap())?;
+        assert!(client.is_handshake_finished());
+        client.finalize_handshake()?;
+        server.finalize_handshake()?;
+
+        Ok((client, server))
+    }
+
+    #[test]
+    fn test_session_resumption() {
+        let ticket_key = [7u8; 32];
+        let replay_guard = Arc::new(ReplayGuard::new());
+        let (client, server) = established_sessions();
+
+        assert_eq!(
+            client.resumption_secret().unwrap(),
+            server.resumption_secret().unwrap()
+        );
+        assert!(matches!(
+            client.issue_session_ticket(&ticket_key, Duration::from_secs(60)),
+            Err(NoiseWrapperError::IllegalState)
+        ));
+        let ticket = server
+            .issue_session_ticket(&ticket_key, Duration::from_secs(60))
+            .unwrap();
+
+        let (resumed_client, resumed_server) = resume(
+            ticket.clone(),
+            client.resumption_secret().unwrap(),
+            &ticket_key,
+            &replay_guard,
+        )
+        .unwrap();
+
+        let ct = resumed_client.encrypt_message(b"Hello, server!").unwrap();
+        assert_eq!(
+            resumed_server.decrypt_message(&ct).unwrap(),
+            b"Hello, server!"
+        );
+        let ct = resumed_server.encrypt_message(b"Hello, client!").unwrap();
+        assert_eq!(
+            resumed_client.decrypt_message(&ct).unwrap(),
+            b"Hello, client!"
+        );
+
+        // Tickets are single use.
+        assert!(matches!(
+            resume(
+                ticket,
+                client.resumption_secret().unwrap(),
+                &ticket_key,
+                &replay_guard
+            ),
+            Err(NoiseWrapperError::TicketReplayed)
+        ));
+
+        // Resumed sessions can be resumed in turn.
+        let ticket = resumed_server
+            .issue_session_ticket(&ticket_key, Duration::from_secs(60))
+            .unwrap();
+        assert!(resume(
+            ticket,
+            resumed_client.resumption_secret().unwrap(),
+            &ticket_key,
+            &replay_guard
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn test_invalid_session_resumption() {
+        let ticket_key = [7u8; 32];
+        let replay_guard = Arc::new(ReplayGuard::new());
+        let (client, server) = established_sessions();
+        let ticket = server
+            .issue_session_ticket(&ticket_key, Duration::from_secs(60))
+            .unwrap();
+
+        // Wrong resumption secret
+        assert!(matches!(
+            resume(ticket.clone(), vec![0u8; 32], &ticket_key, &replay_guard),
+            Err(NoiseWrapperError::InternalError(_))
+        ));
+
+        // Wrong ticket key
+        assert!(matches!(
+            resume(
+                ticket.clone(),
+                client.resumption_secret().unwrap(),
+                &[8u8; 32],
+                &replay_guard
+            ),
+            Err(NoiseWrapperError::InvalidTicket)
+        ));
+
+        // Tampered ticket
+        let mut tampered_ticket = ticket.clone();
+        *tampered_ticket.last_mut().unwrap() ^= 1;
+        assert!(matches!(
+            resume(
+                tampered_ticket,
+                client.resumption_secret().unwrap(),
+                &ticket_key,
+                &replay_guard
+            ),
+            Err(NoiseWrapperError::InvalidTicket)
+        ));
+
+        // Expired ticket
+        let expired_ticket = server
+            .issue_session_ticket(&ticket_key, Duration::ZERO)
+            .unwrap();
+        assert!(matches!(
+            resume(
+                expired_ticket,
+                client.resumption_secret().unwrap(),
+                &ticket_key,
+                &replay_guard
+            ),
+            Err(NoiseWrapperError::TicketExpired)
+        ));
+
+        // Failed attempts don't use up the ticket.
+        assert!(resume(
+            ticket,
+            client.resumption_secret().unwrap(),
+            &ticket_key,
+            &replay_guard
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn test_rekey() {
+        let policy = RekeyPolicy {
+            max_messages: 2,
+            max_duration_secs: 3600,
+        };
+        let (client, server) = established_sessions();
+        assert!(matches!(
+            client.request_rekey(),
+            Err(NoiseWrapperError::RekeyNotEnabled)
+        ));
+        client.set_rekey_policy(policy).unwrap();
+        server.set_rekey_policy(policy).unwrap();
+
+        for i in 0..5u8 {
+            let ct = client.encrypt_message(&[i]).unwrap();
+            assert_eq!(ct[0], u8::from(i > 0 && i % 2 == 0));
+            assert_eq!(server.decrypt_message(&ct).unwrap(), [i]);
+        }
+
+        client.request_rekey().unwrap();
+        let ct = client.encrypt_message(b"rekeyed").unwrap();
+        assert_eq!(ct[0], 1);
+        assert_eq!(server.decrypt_message(&ct).unwrap(), b"rekeyed");
+
+        let ct = server.encrypt_message(b"Hello, client!").unwrap();
+        assert_eq!(client.decrypt_message(&ct).unwrap(), b"Hello, client!");
+
+        // Policies can't be changed once messages have been exchanged.
+        assert!(matches!(
+            client.set_rekey_policy(policy),
+            Err(NoiseWrapperError::IllegalState)
+        ));
+    }
+
+    #[test]
+    fn test_rekey_enforced_by_receiver() {
+        let policy = RekeyPolicy {
+            max_messages: 1,
+            max_duration_secs: 3600,
+        };
+        let (client, server) = established_sessions();
+        client.set_rekey_policy(policy).unwrap();
+        server.set_rekey_policy(policy).unwrap();
+
+        let ct = client.encrypt_message(b"first").unwrap();
+        server.decrypt_message(&ct).unwrap();
+
+        // Clearing the rekey flag leaves the receiver on the old key.
+        let mut ct = client.encrypt_message(b"second").unwrap();
+        assert_eq!(ct[0], 1);
+        ct[0] = 0;
+        assert!(matches!(
+            server.decrypt_message(&ct),
+            Err(NoiseWrapperError::RekeyRequired)
+        ));
+
+        // Setting it fails decryption under the next key.
+        let (client, server) = established_sessions();
+        client.set_rekey_policy(policy).unwrap();
+        server.set_rekey_policy(policy).unwrap();
+        let mut ct = client.encrypt_message(b"first").unwrap();
+        ct[0] = 1;
+        assert!(matches!(
+            server.decrypt_message(&ct),
+            Err(NoiseWrapperError::InternalError(snow::Error::Decrypt))
+        ));
+    }
+
+    #[test]
+    fn test_tampered_rekey_flag_keeps_session() {
+        let policy = RekeyPolicy {
+            max_messages: 2,
+            max_duration_secs: 3600,
+        };
+        let (client, server) = established_sessions();
+        client.set_rekey_policy(policy).unwrap();
+        server.set_rekey_policy(policy).unwrap();
+
+        // A flipped flag fails only its own message; the receiver stays on the current key.
+        let mut ct = client.encrypt_message(b"first").unwrap();
+        assert_eq!(ct[0], 0);
+        ct[0] = 1;
+        assert!(server.decrypt_message(&ct).is_err());
+        ct[0] = 0;
+        assert_eq!(server.decrypt_message(&ct).unwrap(), b"first");
+
+        let ct = client.encrypt_message(b"second").unwrap();
+        assert_eq!(server.decrypt_message(&ct).unwrap(), b"second");
+
+        // A cleared flag doesn't stop the genuine rekeyed message from decrypting afterwards.
+        let mut ct = client.encrypt_message(b"third").unwrap();
+        assert_eq!(ct[0], 1);
+        ct[0] = 0;
+        assert!(server.decrypt_message(&ct).is_err());
+        ct[0] = 1;
+        assert_eq!(server.decrypt_message(&ct).unwrap(), b"third");
+
+        let ct = client.encrypt_message(b"fourth").unwrap();
+        assert_eq!(server.decrypt_message(&ct).unwrap(), b"fourth");
+    }
+
     #[test]
     fn test_decode_sec1_uncompressed() {
         let sec1_uncompressed_hex = "046d0f2d82024c8a9defa34ac4a82f659247b38e0fdf3024d579d981f9ed7a8661f8efe8bd86dc1ba05fc986f1c9f12e450edcb1c34d072c7cde13a897767050ab";
@@ -0,0 +1,219 @@
+extern crate boring_sys;
+use super::{Spake2Error, Spake2Role};
+use boring_sys::*;
+use std::sync::{Arc, Mutex};
+use std::{ptr, slice};
+
+impl From<spake2_role_t> for Spake2Role {
+    fn from(role: spake2_role_t) -> Self {
+        match role {
+            spake2_role_t::spake2_role_alice => Spake2Role::Alice,
+            spake2_role_t::spake2_role_bob => Spake2Role::Bob,
+            _ => panic!("Invalid role"),
+        }
+    }
+}
+
+impl From<Spake2Role> for spake2_role_t {
+    fn from(val: Spake2Role) -> Self {
+        match val {
+            Spake2Role::Alice => spake2_role_t::spake2_role_alice,
+            Spake2Role::Bob => spake2_role_t::spake2_role_bob,
+        }
+    }
+}
+
+/// Wapper around the SPAKE2_CTX struct from BoringSSL.
+/// * Automatically handles BoringSSL initialization
+/// * Thread safe
+pub(super) struct Spake2Backend {
+    ctx: Arc<Mutex<*mut SPAKE2_CTX>>,
+}
+
+impl Spake2Backend {
+    /// BoringSSL documentation for this function is repeated below:
+    ///
+    /// SPAKE2_CTX_new creates a new |SPAKE2_CTX| (which can only be used for a
+    /// single execution of the protocol). SPAKE2 requires the symmetry of the two
+    /// parties to be broken which is indicated via |my_role| – each party must pass
+    /// a different value for this argument.
+    pub(super) fn new(
+        my_role: Spake2Role,
+        my_name: Vec<u8>,
+        their_name: Vec<u8>,
+    ) -> Result<Self, Spake2Error> {
+        unsafe {
+            // CRYPTO_library_init initializes the crypto library.
+            // It must be called if the library is built with BORINGSSL_NO_STATIC_INITIALIZER.
+            // Otherwise, it does nothing and a static initializer is used instead.
+            // It is safe to call this function multiple times and concurrently from multiple threads.
+            // On some ARM configurations, this function may require filesystem access and should be called before entering a sandbox.
+            boring_sys::CRYPTO_library_init();
+        }
+
+        let ctx = unsafe {
+            SPAKE2_CTX_new(
+                my_role.into(),
+                my_name.as_ptr(),
+                my_name.len(),
+                their_name.as_ptr(),
+                their_name.len(),
+            )
+        };
+
+        if ctx.is_null() {
+            Err(Spake2Error::ContextCreationError)
+        } else {
+            Ok(Spake2Backend {
+                ctx: Arc::new(Mutex::new(ctx)),
+            })
+        }
+    }
+
+    /// SPAKE2_generate_msg generates a SPAKE2 message for the given password.
+    /// This function can only be called once for a given SPAKE2_CTX, and will error if so.
+    pub(super) fn generate_msg(&self, password: &[u8]) -> Result<Vec<u8>, Spake2Error> {
+        let ctx_guard = self.ctx.lock().unwrap();
+        let ctx = *ctx_guard;
+
+        let mut out = vec![0u8; boring_sys::SPAKE2_MAX_MSG_SIZE as usize];
+        let mut out_len = 0;
+
+        let result = unsafe {
+            SPAKE2_generate_msg(
+                ctx,
+                out.as_mut_ptr(),
+                &mut out_len,
+                boring_sys::SPAKE2_MAX_MSG_SIZE as usize,
+                password.as_ptr(),
+                password.len(),
+            )
+        };
+
+        if result == 0 {
+            Err(Spake2Error::GenerateMessageError)
+        } else {
+            out.truncate(out_len);
+            Ok(out)
+        }
+    }
+
+    /// BoringSSL's documentation for this function is repeated below:
+    ///
+    /// SPAKE2_process_msg completes the SPAKE2 exchange given the peer's message in
+    /// |their_msg|, writes at most |max_out_key_len| bytes to |out_key| and sets
+    /// |*out_key_len| to the number of bytes written.
+    ///
+    /// The resulting keying material is suitable for:
+    ///    - Using directly in a key-confirmation step: i.e. each side could
+    ///      transmit a hash of their role, a channel-binding value and the key
+    ///      material to prove to the other side that they know the shared key.
+    ///   -  Using as input keying material to HKDF to generate a variety of subkeys
+    ///      for encryption etc.
+    ///
+    /// If |max_out_key_key| is smaller than the amount of key material generated
+    /// then the key is silently truncated. If you want to ensure that no truncation
+    /// occurs then |max_out_key| should be at least |SPAKE2_MAX_KEY_SIZE|.
+    ///
+    /// You must call |SPAKE2_generate_msg| on a given |SPAKE2_CTX| before calling
+    /// this function. On successful return, |ctx| is complete and calling
+    /// |SPAKE2_CTX_free| is the only acceptable operation on it.
+    ///
+    /// Returns one on success or zero on error.
+    pub(super) fn process_msg(&self, their_msg: &[u8]) -> Result<Vec<u8>, Spake2Error> {
+        let ctx_guard = self.ctx.lock().unwrap();
+        let ctx = *ctx_guard;
+
+        let mut out_key_material = vec![0u8; boring_sys::SPAKE2_MAX_KEY_SIZE as usize];
+        let mut out_key_material_len = 0;
+
+        let result = unsafe {
+            SPAKE2_process_msg(
+                ctx,
+                out_key_material.as_mut_ptr(),
+                &mut out_key_material_len,
+                boring_sys::SPAKE2_MAX_KEY_SIZE as usize,
+                their_msg.as_ptr(),
+                their_msg.len(),
+            )
+        };
+
+        if result == 0 {
+            Err(Spake2Error::ProcessMessageError)
+        } else {
+            out_key_material.truncate(out_key_material_len);
+            Ok(out_key_material)
+        }
+    }
+
+    pub(super) fn read_private_key(&self) -> Vec<u8> {
+        // This is not good. We are bypassing BoringSSL's struct hiding here so that we can read the private key
+        // to persist it, to support async communication.
+        // This **BADLY** breaks if BoringSSL changes the struct layout.
+        //
+        // struct spake2_ctx_st {
+        //     uint8_t private_key[32];
+        //     uint8_t my_msg[32];
+        //     uint8_t password_scalar[32];
+        //     uint8_t password_hash[64];
+        //     uint8_t *my_name;
+        //     size_t my_name_len;
+        //     uint8_t *their_name;
+        //     size_t their_name_len;
+        //     enum spake2_role_t my_role;
+        //     enum spake2_state_t state;
+        //     char disable_password_scalar_hack;
+        //   };
+        let ctx_guard = self.ctx.lock().unwrap();
+        let ctx = *ctx_guard;
+
+        unsafe {
+            let private_key_ptr = ctx as *mut u8;
+            let private_key_slice = slice::from_raw_parts(private_key_ptr, 32);
+            private_key_slice.to_vec()
+        }
+    }
+
+    pub(super) fn read_public_key(&self) -> Vec<u8> {
+        // See note in `read_private_key`.
+
+        let ctx_guard = self.ctx.lock().unwrap();
+        let ctx = *ctx_guard as *mut u8;
+
+        unsafe {
+            let my_msg_offset = ctx.add(32); // Offset by the size of private_key
+            let public_key_slice = slice::from_raw_parts(my_msg_offset, 32);
+            public_key_slice.to_vec()
+        }
+    }
+
+    /// Both keys must be 32 bytes long.
+    pub(super) fn write_key_pair(&self, private_key: &[u8], public_key: &[u8]) {
+        // See note in `read_private_key`. EVIL! BAD! NO! Anyway.
+
+        let ctx_guard = self.ctx.lock().unwrap();
+        let ctx = *ctx_guard as *mut u8;
+
+        unsafe {
+            // Copy private_key to the start of the context
+            ptr::copy_nonoverlapping(private_key.as_ptr(), ctx, 32);
+            // Offset by the size of private_key
+            let my_msg_offset = ctx.add(32);
+            ptr::copy_nonoverlapping(public_key.as_ptr(), my_msg_offset, 32);
+        }
+    }
+}
+
+impl Drop for Spake2Backend {
+    fn drop(&mut self) {
+        let ctx_guard = self.ctx.lock().unwrap();
+        if !(*ctx_guard).is_null() {
+            unsafe {
+                SPAKE2_CTX_free(*ctx_guard);
+            }
+        }
+    }
+}
+
+unsafe impl Send for Spake2Backend {}
+unsafe impl Sync for Spake2Backend {}
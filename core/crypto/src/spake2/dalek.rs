@@ -0,0 +1,435 @@
+use super::{Spake2Error, Spake2Role};
+use curve25519_dalek::{
+    constants::ED25519_BASEPOINT_TABLE,
+    edwards::{CompressedEdwardsY, EdwardsPoint},
+    Scalar,
+};
+use rand::RngCore;
+use sha2::{Digest, Sha512};
+use std::sync::Mutex;
+
+// The points BoringSSL uses to mask each party's public key, generated by hashing the seeds
+// "edwards25519 point generation seed (M)" and "(N)" until they decode to a point.
+const M: CompressedEdwardsY = CompressedEdwardsY([
+    0x5a, 0xda, 0x7e, 0x4b, 0xf6, 0xdd, 0xd9, 0xad, 0xb6, 0x62, 0x6d, 0x32, 0x13, 0x1c, 0x6b, 0x5c,
+    0x51, 0xa1, 0xe3, 0x47, 0xa3, 0x47, 0x8f, 0x53, 0xcf, 0xcf, 0x44, 0x1b, 0x88, 0xee, 0xd1, 0x2e,
+]);
+const N: CompressedEdwardsY = CompressedEdwardsY([
+    0x10, 0xe3, 0xdf, 0x0a, 0xe3, 0x7d, 0x8e, 0x7a, 0x99, 0xb5, 0xfe, 0x74, 0xb4, 0x46, 0x72, 0x10,
+    0x3d, 0xbd, 0xdc, 0xbd, 0x06, 0xaf, 0x68, 0x0d, 0x71, 0x32, 0x9a, 0x11, 0x69, 0x3b, 0xc7, 0x78,
+]);
+
+// The order of the prime-order subgroup, in little-endian order.
+const ORDER: [u8; 32] = [
+    0xed, 0xd3, 0xf5, 0x5c, 0x1a, 0x63, 0x12, 0x58, 0xd6, 0x9c, 0xf7, 0xa2, 0xde, 0xf9, 0xde, 0x14,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10,
+];
+
+const KEY_MATERIAL_LENGTH: usize = 64;
+
+#[derive(Debug, PartialEq)]
+enum Stage {
+    Init,
+    MsgGenerated,
+    KeyGenerated,
+}
+
+// Mirrors BoringSSL's `spake2_ctx_st`, so that keys read from one backend can be written to the
+// other.
+struct State {
+    private_key: [u8; 32],
+    my_msg: [u8; 32],
+    password_scalar: [u8; 32],
+    password_hash: [u8; 64],
+    stage: Stage,
+}
+
+/// A pure-Rust port of BoringSSL's SPAKE2, producing the same messages and key material.
+///
+/// BoringSSL keeps its scalars as unreduced 256-bit integers, which matters for points outside
+/// the prime-order subgroup, so scalar multiplications here use [`mul_integer`] rather than
+/// reducing them.
+pub(super) struct Spake2Backend {
+    role: Spake2Role,
+    my_name: Vec<u8>,
+    their_name: Vec<u8>,
+    state: Mutex<State>,
+}
+
+impl Spake2Backend {
+    pub(super) fn new(
+        my_role: Spake2Role,
+        my_name: Vec<u8>,
+        their_name: Vec<u8>,
+    ) -> Result<Self, Spake2Error> {
+        Ok(Spake2Backend {
+            role: my_role,
+            my_name,
+            their_name,
+            state: Mutex::new(State {
+                private_key: [0u8; 32],
+                my_msg: [0u8; 32],
+                password_scalar: [0u8; 32],
+                password_hash: [0u8; 64],
+                stage: Stage::Init,
+            }),
+        })
+    }
+
+    pub(super) fn generate_msg(&self, password: &[u8]) -> Result<Vec<u8>, Spake2Error> {
+        let mut private_tmp = [0u8; 64];
+        rand::thread_rng().fill_bytes(&mut private_tmp);
+        // Multiply by the cofactor (eight) so that we'll clear it when operating on the peer's
+        // point later in the protocol.
+        let mut private_key = Scalar::from_bytes_mod_order_wide(&private_tmp).to_bytes();
+        left_shift_3(&mut private_key);
+
+        self.generate_msg_with_private_key(password, private_key)
+    }
+
+    fn generate_msg_with_private_key(
+        &self,
+        password: &[u8],
+        private_key: [u8; 32],
+    ) -> Result<Vec<u8>, Spake2Error> {
+        let mut state = self.state.lock().unwrap();
+        if state.stage != Stage::Init {
+            return Err(Spake2Error::GenerateMessageError);
+        }
+
+        let public_key = ED25519_BASEPOINT_TABLE * &Scalar::from_bytes_mod_order(private_key);
+
+        // mask = h(password) * <N or M>.
+        let password_hash: [u8; 64] = Sha512::digest(password).into();
+        let password_scalar =
+            clear_cofactor_bits(Scalar::from_bytes_mod_order_wide(&password_hash));
+        let mask = mul_integer(&password_scalar, &self.my_mask_point());
+
+        state.private_key = private_key;
+        state.my_msg = (public_key + mask).compress().to_bytes();
+        state.password_scalar = password_scalar;
+        state.password_hash = password_hash;
+        state.stage = Stage::MsgGenerated;
+
+        Ok(state.my_msg.to_vec())
+    }
+
+    pub(super) fn process_msg(&self, their_msg: &[u8]) -> Result<Vec<u8>, Spake2Error> {
+        let mut state = self.state.lock().unwrap();
+        if state.stage != Stage::MsgGenerated {
+            return Err(Spake2Error::ProcessMessageError);
+        }
+
+        let their_msg: [u8; 32] = their_msg
+            .try_into()
+            .map_err(|_| Spake2Error::ProcessMessageError)?;
+        // Point received from peer must be on the curve.
+        let their_masked_key = CompressedEdwardsY(their_msg)
+            .decompress()
+            .ok_or(Spake2Error::ProcessMessageError)?;
+
+        // Unmask peer's value.
+        let their_key =
+            their_masked_key - mul_integer(&state.password_scalar, &self.their_mask_point());
+        let dh_shared = mul_integer(&state.private_key, &their_key).compress();
+
+        let mut sha = Sha512::new();
+        match self.role {
+            Spake2Role::Alice => {
+                update_with_length_prefix(&mut sha, &self.my_name);
+                update_with_length_prefix(&mut sha, &self.their_name);
+                update_with_length_prefix(&mut sha, &state.my_msg);
+                update_with_length_prefix(&mut sha, &their_msg);
+            }
+            Spake2Role::Bob => {
+                update_with_length_prefix(&mut sha, &self.their_name);
+                update_with_length_prefix(&mut sha, &self.my_name);
+                update_with_length_prefix(&mut sha, &their_msg);
+                update_with_length_prefix(&mut sha, &state.my_msg);
+            }
+        }
+        update_with_length_prefix(&mut sha, dh_shared.as_bytes());
+        update_with_length_prefix(&mut sha, &state.password_hash);
+        state.stage = Stage::KeyGenerated;
+
+        let key: [u8; KEY_MATERIAL_LENGTH] = sha.finalize().into();
+        Ok(key.to_vec())
+    }
+
+    pub(super) fn read_private_key(&self) -> Vec<u8> {
+        self.state.lock().unwrap().private_key.to_vec()
+    }
+
+    pub(super) fn read_public_key(&self) -> Vec<u8> {
+        self.state.lock().unwrap().my_msg.to_vec()
+    }
+
+    /// Both keys must be 32 bytes long.
+    pub(super) fn write_key_pair(&self, private_key: &[u8], public_key: &[u8]) {
+        let mut state = self.state.lock().unwrap();
+        state.private_key.copy_from_slice(private_key);
+        state.my_msg.copy_from_slice(public_key);
+    }
+
+    fn my_mask_point(&self) -> EdwardsPoint {
+        match self.role {
+            Spake2Role::Alice => decompress(&M),
+            Spake2Role::Bob => decompress(&N),
+        }
+    }
+
+    fn their_mask_point(&self) -> EdwardsPoint {
+        match self.role {
+            Spake2Role::Alice => decompress(&N),
+            Spake2Role::Bob => decompress(&M),
+        }
+    }
+}
+
+fn decompress(point: &CompressedEdwardsY) -> EdwardsPoint {
+    point.decompress().expect("M and N are valid points")
+}
+
+/// Sets `n` to `n`*8, where `n` is represented in little-endian order.
+fn left_shift_3(n: &mut [u8; 32]) {
+    let mut carry = 0;
+    for byte in n.iter_mut() {
+        let next_carry = *byte >> 5;
+        *byte = (*byte << 3) | carry;
+        carry = next_carry;
+    }
+}
+
+/// BoringSSL originally forgot to multiply the password scalar by the cofactor, leaking three
+/// bits of the password hash. It fixed this compatibly by adding multiples of the group order
+/// until the scalar is a multiple of eight, which we must replicate for our masks to match.
+fn clear_cofactor_bits(password_scalar: Scalar) -> [u8; 32] {
+    let mut scalar = password_scalar.to_bytes();
+    let mut order = ORDER;
+    for shift in 0..3 {
+        // The order is odd, so adding order * 2^shift flips bit `shift` and leaves lower bits
+        // alone.
+        let mask = 0u8.wrapping_sub((scalar[0] >> shift) & 1);
+        add_assign(&mut scalar, &order.map(|byte| byte & mask));
+        let doubled = order;
+        add_assign(&mut order, &doubled);
+    }
+    debug_assert_eq!(scalar[0] & 7, 0);
+    scalar
+}
+
+/// Adds `src` to `dest`, both little-endian 256-bit integers. The scalars here are at most
+/// 8 times the group order, so this never overflows.
+fn add_assign(dest: &mut [u8; 32], src: &[u8; 32]) {
+    let mut carry = 0u16;
+    for (d, s) in dest.iter_mut().zip(src) {
+        let sum = u16::from(*d) + u16::from(*s) + carry;
+        *d = sum as u8;
+        carry = sum >> 8;
+    }
+}
+
+/// Multiplies `point` by `n`, a little-endian 256-bit integer, without reducing `n` modulo the
+/// group order. That differs from reducing it whenever `point` has a small-order component.
+fn mul_integer(n: &[u8; 32], point: &EdwardsPoint) -> EdwardsPoint {
+    // n * P = (n >> 3) * 8P + (n & 7) * P, and 8P is in the prime-order subgroup, where reducing
+    // the scalar is fine.
+    let high: [u8; 32] =
+        std::array::from_fn(|i| (n[i] >> 3) | n.get(i + 1).map_or(0, |next| next << 5));
+    point.mul_by_cofactor() * Scalar::from_bytes_mod_order(high) + point * Scalar::from(n[0] & 7)
+}
+
+fn update_with_length_prefix(sha: &mut Sha512, data: &[u8]) {
+    sha.update((data.len() as u64).to_le_bytes());
+    sha.update(data);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TestVector {
+        alice_name: &'static str,
+        bob_name: &'static str,
+        alice_password: &'static str,
+        bob_password: &'static str,
+        alice_private_key: &'static str,
+        alice_msg: &'static str,
+        bob_private_key: &'static str,
+        bob_msg: &'static str,
+        alice_key: &'static str,
+        bob_key: &'static str,
+    }
+
+    // Generated with BoringSSL's SPAKE2_generate_msg and SPAKE2_process_msg, with RAND_bytes
+    // stubbed out to produce the private keys below.
+    const TEST_VECTORS: &[TestVector] = &[
+        TestVector {
+            alice_name: "alice",
+            bob_name: "bob",
+            alice_password: "password",
+            bob_password: "password",
+            alice_private_key: "d8973693f9778a8b3d0966f03d38597471ec4a6450304e66091ff002da52864a",
+            alice_msg: "cb95da0204e71fa807f53a5a8c59c0082eff979f26405ddf5f53a245f9697a2c",
+            bob_private_key: "d090f9826bb6b4b6ca8e2f44d9efabbb7a78c6a90ebbc86b2ed893578eeca870",
+            bob_msg: "6d77668b9a112a918554913956b8780f1fba45e1999108a0ef23e56389c63dd6",
+            alice_key: "87d844193964b2caa374d3d28bc0d904c51996463bfb30dc944d9f194152a443375a15ec86f6d179f83ee1e1158ae86f3a3999034dc0276a80f165b32aeda8f1",
+            bob_key: "87d844193964b2caa374d3d28bc0d904c51996463bfb30dc944d9f194152a443375a15ec86f6d179f83ee1e1158ae86f3a3999034dc0276a80f165b32aeda8f1",
+        },
+        TestVector {
+            alice_name: "customer",
+            bob_name: "trusted-contact",
+            alice_password: "correct horse battery staple",
+            bob_password: "correct horse battery staple",
+            alice_private_key: "50621340ec600e1de2eb92ba701a078a43ca07299b6faa7d9439315006c64974",
+            alice_msg: "abebe0dc193227909ba9a590859d9b362b440f7c43b7e2fc8e270b66bc2e1857",
+            bob_private_key: "e03b9e28f02f6146f7bf363061d0a29638d08d6e59fa2483b9f2d4a4ba676c1a",
+            bob_msg: "1d7da97b6e8a179b2f4fad6ef57d7068d2aef39085df017c456868057b72e69a",
+            alice_key: "68801ceaff39412d37b5dea0e9cba8b38122a24638b534d557fe44285ad14cf3448b851d6c957ec47b21acc6926b466c0ed520c88030daf21cd9b128d063a8bf",
+            bob_key: "68801ceaff39412d37b5dea0e9cba8b38122a24638b534d557fe44285ad14cf3448b851d6c957ec47b21acc6926b466c0ed520c88030daf21cd9b128d063a8bf",
+        },
+        TestVector {
+            alice_name: "",
+            bob_name: "",
+            alice_password: "",
+            bob_password: "",
+            alice_private_key: "e8b72cda3c1f5e7c56dc6ad1338a9d08ea12a59cc2dda7b42c75e413ef90d554",
+            alice_msg: "169669c37555d424727b0e3a7dc358333bcb937adb9b9dfbc76da06d79d9d231",
+            bob_private_key: "184f4df9942d1adf9ab2a7d9d55bb26ede368525524ccf21fe308a1e71bc206b",
+            bob_msg: "0700b75a65b9252334605c0a069917e9620bec60e3986692fd9ba13523b6d536",
+            alice_key: "9f4516055f9308b9061a612dc0a4a927c6079e712b477713615c222fb0a2c805ca8a5dfb48f514e98e9d9765336140a928552b1c3935560f7794231ad9a13c37",
+            bob_key: "9f4516055f9308b9061a612dc0a4a927c6079e712b477713615c222fb0a2c805ca8a5dfb48f514e98e9d9765336140a928552b1c3935560f7794231ad9a13c37",
+        },
+        TestVector {
+            alice_name: "alice",
+            bob_name: "bob",
+            alice_password: "password",
+            bob_password: "passworf",
+            alice_private_key: "d090f9826bb6b4b6ca8e2f44d9efabbb7a78c6a90ebbc86b2ed893578eeca870",
+            alice_msg: "ff96d0e3ae4553bd448dd4873c965c0d4ff728a2262bb499029354b814c25101",
+            bob_private_key: "e8b72cda3c1f5e7c56dc6ad1338a9d08ea12a59cc2dda7b42c75e413ef90d554",
+            bob_msg: "9a5cefce8f8365853190597239e1b7cbb1a527d69083a04d12546a9c4c834d9d",
+            alice_key: "43dbc9f7f5113f60e86527d7ae75a499f058f48e955aacf4ba01ebfaaf5970d70e0b6107d58c3e5d8433a7b6d84b1df21e8f38e9897c38a927af4bcfae4db654",
+            bob_key: "007b4bc9f9a6730aafddcaffd347c84295ae5d58063b422f64295dd41a57cdb20cc42f78a5eea833b1e7bc56b7225804dab531eddedb485e62c98350b7f5569d",
+        },
+    ];
+
+    fn private_key(hex_key: &str) -> [u8; 32] {
+        hex::decode(hex_key).unwrap().try_into().unwrap()
+    }
+
+    #[test]
+    fn test_boringssl_vectors() {
+        for vector in TEST_VECTORS {
+            let alice = Spake2Backend::new(
+                Spake2Role::Alice,
+                vector.alice_name.into(),
+                vector.bob_name.into(),
+            )
+            .unwrap();
+            let bob = Spake2Backend::new(
+                Spake2Role::Bob,
+                vector.bob_name.into(),
+                vector.alice_name.into(),
+            )
+            .unwrap();
+
+            let alice_msg = alice
+                .generate_msg_with_private_key(
+                    vector.alice_password.as_bytes(),
+                    private_key(vector.alice_private_key),
+                )
+                .unwrap();
+            let bob_msg = bob
+                .generate_msg_with_private_key(
+                    vector.bob_password.as_bytes(),
+                    private_key(vector.bob_private_key),
+                )
+                .unwrap();
+            assert_eq!(hex::encode(&alice_msg), vector.alice_msg);
+            assert_eq!(hex::encode(&bob_msg), vector.bob_msg);
+
+            assert_eq!(
+                hex::encode(alice.process_msg(&bob_msg).unwrap()),
+                vector.alice_key
+            );
+            assert_eq!(
+                hex::encode(bob.process_msg(&alice_msg).unwrap()),
+                vector.bob_key
+            );
+        }
+    }
+
+    #[test]
+    fn test_boringssl_vectors_with_small_order_components() {
+        // BoringSSL accepts any point on the curve, so the results for points outside the
+        // prime-order subgroup must match too.
+        let vector = &TEST_VECTORS[0];
+        for (their_msg, expected_key) in [
+            // A point of order 8
+            (
+                "26e8958fc2b227b045c3f489f2ef98f0d5dfac05d3c63339b13802886d53fc05",
+                "1c9de37d49ce2995679d864863e7abdbb1c6a94840cb1cec28ba1f81b8b5d760bc4da9dd8ae115d4f55da37d4ca9d42f40cf35ace8aefe3f5484e1e36b32f47b",
+            ),
+            (
+                "c7176a703d4dd84fba3c0b760d10670f2a2053fa2c39ccc64ec7fd7792ac03fa",
+                "299e8ab821c3ce9970e2f00187905d1488cf564ae2f9000547ea66c47b405fcae57688776bbda6a4066f374ca720e8a2d735530bc989ba64bdabbfcc426413bf",
+            ),
+        ] {
+            let alice = Spake2Backend::new(
+                Spake2Role::Alice,
+                vector.alice_name.into(),
+                vector.bob_name.into(),
+            )
+            .unwrap();
+            alice
+                .generate_msg_with_private_key(
+                    vector.alice_password.as_bytes(),
+                    private_key(vector.alice_private_key),
+                )
+                .unwrap();
+
+            let key = alice.process_msg(&hex::decode(their_msg).unwrap()).unwrap();
+            assert_eq!(hex::encode(key), expected_key);
+        }
+    }
+
+    #[test]
+    fn test_rejects_invalid_points() {
+        let alice = Spake2Backend::new(Spake2Role::Alice, vec![], vec![]).unwrap();
+        alice.generate_msg(b"password").unwrap();
+
+        // y = 2 isn't on the curve.
+        let mut not_on_curve = [0u8; 32];
+        not_on_curve[0] = 2;
+        assert!(matches!(
+            alice.process_msg(&not_on_curve),
+            Err(Spake2Error::ProcessMessageError)
+        ));
+        assert!(matches!(
+            alice.process_msg(&[0u8; 31]),
+            Err(Spake2Error::ProcessMessageError)
+        ));
+    }
+
+    #[cfg(feature = "spake2_boringssl")]
+    #[test]
+    fn test_interoperates_with_boringssl() {
+        use super::super::boringssl;
+
+        for password in ["password", ""] {
+            let alice =
+                Spake2Backend::new(Spake2Role::Alice, b"alice".to_vec(), b"bob".to_vec()).unwrap();
+            let bob =
+                boringssl::Spake2Backend::new(Spake2Role::Bob, b"bob".to_vec(), b"alice".to_vec())
+                    .unwrap();
+
+            let alice_msg = alice.generate_msg(password.as_bytes()).unwrap();
+            let bob_msg = bob.generate_msg(password.as_bytes()).unwrap();
+            assert_eq!(
+                alice.process_msg(&bob_msg).unwrap(),
+                bob.process_msg(&alice_msg).unwrap()
+            );
+        }
+    }
+}
@@ -1,15 +1,25 @@
-extern crate boring_sys;
 use crate::hkdf::Hkdf;
 use crate::hmac::{generate_mac, verify_mac};
-use boring_sys::*;
-use std::ffi::CString;
-use std::os::raw::c_uchar;
-use std::sync::{Arc, Mutex};
-use std::{ptr, slice};
 use thiserror::Error;
 
+#[cfg(feature = "spake2_boringssl")]
+mod boringssl;
+// With the BoringSSL backend, this is only compiled to test the two against each other.
+#[cfg(any(not(feature = "spake2_boringssl"), test))]
+#[cfg_attr(feature = "spake2_boringssl", allow(dead_code))]
+mod dalek;
+
+#[cfg(feature = "spake2_boringssl")]
+use boringssl::Spake2Backend;
+#[cfg(not(feature = "spake2_boringssl"))]
+use dalek::Spake2Backend;
+
+/// SPAKE2 over the Ed25519 group, as implemented by BoringSSL.
+///
+/// The `spake2` feature selects a pure-Rust backend; `spake2_boringssl` selects BoringSSL's
+/// `SPAKE2_CTX` instead. Both backends produce the same messages and key material.
 pub struct Spake2Context {
-    ctx: Arc<Mutex<*mut SPAKE2_CTX>>,
+    backend: Spake2Backend,
     role: Spake2Role,
 }
 
@@ -27,25 +37,6 @@ pub enum Spake2Role {
     Bob,
 }
 
-impl From<spake2_role_t> for Spake2Role {
-    fn from(role: spake2_role_t) -> Self {
-        match role {
-            spake2_role_t::spake2_role_alice => Spake2Role::Alice,
-            spake2_role_t::spake2_role_bob => Spake2Role::Bob,
-            _ => panic!("Invalid role"),
-        }
-    }
-}
-
-impl From<Spake2Role> for spake2_role_t {
-    fn from(val: Spake2Role) -> Self {
-        match val {
-            Spake2Role::Alice => spake2_role_t::spake2_role_alice,
-            Spake2Role::Bob => spake2_role_t::spake2_role_bob,
-        }
-    }
-}
-
 const CONFIRMATION_KEYS_INFO: &str = "ConfirmationKeys";
 const CONFIRMATION_LABEL: &str = "SocRecKeyConfirmationV1";
 const ENCRYPTION_KEYS_INFO: &str = "EncryptionKeys";
@@ -114,137 +105,51 @@ fn derive_keys(key_material: &[u8], aad: Option<Vec<u8>>) -> Result<Spake2Keys,
     })
 }
 
-/// Wapper around the SPAKE2_CTX struct from BoringSSL.
-/// * Automatically handles BoringSSL initialization
+/// Wrapper around a SPAKE2 backend.
 /// * Thread safe
 /// * Provides a key confirmation API
 impl Spake2Context {
-    /// BoringSSL documentation for this function is repeated below:
-    ///
-    /// SPAKE2_CTX_new creates a new |SPAKE2_CTX| (which can only be used for a
-    /// single execution of the protocol). SPAKE2 requires the symmetry of the two
-    /// parties to be broken which is indicated via |my_role| – each party must pass
-    /// a different value for this argument.
+    /// Creates a new context, which can only be used for a single execution of the protocol.
+    /// SPAKE2 requires the symmetry of the two parties to be broken which is indicated via
+    /// `my_role` – each party must pass a different value for this argument.
     ///
-    /// The |my_name| and |their_name| arguments allow optional, opaque names to be
-    /// bound into the protocol. For example MAC addresses, hostnames, usernames
-    /// etc. These values are not exposed and can avoid context-confusion attacks
-    /// when a password is shared between several devices.
+    /// The `my_name` and `their_name` arguments allow optional, opaque names to be bound into
+    /// the protocol. For example MAC addresses, hostnames, usernames etc. These values are not
+    /// exposed and can avoid context-confusion attacks when a password is shared between
+    /// several devices. Names can't contain NUL bytes.
     pub fn new(
         my_role: Spake2Role,
         my_name: String,
         their_name: String,
     ) -> Result<Self, Spake2Error> {
-        unsafe {
-            // CRYPTO_library_init initializes the crypto library.
-            // It must be called if the library is built with BORINGSSL_NO_STATIC_INITIALIZER.
-            // Otherwise, it does nothing and a static initializer is used instead.
-            // It is safe to call this function multiple times and concurrently from multiple threads.
-            // On some ARM configurations, this function may require filesystem access and should be called before entering a sandbox.
-            boring_sys::CRYPTO_library_init();
+        if my_name.contains('\0') || their_name.contains('\0') {
+            return Err(Spake2Error::InvalidName);
         }
 
-        let my_name_cstr = CString::new(my_name).map_err(|_| Spake2Error::InvalidName)?;
-        let their_name_cstr = CString::new(their_name).map_err(|_| Spake2Error::InvalidName)?;
-
-        let ctx = unsafe {
-            SPAKE2_CTX_new(
-                my_role.into(),
-                my_name_cstr.as_ptr() as *const c_uchar,
-                my_name_cstr.to_bytes().len(),
-                their_name_cstr.as_ptr() as *const c_uchar,
-                their_name_cstr.to_bytes().len(),
-            )
-        };
-
-        if ctx.is_null() {
-            Err(Spake2Error::ContextCreationError)
-        } else {
-            Ok(Spake2Context {
-                ctx: Arc::new(Mutex::new(ctx)),
-                role: my_role,
-            })
-        }
+        Ok(Spake2Context {
+            backend: Spake2Backend::new(my_role, my_name.into_bytes(), their_name.into_bytes())?,
+            role: my_role,
+        })
     }
 
-    /// SPAKE2_generate_msg generates a SPAKE2 message for the given password.
-    /// This function can only be called once for a given SPAKE2_CTX, and will error if so.
+    /// Generates a SPAKE2 message for the given password.
+    /// This function can only be called once for a given context, and will error if so.
     pub fn generate_msg(&self, password: Vec<u8>) -> Result<Vec<u8>, Spake2Error> {
-        let ctx_guard = self.ctx.lock().unwrap();
-        let ctx = *ctx_guard;
-
-        let mut out = vec![0u8; boring_sys::SPAKE2_MAX_MSG_SIZE as usize];
-        let mut out_len = 0;
-
-        let result = unsafe {
-            SPAKE2_generate_msg(
-                ctx,
-                out.as_mut_ptr(),
-                &mut out_len,
-                boring_sys::SPAKE2_MAX_MSG_SIZE as usize,
-                password.as_ptr(),
-                password.len(),
-            )
-        };
-
-        if result == 0 {
-            Err(Spake2Error::GenerateMessageError)
-        } else {
-            out.truncate(out_len);
-            Ok(out)
-        }
+        self.backend.generate_msg(&password)
     }
 
-    /// BoringSSL's documentation for this function is repeated below:
-    ///
-    /// SPAKE2_process_msg completes the SPAKE2 exchange given the peer's message in
-    /// |their_msg|, writes at most |max_out_key_len| bytes to |out_key| and sets
-    /// |*out_key_len| to the number of bytes written.
-    ///
-    /// The resulting keying material is suitable for:
-    ///    - Using directly in a key-confirmation step: i.e. each side could
-    ///      transmit a hash of their role, a channel-binding value and the key
-    ///      material to prove to the other side that they know the shared key.
-    ///   -  Using as input keying material to HKDF to generate a variety of subkeys
-    ///      for encryption etc.
-    ///
-    /// If |max_out_key_key| is smaller than the amount of key material generated
-    /// then the key is silently truncated. If you want to ensure that no truncation
-    /// occurs then |max_out_key| should be at least |SPAKE2_MAX_KEY_SIZE|.
+    /// Completes the SPAKE2 exchange given the peer's message, and derives encryption and
+    /// confirmation keys from the resulting key material.
     ///
-    /// You must call |SPAKE2_generate_msg| on a given |SPAKE2_CTX| before calling
-    /// this function. On successful return, |ctx| is complete and calling
-    /// |SPAKE2_CTX_free| is the only acceptable operation on it.
-    ///
-    /// Returns one on success or zero on error.
+    /// You must call `generate_msg` on a given context before calling this function, and it can
+    /// only be called once.
     pub fn process_msg(
         &self,
         their_msg: Vec<u8>,
         aad: Option<Vec<u8>>,
     ) -> Result<Spake2Keys, Spake2Error> {
-        let ctx_guard = self.ctx.lock().unwrap();
-        let ctx = *ctx_guard;
-
-        let mut out_key_material = vec![0u8; boring_sys::SPAKE2_MAX_KEY_SIZE as usize];
-        let mut out_key_material_len = 0;
-
-        let result = unsafe {
-            SPAKE2_process_msg(
-                ctx,
-                out_key_material.as_mut_ptr(),
-                &mut out_key_material_len,
-                boring_sys::SPAKE2_MAX_KEY_SIZE as usize,
-                their_msg.as_ptr(),
-                their_msg.len(),
-            )
-        };
-
-        if result == 0 {
-            Err(Spake2Error::ProcessMessageError)
-        } else {
-            out_key_material.truncate(out_key_material_len);
-            derive_keys(out_key_material.as_slice(), aad)
-        }
+        let key_material = self.backend.process_msg(&their_msg)?;
+        derive_keys(key_material.as_slice(), aad)
     }
 
     /// MAC a fixed message with our confirmation key derived from the SPAKE2 key material.
@@ -272,47 +177,19 @@ impl Spake2Context {
             .map_err(|_| Spake2Error::MacError)
     }
 
+    /// Reads our ephemeral private key so that it can be persisted, to support async
+    /// communication. The key is in BoringSSL's format, so it can be moved between backends.
     pub fn read_private_key(&self) -> Vec<u8> {
-        // This is not good. We are bypassing BoringSSL's struct hiding here so that we can read the private key
-        // to persist it, to support async communication.
-        // This **BADLY** breaks if BoringSSL changes the struct layout.
-        //
-        // struct spake2_ctx_st {
-        //     uint8_t private_key[32];
-        //     uint8_t my_msg[32];
-        //     uint8_t password_scalar[32];
-        //     uint8_t password_hash[64];
-        //     uint8_t *my_name;
-        //     size_t my_name_len;
-        //     uint8_t *their_name;
-        //     size_t their_name_len;
-        //     enum spake2_role_t my_role;
-        //     enum spake2_state_t state;
-        //     char disable_password_scalar_hack;
-        //   };
-        let ctx_guard = self.ctx.lock().unwrap();
-        let ctx = *ctx_guard;
-
-        unsafe {
-            let private_key_ptr = ctx as *mut u8;
-            let private_key_slice = slice::from_raw_parts(private_key_ptr, 32);
-            private_key_slice.to_vec()
-        }
+        self.backend.read_private_key()
     }
 
+    /// Reads the message generated by `generate_msg`, which is our masked public key.
     pub fn read_public_key(&self) -> Vec<u8> {
-        // See note in `read_private_key`.
-
-        let ctx_guard = self.ctx.lock().unwrap();
-        let ctx = *ctx_guard as *mut u8;
-
-        unsafe {
-            let my_msg_offset = ctx.add(32); // Offset by the size of private_key
-            let public_key_slice = slice::from_raw_parts(my_msg_offset, 32);
-            public_key_slice.to_vec()
-        }
+        self.backend.read_public_key()
     }
 
+    /// Restores a key pair read from another context. `generate_msg` must have been called with
+    /// the same password first.
     pub fn write_key_pair(
         &self,
         private_key: Vec<u8>,
@@ -322,36 +199,11 @@ impl Spake2Context {
             return Err(Spake2Error::LengthError);
         }
 
-        // See note in `read_private_key`. EVIL! BAD! NO! Anyway.
-
-        let ctx_guard = self.ctx.lock().unwrap();
-        let ctx = *ctx_guard as *mut u8;
-
-        unsafe {
-            // Copy private_key to the start of the context
-            ptr::copy_nonoverlapping(private_key.as_ptr(), ctx, 32);
-            // Offset by the size of private_key
-            let my_msg_offset = ctx.add(32);
-            ptr::copy_nonoverlapping(public_key.as_ptr(), my_msg_offset, 32);
-        }
+        self.backend.write_key_pair(&private_key, &public_key);
         Ok(())
     }
 }
 
-impl Drop for Spake2Context {
-    fn drop(&mut self) {
-        let ctx_guard = self.ctx.lock().unwrap();
-        if !(*ctx_guard).is_null() {
-            unsafe {
-                SPAKE2_CTX_free(*ctx_guard);
-            }
-        }
-    }
-}
-
-unsafe impl Send for Spake2Context {}
-unsafe impl Sync for Spake2Context {}
-
 #[derive(Debug, Error)]
 pub enum Spake2Error {
     #[error("Failed to create SPAKE2_CTX")]
@@ -1,6 +1,34 @@
+//! Self-sovereign backups: a payload encrypted such that each recipient, holding a pair of local
+//! wrapping keys (LKA and LKN), can decrypt it on their own.
+//!
+//! There are two formats:
+//!
+//! * v1, which has a single recipient and no header. The payload key is derived from the ECDH
+//!   shared secrets of an ephemeral key with the recipient's keys. Its serialization starts with
+//!   the ephemeral public key, whose SEC1 tag is always `0x04`.
+//! * v2, which starts with a version byte and a header holding its creation time, purpose and
+//!   recipient key ids. The payload is encrypted under a random data key, which is wrapped for
+//!   each recipient as in v1. The header is bound to both the payload and the wrapped keys as
+//!   associated data.
+
 use p256::elliptic_curve;
+use sha2::{Digest, Sha256};
 use thiserror::Error;
 
+/// The version byte of the v2 format.
+pub const SSB_V2: u8 = 2;
+
+/// The SEC1 tag of an uncompressed public key, which v1 backups start with.
+const SEC1_UNCOMPRESSED_TAG: u8 = 0x04;
+
+const PUBLIC_KEY_LEN: usize = 65;
+const NONCE_LEN: usize = 24;
+const KEY_ID_LEN: usize = 32;
+/// A 32 byte data key, followed by a 16 byte Poly1305 tag.
+const WRAPPED_KEY_LEN: usize = 48;
+
+const V2_KEY_WRAPPING_INFO: &[u8] = b"SSB/v2/key-wrapping";
+
 #[derive(Debug, Error)]
 pub enum SsbError {
     #[error(transparent)]
@@ -13,17 +41,74 @@ pub enum SsbError {
     ChaCha20Poly1305Error(chacha20poly1305::Error),
     #[error(transparent)]
     TryFromSliceError(#[from] std::array::TryFromSliceError),
+    #[error("Unsupported backup version {0}")]
+    UnsupportedVersion(u8),
+    #[error("Malformed backup")]
+    MalformedBackup,
+    #[error("Backups must have between 1 and 255 distinct recipients")]
+    InvalidRecipients,
+    #[error("Backup purpose is too long")]
+    PurposeTooLong,
+    #[error("Backup isn't encrypted to the given keys")]
+    RecipientNotFound,
+}
+
+/// Identifies a recipient by their local wrapping public keys.
+pub type KeyId = [u8; KEY_ID_LEN];
+
+/// The key id of the recipient with the given local wrapping public keys, as the SHA-256 digest of
+/// their concatenated SEC1 encodings.
+pub fn key_id(lka_pub: &[u8; PUBLIC_KEY_LEN], lkn_pub: &[u8; PUBLIC_KEY_LEN]) -> KeyId {
+    Sha256::new()
+        .chain_update(lka_pub)
+        .chain_update(lkn_pub)
+        .finalize()
+        .into()
+}
+
+/// Derives a symmetric key from the shared secrets with a recipient's LKA and LKN.
+fn derive_key(s_1: &[u8], s_2: &[u8], info: &[u8]) -> Result<[u8; 32], SsbError> {
+    let mut k = [0u8; 32];
+    hkdf::Hkdf::<Sha256>::new(None, [s_1, s_2].concat().as_slice())
+        .expand(info, &mut k)
+        .map_err(SsbError::HkdfInvalidLength)?;
+    Ok(k)
+}
+
+/// Reads a backup's fields in order, failing on truncated input.
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], SsbError> {
+        if self.bytes.len() < len {
+            return Err(SsbError::MalformedBackup);
+        }
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    fn take_array<const N: usize>(&mut self) -> Result<[u8; N], SsbError> {
+        Ok(self.take(N)?.try_into()?)
+    }
 }
 
 pub mod testapp {
-    use chacha20poly1305::{aead::Aead, XChaCha20Poly1305};
+    use chacha20poly1305::{
+        aead::{Aead, Payload},
+        XChaCha20Poly1305,
+    };
     use crypto_common::KeyInit;
-    use hkdf::Hkdf;
     use p256::{ecdh::diffie_hellman, PublicKey, SecretKey};
     use rand::rngs::OsRng;
-    use sha2::Sha256;
 
-    use super::{server::SelfSovereignBackup, SsbError};
+    use super::{
+        derive_key, key_id,
+        server::{SelfSovereignBackup, SelfSovereignBackupV1, SelfSovereignBackupV2},
+        SsbError, V2_KEY_WRAPPING_INFO,
+    };
 
     pub fn generate_lka_lkn() -> (SecretKey, SecretKey) {
         let lka = SecretKey::random(&mut OsRng);
@@ -31,103 +116,444 @@ pub mod testapp {
         (lka, lkn)
     }
 
+    /// Decrypts a backup of any version with a recipient's local wrapping keys.
     pub fn decrypt_ssb(
         lka: SecretKey,
         lkn: SecretKey,
         backup: SelfSovereignBackup,
     ) -> Result<Vec<u8>, super::SsbError> {
-        let s_1 = diffie_hellman(
-            lka.to_nonzero_scalar(),
-            PublicKey::from_sec1_bytes(&backup.eph_pub)?.as_affine(),
-        );
-        let s_2 = diffie_hellman(
-            lkn.to_nonzero_scalar(),
-            PublicKey::from_sec1_bytes(&backup.eph_pub)?.as_affine(),
-        );
+        match backup {
+            SelfSovereignBackup::V1(backup) => decrypt_v1(&lka, &lkn, backup),
+            SelfSovereignBackup::V2(backup) => decrypt_v2(&lka, &lkn, backup),
+        }
+    }
 
-        let mut k = [0u8; 32];
-        Hkdf::<Sha256>::new(
-            None,
-            [
-                s_1.raw_secret_bytes().as_slice(),
-                s_2.raw_secret_bytes().as_slice(),
-            ]
-            .concat()
-            .as_slice(),
-        )
-        .expand(&[], &mut k)
-        .map_err(|e| SsbError::HkdfInvalidLength(e))?;
+    fn shared_key(
+        lka: &SecretKey,
+        lkn: &SecretKey,
+        eph_pub: &[u8],
+        info: &[u8],
+    ) -> Result<XChaCha20Poly1305, SsbError> {
+        let eph_pub = PublicKey::from_sec1_bytes(eph_pub)?;
+        let s_1 = diffie_hellman(lka.to_nonzero_scalar(), eph_pub.as_affine());
+        let s_2 = diffie_hellman(lkn.to_nonzero_scalar(), eph_pub.as_affine());
+
+        let k = derive_key(
+            s_1.raw_secret_bytes().as_slice(),
+            s_2.raw_secret_bytes().as_slice(),
+            info,
+        )?;
+        Ok(XChaCha20Poly1305::new_from_slice(&k)?)
+    }
 
-        let cipher = XChaCha20Poly1305::new_from_slice(&k)?;
+    fn decrypt_v1(
+        lka: &SecretKey,
+        lkn: &SecretKey,
+        backup: SelfSovereignBackupV1,
+    ) -> Result<Vec<u8>, SsbError> {
+        let cipher = shared_key(lka, lkn, &backup.eph_pub, &[])?;
 
         let plaintext = cipher
             .decrypt(&backup.nonce.into(), backup.ciphertext.as_slice())
-            .map_err(|e| SsbError::ChaCha20Poly1305Error(e))?;
+            .map_err(SsbError::ChaCha20Poly1305Error)?;
+
+        Ok(plaintext)
+    }
+
+    fn decrypt_v2(
+        lka: &SecretKey,
+        lkn: &SecretKey,
+        backup: SelfSovereignBackupV2,
+    ) -> Result<Vec<u8>, SsbError> {
+        let recipient_key_id = key_id(
+            &(*lka.public_key().to_sec1_bytes()).try_into()?,
+            &(*lkn.public_key().to_sec1_bytes()).try_into()?,
+        );
+        let wrapped_key = backup
+            .header
+            .key_ids
+            .iter()
+            .position(|key_id| *key_id == recipient_key_id)
+            .and_then(|idx| backup.wrapped_keys.get(idx))
+            .ok_or(SsbError::RecipientNotFound)?;
+
+        let header = backup.header.to_bytes()?;
+        let key_wrapping_cipher = shared_key(lka, lkn, &wrapped_key.eph_pub, V2_KEY_WRAPPING_INFO)?;
+        let data_key = key_wrapping_cipher
+            .decrypt(
+                &wrapped_key.nonce.into(),
+                Payload {
+                    msg: &wrapped_key.wrapped_key,
+                    aad: &header,
+                },
+            )
+            .map_err(SsbError::ChaCha20Poly1305Error)?;
+
+        let cipher = XChaCha20Poly1305::new_from_slice(&data_key)?;
+        let plaintext = cipher
+            .decrypt(
+                &backup.nonce.into(),
+                Payload {
+                    msg: &backup.ciphertext,
+                    aad: &header,
+                },
+            )
+            .map_err(SsbError::ChaCha20Poly1305Error)?;
 
         Ok(plaintext)
     }
 }
 
 pub mod server {
-    use chacha20poly1305::{aead::Aead, AeadCore, XChaCha20Poly1305};
+    use std::collections::BTreeSet;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    use chacha20poly1305::{
+        aead::{Aead, Payload},
+        AeadCore, XChaCha20Poly1305,
+    };
     use crypto_common::KeyInit;
-    use hkdf::Hkdf;
     use p256::{ecdh::EphemeralSecret, PublicKey};
     use rand::rngs::OsRng;
-    use sha2::Sha256;
 
-    use super::SsbError;
+    use super::{
+        derive_key, key_id, KeyId, Reader, SsbError, KEY_ID_LEN, NONCE_LEN, PUBLIC_KEY_LEN,
+        SEC1_UNCOMPRESSED_TAG, SSB_V2, V2_KEY_WRAPPING_INFO, WRAPPED_KEY_LEN,
+    };
 
-    pub struct SelfSovereignBackup {
+    /// A backup in any supported format.
+    #[derive(Debug, Clone, PartialEq)]
+    pub enum SelfSovereignBackup {
+        V1(SelfSovereignBackupV1),
+        V2(SelfSovereignBackupV2),
+    }
+
+    /// A single recipient backup, without a header.
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct SelfSovereignBackupV1 {
         pub eph_pub: [u8; 65],
         pub nonce: [u8; 24],
         pub ciphertext: Vec<u8>,
     }
 
+    /// A multi-recipient backup with an authenticated header.
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct SelfSovereignBackupV2 {
+        pub header: SsbHeader,
+        /// The data key wrapped for each recipient, in the order of `header.key_ids`.
+        pub wrapped_keys: Vec<WrappedKey>,
+        pub nonce: [u8; 24],
+        pub ciphertext: Vec<u8>,
+    }
+
+    /// The metadata of a v2 backup. It's only authenticated once the backup has been decrypted.
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct SsbHeader {
+        pub version: u8,
+        /// Seconds since the Unix epoch.
+        pub created_at: u64,
+        pub purpose: String,
+        pub key_ids: Vec<KeyId>,
+    }
+
+    /// A backup's data key, encrypted for a single recipient.
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct WrappedKey {
+        pub eph_pub: [u8; PUBLIC_KEY_LEN],
+        pub nonce: [u8; NONCE_LEN],
+        pub wrapped_key: [u8; WRAPPED_KEY_LEN],
+    }
+
+    /// A recipient's local wrapping public keys.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct SsbRecipient {
+        pub lka_pub: [u8; 65],
+        pub lkn_pub: [u8; 65],
+    }
+
+    impl SsbRecipient {
+        pub fn key_id(&self) -> KeyId {
+            key_id(&self.lka_pub, &self.lkn_pub)
+        }
+    }
+
+    impl From<SelfSovereignBackupV1> for SelfSovereignBackup {
+        fn from(backup: SelfSovereignBackupV1) -> Self {
+            SelfSovereignBackup::V1(backup)
+        }
+    }
+
+    impl From<SelfSovereignBackupV2> for SelfSovereignBackup {
+        fn from(backup: SelfSovereignBackupV2) -> Self {
+            SelfSovereignBackup::V2(backup)
+        }
+    }
+
+    impl SelfSovereignBackup {
+        /// The backup's version, where v1 backups don't carry one explicitly.
+        pub fn version(&self) -> u8 {
+            match self {
+                SelfSovereignBackup::V1(_) => 1,
+                SelfSovereignBackup::V2(backup) => backup.header.version,
+            }
+        }
+
+        pub fn header(&self) -> Option<&SsbHeader> {
+            match self {
+                SelfSovereignBackup::V1(_) => None,
+                SelfSovereignBackup::V2(backup) => Some(&backup.header),
+            }
+        }
+
+        pub fn to_bytes(&self) -> Result<Vec<u8>, SsbError> {
+            match self {
+                SelfSovereignBackup::V1(backup) => {
+                    Ok([&backup.eph_pub[..], &backup.nonce, &backup.ciphertext].concat())
+                }
+                SelfSovereignBackup::V2(backup) => {
+                    let mut bytes = backup.header.to_bytes()?;
+                    for wrapped_key in &backup.wrapped_keys {
+                        bytes.extend_from_slice(&wrapped_key.eph_pub);
+                        bytes.extend_from_slice(&wrapped_key.nonce);
+                        bytes.extend_from_slice(&wrapped_key.wrapped_key);
+                    }
+                    bytes.extend_from_slice(&backup.nonce);
+                    bytes.extend_from_slice(&backup.ciphertext);
+                    Ok(bytes)
+                }
+            }
+        }
+
+        /// Parses a backup of any supported version.
+        pub fn from_bytes(bytes: &[u8]) -> Result<Self, SsbError> {
+            let mut reader = Reader { bytes };
+            match bytes.first() {
+                Some(&SEC1_UNCOMPRESSED_TAG) => Ok(SelfSovereignBackupV1 {
+                    eph_pub: reader.take_array()?,
+                    nonce: reader.take_array()?,
+                    ciphertext: reader.bytes.to_vec(),
+                }
+                .into()),
+                Some(&SSB_V2) => {
+                    let header = SsbHeader::read(&mut reader)?;
+                    let wrapped_keys = header
+                        .key_ids
+                        .iter()
+                        .map(|_| {
+                            Ok(WrappedKey {
+                                eph_pub: reader.take_array()?,
+                                nonce: reader.take_array()?,
+                                wrapped_key: reader.take_array()?,
+                            })
+                        })
+                        .collect::<Result<Vec<_>, SsbError>>()?;
+                    Ok(SelfSovereignBackupV2 {
+                        header,
+                        wrapped_keys,
+                        nonce: reader.take_array()?,
+                        ciphertext: reader.bytes.to_vec(),
+                    }
+                    .into())
+                }
+                Some(version) => Err(SsbError::UnsupportedVersion(*version)),
+                None => Err(SsbError::MalformedBackup),
+            }
+        }
+    }
+
+    impl SsbHeader {
+        /// The header's canonical encoding, which is also the associated data of a v2 backup:
+        /// the version, the big-endian creation time, the length-prefixed purpose and the
+        /// count-prefixed key ids.
+        pub fn to_bytes(&self) -> Result<Vec<u8>, SsbError> {
+            let purpose_len =
+                u16::try_from(self.purpose.len()).map_err(|_| SsbError::PurposeTooLong)?;
+            let recipient_count =
+                u8::try_from(self.key_ids.len()).map_err(|_| SsbError::InvalidRecipients)?;
+
+            let mut bytes = vec![self.version];
+            bytes.extend_from_slice(&self.created_at.to_be_bytes());
+            bytes.extend_from_slice(&purpose_len.to_be_bytes());
+            bytes.extend_from_slice(self.purpose.as_bytes());
+            bytes.push(recipient_count);
+            for key_id in &self.key_ids {
+                bytes.extend_from_slice(key_id);
+            }
+            Ok(bytes)
+        }
+
+        fn read(reader: &mut Reader) -> Result<Self, SsbError> {
+            let [version] = reader.take_array()?;
+            let created_at = u64::from_be_bytes(reader.take_array()?);
+            let purpose_len = u16::from_be_bytes(reader.take_array()?);
+            let purpose = String::from_utf8(reader.take(purpose_len as usize)?.to_vec())
+                .map_err(|_| SsbError::MalformedBackup)?;
+            let [recipient_count] = reader.take_array()?;
+            let key_ids = (0..recipient_count)
+                .map(|_| reader.take_array::<KEY_ID_LEN>())
+                .collect::<Result<Vec<_>, SsbError>>()?;
+
+            Ok(SsbHeader {
+                version,
+                created_at,
+                purpose,
+                key_ids,
+            })
+        }
+    }
+
+    /// Creates a backup for a single recipient, in the latest format.
     pub fn create_ssb(
         lka_pub: [u8; 65],
         lkn_pub: [u8; 65],
         message: Vec<u8>,
     ) -> Result<SelfSovereignBackup, SsbError> {
-        let eph_secret = EphemeralSecret::random(&mut OsRng);
+        let created_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default();
+        Ok(create_multi_recipient_ssb(
+            created_at,
+            String::new(),
+            &[SsbRecipient { lka_pub, lkn_pub }],
+            message,
+        )?
+        .into())
+    }
 
-        let s_1 = eph_secret.diffie_hellman(&PublicKey::from_sec1_bytes(&lka_pub)?);
-        let s_2 = eph_secret.diffie_hellman(&PublicKey::from_sec1_bytes(&lkn_pub)?);
-
-        let mut k = [0u8; 32];
-        Hkdf::<Sha256>::new(
-            None,
-            [
-                s_1.raw_secret_bytes().as_slice(),
-                s_2.raw_secret_bytes().as_slice(),
-            ]
-            .concat()
-            .as_slice(),
-        )
-        .expand(&[], &mut k)
-        .map_err(|e| SsbError::HkdfInvalidLength(e))?;
+    /// Creates a v2 backup which each of `recipients` can decrypt on their own.
+    pub fn create_multi_recipient_ssb(
+        created_at: u64,
+        purpose: String,
+        recipients: &[SsbRecipient],
+        message: Vec<u8>,
+    ) -> Result<SelfSovereignBackupV2, SsbError> {
+        let key_ids = recipients
+            .iter()
+            .map(SsbRecipient::key_id)
+            .collect::<Vec<KeyId>>();
+        if key_ids.is_empty() || key_ids.iter().collect::<BTreeSet<_>>().len() != key_ids.len() {
+            return Err(SsbError::InvalidRecipients);
+        }
+
+        let header = SsbHeader {
+            version: SSB_V2,
+            created_at,
+            purpose,
+            key_ids,
+        };
+        let aad = header.to_bytes()?;
+
+        let data_key = XChaCha20Poly1305::generate_key(&mut OsRng);
+        let wrapped_keys = recipients
+            .iter()
+            .map(|recipient| wrap_key(recipient, &data_key, &aad))
+            .collect::<Result<Vec<_>, SsbError>>()?;
 
-        let cipher = XChaCha20Poly1305::new_from_slice(&k)?;
+        let cipher = XChaCha20Poly1305::new(&data_key);
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: &message,
+                    aad: &aad,
+                },
+            )
+            .map_err(SsbError::ChaCha20Poly1305Error)?;
+
+        Ok(SelfSovereignBackupV2 {
+            header,
+            wrapped_keys,
+            nonce: nonce.as_slice().try_into()?,
+            ciphertext,
+        })
+    }
+
+    /// Creates a v1 backup, for clients which can't decrypt later versions yet.
+    pub fn create_v1_ssb(
+        lka_pub: [u8; 65],
+        lkn_pub: [u8; 65],
+        message: Vec<u8>,
+    ) -> Result<SelfSovereignBackupV1, SsbError> {
+        let (eph_pub, cipher) = ephemeral_key(&SsbRecipient { lka_pub, lkn_pub }, &[])?;
         let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
         let ciphertext = cipher
             .encrypt(&nonce, message.as_slice())
-            .map_err(|e| SsbError::ChaCha20Poly1305Error(e))?;
+            .map_err(SsbError::ChaCha20Poly1305Error)?;
 
-        Ok(SelfSovereignBackup {
-            eph_pub: (*eph_secret.public_key().to_sec1_bytes()).try_into()?,
+        Ok(SelfSovereignBackupV1 {
+            eph_pub,
             nonce: nonce.as_slice().try_into()?,
             ciphertext,
         })
     }
+
+    /// Generates an ephemeral key, returning its public key and a cipher keyed with its shared
+    /// secrets with the recipient.
+    fn ephemeral_key(
+        recipient: &SsbRecipient,
+        info: &[u8],
+    ) -> Result<([u8; PUBLIC_KEY_LEN], XChaCha20Poly1305), SsbError> {
+        let eph_secret = EphemeralSecret::random(&mut OsRng);
+
+        let s_1 = eph_secret.diffie_hellman(&PublicKey::from_sec1_bytes(&recipient.lka_pub)?);
+        let s_2 = eph_secret.diffie_hellman(&PublicKey::from_sec1_bytes(&recipient.lkn_pub)?);
+
+        let k = derive_key(
+            s_1.raw_secret_bytes().as_slice(),
+            s_2.raw_secret_bytes().as_slice(),
+            info,
+        )?;
+
+        Ok((
+            (*eph_secret.public_key().to_sec1_bytes()).try_into()?,
+            XChaCha20Poly1305::new_from_slice(&k)?,
+        ))
+    }
+
+    fn wrap_key(
+        recipient: &SsbRecipient,
+        data_key: &[u8],
+        aad: &[u8],
+    ) -> Result<WrappedKey, SsbError> {
+        let (eph_pub, cipher) = ephemeral_key(recipient, V2_KEY_WRAPPING_INFO)?;
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let wrapped_key = cipher
+            .encrypt(&nonce, Payload { msg: data_key, aad })
+            .map_err(SsbError::ChaCha20Poly1305Error)?;
+
+        Ok(WrappedKey {
+            eph_pub,
+            nonce: nonce.as_slice().try_into()?,
+            wrapped_key: wrapped_key
+                .as_slice()
+                .try_into()
+                .map_err(|_| SsbError::MalformedBackup)?,
+        })
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use p256::SecretKey;
+
     use super::{
-        server::create_ssb,
+        server::{
+            create_multi_recipient_ssb, create_ssb, create_v1_ssb, SelfSovereignBackup,
+            SsbRecipient,
+        },
         testapp::{decrypt_ssb, generate_lka_lkn},
+        SsbError, SSB_V2,
     };
 
+    fn recipient(lka: &SecretKey, lkn: &SecretKey) -> SsbRecipient {
+        SsbRecipient {
+            lka_pub: (*lka.public_key().to_sec1_bytes()).try_into().unwrap(),
+            lkn_pub: (*lkn.public_key().to_sec1_bytes()).try_into().unwrap(),
+        }
+    }
+
     #[test]
     fn test_ssb_creation() {
         let expected_message = b"Hello world!";
@@ -143,4 +569,130 @@ mod tests {
 
         assert_eq!(expected_message, actual_message.as_slice());
     }
+
+    #[test]
+    fn test_v1_backups_still_decrypt() {
+        let expected_message = b"Hello world!";
+
+        let (lka, lkn) = generate_lka_lkn();
+        let keys = recipient(&lka, &lkn);
+        let backup: SelfSovereignBackup =
+            create_v1_ssb(keys.lka_pub, keys.lkn_pub, expected_message.to_vec())
+                .unwrap()
+                .into();
+        assert_eq!(backup.version(), 1);
+        assert_eq!(backup.header(), None);
+
+        let parsed = SelfSovereignBackup::from_bytes(&backup.to_bytes().unwrap()).unwrap();
+        assert_eq!(parsed, backup);
+
+        let actual_message = decrypt_ssb(lka, lkn, parsed).unwrap();
+        assert_eq!(expected_message, actual_message.as_slice());
+    }
+
+    #[test]
+    fn test_multi_recipient_ssb() {
+        let expected_message = b"Hello world!";
+
+        let recipient_keys = (0..3).map(|_| generate_lka_lkn()).collect::<Vec<_>>();
+        let recipients = recipient_keys
+            .iter()
+            .map(|(lka, lkn)| recipient(lka, lkn))
+            .collect::<Vec<_>>();
+        let backup: SelfSovereignBackup = create_multi_recipient_ssb(
+            1_700_000_000,
+            "inheritance".to_string(),
+            &recipients,
+            expected_message.to_vec(),
+        )
+        .unwrap()
+        .into();
+
+        let header = backup.header().unwrap();
+        assert_eq!(header.version, SSB_V2);
+        assert_eq!(header.created_at, 1_700_000_000);
+        assert_eq!(header.purpose, "inheritance");
+        assert_eq!(
+            header.key_ids,
+            recipients
+                .iter()
+                .map(SsbRecipient::key_id)
+                .collect::<Vec<_>>()
+        );
+
+        let bytes = backup.to_bytes().unwrap();
+        assert_eq!(bytes[0], SSB_V2);
+        let parsed = SelfSovereignBackup::from_bytes(&bytes).unwrap();
+        assert_eq!(parsed, backup);
+
+        // Each recipient decrypts on their own.
+        for (lka, lkn) in recipient_keys {
+            let actual_message = decrypt_ssb(lka, lkn, parsed.clone()).unwrap();
+            assert_eq!(expected_message, actual_message.as_slice());
+        }
+
+        // Keys that aren't a recipient can't.
+        let (lka, lkn) = generate_lka_lkn();
+        assert!(matches!(
+            decrypt_ssb(lka, lkn, parsed),
+            Err(SsbError::RecipientNotFound)
+        ));
+    }
+
+    #[test]
+    fn test_ssb_header_is_authenticated() {
+        let (lka, lkn) = generate_lka_lkn();
+        let backup: SelfSovereignBackup = create_multi_recipient_ssb(
+            1_700_000_000,
+            "inheritance".to_string(),
+            &[recipient(&lka, &lkn)],
+            b"Hello world!".to_vec(),
+        )
+        .unwrap()
+        .into();
+
+        let SelfSovereignBackup::V2(mut tampered) = backup else {
+            panic!("Expected a v2 backup");
+        };
+        tampered.header.created_at += 1;
+        assert!(matches!(
+            decrypt_ssb(lka, lkn, tampered.into()),
+            Err(SsbError::ChaCha20Poly1305Error(_))
+        ));
+    }
+
+    #[test]
+    fn test_invalid_ssbs() {
+        let (lka, lkn) = generate_lka_lkn();
+        let keys = recipient(&lka, &lkn);
+
+        assert!(matches!(
+            create_multi_recipient_ssb(0, String::new(), &[], vec![]),
+            Err(SsbError::InvalidRecipients)
+        ));
+        assert!(matches!(
+            create_multi_recipient_ssb(0, String::new(), &[keys, keys], vec![]),
+            Err(SsbError::InvalidRecipients)
+        ));
+
+        assert!(matches!(
+            SelfSovereignBackup::from_bytes(&[3, 0, 0]),
+            Err(SsbError::UnsupportedVersion(3))
+        ));
+        assert!(matches!(
+            SelfSovereignBackup::from_bytes(&[]),
+            Err(SsbError::MalformedBackup)
+        ));
+
+        let backup: SelfSovereignBackup =
+            create_multi_recipient_ssb(0, String::new(), &[keys], vec![])
+                .unwrap()
+                .into();
+        let bytes = backup.to_bytes().unwrap();
+        // Truncated within the wrapped keys.
+        assert!(matches!(
+            SelfSovereignBackup::from_bytes(&bytes[..60]),
+            Err(SsbError::MalformedBackup)
+        ));
+    }
 }
@@ -0,0 +1,488 @@
+//! Chunked XChaCha20-Poly1305 encryption for payloads too large to hold in memory, following the
+//! STREAM construction from "Online Authenticated-Encryption and its Nonce-Reuse
+//! Misuse-Resistance" (Hoang, Reyhanitabar, Rogaway and Vizár).
+//!
+//! A stream starts with a random [`HEADER_SIZE`] byte nonce prefix, followed by segments of
+//! [`SEGMENT_SIZE`] plaintext bytes, each sealed separately. Each segment's nonce is the prefix,
+//! the big-endian segment counter and a flag marking the final segment, so decryption fails if
+//! segments are reordered, dropped or the stream is truncated, including at a segment boundary.
+//! The final segment holds the remaining `0..=SEGMENT_SIZE` bytes.
+
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    XChaCha20Poly1305 as RustCryptoXChaCha20Poly1305, XNonce,
+};
+use crypto_common::InvalidLength;
+use rand::RngCore;
+use std::io::{self, Read, Write};
+use std::sync::Mutex;
+use thiserror::Error;
+
+/// The size of the nonce prefix that starts every stream.
+pub const HEADER_SIZE: usize = 19;
+/// The plaintext size of every segment but the last.
+pub const SEGMENT_SIZE: usize = 64 * 1024;
+/// The ciphertext size of every segment but the last.
+pub const ENCRYPTED_SEGMENT_SIZE: usize = SEGMENT_SIZE + TAG_SIZE;
+const TAG_SIZE: usize = 16;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum StreamingAeadError {
+    #[error("Failed to create a new XChaCha20Poly1305 instance: {0}")]
+    XChaCha20InstantiationError(#[from] InvalidLength),
+    #[error("Invalid stream header")]
+    InvalidHeader,
+    #[error("Invalid segment size")]
+    InvalidSegmentSize,
+    #[error("Failed to encrypt")]
+    EncryptError,
+    #[error("Failed to decrypt")]
+    DecryptError,
+    #[error("Stream already finalized")]
+    StreamFinalized,
+    #[error("Too many segments")]
+    SegmentLimitExceeded,
+}
+
+impl From<StreamingAeadError> for io::Error {
+    fn from(error: StreamingAeadError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, error)
+    }
+}
+
+/// The position of a stream, shared by encryption and decryption.
+struct StreamState {
+    cipher: RustCryptoXChaCha20Poly1305,
+    nonce_prefix: [u8; HEADER_SIZE],
+    counter: u32,
+    finalized: bool,
+    /// Set once a segment fails to decrypt, after which the stream can't be trusted.
+    failed: bool,
+}
+
+impl StreamState {
+    fn new(key: &[u8], nonce_prefix: [u8; HEADER_SIZE]) -> Result<Self, StreamingAeadError> {
+        Ok(Self {
+            cipher: RustCryptoXChaCha20Poly1305::new_from_slice(key)?,
+            nonce_prefix,
+            counter: 0,
+            finalized: false,
+            failed: false,
+        })
+    }
+
+    /// Returns the nonce of the next segment, and advances the stream past it.
+    fn next_nonce(&mut self, last: bool) -> Result<XNonce, StreamingAeadError> {
+        if self.failed {
+            return Err(StreamingAeadError::DecryptError);
+        }
+        if self.finalized {
+            return Err(StreamingAeadError::StreamFinalized);
+        }
+
+        let mut nonce = [0u8; 24];
+        nonce[..HEADER_SIZE].copy_from_slice(&self.nonce_prefix);
+        nonce[HEADER_SIZE..23].copy_from_slice(&self.counter.to_be_bytes());
+        nonce[23] = last as u8;
+
+        if last {
+            self.finalized = true;
+        } else {
+            self.counter = self
+                .counter
+                .checked_add(1)
+                .ok_or(StreamingAeadError::SegmentLimitExceeded)?;
+        }
+        Ok(*XNonce::from_slice(&nonce))
+    }
+}
+
+/// Encrypts a stream segment by segment.
+pub struct StreamEncryptor {
+    state_mutex: Mutex<StreamState>,
+    aad: Vec<u8>,
+}
+
+impl StreamEncryptor {
+    /// Creates an encryptor for a new stream with a random nonce prefix. `aad` is authenticated
+    /// with every segment.
+    pub fn new(key: &[u8], aad: &[u8]) -> Result<Self, StreamingAeadError> {
+        let mut nonce_prefix = [0u8; HEADER_SIZE];
+        rand::thread_rng().fill_bytes(&mut nonce_prefix);
+
+        Ok(Self {
+            state_mutex: Mutex::new(StreamState::new(key, nonce_prefix)?),
+            aad: aad.to_vec(),
+        })
+    }
+
+    /// The header to send ahead of the stream's segments.
+    pub fn header(&self) -> Vec<u8> {
+        self.state_mutex.lock().unwrap().nonce_prefix.to_vec()
+    }
+
+    /// Encrypts a segment of exactly [`SEGMENT_SIZE`] bytes which isn't the last.
+    pub fn encrypt_segment(&self, plaintext: &[u8]) -> Result<Vec<u8>, StreamingAeadError> {
+        if plaintext.len() != SEGMENT_SIZE {
+            return Err(StreamingAeadError::InvalidSegmentSize);
+        }
+        self.seal(plaintext, false)
+    }
+
+    /// Encrypts the last segment of at most [`SEGMENT_SIZE`] bytes, after which the stream can't be
+    /// extended.
+    pub fn encrypt_final_segment(&self, plaintext: &[u8]) -> Result<Vec<u8>, StreamingAeadError> {
+        if plaintext.len() > SEGMENT_SIZE {
+            return Err(StreamingAeadError::InvalidSegmentSize);
+        }
+        self.seal(plaintext, true)
+    }
+
+    fn seal(&self, plaintext: &[u8], last: bool) -> Result<Vec<u8>, StreamingAeadError> {
+        let mut state = self.state_mutex.lock().unwrap();
+        let nonce = state.next_nonce(last)?;
+
+        state
+            .cipher
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: plaintext,
+                    aad: &self.aad,
+                },
+            )
+            .map_err(|_| StreamingAeadError::EncryptError)
+    }
+}
+
+/// Decrypts a stream segment by segment, in the order they were encrypted.
+pub struct StreamDecryptor {
+    state_mutex: Mutex<StreamState>,
+    aad: Vec<u8>,
+}
+
+impl StreamDecryptor {
+    pub fn new(key: &[u8], header: &[u8], aad: &[u8]) -> Result<Self, StreamingAeadError> {
+        let nonce_prefix = header
+            .try_into()
+            .map_err(|_| StreamingAeadError::InvalidHeader)?;
+
+        Ok(Self {
+            state_mutex: Mutex::new(StreamState::new(key, nonce_prefix)?),
+            aad: aad.to_vec(),
+        })
+    }
+
+    /// Decrypts a segment of exactly [`ENCRYPTED_SEGMENT_SIZE`] bytes which isn't the last.
+    pub fn decrypt_segment(&self, ciphertext: &[u8]) -> Result<Vec<u8>, StreamingAeadError> {
+        if ciphertext.len() != ENCRYPTED_SEGMENT_SIZE {
+            return Err(StreamingAeadError::InvalidSegmentSize);
+        }
+        self.open(ciphertext, false)
+    }
+
+    /// Decrypts the last segment. Fails if the segment wasn't encrypted as the last, i.e. if the
+    /// stream was truncated.
+    pub fn decrypt_final_segment(&self, ciphertext: &[u8]) -> Result<Vec<u8>, StreamingAeadError> {
+        if ciphertext.len() < TAG_SIZE || ciphertext.len() > ENCRYPTED_SEGMENT_SIZE {
+            return Err(StreamingAeadError::InvalidSegmentSize);
+        }
+        self.open(ciphertext, true)
+    }
+
+    /// Whether the final segment has been decrypted, i.e. the whole stream is authenticated.
+    pub fn is_finalized(&self) -> bool {
+        let state = self.state_mutex.lock().unwrap();
+        state.finalized && !state.failed
+    }
+
+    fn open(&self, ciphertext: &[u8], last: bool) -> Result<Vec<u8>, StreamingAeadError> {
+        let mut state = self.state_mutex.lock().unwrap();
+        let nonce = state.next_nonce(last)?;
+
+        let plaintext = state.cipher.decrypt(
+            &nonce,
+            Payload {
+                msg: ciphertext,
+                aad: &self.aad,
+            },
+        );
+        if plaintext.is_err() {
+            // Don't let a failed segment be retried as a different one.
+            state.failed = true;
+        }
+        plaintext.map_err(|_| StreamingAeadError::DecryptError)
+    }
+}
+
+/// Encrypts everything written to it into `inner`. [`EncryptingWriter::finish`] must be called to
+/// write the final segment, or the stream will fail to decrypt as truncated.
+pub struct EncryptingWriter<W: Write> {
+    inner: W,
+    encryptor: StreamEncryptor,
+    buffer: Vec<u8>,
+}
+
+impl<W: Write> EncryptingWriter<W> {
+    /// Starts a stream, writing its header to `inner`.
+    pub fn new(mut inner: W, key: &[u8], aad: &[u8]) -> io::Result<Self> {
+        let encryptor = StreamEncryptor::new(key, aad)?;
+        inner.write_all(&encryptor.header())?;
+
+        Ok(Self {
+            inner,
+            encryptor,
+            buffer: Vec::with_capacity(SEGMENT_SIZE),
+        })
+    }
+
+    /// Writes the final segment and returns the inner writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        let segment = self.encryptor.encrypt_final_segment(&self.buffer)?;
+        self.inner.write_all(&segment)?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for EncryptingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+
+        // A full segment is only known not to be the last once there's more to write.
+        while self.buffer.len() > SEGMENT_SIZE {
+            let segment = self
+                .encryptor
+                .encrypt_segment(&self.buffer[..SEGMENT_SIZE])?;
+            self.inner.write_all(&segment)?;
+            self.buffer.drain(..SEGMENT_SIZE);
+        }
+        Ok(buf.len())
+    }
+
+    /// Flushes the inner writer. Buffered plaintext which doesn't fill a segment yet stays
+    /// buffered.
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Decrypts a stream read from `inner`. Reads fail with [`io::ErrorKind::InvalidData`] if the
+/// stream was tampered with or truncated, so only plaintext read before the reader returns `Ok(0)`
+/// is known to be the complete stream.
+pub struct DecryptingReader<R: Read> {
+    inner: R,
+    decryptor: StreamDecryptor,
+    ciphertext: Vec<u8>,
+    plaintext: Vec<u8>,
+    position: usize,
+}
+
+impl<R: Read> DecryptingReader<R> {
+    /// Reads the stream's header from `inner`.
+    pub fn new(mut inner: R, key: &[u8], aad: &[u8]) -> io::Result<Self> {
+        let mut header = [0u8; HEADER_SIZE];
+        inner.read_exact(&mut header)?;
+
+        Ok(Self {
+            inner,
+            decryptor: StreamDecryptor::new(key, &header, aad)?,
+            ciphertext: Vec::with_capacity(ENCRYPTED_SEGMENT_SIZE + 1),
+            plaintext: Vec::new(),
+            position: 0,
+        })
+    }
+
+    /// Decrypts the next segment into the plaintext buffer.
+    fn next_segment(&mut self) -> io::Result<()> {
+        // Read one byte past a full segment, to tell whether it's the last.
+        let wanted = ENCRYPTED_SEGMENT_SIZE + 1 - self.ciphertext.len();
+        (&mut self.inner)
+            .take(wanted as u64)
+            .read_to_end(&mut self.ciphertext)?;
+
+        self.plaintext = if self.ciphertext.len() > ENCRYPTED_SEGMENT_SIZE {
+            let plaintext = self
+                .decryptor
+                .decrypt_segment(&self.ciphertext[..ENCRYPTED_SEGMENT_SIZE])?;
+            self.ciphertext.drain(..ENCRYPTED_SEGMENT_SIZE);
+            plaintext
+        } else {
+            let plaintext = self.decryptor.decrypt_final_segment(&self.ciphertext)?;
+            self.ciphertext.clear();
+            plaintext
+        };
+        self.position = 0;
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for DecryptingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.plaintext.len() {
+            if self.decryptor.is_finalized() {
+                return Ok(0);
+            }
+            self.next_segment()?;
+        }
+
+        let len = buf.len().min(self.plaintext.len() - self.position);
+        buf[..len].copy_from_slice(&self.plaintext[self.position..self.position + len]);
+        self.position += len;
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quickcheck_macros::quickcheck;
+    use rand::RngCore;
+    use std::io::{Read, Write};
+
+    use super::{
+        DecryptingReader, EncryptingWriter, StreamDecryptor, StreamEncryptor, StreamingAeadError,
+        ENCRYPTED_SEGMENT_SIZE, HEADER_SIZE, SEGMENT_SIZE,
+    };
+
+    fn random_bytes(len: usize) -> Vec<u8> {
+        let mut bytes = vec![0u8; len];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        bytes
+    }
+
+    fn encrypt(key: &[u8], aad: &[u8], plaintext: &[u8]) -> Vec<u8> {
+        let mut writer = EncryptingWriter::new(Vec::new(), key, aad).unwrap();
+        // Write in uneven pieces, so segments don't line up with writes.
+        for piece in plaintext.chunks(10_000) {
+            writer.write_all(piece).unwrap();
+        }
+        writer.finish().unwrap()
+    }
+
+    fn decrypt(key: &[u8], aad: &[u8], ciphertext: &[u8]) -> std::io::Result<Vec<u8>> {
+        let mut plaintext = Vec::new();
+        DecryptingReader::new(ciphertext, key, aad)?.read_to_end(&mut plaintext)?;
+        Ok(plaintext)
+    }
+
+    #[test]
+    fn test_stream_roundtrip() {
+        let key = random_bytes(32);
+        for len in [
+            0,
+            1,
+            SEGMENT_SIZE - 1,
+            SEGMENT_SIZE,
+            SEGMENT_SIZE + 1,
+            3 * SEGMENT_SIZE,
+            3 * SEGMENT_SIZE + 17,
+        ] {
+            let plaintext = random_bytes(len);
+            let ciphertext = encrypt(&key, b"aad", &plaintext);
+
+            let segments = len.div_ceil(SEGMENT_SIZE).max(1);
+            assert_eq!(ciphertext.len(), HEADER_SIZE + len + 16 * segments);
+            assert_eq!(decrypt(&key, b"aad", &ciphertext).unwrap(), plaintext);
+        }
+    }
+
+    #[quickcheck]
+    fn test_stream_roundtrip_quickcheck(plaintext: Vec<u8>, aad: Vec<u8>) -> bool {
+        let key = random_bytes(32);
+        decrypt(&key, &aad, &encrypt(&key, &aad, &plaintext)).unwrap() == plaintext
+    }
+
+    #[test]
+    fn test_stream_authentication() {
+        let key = random_bytes(32);
+        let ciphertext = encrypt(&key, b"aad", &random_bytes(2 * SEGMENT_SIZE + 100));
+
+        assert!(decrypt(&random_bytes(32), b"aad", &ciphertext).is_err());
+        assert!(decrypt(&key, b"other aad", &ciphertext).is_err());
+
+        let mut tampered = ciphertext.clone();
+        tampered[HEADER_SIZE + 1] ^= 1;
+        assert!(decrypt(&key, b"aad", &tampered).is_err());
+
+        let mut tampered_header = ciphertext.clone();
+        tampered_header[0] ^= 1;
+        assert!(decrypt(&key, b"aad", &tampered_header).is_err());
+    }
+
+    #[test]
+    fn test_stream_truncation_and_reordering() {
+        let key = random_bytes(32);
+        let ciphertext = encrypt(&key, &[], &random_bytes(2 * SEGMENT_SIZE + 100));
+        let segment = |idx: usize| {
+            let start = HEADER_SIZE + idx * ENCRYPTED_SEGMENT_SIZE;
+            &ciphertext[start..(start + ENCRYPTED_SEGMENT_SIZE).min(ciphertext.len())]
+        };
+
+        // Truncated at a segment boundary, and mid-segment.
+        let truncated = &ciphertext[..HEADER_SIZE + 2 * ENCRYPTED_SEGMENT_SIZE];
+        assert!(decrypt(&key, &[], truncated).is_err());
+        assert!(decrypt(&key, &[], &ciphertext[..ciphertext.len() - 1]).is_err());
+
+        // Reordered, and with a segment dropped.
+        let reordered = [
+            &ciphertext[..HEADER_SIZE],
+            segment(1),
+            segment(0),
+            segment(2),
+        ]
+        .concat();
+        assert!(decrypt(&key, &[], &reordered).is_err());
+        let dropped = [&ciphertext[..HEADER_SIZE], segment(0), segment(2)].concat();
+        assert!(decrypt(&key, &[], &dropped).is_err());
+
+        // Extended past the final segment.
+        let extended = [&ciphertext[..], segment(0)].concat();
+        assert!(decrypt(&key, &[], &extended).is_err());
+    }
+
+    #[test]
+    fn test_segment_api() {
+        let key = random_bytes(32);
+        let encryptor = StreamEncryptor::new(&key, &[]).unwrap();
+        let decryptor = StreamDecryptor::new(&key, &encryptor.header(), &[]).unwrap();
+
+        assert_eq!(
+            encryptor.encrypt_segment(&random_bytes(SEGMENT_SIZE - 1)),
+            Err(StreamingAeadError::InvalidSegmentSize)
+        );
+
+        let first = random_bytes(SEGMENT_SIZE);
+        let last = random_bytes(5);
+        let encrypted_first = encryptor.encrypt_segment(&first).unwrap();
+        let encrypted_last = encryptor.encrypt_final_segment(&last).unwrap();
+        assert_eq!(
+            encryptor.encrypt_final_segment(&last),
+            Err(StreamingAeadError::StreamFinalized)
+        );
+
+        // A non-final segment can't be passed off as the final one.
+        let truncating_decryptor = StreamDecryptor::new(&key, &encryptor.header(), &[]).unwrap();
+        assert_eq!(
+            truncating_decryptor.decrypt_final_segment(&encrypted_first),
+            Err(StreamingAeadError::DecryptError)
+        );
+        assert!(!truncating_decryptor.is_finalized());
+        assert_eq!(
+            truncating_decryptor.decrypt_segment(&encrypted_first),
+            Err(StreamingAeadError::DecryptError)
+        );
+
+        assert_eq!(decryptor.decrypt_segment(&encrypted_first).unwrap(), first);
+        assert!(!decryptor.is_finalized());
+        assert_eq!(
+            decryptor.decrypt_final_segment(&encrypted_last).unwrap(),
+            last
+        );
+        assert!(decryptor.is_finalized());
+
+        assert_eq!(
+            StreamDecryptor::new(&key, &[0u8; HEADER_SIZE - 1], &[]).err(),
+            Some(StreamingAeadError::InvalidHeader)
+        );
+    }
+}
@@ -1,7 +1,7 @@
 //! Shared primitives for verifying Bitkey hardware attestations: cert
-//! chain walking to the Silicon Labs root, Block-device identity check,
-//! and signature verification. Used by both `server` and `app` with
-//! their own domain-separated payloads.
+//! chain walking to a [`TrustStore`] root, Block-device identity and
+//! denylist checks, and signature verification. Used by both `server` and
+//! `app` with their own domain-separated payloads.
 
 use ring::signature::{UnparsedPublicKey, ECDSA_P256_SHA256_FIXED};
 use thiserror::Error;
@@ -9,10 +9,11 @@ use x509_parser::certificate::X509Certificate;
 use x509_parser::prelude::FromDer;
 use x509_parser::public_key::PublicKey as X509PublicKey;
 
-const SILABS_FACTORY_INTERMEDIATE: &[u8] =
-    include_bytes!("../../../firmware/config/keys/silabs-certs/factory-prod.der");
-const SILABS_DEVICE_ROOT: &[u8] =
-    include_bytes!("../../../firmware/config/keys/silabs-certs/device-root-prod.der");
+mod trust_store;
+
+#[cfg(any(test, feature = "test-signing-key"))]
+pub use trust_store::test_signing_key;
+pub use trust_store::{Denylist, TrustStore, TRUST_STORE_BUNDLE_MAGIC, TRUST_STORE_BUNDLE_VERSION};
 
 pub const ORG_W1: &str = "Block Inc";
 pub const ORG_W3: &str = "Bitkey W3, Block Inc";
@@ -27,6 +28,10 @@ pub enum AttestationError {
     ParseFailure,
     #[error("failed to verify signature")]
     VerificationFailure,
+    #[error("device or certificate has been revoked")]
+    Revoked,
+    #[error("trust store bundle is invalid")]
+    InvalidTrustStore,
 }
 
 /// A device identity cert that's been verified to chain to the Silicon
@@ -74,10 +79,12 @@ pub fn verify_signature_with_identity_der(
         .map_err(|_| AttestationError::VerificationFailure)
 }
 
-/// Walk the supplied identity + batch cert pair to the baked-in Silicon
-/// Labs root and check that the identity is for a Block-manufactured
-/// Bitkey (W1 or W3). On success returns a [`DeviceCert`] handle.
+/// Walk the supplied identity + batch cert pair to one of `trust_store`'s
+/// roots and check that the identity is for a Block-manufactured Bitkey
+/// (W1 or W3) that hasn't been denied. On success returns a [`DeviceCert`]
+/// handle.
 pub fn verify_device_identity_chain(
+    trust_store: &TrustStore,
     identity_cert_der: &[u8],
     batch_cert_der: &[u8],
 ) -> Result<DeviceCert, AttestationError> {
@@ -85,28 +92,51 @@ pub fn verify_device_identity_chain(
         X509Certificate::from_der(identity_cert_der).map_err(|_| AttestationError::ParseFailure)?;
     let (_, batch_cert) =
         X509Certificate::from_der(batch_cert_der).map_err(|_| AttestationError::ParseFailure)?;
-    let (_, silabs_factory_intermediate) = X509Certificate::from_der(SILABS_FACTORY_INTERMEDIATE)
-        .map_err(|_| AttestationError::ParseFailure)?;
-    let (_, silabs_root) =
-        X509Certificate::from_der(SILABS_DEVICE_ROOT).map_err(|_| AttestationError::ParseFailure)?;
+    let intermediates = parse_all(&trust_store.intermediates)?;
+    let roots = parse_all(&trust_store.roots)?;
 
     let serial = check_device_cert_is_for_block(&identity_cert)?;
 
-    if !verify_cert_chain(&[
-        &identity_cert,
-        &batch_cert,
-        &silabs_factory_intermediate,
-        &silabs_root,
-    ]) {
+    // Every path to a root, so a revoked intermediate only rejects the
+    // device when no other intermediate vouches for its batch.
+    let issuers: Vec<&X509Certificate> = intermediates
+        .iter()
+        .filter(|intermediate| {
+            roots
+                .iter()
+                .any(|root| verify_cert_chain(&[&identity_cert, &batch_cert, *intermediate, root]))
+        })
+        .collect();
+    if issuers.is_empty() {
         return Err(AttestationError::InvalidChain);
     }
 
+    if trust_store.is_denied_device(&serial)
+        || trust_store.is_denied_certificate(&identity_cert)
+        || trust_store.is_denied_certificate(&batch_cert)
+        || issuers
+            .iter()
+            .all(|intermediate| trust_store.is_denied_certificate(intermediate))
+    {
+        return Err(AttestationError::Revoked);
+    }
+
     Ok(DeviceCert {
         identity_der: identity_cert_der.to_vec(),
         serial,
     })
 }
 
+fn parse_all(ders: &[Vec<u8>]) -> Result<Vec<X509Certificate<'_>>, AttestationError> {
+    ders.iter()
+        .map(|der| {
+            X509Certificate::from_der(der)
+                .map(|(_, cert)| cert)
+                .map_err(|_| AttestationError::ParseFailure)
+        })
+        .collect()
+}
+
 fn check_device_cert_is_for_block(cert: &X509Certificate) -> Result<String, AttestationError> {
     let subject = cert.subject();
     let organization = subject
@@ -139,8 +169,7 @@ fn extract_serial_from_cn(cn: &str) -> Result<String, AttestationError> {
 }
 
 fn verify_directly_issued_by(cert: &X509Certificate, issuer: &X509Certificate) -> bool {
-    cert.issuer() == issuer.subject()
-        && cert.verify_signature(Some(issuer.public_key())).is_ok()
+    cert.issuer() == issuer.subject() && cert.verify_signature(Some(issuer.public_key())).is_ok()
 }
 
 fn verify_cert_chain(chain: &[&X509Certificate]) -> bool {
@@ -179,7 +208,8 @@ mod tests {
     fn verifies_real_w1_chain_and_extracts_serial() {
         let identity = decode_hex(W1_IDENTITY_CERT_HEX).unwrap();
         let batch = decode_hex(W1_BATCH_CERT_HEX).unwrap();
-        let device = verify_device_identity_chain(&identity, &batch).unwrap();
+        let device =
+            verify_device_identity_chain(&TrustStore::builtin(), &identity, &batch).unwrap();
         assert_eq!(device.serial(), "38398FFFFED081B6");
     }
 
@@ -187,7 +217,8 @@ mod tests {
     fn verifies_real_w3_chain_and_extracts_serial() {
         let identity = decode_hex(W3_PROD_MCU_CERT_HEX).unwrap();
         let batch = decode_hex(W3_PROD_BATCH_CERT_HEX).unwrap();
-        let device = verify_device_identity_chain(&identity, &batch).unwrap();
+        let device =
+            verify_device_identity_chain(&TrustStore::builtin(), &identity, &batch).unwrap();
         assert_eq!(device.serial(), "6CA042FFFE3C4094");
     }
 
@@ -196,14 +227,15 @@ mod tests {
         let identity = decode_hex(W1_IDENTITY_CERT_HEX).unwrap();
         let batch = decode_hex(W1_BATCH_CERT_HEX).unwrap();
         assert_eq!(
-            verify_device_identity_chain(&batch, &identity).unwrap_err(),
+            verify_device_identity_chain(&TrustStore::builtin(), &batch, &identity).unwrap_err(),
             AttestationError::NotForBlock,
         );
     }
 
     #[test]
     fn rejects_garbage_cert_bytes() {
-        let err = verify_device_identity_chain(&[0u8; 32], &[0u8; 32]).unwrap_err();
+        let err = verify_device_identity_chain(&TrustStore::builtin(), &[0u8; 32], &[0u8; 32])
+            .unwrap_err();
         assert_eq!(err, AttestationError::ParseFailure);
     }
 
@@ -211,8 +243,140 @@ mod tests {
     fn rejects_invalid_signature() {
         let identity = decode_hex(W1_IDENTITY_CERT_HEX).unwrap();
         let batch = decode_hex(W1_BATCH_CERT_HEX).unwrap();
-        let device = verify_device_identity_chain(&identity, &batch).unwrap();
-        let err = device.verify_signature(b"HWV1payload", &[0u8; 64]).unwrap_err();
+        let device =
+            verify_device_identity_chain(&TrustStore::builtin(), &identity, &batch).unwrap();
+        let err = device
+            .verify_signature(b"HWV1payload", &[0u8; 64])
+            .unwrap_err();
         assert_eq!(err, AttestationError::VerificationFailure);
     }
+
+    fn raw_serial(der: &[u8]) -> Vec<u8> {
+        let (_, cert) = X509Certificate::from_der(der).unwrap();
+        cert.raw_serial().to_vec()
+    }
+
+    #[test]
+    fn rejects_chain_without_trusted_intermediate() {
+        let identity = decode_hex(W1_IDENTITY_CERT_HEX).unwrap();
+        let batch = decode_hex(W1_BATCH_CERT_HEX).unwrap();
+        let trust_store = TrustStore {
+            intermediates: vec![],
+            ..TrustStore::builtin()
+        };
+        assert_eq!(
+            verify_device_identity_chain(&trust_store, &identity, &batch).unwrap_err(),
+            AttestationError::InvalidChain,
+        );
+    }
+
+    #[test]
+    fn rejects_denied_device_serial() {
+        let identity = decode_hex(W3_PROD_MCU_CERT_HEX).unwrap();
+        let batch = decode_hex(W3_PROD_BATCH_CERT_HEX).unwrap();
+        let mut trust_store = TrustStore::builtin();
+        trust_store
+            .denylist
+            .device_serials
+            .insert("6CA042FFFE3C4094".to_string());
+        assert_eq!(
+            verify_device_identity_chain(&trust_store, &identity, &batch).unwrap_err(),
+            AttestationError::Revoked,
+        );
+
+        // Other devices are unaffected.
+        let w1_identity = decode_hex(W1_IDENTITY_CERT_HEX).unwrap();
+        let w1_batch = decode_hex(W1_BATCH_CERT_HEX).unwrap();
+        assert!(verify_device_identity_chain(&trust_store, &w1_identity, &w1_batch).is_ok());
+    }
+
+    #[test]
+    fn rejects_devices_from_denied_batch_or_intermediate() {
+        let identity = decode_hex(W1_IDENTITY_CERT_HEX).unwrap();
+        let batch = decode_hex(W1_BATCH_CERT_HEX).unwrap();
+
+        for denied in [
+            raw_serial(&batch),
+            raw_serial(&TrustStore::builtin().intermediates[0]),
+        ] {
+            let mut trust_store = TrustStore::builtin();
+            trust_store.denylist.certificate_serials.insert(denied);
+            assert_eq!(
+                verify_device_identity_chain(&trust_store, &identity, &batch).unwrap_err(),
+                AttestationError::Revoked,
+            );
+        }
+    }
+
+    #[test]
+    fn loads_signed_trust_store_bundle() {
+        let mut trust_store = TrustStore::builtin();
+        trust_store.sequence = 7;
+        trust_store
+            .denylist
+            .device_serials
+            .insert("38398FFFFED081B6".to_string());
+        trust_store
+            .denylist
+            .certificate_serials
+            .insert(vec![0x01, 0x02]);
+        let bundle = trust_store.serialize_bundle().unwrap();
+        let signature = test_signing_key::sign_bundle(&bundle);
+
+        let loaded = TrustStore::from_signed_bundle(&bundle, &signature).unwrap();
+        assert_eq!(loaded, trust_store);
+
+        let identity = decode_hex(W1_IDENTITY_CERT_HEX).unwrap();
+        let batch = decode_hex(W1_BATCH_CERT_HEX).unwrap();
+        assert_eq!(
+            verify_device_identity_chain(&loaded, &identity, &batch).unwrap_err(),
+            AttestationError::Revoked,
+        );
+
+        let mut tampered = bundle.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert_eq!(
+            TrustStore::from_signed_bundle(&tampered, &signature).unwrap_err(),
+            AttestationError::VerificationFailure,
+        );
+
+        let mut truncated = bundle[..bundle.len() - 1].to_vec();
+        let signature = test_signing_key::sign_bundle(&truncated);
+        assert_eq!(
+            TrustStore::from_signed_bundle(&truncated, &signature).unwrap_err(),
+            AttestationError::InvalidTrustStore,
+        );
+        truncated[4] = TRUST_STORE_BUNDLE_VERSION + 1;
+        let signature = test_signing_key::sign_bundle(&truncated);
+        assert_eq!(
+            TrustStore::from_signed_bundle(&truncated, &signature).unwrap_err(),
+            AttestationError::InvalidTrustStore,
+        );
+    }
+
+    #[test]
+    fn rejects_bundle_signed_by_unpinned_key() {
+        use ring::rand::SystemRandom;
+        use ring::signature::{EcdsaKeyPair, ECDSA_P256_SHA256_FIXED_SIGNING};
+
+        let rng = SystemRandom::new();
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &rng).unwrap();
+        let signing_key =
+            EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, pkcs8.as_ref(), &rng)
+                .unwrap();
+
+        // A store that trusts an extra root of the signer's choosing.
+        let mut trust_store = TrustStore::builtin();
+        trust_store.sequence = 1;
+        trust_store
+            .roots
+            .push(decode_hex(W1_BATCH_CERT_HEX).unwrap());
+        let bundle = trust_store.serialize_bundle().unwrap();
+        let signature = signing_key.sign(&rng, &bundle).unwrap();
+
+        assert_eq!(
+            TrustStore::from_signed_bundle(&bundle, signature.as_ref()).unwrap_err(),
+            AttestationError::VerificationFailure,
+        );
+    }
 }
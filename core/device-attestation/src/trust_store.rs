@@ -0,0 +1,247 @@
+//! The factory certificates device identity chains are walked to, and the
+//! devices and certificates that are no longer trusted. Ships with the
+//! prod Silicon Labs certs baked in, and can be replaced at runtime by a
+//! bundle signed with one of the baked-in bundle signing keys, so new
+//! factory intermediates and revocations don't need a release.
+
+use std::collections::BTreeSet;
+
+use ring::signature::{UnparsedPublicKey, ECDSA_P256_SHA256_FIXED};
+use x509_parser::certificate::X509Certificate;
+use x509_parser::prelude::FromDer;
+
+use crate::AttestationError;
+
+const SILABS_FACTORY_INTERMEDIATE: &[u8] =
+    include_bytes!("../../../firmware/config/keys/silabs-certs/factory-prod.der");
+const SILABS_DEVICE_ROOT: &[u8] =
+    include_bytes!("../../../firmware/config/keys/silabs-certs/device-root-prod.der");
+
+/// Keys whose signatures on a trust store bundle are accepted, as
+/// uncompressed SEC1 P-256 points. Pinned here rather than supplied with
+/// the bundle, so only their holders can change what's trusted.
+const TRUST_STORE_SIGNING_KEYS: &[&[u8]] = &[
+    include_bytes!("../keys/trust-store-signing-prod.pub"),
+    #[cfg(any(test, feature = "test-signing-key"))]
+    test_signing_key::PUBLIC_KEY,
+];
+
+/// Leads every trust store bundle, so bundle signatures can't be replayed
+/// as signatures over anything else.
+pub const TRUST_STORE_BUNDLE_MAGIC: &[u8; 4] = b"BKTS";
+pub const TRUST_STORE_BUNDLE_VERSION: u8 = 1;
+
+/// Devices and certificates to reject even when their chain is valid.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Denylist {
+    /// Device serials as extracted from the identity cert, e.g.
+    /// `38398FFFFED081B6`.
+    pub device_serials: BTreeSet<String>,
+    /// Raw serial numbers of identity, batch or factory intermediate
+    /// certs. Revoking a batch cert rejects every device in the batch.
+    pub certificate_serials: BTreeSet<Vec<u8>>,
+}
+
+/// Roots and intermediates in DER form, plus a [`Denylist`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrustStore {
+    /// Increases with every bundle, so an older bundle can't be swapped
+    /// back in to un-revoke a device.
+    pub sequence: u64,
+    pub roots: Vec<Vec<u8>>,
+    pub intermediates: Vec<Vec<u8>>,
+    pub denylist: Denylist,
+}
+
+impl Default for TrustStore {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+impl TrustStore {
+    /// The prod Silicon Labs device root and factory intermediate, with
+    /// nothing denied.
+    pub fn builtin() -> Self {
+        Self {
+            sequence: 0,
+            roots: vec![SILABS_DEVICE_ROOT.to_vec()],
+            intermediates: vec![SILABS_FACTORY_INTERMEDIATE.to_vec()],
+            denylist: Denylist::default(),
+        }
+    }
+
+    /// Parses a bundle produced by [`TrustStore::serialize_bundle`] after
+    /// checking `signature`, a fixed-width ECDSA P-256 SHA-256 signature
+    /// over the whole bundle by one of the pinned bundle signing keys.
+    pub fn from_signed_bundle(bundle: &[u8], signature: &[u8]) -> Result<Self, AttestationError> {
+        let signed_by_pinned_key = TRUST_STORE_SIGNING_KEYS.iter().any(|key| {
+            UnparsedPublicKey::new(&ECDSA_P256_SHA256_FIXED, key)
+                .verify(bundle, signature)
+                .is_ok()
+        });
+        if !signed_by_pinned_key {
+            return Err(AttestationError::VerificationFailure);
+        }
+        Self::deserialize_bundle(bundle)
+    }
+
+    /// Encodes the store as:
+    ///
+    /// ```text
+    /// "BKTS" || version (u8) || sequence (u64 BE)
+    ///     || root count (u8) || { der length (u16 BE) || der }
+    ///     || intermediate count (u8) || { der length (u16 BE) || der }
+    ///     || device serial count (u16 BE) || { length (u8) || ascii }
+    ///     || certificate serial count (u16 BE) || { length (u8) || bytes }
+    /// ```
+    pub fn serialize_bundle(&self) -> Result<Vec<u8>, AttestationError> {
+        let mut out = Vec::new();
+        out.extend_from_slice(TRUST_STORE_BUNDLE_MAGIC);
+        out.push(TRUST_STORE_BUNDLE_VERSION);
+        out.extend_from_slice(&self.sequence.to_be_bytes());
+        for certs in [&self.roots, &self.intermediates] {
+            out.push(u8::try_from(certs.len()).map_err(|_| AttestationError::InvalidTrustStore)?);
+            for der in certs {
+                let len =
+                    u16::try_from(der.len()).map_err(|_| AttestationError::InvalidTrustStore)?;
+                out.extend_from_slice(&len.to_be_bytes());
+                out.extend_from_slice(der);
+            }
+        }
+        let device_serials = self.denylist.device_serials.iter().map(String::as_bytes);
+        write_serials(&mut out, device_serials, self.denylist.device_serials.len())?;
+        let certificate_serials = self.denylist.certificate_serials.iter().map(Vec::as_slice);
+        write_serials(
+            &mut out,
+            certificate_serials,
+            self.denylist.certificate_serials.len(),
+        )?;
+        Ok(out)
+    }
+
+    fn deserialize_bundle(bundle: &[u8]) -> Result<Self, AttestationError> {
+        let mut reader = Reader(bundle);
+        if reader.take(TRUST_STORE_BUNDLE_MAGIC.len())? != TRUST_STORE_BUNDLE_MAGIC
+            || reader.u8()? != TRUST_STORE_BUNDLE_VERSION
+        {
+            return Err(AttestationError::InvalidTrustStore);
+        }
+        let sequence = u64::from_be_bytes(reader.array()?);
+
+        let mut read_certs = || -> Result<Vec<Vec<u8>>, AttestationError> {
+            let count = reader.u8()?;
+            (0..count)
+                .map(|_| {
+                    let len = u16::from_be_bytes(reader.array()?) as usize;
+                    let der = reader.take(len)?;
+                    X509Certificate::from_der(der).map_err(|_| AttestationError::ParseFailure)?;
+                    Ok(der.to_vec())
+                })
+                .collect()
+        };
+        let roots = read_certs()?;
+        let intermediates = read_certs()?;
+
+        let device_serials = read_serials(&mut reader)?
+            .into_iter()
+            .map(|serial| {
+                String::from_utf8(serial).map_err(|_| AttestationError::InvalidTrustStore)
+            })
+            .collect::<Result<_, _>>()?;
+        let certificate_serials = read_serials(&mut reader)?.into_iter().collect();
+
+        if !reader.0.is_empty() || roots.is_empty() {
+            return Err(AttestationError::InvalidTrustStore);
+        }
+
+        Ok(Self {
+            sequence,
+            roots,
+            intermediates,
+            denylist: Denylist {
+                device_serials,
+                certificate_serials,
+            },
+        })
+    }
+
+    pub(crate) fn is_denied_device(&self, serial: &str) -> bool {
+        self.denylist.device_serials.contains(serial)
+    }
+
+    pub(crate) fn is_denied_certificate(&self, cert: &X509Certificate) -> bool {
+        self.denylist
+            .certificate_serials
+            .contains(cert.raw_serial())
+    }
+}
+
+fn write_serials<'a>(
+    out: &mut Vec<u8>,
+    serials: impl Iterator<Item = &'a [u8]>,
+    count: usize,
+) -> Result<(), AttestationError> {
+    let count = u16::try_from(count).map_err(|_| AttestationError::InvalidTrustStore)?;
+    out.extend_from_slice(&count.to_be_bytes());
+    for serial in serials {
+        out.push(u8::try_from(serial.len()).map_err(|_| AttestationError::InvalidTrustStore)?);
+        out.extend_from_slice(serial);
+    }
+    Ok(())
+}
+
+fn read_serials(reader: &mut Reader) -> Result<Vec<Vec<u8>>, AttestationError> {
+    let count = u16::from_be_bytes(reader.array()?);
+    (0..count)
+        .map(|_| {
+            let len = reader.u8()? as usize;
+            Ok(reader.take(len)?.to_vec())
+        })
+        .collect()
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], AttestationError> {
+        if self.0.len() < len {
+            return Err(AttestationError::InvalidTrustStore);
+        }
+        let (head, tail) = self.0.split_at(len);
+        self.0 = tail;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> Result<u8, AttestationError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], AttestationError> {
+        Ok(self.take(N)?.try_into().expect("took N bytes"))
+    }
+}
+
+/// A bundle signing key whose private half is checked in, for signing
+/// bundles in tests. Only pinned with the `test-signing-key` feature, which
+/// must never be enabled outside of dev-dependencies.
+#[cfg(any(test, feature = "test-signing-key"))]
+pub mod test_signing_key {
+    use ring::rand::SystemRandom;
+    use ring::signature::{EcdsaKeyPair, ECDSA_P256_SHA256_FIXED_SIGNING};
+
+    pub const PUBLIC_KEY: &[u8] = include_bytes!("../keys/trust-store-signing-test.pub");
+    const PKCS8: &[u8] = include_bytes!("../keys/trust-store-signing-test.pk8");
+
+    /// Signs `bundle` so [`TrustStore::from_signed_bundle`](super::TrustStore::from_signed_bundle)
+    /// accepts it.
+    pub fn sign_bundle(bundle: &[u8]) -> Vec<u8> {
+        let rng = SystemRandom::new();
+        let key = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, PKCS8, &rng)
+            .expect("test signing key is valid");
+        key.sign(&rng, bundle)
+            .expect("signing succeeds")
+            .as_ref()
+            .to_vec()
+    }
+}
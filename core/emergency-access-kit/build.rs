@@ -0,0 +1,10 @@
+extern crate prost_build;
+
+fn main() {
+    let manifest_dir = std::path::PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap());
+    let proto_dir = manifest_dir.join("../../proto");
+    println!("cargo:rerun-if-changed={}", proto_dir.display());
+
+    let protos = [proto_dir.join("build/wallet/emergencyaccesskit/v1/payload.proto")];
+    prost_build::compile_protos(&protos, &[proto_dir]).unwrap();
+}
@@ -0,0 +1,167 @@
+use std::{fmt, str::FromStr};
+
+use bitcoin::{bip32::ChildNumber, secp256k1::Secp256k1, Network, NetworkKind};
+use miniscript::{
+    descriptor::{DescriptorSecretKey, DescriptorXKey, Wildcard},
+    Descriptor, DescriptorPublicKey,
+};
+
+use crate::{proto, EmergencyAccessKitError};
+
+const RECEIVING_PATH: [ChildNumber; 1] = [ChildNumber::Normal { index: 0 }];
+const CHANGE_PATH: [ChildNumber; 1] = [ChildNumber::Normal { index: 1 }];
+
+/// The active spending keyset recovered from a payload, checked to be consistent: every key is
+/// on the keyset's network and the App's private key matches its public key.
+pub struct RecoveredKeyset {
+    pub local_id: String,
+    pub network: Network,
+    pub app_key: DescriptorPublicKey,
+    pub app_private_key: DescriptorSecretKey,
+    pub hardware_key: DescriptorPublicKey,
+    pub server_key: DescriptorPublicKey,
+    receiving_descriptor: Descriptor<DescriptorPublicKey>,
+    change_descriptor: Descriptor<DescriptorPublicKey>,
+}
+
+impl RecoveredKeyset {
+    pub(crate) fn from_proto(
+        keyset: proto::ActiveSpendingKeysetV1,
+    ) -> Result<Self, EmergencyAccessKitError> {
+        let network = match proto::BitcoinNetworkType::try_from(keyset.bitcoin_network_type) {
+            Ok(proto::BitcoinNetworkType::Bitcoin) => Network::Bitcoin,
+            Ok(proto::BitcoinNetworkType::Signet) => Network::Signet,
+            Ok(proto::BitcoinNetworkType::Testnet) => Network::Testnet,
+            Ok(proto::BitcoinNetworkType::Regtest) => Network::Regtest,
+            Ok(proto::BitcoinNetworkType::Unspecified) | Err(_) => {
+                return Err(EmergencyAccessKitError::InvalidNetwork)
+            }
+        };
+
+        let app = keyset
+            .app_key
+            .ok_or(EmergencyAccessKitError::MissingField("app key"))?;
+        let app_key = public_key(network, "app key", app.key)?;
+        let hardware_key = public_key(network, "hardware key", keyset.hardware_key)?;
+        let server_key = public_key(network, "server key", keyset.f8e_key)?;
+        let app_private_key = private_key(network, app.xprv)?;
+
+        let secp = Secp256k1::new();
+        if app_private_key.to_public(&secp).ok().as_ref() != Some(&app_key) {
+            return Err(EmergencyAccessKitError::AppKeyMismatch);
+        }
+
+        let keys = [&app_key, &hardware_key, &server_key];
+        let multisig = |path: &[ChildNumber]| {
+            Descriptor::new_wsh_sortedmulti(2, keys.iter().map(|key| extend(key, path)).collect())
+        };
+        let receiving_descriptor = multisig(&RECEIVING_PATH)?;
+        let change_descriptor = multisig(&CHANGE_PATH)?;
+
+        Ok(Self {
+            local_id: keyset
+                .local_id
+                .ok_or(EmergencyAccessKitError::MissingField("local id"))?,
+            network,
+            app_key,
+            app_private_key,
+            hardware_key,
+            server_key,
+            receiving_descriptor,
+            change_descriptor,
+        })
+    }
+
+    /// The 2-of-3 descriptor for the receiving keychain.
+    pub fn receiving_descriptor(&self) -> &Descriptor<DescriptorPublicKey> {
+        &self.receiving_descriptor
+    }
+
+    /// The 2-of-3 descriptor for the change keychain.
+    pub fn change_descriptor(&self) -> &Descriptor<DescriptorPublicKey> {
+        &self.change_descriptor
+    }
+}
+
+impl fmt::Debug for RecoveredKeyset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RecoveredKeyset")
+            .field("local_id", &self.local_id)
+            .field("network", &self.network)
+            .field("app_key", &self.app_key)
+            .field("app_private_key", &"[REDACTED]")
+            .field("hardware_key", &self.hardware_key)
+            .field("server_key", &self.server_key)
+            .finish()
+    }
+}
+
+fn public_key(
+    network: Network,
+    field: &'static str,
+    key: Option<proto::SpendingPublicKey>,
+) -> Result<DescriptorPublicKey, EmergencyAccessKitError> {
+    let missing = EmergencyAccessKitError::MissingField(field);
+    let invalid = |reason: String| EmergencyAccessKitError::InvalidKey { field, reason };
+
+    let key = key.ok_or(missing)?;
+    let (Some(origin), Some(xpub), Some(derivation_path)) =
+        (key.origin, key.xpub, key.derivation_path)
+    else {
+        return Err(EmergencyAccessKitError::MissingField(field));
+    };
+    let (Some(fingerprint), Some(origin_path)) = (origin.fingerprint, origin.derivation_path)
+    else {
+        return Err(EmergencyAccessKitError::MissingField(field));
+    };
+    let wildcard = match proto::Wildcard::try_from(key.wildcard) {
+        Ok(proto::Wildcard::None) => Wildcard::None,
+        Ok(proto::Wildcard::Unhardened) => Wildcard::Unhardened,
+        Ok(proto::Wildcard::Hardened) => Wildcard::Hardened,
+        Ok(proto::Wildcard::Unspecified) | Err(_) => {
+            return Err(invalid("unspecified wildcard".to_string()))
+        }
+    };
+
+    // The App stores keys in pieces, with the wildcard step included in the derivation path.
+    let dpub = format!("[{fingerprint}{origin_path}]{xpub}{derivation_path}");
+    match DescriptorPublicKey::from_str(&dpub).map_err(|e| invalid(e.to_string()))? {
+        DescriptorPublicKey::XPub(key) if key.wildcard != wildcard => Err(invalid(
+            "wildcard does not match derivation path".to_string(),
+        )),
+        DescriptorPublicKey::XPub(key) if key.xkey.network != NetworkKind::from(network) => {
+            Err(invalid(format!("not a {network} key")))
+        }
+        DescriptorPublicKey::XPub(key) => Ok(DescriptorPublicKey::XPub(key)),
+        _ => Err(invalid("not an extended public key".to_string())),
+    }
+}
+
+fn private_key(
+    network: Network,
+    xprv: Option<String>,
+) -> Result<DescriptorSecretKey, EmergencyAccessKitError> {
+    let field = "app private key";
+    let invalid = |reason: String| EmergencyAccessKitError::InvalidKey { field, reason };
+
+    let xprv = xprv.ok_or(EmergencyAccessKitError::MissingField(field))?;
+    match DescriptorSecretKey::from_str(&xprv).map_err(|e| invalid(e.to_string()))? {
+        DescriptorSecretKey::XPrv(key) if key.xkey.network != NetworkKind::from(network) => {
+            Err(invalid(format!("not a {network} key")))
+        }
+        DescriptorSecretKey::XPrv(key) => Ok(DescriptorSecretKey::XPrv(key)),
+        _ => Err(invalid("not an extended private key".to_string())),
+    }
+}
+
+fn extend(key: &DescriptorPublicKey, path: &[ChildNumber]) -> DescriptorPublicKey {
+    match key {
+        DescriptorPublicKey::XPub(xpub) => DescriptorPublicKey::XPub(DescriptorXKey {
+            derivation_path: xpub.derivation_path.extend(path),
+            origin: xpub.origin.clone(),
+            ..*xpub
+        }),
+        // Only extended keys get past `public_key`.
+        _ => unreachable!(),
+    }
+}
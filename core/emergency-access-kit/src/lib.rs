@@ -0,0 +1,46 @@
+//! Emergency Access Kit payloads: the base58 string printed in the Emergency Access Kit PDF.
+//!
+//! A payload holds the active spending keyset, including the App's private key, sealed under a
+//! key that only the hardware can unseal. [`EmergencyAccessKitPayload::open`] takes the unsealing
+//! step as a callback, so the same code serves the App and offline recovery tooling.
+
+use thiserror::Error;
+
+mod keyset;
+mod payload;
+
+pub mod proto {
+    include!(concat!(
+        env!("OUT_DIR"),
+        "/build.wallet.emergencyaccesskit.v1.rs"
+    ));
+}
+
+pub use keyset::RecoveredKeyset;
+pub use payload::{EmergencyAccessKitPayload, SealedData};
+
+#[derive(Debug, Error)]
+pub enum EmergencyAccessKitError {
+    #[error("Payload is not valid base58")]
+    InvalidBase58,
+    #[error("Payload is not a valid protobuf: {0}")]
+    InvalidProto(#[from] prost::DecodeError),
+    #[error("Payload has no supported backup version")]
+    UnsupportedBackupVersion,
+    #[error("Payload is missing {0}")]
+    MissingField(&'static str),
+    #[error("Failed to unseal the hardware encryption key: {0}")]
+    UnsealKey(#[source] Box<dyn std::error::Error + Send + Sync>),
+    #[error("Payload is sealed with a legacy cipher")]
+    UnsupportedCipher,
+    #[error("Failed to decrypt the keyset: {0}")]
+    Decrypt(#[from] crypto::chacha20poly1305::ChaCha20Poly1305Error),
+    #[error("Keyset has no valid network")]
+    InvalidNetwork,
+    #[error("Invalid {field}: {reason}")]
+    InvalidKey { field: &'static str, reason: String },
+    #[error("App private key does not match the App public key")]
+    AppKeyMismatch,
+    #[error("Failed to build descriptor: {0}")]
+    InvalidDescriptor(#[from] miniscript::Error),
+}
@@ -0,0 +1,314 @@
+use crypto::chacha20poly1305::XChaCha20Poly1305;
+use prost::Message;
+
+use crate::{proto, EmergencyAccessKitError, RecoveredKeyset};
+
+const BASE58_ALPHABET: &str = "123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+const XNONCE_LENGTH: usize = 24;
+
+/// XChaCha20-Poly1305 output, with the tag split off the ciphertext.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SealedData {
+    pub ciphertext: Vec<u8>,
+    pub nonce: Vec<u8>,
+    pub tag: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmergencyAccessKitPayload {
+    /// The key sealing the keyset, itself sealed by the hardware.
+    pub sealed_hw_encryption_key: Vec<u8>,
+    pub sealed_active_spending_keyset: SealedData,
+}
+
+impl EmergencyAccessKitPayload {
+    /// Parses the payload as printed in the PDF. Anything outside the base58 alphabet, like the
+    /// line breaks used to wrap it, is ignored.
+    pub fn from_base58(encoded: &str) -> Result<Self, EmergencyAccessKitError> {
+        let encoded: String = encoded
+            .chars()
+            .filter(|c| BASE58_ALPHABET.contains(*c))
+            .collect();
+        let data = bitcoin::base58::decode(&encoded)
+            .map_err(|_| EmergencyAccessKitError::InvalidBase58)?;
+        Self::from_proto_bytes(&data)
+    }
+
+    pub fn from_proto_bytes(data: &[u8]) -> Result<Self, EmergencyAccessKitError> {
+        let Some(proto::payload::Backup::BackupV1(backup)) = proto::Payload::decode(data)?.backup
+        else {
+            return Err(EmergencyAccessKitError::UnsupportedBackupVersion);
+        };
+
+        let sealed = backup
+            .sealed_active_spending_keyset
+            .ok_or(EmergencyAccessKitError::MissingField("sealed keyset"))?;
+        Ok(Self {
+            sealed_hw_encryption_key: backup.hw_encryption_key_ciphertext.ok_or(
+                EmergencyAccessKitError::MissingField("sealed hardware encryption key"),
+            )?,
+            sealed_active_spending_keyset: SealedData {
+                ciphertext: sealed
+                    .ciphertext
+                    .ok_or(EmergencyAccessKitError::MissingField("keyset ciphertext"))?,
+                nonce: sealed
+                    .nonce
+                    .ok_or(EmergencyAccessKitError::MissingField("keyset nonce"))?,
+                tag: sealed
+                    .tag
+                    .ok_or(EmergencyAccessKitError::MissingField("keyset tag"))?,
+            },
+        })
+    }
+
+    /// Decrypts and validates the keyset. `unseal_hw_encryption_key` is given the sealed key,
+    /// and should return it unsealed by the hardware.
+    pub fn open<F, E>(
+        &self,
+        unseal_hw_encryption_key: F,
+    ) -> Result<RecoveredKeyset, EmergencyAccessKitError>
+    where
+        F: FnOnce(&[u8]) -> Result<Vec<u8>, E>,
+        E: Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        let sealed = &self.sealed_active_spending_keyset;
+        // Payloads from before the switch to XChaCha20-Poly1305 were sealed with AES-GCM.
+        if sealed.nonce.len() != XNONCE_LENGTH {
+            return Err(EmergencyAccessKitError::UnsupportedCipher);
+        }
+
+        let key = unseal_hw_encryption_key(&self.sealed_hw_encryption_key)
+            .map_err(|e| EmergencyAccessKitError::UnsealKey(e.into()))?;
+        let ciphertext_and_tag = [sealed.ciphertext.as_slice(), sealed.tag.as_slice()].concat();
+        let keyset =
+            XChaCha20Poly1305::new(&key)?.decrypt(&sealed.nonce, &ciphertext_and_tag, b"")?;
+
+        RecoveredKeyset::from_proto(proto::ActiveSpendingKeysetV1::decode(keyset.as_slice())?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use bitcoin::{
+        bip32::{DerivationPath, Xpriv, Xpub},
+        secp256k1::Secp256k1,
+        Network,
+    };
+    use crypto::chacha20poly1305::XChaCha20Poly1305;
+    use miniscript::{descriptor::DescriptorSecretKey, DescriptorPublicKey};
+    use prost::Message;
+    use rand::RngCore;
+
+    use crate::{proto, EmergencyAccessKitError, EmergencyAccessKitPayload};
+
+    const SEALED_HW_ENCRYPTION_KEY: &[u8] = b"sealed hardware encryption key";
+    const ORIGIN_PATH: &str = "/84'/1'/0'";
+
+    fn xprv(seed: u8) -> Xpriv {
+        Xpriv::new_master(Network::Signet, &[seed; 32]).unwrap()
+    }
+
+    fn spending_public_key(seed: u8) -> proto::SpendingPublicKey {
+        let secp = Secp256k1::new();
+        let root = xprv(seed);
+        let path = DerivationPath::from_str(&format!("m{ORIGIN_PATH}")).unwrap();
+        proto::SpendingPublicKey {
+            origin: Some(proto::Origin {
+                fingerprint: Some(root.fingerprint(&secp).to_string()),
+                derivation_path: Some(ORIGIN_PATH.to_string()),
+            }),
+            xpub: Some(
+                Xpub::from_priv(&secp, &root.derive_priv(&secp, &path).unwrap()).to_string(),
+            ),
+            derivation_path: Some("/*".to_string()),
+            wildcard: proto::Wildcard::Unhardened.into(),
+        }
+    }
+
+    fn app_xprv(seed: u8) -> String {
+        let secp = Secp256k1::new();
+        let root = xprv(seed);
+        let path = DerivationPath::from_str(&format!("m{ORIGIN_PATH}")).unwrap();
+        format!(
+            "[{}{ORIGIN_PATH}]{}/*",
+            root.fingerprint(&secp),
+            root.derive_priv(&secp, &path).unwrap()
+        )
+    }
+
+    fn keyset() -> proto::ActiveSpendingKeysetV1 {
+        proto::ActiveSpendingKeysetV1 {
+            local_id: Some("keyset-id".to_string()),
+            bitcoin_network_type: proto::BitcoinNetworkType::Signet.into(),
+            app_key: Some(proto::AppSpendingKey {
+                key: Some(spending_public_key(1)),
+                xprv: Some(app_xprv(1)),
+            }),
+            hardware_key: Some(spending_public_key(2)),
+            f8e_key: Some(spending_public_key(3)),
+        }
+    }
+
+    fn seal(keyset: &proto::ActiveSpendingKeysetV1, key: &[u8; 32]) -> String {
+        let mut nonce = [0u8; 24];
+        rand::thread_rng().fill_bytes(&mut nonce);
+        let mut ciphertext = XChaCha20Poly1305::new(key)
+            .unwrap()
+            .encrypt(&nonce, &keyset.encode_to_vec(), b"")
+            .unwrap();
+        let tag = ciphertext.split_off(ciphertext.len() - 16);
+
+        let payload = proto::Payload {
+            backup: Some(proto::payload::Backup::BackupV1(proto::BackupV1 {
+                hw_encryption_key_ciphertext: Some(SEALED_HW_ENCRYPTION_KEY.to_vec()),
+                sealed_active_spending_keyset: Some(proto::SealedData {
+                    ciphertext: Some(ciphertext),
+                    nonce: Some(nonce.to_vec()),
+                    tag: Some(tag),
+                }),
+            })),
+        };
+        bitcoin::base58::encode(&payload.encode_to_vec())
+    }
+
+    fn unseal(key: [u8; 32]) -> impl FnOnce(&[u8]) -> Result<Vec<u8>, std::io::Error> {
+        move |sealed| {
+            assert_eq!(sealed, SEALED_HW_ENCRYPTION_KEY);
+            Ok(key.to_vec())
+        }
+    }
+
+    #[test]
+    fn test_open_payload() {
+        let key = [7u8; 32];
+        let encoded = seal(&keyset(), &key);
+        // Wrapped the way the PDF prints it.
+        let wrapped = encoded
+            .as_bytes()
+            .chunks(40)
+            .map(|line| std::str::from_utf8(line).unwrap())
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let payload = EmergencyAccessKitPayload::from_base58(&wrapped).unwrap();
+        assert_eq!(payload.sealed_hw_encryption_key, SEALED_HW_ENCRYPTION_KEY);
+        let keyset = payload.open(unseal(key)).unwrap();
+
+        assert_eq!(keyset.local_id, "keyset-id");
+        assert_eq!(keyset.network, Network::Signet);
+        assert_eq!(
+            keyset.app_private_key,
+            DescriptorSecretKey::from_str(&app_xprv(1)).unwrap()
+        );
+        assert!(!format!("{keyset:?}").contains("tprv"));
+
+        let dpub = |key: proto::SpendingPublicKey| {
+            let origin = key.origin.unwrap();
+            format!(
+                "[{}{}]{}/0/*",
+                origin.fingerprint.unwrap(),
+                origin.derivation_path.unwrap(),
+                key.xpub.unwrap()
+            )
+        };
+        let expected = format!(
+            "wsh(sortedmulti(2,{},{},{}))",
+            dpub(spending_public_key(1)),
+            dpub(spending_public_key(2)),
+            dpub(spending_public_key(3)),
+        );
+        assert_eq!(
+            keyset.receiving_descriptor(),
+            &miniscript::Descriptor::<DescriptorPublicKey>::from_str(&expected).unwrap()
+        );
+        assert!(keyset
+            .change_descriptor()
+            .to_string()
+            .contains(&format!("{}/1/*", spending_public_key(2).xpub.unwrap())));
+    }
+
+    #[test]
+    fn test_open_payload_with_wrong_key() {
+        let payload = EmergencyAccessKitPayload::from_base58(&seal(&keyset(), &[7u8; 32])).unwrap();
+
+        assert!(matches!(
+            payload.open(unseal([8u8; 32])),
+            Err(EmergencyAccessKitError::Decrypt(_))
+        ));
+        assert!(matches!(
+            payload.open(|_| Err(std::io::Error::other("card removed"))),
+            Err(EmergencyAccessKitError::UnsealKey(_))
+        ));
+    }
+
+    #[test]
+    fn test_open_payload_with_inconsistent_keyset() {
+        let key = [7u8; 32];
+        let open = |keyset| {
+            EmergencyAccessKitPayload::from_base58(&seal(&keyset, &key))
+                .unwrap()
+                .open(unseal(key))
+        };
+
+        let mut other_app_xprv = keyset();
+        other_app_xprv.app_key.as_mut().unwrap().xprv = Some(app_xprv(4));
+        assert!(matches!(
+            open(other_app_xprv),
+            Err(EmergencyAccessKitError::AppKeyMismatch)
+        ));
+
+        let mut wrong_network = keyset();
+        wrong_network.bitcoin_network_type = proto::BitcoinNetworkType::Bitcoin.into();
+        assert!(matches!(
+            open(wrong_network),
+            Err(EmergencyAccessKitError::InvalidKey { .. })
+        ));
+
+        let mut wrong_wildcard = keyset();
+        wrong_wildcard.hardware_key.as_mut().unwrap().wildcard = proto::Wildcard::None.into();
+        assert!(matches!(
+            open(wrong_wildcard),
+            Err(EmergencyAccessKitError::InvalidKey {
+                field: "hardware key",
+                ..
+            })
+        ));
+
+        let mut missing_server_key = keyset();
+        missing_server_key.f8e_key = None;
+        assert!(matches!(
+            open(missing_server_key),
+            Err(EmergencyAccessKitError::MissingField("server key"))
+        ));
+    }
+
+    #[test]
+    fn test_parse_invalid_payload() {
+        assert!(matches!(
+            EmergencyAccessKitPayload::from_base58(""),
+            Err(EmergencyAccessKitError::UnsupportedBackupVersion)
+        ));
+        assert!(matches!(
+            EmergencyAccessKitPayload::from_base58("1111"),
+            Err(EmergencyAccessKitError::InvalidProto(_))
+        ));
+
+        let legacy = proto::Payload {
+            backup: Some(proto::payload::Backup::BackupV1(proto::BackupV1 {
+                hw_encryption_key_ciphertext: Some(SEALED_HW_ENCRYPTION_KEY.to_vec()),
+                sealed_active_spending_keyset: Some(proto::SealedData {
+                    ciphertext: Some(vec![0; 32]),
+                    nonce: Some(vec![0; 12]),
+                    tag: Some(vec![0; 16]),
+                }),
+            })),
+        };
+        let payload = EmergencyAccessKitPayload::from_proto_bytes(&legacy.encode_to_vec()).unwrap();
+        assert!(matches!(
+            payload.open(unseal([7u8; 32])),
+            Err(EmergencyAccessKitError::UnsupportedCipher)
+        ));
+    }
+}
@@ -23,6 +23,14 @@ use x509_parser::prelude::*;
 
 use aws_nitro_enclaves_image_format::utils::eif_reader::EifReader;
 
+mod report;
+
+pub use report::{
+    verify_enclave_report, AttestationReport, CertificateReport, ChallengeReport,
+    CodesigningReport, EnclaveReportParams, InputDigests, PcrReport, StepResult, StepStatus,
+    VerificationReport,
+};
+
 #[derive(Error, Debug)]
 pub enum EnclaveToolsError {
     #[error("I/O error")]
@@ -47,6 +55,13 @@ pub enum EnclaveCodesigningKeyType {
 }
 
 impl EnclaveCodesigningKeyType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            EnclaveCodesigningKeyType::Development => "development",
+            EnclaveCodesigningKeyType::Production => "production",
+        }
+    }
+
     fn root_public_key(&self) -> Vec<u8> {
         match self {
             // Extract from:
@@ -619,8 +634,22 @@ fn verify_codesigning_certificate_chain(
     pcr8: Vec<u8>,
 ) -> Result<CertWrapper, EnclaveToolsError> {
     let certs = collect_der_encoded_certs(cabundle, leaf)?;
+    verify_codesigning_chain(&certs, &key_type)?;
+
+    // At this point, we trust the certificate chain and the leaf.
 
-    let cabundle = certs.cabundle;
+    // Does PCR8 match the hash of the leaf certificate, in both the attestation document and the EIF?
+    match pcr8 == pcr8_hash(&certs.leaf.der) {
+        true => Ok(certs.leaf),
+        false => Err(EnclaveToolsError::CertChainValidationError),
+    }
+}
+
+fn verify_codesigning_chain(
+    certs: &CertificateBundle,
+    key_type: &EnclaveCodesigningKeyType,
+) -> Result<(), EnclaveToolsError> {
+    let cabundle = &certs.cabundle;
     let root = &cabundle[0].cert()?;
 
     verify_directly_issued_by(root, root)?;
@@ -645,15 +674,7 @@ fn verify_codesigning_certificate_chain(
     // Verify the leaf.
     let leaf = certs.leaf.cert()?;
     let issuer = &cabundle[cabundle.len() - 1].cert()?;
-    verify_directly_issued_by(&leaf, issuer)?;
-
-    // At this point, we trust the certificate chain and the leaf.
-
-    // Does PCR8 match the hash of the leaf certificate, in both the attestation document and the EIF?
-    match pcr8 == pcr8_hash(&certs.leaf.der) {
-        true => Ok(certs.leaf),
-        false => Err(EnclaveToolsError::CertChainValidationError),
-    }
+    verify_directly_issued_by(&leaf, issuer)
 }
 
 pub fn verify_enclave(
@@ -734,6 +755,9 @@ pub fn verify_enclave(
 mod tests {
     use super::*;
 
+    /// When the test attestation document was generated, in Unix seconds.
+    const NOW: u64 = 1721236734;
+
     fn get_test_data() -> (
         String,
         Vec<u8>,
@@ -860,4 +884,80 @@ mod tests {
         );
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_verify_enclave_report() {
+        let (eif_path, signed_doc_bytes, codesigning_leaf_der, _, codesigning_cabundle, challenge) =
+            get_test_data();
+
+        let report = verify_enclave_report(EnclaveReportParams {
+            eif_path: &eif_path,
+            signed_attestation_document: signed_doc_bytes,
+            codesigning_leaf_der,
+            codesigning_cabundle,
+            key_type: EnclaveCodesigningKeyType::Development,
+            challenge,
+            max_age_seconds: None,
+            now: NOW,
+        })
+        .unwrap();
+        assert!(report.verified);
+        assert_eq!(report.codesigning.eif_signature.status, StepStatus::Passed);
+        assert_eq!(report.challenge.status.status, StepStatus::Passed);
+        assert!(report.pcrs.iter().all(|pcr| pcr.matches != Some(false)));
+    }
+
+    #[test]
+    fn test_verify_invalid_enclave_report() {
+        let (
+            eif_path,
+            signed_doc_bytes,
+            codesigning_leaf_der,
+            _,
+            mut codesigning_cabundle,
+            challenge,
+        ) = get_test_data();
+
+        codesigning_cabundle[0][0] ^= 1;
+
+        let report = verify_enclave_report(EnclaveReportParams {
+            eif_path: &eif_path,
+            signed_attestation_document: signed_doc_bytes,
+            codesigning_leaf_der,
+            codesigning_cabundle,
+            key_type: EnclaveCodesigningKeyType::Development,
+            challenge,
+            max_age_seconds: None,
+            now: NOW,
+        })
+        .unwrap();
+        assert!(!report.verified);
+        assert_eq!(
+            report.attestation_document.signature.status,
+            StepStatus::Passed
+        );
+        assert_eq!(report.codesigning.chain.status, StepStatus::Failed);
+        assert_eq!(report.codesigning.eif_signature.status, StepStatus::Skipped);
+    }
+
+    #[test]
+    fn test_verify_future_enclave_report() {
+        let (eif_path, signed_doc_bytes, codesigning_leaf_der, _, codesigning_cabundle, challenge) =
+            get_test_data();
+
+        let report = verify_enclave_report(EnclaveReportParams {
+            eif_path: &eif_path,
+            signed_attestation_document: signed_doc_bytes,
+            codesigning_leaf_der,
+            codesigning_cabundle,
+            key_type: EnclaveCodesigningKeyType::Development,
+            challenge,
+            max_age_seconds: None,
+            now: NOW - 3600,
+        })
+        .unwrap();
+        assert!(!report.verified);
+        assert_eq!(report.challenge.age_seconds, -3600);
+        assert_eq!(report.challenge.status.status, StepStatus::Failed);
+    }
 }
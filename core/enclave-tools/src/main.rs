@@ -1,7 +1,11 @@
 use aws_nitro_enclaves_image_format::utils::eif_reader::EifReader;
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
-use clap::{Parser, Subcommand};
-use enclave_tools::{calculate_pcrs, parse_and_verify_signed_attestation_document};
+use clap::{Parser, Subcommand, ValueEnum};
+use enclave_tools::{
+    calculate_pcrs, parse_and_verify_signed_attestation_document, verify_enclave_report,
+    EnclaveCodesigningKeyType, EnclaveReportParams,
+};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 #[derive(Parser, Debug)]
 struct Cli {
@@ -9,6 +13,21 @@ struct Cli {
     cmd: Command,
 }
 
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum KeyType {
+    Development,
+    Production,
+}
+
+impl From<KeyType> for EnclaveCodesigningKeyType {
+    fn from(key_type: KeyType) -> Self {
+        match key_type {
+            KeyType::Development => EnclaveCodesigningKeyType::Development,
+            KeyType::Production => EnclaveCodesigningKeyType::Production,
+        }
+    }
+}
+
 #[derive(Subcommand, Debug)]
 enum Command {
     GetPcr {
@@ -21,6 +40,34 @@ enum Command {
         #[arg(long, required = true, help = "Base64 encoded attestation document")]
         attestation: String,
     },
+    /// Verify an enclave end to end and print a JSON report of every step.
+    Verify {
+        #[arg(long, required = true, help = "Path to EIF file")]
+        eif: String,
+        #[arg(long, required = true, help = "Base64 encoded attestation document")]
+        attestation: String,
+        #[arg(
+            long,
+            required = true,
+            help = "Path to DER encoded codesigning leaf certificate"
+        )]
+        codesigning_leaf: String,
+        #[arg(
+            long,
+            required = true,
+            help = "Path to DER encoded codesigning CA certificate, root first; repeat for each"
+        )]
+        codesigning_cabundle: Vec<String>,
+        #[arg(long, value_enum, default_value_t = KeyType::Development)]
+        key_type: KeyType,
+        #[arg(
+            long,
+            help = "Hex encoded challenge the attestation document must echo"
+        )]
+        challenge: Option<String>,
+        #[arg(long, help = "Maximum age of the attestation document, in seconds")]
+        max_age_secs: Option<u64>,
+    },
 }
 
 fn main() {
@@ -41,5 +88,48 @@ fn main() {
                 .expect("Unable to parse attestation document");
             println!("{}", parsed_doc);
         }
+        Command::Verify {
+            eif,
+            attestation,
+            codesigning_leaf,
+            codesigning_cabundle,
+            key_type,
+            challenge,
+            max_age_secs,
+        } => {
+            let decoded_doc = BASE64
+                .decode(attestation.trim().as_bytes())
+                .expect("Invalid Base64");
+            let codesigning_leaf =
+                std::fs::read(codesigning_leaf).expect("Unable to read codesigning leaf");
+            let codesigning_cabundle = codesigning_cabundle
+                .iter()
+                .map(|path| std::fs::read(path).expect("Unable to read codesigning CA"))
+                .collect();
+            let challenge = challenge.map(|c| hex::decode(c).expect("Invalid hex challenge"));
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .expect("Clock is before the Unix epoch")
+                .as_secs();
+
+            let report = verify_enclave_report(EnclaveReportParams {
+                eif_path: &eif,
+                signed_attestation_document: decoded_doc,
+                codesigning_leaf_der: codesigning_leaf,
+                codesigning_cabundle,
+                key_type: key_type.into(),
+                challenge,
+                max_age_seconds: max_age_secs,
+                now,
+            })
+            .expect("Unable to read verification inputs");
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&report).expect("Unable to serialize report")
+            );
+            if !report.verified {
+                std::process::exit(1);
+            }
+        }
     }
 }
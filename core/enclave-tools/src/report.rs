@@ -0,0 +1,367 @@
+//! A step-by-step account of verifying an enclave, for auditors reproducing a verification
+//! independently. Unlike `verify_enclave`, which stops at the first failure, every step here is
+//! run and recorded so the report shows everything that does (and doesn't) check out.
+
+use std::collections::BTreeMap;
+
+use aws_nitro_enclaves_cose::crypto::Openssl;
+use aws_nitro_enclaves_cose::CoseSign1;
+use aws_nitro_enclaves_image_format::utils::eif_reader::EifReader;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use x509_parser::prelude::*;
+
+use crate::{
+    calculate_pcrs, collect_der_encoded_certs, compare_pcrs, pcr8_hash, verify_codesigning_chain,
+    verify_enclave_signature, AttestationDocument, EnclaveCodesigningKeyType, EnclaveToolsError,
+    NitroEnclaveVerifier,
+};
+
+/// How far in the future an attestation document's timestamp may be before it's rejected.
+const MAX_CLOCK_SKEW_SECONDS: i64 = 60;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StepStatus {
+    Passed,
+    Failed,
+    Skipped,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct StepResult {
+    pub status: StepStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl StepResult {
+    fn passed() -> Self {
+        Self {
+            status: StepStatus::Passed,
+            detail: None,
+        }
+    }
+
+    fn failed(detail: impl Into<String>) -> Self {
+        Self {
+            status: StepStatus::Failed,
+            detail: Some(detail.into()),
+        }
+    }
+
+    fn skipped(detail: impl Into<String>) -> Self {
+        Self {
+            status: StepStatus::Skipped,
+            detail: Some(detail.into()),
+        }
+    }
+
+    fn from_result<T>(result: Result<T, EnclaveToolsError>) -> Self {
+        match result {
+            Ok(_) => Self::passed(),
+            // The error's Display drops the message carried by some variants; keep it.
+            Err(
+                ref e @ (EnclaveToolsError::InternalError(ref message)
+                | EnclaveToolsError::IssuanceError(ref message)),
+            ) => Self::failed(format!("{}: {}", e, message)),
+            Err(e) => Self::failed(e.to_string()),
+        }
+    }
+
+    pub fn is_failed(&self) -> bool {
+        self.status == StepStatus::Failed
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct CertificateReport {
+    pub subject: String,
+    pub issuer: String,
+    pub serial: String,
+    pub not_before: String,
+    pub not_after: String,
+    /// Whether the certificate was within its validity period when the report was generated.
+    pub valid_at_generation: bool,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct AttestationReport {
+    pub module_id: String,
+    pub digest: String,
+    pub timestamp_ms: u64,
+    /// Root first, attestation leaf last.
+    pub certificates: Vec<CertificateReport>,
+    /// The chain up to the AWS Nitro root and the COSE signature over the document.
+    pub signature: StepResult,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct PcrReport {
+    pub index: u8,
+    pub attestation_document: String,
+    /// `None` for PCRs that aren't computed from the EIF.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub eif: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub matches: Option<bool>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct CodesigningReport {
+    pub key_type: &'static str,
+    /// Root first, codesigning leaf last.
+    pub certificates: Vec<CertificateReport>,
+    pub chain: StepResult,
+    /// PCR8 as computed from the codesigning leaf.
+    pub leaf_pcr8: String,
+    /// PCR8 of the attestation document and of the EIF both match `leaf_pcr8`.
+    pub pcr8_match: StepResult,
+    pub eif_signature: StepResult,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ChallengeReport {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expected: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+    /// Seconds between the attestation document timestamp and report generation; negative
+    /// when the document is dated after the report.
+    pub age_seconds: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_age_seconds: Option<u64>,
+    pub status: StepResult,
+}
+
+/// SHA-256 digests of the inputs, so a report can be tied to the exact artifacts it covers.
+#[derive(Clone, Debug, Serialize)]
+pub struct InputDigests {
+    pub eif_sha256: String,
+    pub attestation_document_sha256: String,
+    pub codesigning_leaf_sha256: String,
+    pub codesigning_cabundle_sha256: Vec<String>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct VerificationReport {
+    /// True when no step failed.
+    pub verified: bool,
+    /// Unix seconds.
+    pub generated_at: u64,
+    pub inputs: InputDigests,
+    pub attestation_document: AttestationReport,
+    pub pcrs: Vec<PcrReport>,
+    pub pcr_match: StepResult,
+    pub codesigning: CodesigningReport,
+    pub challenge: ChallengeReport,
+}
+
+/// The inputs to [`verify_enclave_report`].
+pub struct EnclaveReportParams<'a> {
+    pub eif_path: &'a str,
+    pub signed_attestation_document: Vec<u8>,
+    pub codesigning_leaf_der: Vec<u8>,
+    pub codesigning_cabundle: Vec<Vec<u8>>,
+    pub key_type: EnclaveCodesigningKeyType,
+    pub challenge: Option<Vec<u8>>,
+    pub max_age_seconds: Option<u64>,
+    /// Unix seconds.
+    pub now: u64,
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+fn certificate_reports<'a>(
+    ders: impl Iterator<Item = &'a [u8]>,
+    now: u64,
+) -> Result<Vec<CertificateReport>, EnclaveToolsError> {
+    ders.map(|der| {
+        let (_, cert) =
+            X509Certificate::from_der(der).map_err(|_| EnclaveToolsError::ParseError)?;
+        let validity = cert.validity();
+        let now = now as i64;
+        Ok(CertificateReport {
+            subject: cert.subject().to_string(),
+            issuer: cert.issuer().to_string(),
+            serial: cert.raw_serial_as_string(),
+            not_before: validity.not_before.to_string(),
+            not_after: validity.not_after.to_string(),
+            valid_at_generation: validity.not_before.timestamp() <= now
+                && now <= validity.not_after.timestamp(),
+        })
+    })
+    .collect()
+}
+
+/// Runs every check `verify_enclave` does and reports on each one, plus a freshness check that
+/// rejects documents dated more than [`MAX_CLOCK_SKEW_SECONDS`] after `now`.
+///
+/// Only inputs that can't be read or decoded at all are errors; failed checks are recorded
+/// in the report, and `verified` is false.
+pub fn verify_enclave_report(
+    params: EnclaveReportParams,
+) -> Result<VerificationReport, EnclaveToolsError> {
+    let EnclaveReportParams {
+        eif_path,
+        signed_attestation_document,
+        codesigning_leaf_der,
+        codesigning_cabundle,
+        key_type,
+        challenge,
+        max_age_seconds,
+        now,
+    } = params;
+    let inputs = InputDigests {
+        eif_sha256: sha256_hex(&std::fs::read(eif_path)?),
+        attestation_document_sha256: sha256_hex(&signed_attestation_document),
+        codesigning_leaf_sha256: sha256_hex(&codesigning_leaf_der),
+        codesigning_cabundle_sha256: codesigning_cabundle
+            .iter()
+            .map(|der| sha256_hex(der))
+            .collect(),
+    };
+
+    // The attestation document.
+    let cose_doc = CoseSign1::from_bytes(&signed_attestation_document)?;
+    let doc_bytes = cose_doc.get_payload::<Openssl>(None)?;
+    let doc: AttestationDocument =
+        ciborium::de::from_reader(&doc_bytes[..]).map_err(|_| EnclaveToolsError::ParseError)?;
+    let attestation_document = AttestationReport {
+        module_id: doc.module_id.clone(),
+        digest: doc.digest.clone(),
+        timestamp_ms: doc.timestamp,
+        certificates: certificate_reports(
+            doc.cabundle
+                .iter()
+                .chain([&doc.certificate])
+                .map(|der| der.as_slice()),
+            now,
+        )?,
+        signature: StepResult::from_result(doc.verify_aws_nitro_enclave_cert_chain(
+            &cose_doc,
+            &NitroEnclaveVerifier::new().root_public_key,
+        )),
+    };
+
+    // PCRs, as attested and as recomputed from the EIF.
+    let mut eif =
+        EifReader::from_eif(eif_path.to_string()).map_err(EnclaveToolsError::InternalError)?;
+    let eif_pcrs = calculate_pcrs(&mut eif)?;
+    let pcrs = doc
+        .pcrs
+        .iter()
+        .collect::<BTreeMap<_, _>>()
+        .into_iter()
+        .map(|(index, value)| {
+            let attestation_document = hex::encode(value);
+            let eif = eif_pcrs.get(&format!("PCR{}", index)).cloned();
+            let matches = eif.as_ref().map(|eif| *eif == attestation_document);
+            PcrReport {
+                index: *index,
+                attestation_document,
+                eif,
+                matches,
+            }
+        })
+        .collect();
+    let pcr_match = StepResult::from_result(compare_pcrs(&doc, &eif_pcrs));
+
+    // The codesigning chain, and the leaf it vouches for.
+    let leaf_pcr8 = hex::encode(pcr8_hash(&codesigning_leaf_der));
+    let pcr8_match = if doc.pcrs.get(&8).map(hex::encode).as_ref() != Some(&leaf_pcr8) {
+        StepResult::failed("attestation document PCR8 doesn't match the codesigning leaf")
+    } else if eif_pcrs.get("PCR8") != Some(&leaf_pcr8) {
+        StepResult::failed("EIF PCR8 doesn't match the codesigning leaf")
+    } else {
+        StepResult::passed()
+    };
+    let codesigning_certificates = certificate_reports(
+        codesigning_cabundle
+            .iter()
+            .chain([&codesigning_leaf_der])
+            .map(Vec::as_slice),
+        now,
+    )?;
+    let (chain, trusted_leaf) =
+        match collect_der_encoded_certs(codesigning_cabundle, codesigning_leaf_der) {
+            Ok(certs) => match verify_codesigning_chain(&certs, &key_type) {
+                Ok(()) => (StepResult::passed(), Some(certs.leaf)),
+                Err(e) => (StepResult::from_result::<()>(Err(e)), None),
+            },
+            Err(e) => (StepResult::from_result::<()>(Err(e)), None),
+        };
+    let eif_signature = match trusted_leaf {
+        Some(leaf) => match verify_enclave_signature(eif_path, leaf) {
+            Ok(true) => StepResult::passed(),
+            Ok(false) => {
+                StepResult::from_result::<()>(Err(EnclaveToolsError::SignatureVerificationError))
+            }
+            Err(e) => StepResult::from_result::<()>(Err(e)),
+        },
+        None => StepResult::skipped("codesigning chain isn't trusted"),
+    };
+    let codesigning = CodesigningReport {
+        key_type: key_type.name(),
+        certificates: codesigning_certificates,
+        chain,
+        leaf_pcr8,
+        pcr8_match,
+        eif_signature,
+    };
+
+    // Freshness: the document answers our challenge, and is neither from the future nor too
+    // old.
+    let age_seconds = now as i64 - (doc.timestamp / 1000) as i64;
+    let nonce_matches = match (&challenge, &doc.nonce) {
+        (None, _) => None,
+        (Some(expected), Some(nonce)) => Some(expected.as_slice() == nonce.as_slice()),
+        (Some(_), None) => Some(false),
+    };
+    let status = match (nonce_matches, max_age_seconds) {
+        (Some(false), _) => {
+            StepResult::failed("attestation document nonce doesn't match the challenge")
+        }
+        _ if age_seconds < -MAX_CLOCK_SKEW_SECONDS => StepResult::failed(format!(
+            "attestation document is dated {}s in the future, beyond the {}s of clock skew allowed",
+            -age_seconds, MAX_CLOCK_SKEW_SECONDS
+        )),
+        (None, None) => StepResult::skipped("no challenge or maximum age given"),
+        (_, Some(max_age)) if age_seconds > max_age as i64 => StepResult::failed(format!(
+            "attestation document is {}s old, more than the {}s allowed",
+            age_seconds, max_age
+        )),
+        _ => StepResult::passed(),
+    };
+    let challenge = ChallengeReport {
+        expected: challenge.as_ref().map(hex::encode),
+        nonce: doc.nonce.as_ref().map(hex::encode),
+        age_seconds,
+        max_age_seconds,
+        status,
+    };
+
+    let verified = ![
+        &attestation_document.signature,
+        &pcr_match,
+        &codesigning.chain,
+        &codesigning.pcr8_match,
+        &codesigning.eif_signature,
+        &challenge.status,
+    ]
+    .iter()
+    .any(|step| step.is_failed());
+
+    Ok(VerificationReport {
+        verified,
+        generated_at: now,
+        inputs,
+        attestation_document,
+        pcrs,
+        pcr_match,
+        codesigning,
+        challenge,
+    })
+}
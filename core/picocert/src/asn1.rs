@@ -0,0 +1,178 @@
+// DER encodings for interop with standard tooling (openssl asn1parse, openssl ec).
+//
+// Certificates use a picocert-specific ASN.1 wrapper which maps field-for-field onto the
+// native encoding, so conversion is lossless in both directions:
+//
+//   PicocertCertificate ::= SEQUENCE {
+//       version              INTEGER,
+//       issuer               UTF8String,                    -- at most 16 bytes
+//       subject              UTF8String,                    -- at most 16 bytes
+//       validFrom            INTEGER,                       -- seconds since the Unix epoch
+//       validTo              INTEGER,
+//       publicKey            OCTET STRING,                  -- 33-byte compressed secp256k1 point
+//       serial           [0] IMPLICIT INTEGER OPTIONAL,     -- present iff version >= 2
+//       keyUsage         [1] IMPLICIT INTEGER OPTIONAL,     -- present iff version >= 2
+//       signatureAlgorithm   OBJECT IDENTIFIER,             -- ecdsa-with-SHA256
+//       signature            OCTET STRING                   -- 64-byte compact r || s
+//   }
+//
+// PEM-armored as "PICOCERT CERTIFICATE". Private keys use SEC1 ECPrivateKey (RFC 5915) with
+// the secp256k1 named curve, PEM-armored as "EC PRIVATE KEY".
+
+use der::asn1::{ObjectIdentifier, OctetStringRef, Utf8StringRef};
+use der::{Decode, Encode, Header, Reader, Sequence, SliceReader, Tag};
+use sec1::{EcParameters, EcPrivateKey};
+use secp256k1::{PublicKey, SecretKey};
+
+use crate::{copy_with_zeros, Certificate, Error, KeyUsage, MAX_NAME_LEN, VERSION_2};
+
+pub const CERT_PEM_LABEL: &str = "PICOCERT CERTIFICATE";
+pub const KEY_PEM_LABEL: &str = "EC PRIVATE KEY";
+
+const ECDSA_WITH_SHA256: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.10045.4.3.2");
+const SECP256K1: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.132.0.10");
+
+#[derive(Sequence)]
+struct CertificateAsn1<'a> {
+    version: u8,
+    issuer: Utf8StringRef<'a>,
+    subject: Utf8StringRef<'a>,
+    valid_from: u64,
+    valid_to: u64,
+    public_key: OctetStringRef<'a>,
+    #[asn1(context_specific = "0", optional = "true", tag_mode = "IMPLICIT")]
+    serial: Option<u64>,
+    #[asn1(context_specific = "1", optional = "true", tag_mode = "IMPLICIT")]
+    key_usage: Option<u16>,
+    signature_algorithm: ObjectIdentifier,
+    signature: OctetStringRef<'a>,
+}
+
+// Names are NUL-padded; only names that round-trip exactly through a UTF8String are encodable.
+fn name_to_str(name: &[u8; MAX_NAME_LEN], err: Error) -> Result<&str, Error> {
+    let len = name.iter().position(|&c| c == 0).unwrap_or(MAX_NAME_LEN);
+    if name[len..].iter().any(|&c| c != 0) {
+        return Err(err);
+    }
+    std::str::from_utf8(&name[..len]).map_err(|_| err)
+}
+
+fn name_from_str(name: &str, err: Error) -> Result<[u8; MAX_NAME_LEN], Error> {
+    if name.len() > MAX_NAME_LEN || name.as_bytes().contains(&0) {
+        return Err(err);
+    }
+    Ok(copy_with_zeros::<MAX_NAME_LEN>(name.as_bytes()))
+}
+
+pub(crate) fn cert_to_der(cert: &Certificate) -> Result<Vec<u8>, Error> {
+    let is_v2 = cert.version >= VERSION_2;
+    CertificateAsn1 {
+        version: cert.version,
+        issuer: Utf8StringRef::new(name_to_str(&cert.issuer, Error::InvalidIssuer)?)
+            .map_err(|_| Error::InvalidIssuer)?,
+        subject: Utf8StringRef::new(name_to_str(&cert.subject, Error::InvalidSubject)?)
+            .map_err(|_| Error::InvalidSubject)?,
+        valid_from: cert.valid_from,
+        valid_to: cert.valid_to,
+        public_key: OctetStringRef::new(&cert.public_key).map_err(|_| Error::InvalidPublicKey)?,
+        serial: is_v2.then_some(cert.serial),
+        key_usage: is_v2.then_some(cert.key_usage.bits()),
+        signature_algorithm: ECDSA_WITH_SHA256,
+        signature: OctetStringRef::new(&cert.signature).map_err(|_| Error::InvalidSignature)?,
+    }
+    .to_der()
+    .map_err(|_| Error::Encoding)
+}
+
+pub(crate) fn cert_from_der(bytes: &[u8]) -> Result<Certificate, Error> {
+    let asn1 = CertificateAsn1::from_der(bytes).map_err(|_| Error::Encoding)?;
+
+    if asn1.signature_algorithm != ECDSA_WITH_SHA256 {
+        return Err(Error::InvalidSignature);
+    }
+    let is_v2 = asn1.version >= VERSION_2;
+    if is_v2 != (asn1.serial.is_some() && asn1.key_usage.is_some()) {
+        return Err(Error::Version);
+    }
+
+    Ok(Certificate {
+        version: asn1.version,
+        issuer: name_from_str(asn1.issuer.as_str(), Error::InvalidIssuer)?,
+        subject: name_from_str(asn1.subject.as_str(), Error::InvalidSubject)?,
+        valid_from: asn1.valid_from,
+        valid_to: asn1.valid_to,
+        public_key: asn1
+            .public_key
+            .as_bytes()
+            .try_into()
+            .map_err(|_| Error::InvalidPublicKey)?,
+        serial: asn1.serial.unwrap_or(0),
+        key_usage: match asn1.key_usage {
+            Some(bits) => KeyUsage::from_bits(bits).ok_or(Error::KeyUsage)?,
+            None => KeyUsage::NONE,
+        },
+        signature: asn1
+            .signature
+            .as_bytes()
+            .try_into()
+            .map_err(|_| Error::InvalidSignature)?,
+    })
+}
+
+pub(crate) fn private_key_to_der(private_key: &[u8]) -> Result<Vec<u8>, Error> {
+    let secret_key = SecretKey::from_slice(private_key).map_err(|_| Error::InvalidPrivateKey)?;
+    let public_key = PublicKey::from_secret_key_global(&secret_key).serialize_uncompressed();
+    EcPrivateKey {
+        private_key,
+        parameters: Some(EcParameters::NamedCurve(SECP256K1)),
+        public_key: Some(&public_key),
+    }
+    .to_der()
+    .map_err(|_| Error::Encoding)
+}
+
+pub(crate) fn private_key_from_der(bytes: &[u8]) -> Result<Vec<u8>, Error> {
+    let key = EcPrivateKey::from_der(bytes).map_err(|_| Error::Encoding)?;
+
+    if let Some(EcParameters::NamedCurve(curve)) = key.parameters {
+        if curve != SECP256K1 {
+            return Err(Error::InvalidPrivateKey);
+        }
+    }
+    let secret_key =
+        SecretKey::from_slice(key.private_key).map_err(|_| Error::InvalidPrivateKey)?;
+
+    // If the embedded public key is present it must match the private key.
+    if let Some(public_key) = key.public_key {
+        let embedded = PublicKey::from_slice(public_key).map_err(|_| Error::InvalidPublicKey)?;
+        if embedded != PublicKey::from_secret_key_global(&secret_key) {
+            return Err(Error::InvalidPublicKey);
+        }
+    }
+
+    Ok(secret_key.secret_bytes().to_vec())
+}
+
+// Whether `bytes` is exactly one DER SEQUENCE: the tag, a well-formed length, and that
+// many bytes of contents with nothing after them.
+pub(crate) fn is_der_sequence(bytes: &[u8]) -> bool {
+    let Ok(mut reader) = SliceReader::new(bytes) else {
+        return false;
+    };
+    match Header::decode(&mut reader) {
+        Ok(header) => header.tag == Tag::Sequence && reader.remaining_len() == header.length,
+        Err(_) => false,
+    }
+}
+
+pub(crate) fn pem_encode(label: &str, der: &[u8]) -> Result<String, Error> {
+    der::pem::encode_string(label, der::pem::LineEnding::LF, der).map_err(|_| Error::Encoding)
+}
+
+pub(crate) fn pem_decode(label: &str, pem: &[u8]) -> Result<Vec<u8>, Error> {
+    let (actual, der) = der::pem::decode_vec(pem).map_err(|_| Error::Encoding)?;
+    if actual != label {
+        return Err(Error::Encoding);
+    }
+    Ok(der)
+}
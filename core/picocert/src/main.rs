@@ -1,5 +1,9 @@
-use clap::{ArgGroup, Parser, Subcommand};
-use picocert::{issue, validate_cert_chain, Certificate, CertificateWithPrivateKey, Error};
+use clap::{ArgGroup, Parser, Subcommand, ValueEnum};
+use picocert::{
+    decode_private_key, encode_private_key, inspect, issue, issue_v2, reissue,
+    validate_cert_chain_with_revocations, Certificate, CertificateWithPrivateKey, Encoding, Error,
+    KeyUsage, RevocationList,
+};
 use std::fs;
 
 #[derive(Parser, Debug)]
@@ -23,11 +27,99 @@ enum Command {
         subject: String,
         #[arg(long)]
         validity_in_days: u64,
+        // Setting either of these issues a version 2 certificate.
+        #[arg(long, requires = "key_usage")]
+        serial: Option<u64>,
+        #[arg(long, requires = "serial", value_parser = parse_key_usage)]
+        key_usage: Option<KeyUsage>, // e.g. "cert-sign,crl-sign"
     },
     ValidateChain {
         #[arg(long, required = true, value_delimiter = ' ', num_args = 1..)]
         cert_chain: Vec<String>, // List of certificate file paths; root comes LAST
+        #[arg(long, value_delimiter = ' ', num_args = 1..)]
+        revocation_list: Vec<String>,
     },
+    // Issue a revocation list for certificates signed by `issuer`.
+    Revoke {
+        #[arg(long)]
+        issuer: String,
+        #[arg(long)]
+        issuer_key: String,
+        #[arg(long, required = true, value_delimiter = ' ', num_args = 1..)]
+        serials: Vec<u64>,
+        #[arg(long)]
+        out: String,
+    },
+    // Print every field of a certificate. Accepts any supported encoding.
+    Inspect {
+        #[arg(long)]
+        cert: String,
+    },
+    // Re-sign a certificate under a (new) issuer, keeping its subject and public key.
+    Reissue {
+        #[arg(long)]
+        cert: String,
+        #[arg(long)]
+        issuer: String,
+        #[arg(long)]
+        issuer_key: String,
+        // Defaults to the certificate's current validity period.
+        #[arg(long)]
+        validity_in_days: Option<u64>,
+        #[arg(long)]
+        out: String,
+    },
+    // Convert a certificate, or a private key with --key, between encodings.
+    // The input encoding is detected automatically.
+    Convert {
+        #[arg(long)]
+        input: String,
+        #[arg(long)]
+        output: String,
+        #[arg(long, value_enum)]
+        to: Format,
+        #[arg(long)]
+        key: bool,
+    },
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Format {
+    Picocert,
+    Der,
+    Pem,
+}
+
+impl From<Format> for Encoding {
+    fn from(format: Format) -> Self {
+        match format {
+            Format::Picocert => Encoding::Picocert,
+            Format::Der => Encoding::Der,
+            Format::Pem => Encoding::Pem,
+        }
+    }
+}
+
+fn parse_key_usage(s: &str) -> Result<KeyUsage, String> {
+    s.parse().map_err(|_| {
+        format!(
+            "expected a comma-separated list of digital-signature, cert-sign, crl-sign; got '{s}'"
+        )
+    })
+}
+
+fn read_issuer(issuer: &str, issuer_key: &str) -> CertificateWithPrivateKey {
+    let issuer_key_bytes = fs::read(issuer_key).expect("Unable to read issuer key");
+    let issuer_cert = Certificate::from_file(issuer).expect("Unable to read issuer cert");
+    CertificateWithPrivateKey {
+        cert: issuer_cert,
+        private_key: issuer_key_bytes,
+    }
+}
+
+fn read_cert(path: &str) -> Result<Certificate, Error> {
+    let bytes = fs::read(path).map_err(|_| Error::Invalid)?;
+    Certificate::decode(&bytes)
 }
 
 fn main() {
@@ -40,15 +132,11 @@ fn main() {
             issuer_key,
             subject,
             validity_in_days,
+            serial,
+            key_usage,
         } => {
             let issuer_option = if let (Some(issuer), Some(issuer_key)) = (issuer, issuer_key) {
-                let issuer_key_bytes = fs::read(issuer_key).expect("Unable to read issuer key");
-                let issuer_cert =
-                    Certificate::from_file(&issuer).expect("Unable to read issuer cert");
-                Some(CertificateWithPrivateKey {
-                    cert: issuer_cert,
-                    private_key: issuer_key_bytes,
-                })
+                Some(read_issuer(&issuer, &issuer_key))
             } else {
                 None
             };
@@ -62,12 +150,24 @@ fn main() {
             let valid_to = valid_from + validity_in_days * 24 * 60 * 60;
             println!("Valid from: {}\nValid to: {}", valid_from, valid_to);
 
-            match issue(
-                issuer_option.as_ref(),
-                subject.clone(),
-                valid_from,
-                valid_to,
-            ) {
+            let issued = match (serial, key_usage) {
+                (Some(serial), Some(key_usage)) => issue_v2(
+                    issuer_option.as_ref(),
+                    subject.clone(),
+                    valid_from,
+                    valid_to,
+                    serial,
+                    key_usage,
+                ),
+                _ => issue(
+                    issuer_option.as_ref(),
+                    subject.clone(),
+                    valid_from,
+                    valid_to,
+                ),
+            };
+
+            match issued {
                 Ok(issued) => {
                     let cert = issued.cert;
                     let private_key = issued.private_key;
@@ -90,26 +190,117 @@ fn main() {
                 }
             }
         }
-        Command::ValidateChain { cert_chain } => {
+        Command::ValidateChain {
+            cert_chain,
+            revocation_list,
+        } => {
             // Read and parse the certificate chain
             let certificates: Result<Vec<Certificate>, Error> = cert_chain
                 .iter()
                 .map(|path| Certificate::from_file(path))
                 .collect();
 
-            match certificates {
-                Ok(certificates) => match validate_cert_chain(&certificates) {
-                    Ok(_) => {
-                        println!("Certificate chain is valid.");
-                    }
-                    Err(err) => {
-                        println!("Validation failed: {:?}", err);
+            let revocations: Result<Vec<RevocationList>, Error> = revocation_list
+                .iter()
+                .map(|path| RevocationList::from_file(path))
+                .collect();
+
+            match certificates.and_then(|certs| Ok((certs, revocations?))) {
+                Ok((certificates, revocations)) => {
+                    match validate_cert_chain_with_revocations(&certificates, &revocations) {
+                        Ok(_) => {
+                            println!("Certificate chain is valid.");
+                        }
+                        Err(err) => {
+                            println!("Validation failed: {:?}", err);
+                        }
                     }
-                },
+                }
                 Err(err) => {
                     println!("Failed to read certificates: {:?}", err);
                 }
             }
         }
+        Command::Revoke {
+            issuer,
+            issuer_key,
+            serials,
+            out,
+        } => {
+            let issuer = read_issuer(&issuer, &issuer_key);
+            match RevocationList::issue(&issuer, &serials, picocert::current_time()) {
+                Ok(list) => {
+                    fs::write(&out, list.to_bytes()).expect("Unable to write revocation list");
+                    println!(
+                        "Revoked {} serial(s) issued by {}.\nRevocation list: {}",
+                        list.serials().len(),
+                        list.issuer(),
+                        out
+                    );
+                }
+                Err(err) => {
+                    println!("Failed to issue revocation list: {:?}", err);
+                }
+            }
+        }
+        Command::Inspect { cert } => match read_cert(&cert) {
+            Ok(cert) => print!("{}", inspect(&cert)),
+            Err(err) => println!("Failed to read certificate: {:?}", err),
+        },
+        Command::Reissue {
+            cert,
+            issuer,
+            issuer_key,
+            validity_in_days,
+            out,
+        } => {
+            let cert = read_cert(&cert).expect("Unable to read certificate");
+            let issuer = read_issuer(&issuer, &issuer_key);
+            let (valid_from, valid_to) = match validity_in_days {
+                Some(days) => {
+                    let valid_from = picocert::current_time();
+                    (valid_from, valid_from + days * 24 * 60 * 60)
+                }
+                None => (cert.valid_from(), cert.valid_to()),
+            };
+
+            match reissue(&issuer, &cert, valid_from, valid_to) {
+                Ok(reissued) => {
+                    fs::write(&out, reissued.to_bytes()).expect("Unable to write certificate");
+                    println!(
+                        "Certificate for {} reissued by {}.\nCert: {}",
+                        reissued.subject(),
+                        reissued.issuer(),
+                        out
+                    );
+                }
+                Err(err) => {
+                    println!("Failed to reissue certificate: {:?}", err);
+                }
+            }
+        }
+        Command::Convert {
+            input,
+            output,
+            to,
+            key,
+        } => {
+            let bytes = fs::read(&input).expect("Unable to read input");
+            let converted = if key {
+                decode_private_key(&bytes).and_then(|k| encode_private_key(&k, to.into()))
+            } else {
+                Certificate::decode(&bytes).and_then(|c| c.encode(to.into()))
+            };
+
+            match converted {
+                Ok(converted) => {
+                    fs::write(&output, converted).expect("Unable to write output");
+                    println!("Converted {} to {:?}: {}", input, to, output);
+                }
+                Err(err) => {
+                    println!("Failed to convert: {:?}", err);
+                }
+            }
+        }
     }
 }
@@ -0,0 +1,181 @@
+//! Generates the grant envelope test vectors used by firmware:
+//!
+//! ```sh
+//! cargo run -p wsm-grant --example grant_vectors > \
+//!     ../firmware/lib/grant_protocol/inc/grant_envelope_vectors.h
+//! ```
+
+use std::fmt::Write;
+use std::str::FromStr;
+
+use bitcoin::bip32::DerivationPath;
+use bitcoin::secp256k1::{Secp256k1, SecretKey};
+use bitcoin::Network;
+use wsm_grant::grant::{
+    FingerprintReset, FirmwareDowngrade, Grant, GrantKind, KeyExport, PublicKey,
+    TransactionVerification, Wipe, DEVICE_ID_LEN, GRANT_ENVELOPE_SIG_PREFIX,
+};
+
+const WIK_SECRET_KEY: [u8; 32] = [0x42; 32];
+const DEVICE_ID: [u8; DEVICE_ID_LEN] = [0x38, 0x39, 0x8f, 0xff, 0xfe, 0xd0, 0x81, 0xb6];
+const EXPIRES_AT: u64 = 1_700_000_000;
+
+struct Vector {
+    name: &'static str,
+    kind: u8,
+    grant: Vec<u8>,
+    digest: [u8; 32],
+    signature: [u8; 64],
+}
+
+fn vector<K: GrantKind>(
+    name: &'static str,
+    nonce_byte: u8,
+    kind: K,
+    wik_private_key: &SecretKey,
+) -> Vector {
+    let grant = Grant {
+        device_id: DEVICE_ID,
+        nonce: [nonce_byte; 16],
+        expires_at: EXPIRES_AT,
+        kind,
+    };
+    let digest = *grant.signing_message().unwrap().as_ref();
+    let signed = grant.sign(wik_private_key).unwrap();
+    Vector {
+        name,
+        kind: K::ID as u8,
+        grant: signed.grant.serialize().unwrap(),
+        digest,
+        signature: signed.signature.serialize_compact(),
+    }
+}
+
+fn bytes(data: &[u8]) -> String {
+    let lines: Vec<String> = data
+        .chunks(16)
+        .map(|chunk| {
+            let line: Vec<String> = chunk.iter().map(|b| format!("0x{:02x}", b)).collect();
+            format!("  {}", line.join(", "))
+        })
+        .collect();
+    lines.join(",\n")
+}
+
+fn main() {
+    let secp = Secp256k1::new();
+    let wik_private_key = SecretKey::from_slice(&WIK_SECRET_KEY).unwrap();
+    let wik_public_key = PublicKey::from_secret_key(&secp, &wik_private_key);
+    let hw_auth_public_key =
+        PublicKey::from_secret_key(&secp, &SecretKey::from_slice(&[0x11; 32]).unwrap());
+
+    let vectors = [
+        vector("fingerprint_reset", 1, FingerprintReset, &wik_private_key),
+        vector(
+            "transaction_verification",
+            2,
+            TransactionVerification {
+                hw_auth_public_key,
+                commitment: [0xc0; 32],
+                network: Network::Bitcoin,
+            },
+            &wik_private_key,
+        ),
+        vector(
+            "firmware_downgrade",
+            3,
+            FirmwareDowngrade {
+                target_version: 0x0001_0203,
+            },
+            &wik_private_key,
+        ),
+        vector("wipe", 4, Wipe, &wik_private_key),
+        vector(
+            "key_export",
+            5,
+            KeyExport {
+                derivation_path: DerivationPath::from_str("m/84'/0'/0'").unwrap(),
+            },
+            &wik_private_key,
+        ),
+    ];
+
+    let mut out = String::new();
+    writeln!(
+        out,
+        "/* Grant envelope test vectors\n \
+         * Generated by core/wsm-grant/examples/grant_vectors.rs\n \
+         * Auto-generated - DO NOT EDIT MANUALLY\n \
+         */\n\n\
+         #pragma once\n\n\
+         #include <stdint.h>\n\n\
+         #define GRANT_ENVELOPE_SIG_PREFIX \"{}\"\n\
+         #define GRANT_ENVELOPE_EXPIRES_AT {}\n\
+         #define GRANT_ENVELOPE_NUM_VECTORS {}\n\n\
+         typedef struct {{\n  \
+           uint8_t kind;          /* Grant kind id */\n  \
+           const uint8_t* grant;  /* Serialized envelope */\n  \
+           uint32_t grant_len;    /* Envelope length */\n  \
+           uint8_t digest[32];    /* SHA256(prefix || envelope) */\n  \
+           uint8_t signature[64]; /* Compact WIK signature over digest */\n\
+         }} grant_envelope_vector_t;\n",
+        String::from_utf8_lossy(GRANT_ENVELOPE_SIG_PREFIX),
+        EXPIRES_AT,
+        vectors.len(),
+    )
+    .unwrap();
+
+    writeln!(
+        out,
+        "static const uint8_t grant_envelope_wik_pubkey[33] = {{\n{}}};\n",
+        bytes(&wik_public_key.serialize())
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "static const uint8_t grant_envelope_device_id[{}] = {{\n{}}};\n",
+        DEVICE_ID_LEN,
+        bytes(&DEVICE_ID)
+    )
+    .unwrap();
+
+    for vector in &vectors {
+        writeln!(
+            out,
+            "static const uint8_t grant_envelope_{}[{}] = {{\n{}}};\n",
+            vector.name,
+            vector.grant.len(),
+            bytes(&vector.grant)
+        )
+        .unwrap();
+    }
+
+    writeln!(
+        out,
+        "static const grant_envelope_vector_t grant_envelope_vectors[GRANT_ENVELOPE_NUM_VECTORS] = {{"
+    )
+    .unwrap();
+    for vector in &vectors {
+        writeln!(
+            out,
+            "  {{\n    .kind = {},\n    .grant = grant_envelope_{},\n    .grant_len = {},\n    \
+             .digest = {{\n{}    }},\n    .signature = {{\n{}    }},\n  }},",
+            vector.kind,
+            vector.name,
+            vector.grant.len(),
+            indent(&bytes(&vector.digest)),
+            indent(&bytes(&vector.signature)),
+        )
+        .unwrap();
+    }
+    out.push_str("};\n");
+
+    print!("{}", out);
+}
+
+fn indent(lines: &str) -> String {
+    lines
+        .lines()
+        .map(|line| format!("    {}\n", line))
+        .collect()
+}
@@ -0,0 +1,569 @@
+//! A shared envelope for hardware-enforced grants.
+//!
+//! Every grant kind is serialized into the same envelope and signed by the WSM integrity key:
+//!
+//! ```text
+//! version (1) || kind (1) || device_id (8) || nonce (16) || expires_at (8, BE)
+//!     || payload_len (2, BE) || payload
+//! ```
+//!
+//! The signature is over SHA256("BKGrantV2" || envelope). Firmware must check that `device_id`
+//! is its own, that the grant hasn't expired, and that it hasn't seen `nonce` before.
+//!
+//! The fingerprint reset grants in [`crate::fp_reset`] and version 1 transaction verification
+//! approvals in [`crate::tx_verification`] predate this envelope and keep their own formats.
+
+use anyhow::{anyhow, bail, ensure, Context, Error};
+use bitcoin::bip32::{ChildNumber, DerivationPath};
+use bitcoin::hashes::{sha256, Hash};
+use bitcoin::secp256k1::ecdsa::Signature;
+use bitcoin::secp256k1::{Message, Secp256k1, SecretKey};
+use bitcoin::{p2p::Magic, Network};
+
+pub use bitcoin::secp256k1::PublicKey;
+
+pub const GRANT_ENVELOPE_VERSION: u8 = 2;
+pub const GRANT_ENVELOPE_SIG_PREFIX: &[u8] = b"BKGrantV2";
+pub const DEVICE_ID_LEN: usize = 8;
+pub const NONCE_LEN: usize = 16;
+
+const HEADER_LEN: usize = 1 + 1 + DEVICE_ID_LEN + NONCE_LEN + 8 + 2;
+const SIGNATURE_LEN: usize = 64;
+
+/// The registry of grant kinds. Values are part of the wire format and must never be reused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum GrantKindId {
+    FingerprintReset = 1,
+    TransactionVerification = 2,
+    FirmwareDowngrade = 3,
+    Wipe = 4,
+    KeyExport = 5,
+}
+
+impl GrantKindId {
+    pub const ALL: &'static [GrantKindId] = &[
+        GrantKindId::FingerprintReset,
+        GrantKindId::TransactionVerification,
+        GrantKindId::FirmwareDowngrade,
+        GrantKindId::Wipe,
+        GrantKindId::KeyExport,
+    ];
+
+    /// Reads the kind of a serialized grant without parsing the rest, so callers can dispatch to
+    /// the right [`Grant::deserialize`].
+    pub fn of(serialized_grant: &[u8]) -> Result<Self, Error> {
+        ensure!(
+            serialized_grant.len() >= 2,
+            "Grant too short: {} bytes",
+            serialized_grant.len()
+        );
+        check_version(serialized_grant[0])?;
+        Self::try_from(serialized_grant[1])
+    }
+}
+
+impl TryFrom<u8> for GrantKindId {
+    type Error = Error;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        Self::ALL
+            .iter()
+            .find(|kind| **kind as u8 == value)
+            .copied()
+            .ok_or_else(|| anyhow!("Unknown grant kind: {}", value))
+    }
+}
+
+/// The action a grant authorizes, and its kind-specific parameters.
+pub trait GrantKind: Sized {
+    const ID: GrantKindId;
+
+    fn serialize_payload(&self) -> Result<Vec<u8>, Error>;
+    fn deserialize_payload(payload: &[u8]) -> Result<Self, Error>;
+}
+
+/// Authorizes re-enrolling fingerprints.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FingerprintReset;
+
+impl GrantKind for FingerprintReset {
+    const ID: GrantKindId = GrantKindId::FingerprintReset;
+
+    fn serialize_payload(&self) -> Result<Vec<u8>, Error> {
+        Ok(Vec::new())
+    }
+
+    fn deserialize_payload(payload: &[u8]) -> Result<Self, Error> {
+        ensure!(payload.is_empty(), "Unexpected fingerprint reset payload");
+        Ok(Self)
+    }
+}
+
+/// Authorizes signing a transaction on `network`, identified by its chained sighash commitment
+/// (see [`crate::tx_verification::calculate_chained_sighashes`]). Serialized as the hardware auth
+/// key, the commitment, and the network magic.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransactionVerification {
+    pub hw_auth_public_key: PublicKey,
+    pub commitment: [u8; 32],
+    pub network: Network,
+}
+
+impl GrantKind for TransactionVerification {
+    const ID: GrantKindId = GrantKindId::TransactionVerification;
+
+    fn serialize_payload(&self) -> Result<Vec<u8>, Error> {
+        let mut payload = Vec::with_capacity(33 + 32 + 4);
+        payload.extend_from_slice(&self.hw_auth_public_key.serialize());
+        payload.extend_from_slice(&self.commitment);
+        payload.extend_from_slice(&self.network.magic().to_bytes());
+        Ok(payload)
+    }
+
+    fn deserialize_payload(payload: &[u8]) -> Result<Self, Error> {
+        ensure!(
+            payload.len() == 33 + 32 + 4,
+            "Invalid transaction verification payload length: {}",
+            payload.len()
+        );
+        let magic = Magic::from_bytes(payload[65..].try_into()?);
+        Ok(Self {
+            hw_auth_public_key: PublicKey::from_slice(&payload[..33])
+                .context("Invalid hardware auth public key")?,
+            commitment: payload[33..65].try_into()?,
+            network: Network::from_magic(magic)
+                .ok_or_else(|| anyhow!("Unknown network magic: {}", magic))?,
+        })
+    }
+}
+
+/// Authorizes installing firmware older than what's currently running.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FirmwareDowngrade {
+    pub target_version: u32,
+}
+
+impl GrantKind for FirmwareDowngrade {
+    const ID: GrantKindId = GrantKindId::FirmwareDowngrade;
+
+    fn serialize_payload(&self) -> Result<Vec<u8>, Error> {
+        Ok(self.target_version.to_be_bytes().to_vec())
+    }
+
+    fn deserialize_payload(payload: &[u8]) -> Result<Self, Error> {
+        let target_version = payload.try_into().map_err(|_| {
+            anyhow!(
+                "Invalid firmware downgrade payload length: {}",
+                payload.len()
+            )
+        })?;
+        Ok(Self {
+            target_version: u32::from_be_bytes(target_version),
+        })
+    }
+}
+
+/// Authorizes wiping the device.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Wipe;
+
+impl GrantKind for Wipe {
+    const ID: GrantKindId = GrantKindId::Wipe;
+
+    fn serialize_payload(&self) -> Result<Vec<u8>, Error> {
+        Ok(Vec::new())
+    }
+
+    fn deserialize_payload(payload: &[u8]) -> Result<Self, Error> {
+        ensure!(payload.is_empty(), "Unexpected wipe payload");
+        Ok(Self)
+    }
+}
+
+/// Authorizes exporting the key at `derivation_path`. Serialized as a one byte count followed
+/// by each child number as a big-endian u32, hardened bit included.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyExport {
+    pub derivation_path: DerivationPath,
+}
+
+impl GrantKind for KeyExport {
+    const ID: GrantKindId = GrantKindId::KeyExport;
+
+    fn serialize_payload(&self) -> Result<Vec<u8>, Error> {
+        let path: Vec<ChildNumber> = self.derivation_path.clone().into();
+        let count = u8::try_from(path.len())
+            .map_err(|_| anyhow!("Derivation path too long to export: {}", path.len()))?;
+        let mut payload = Vec::with_capacity(1 + 4 * path.len());
+        payload.push(count);
+        for child in path {
+            payload.extend_from_slice(&u32::from(child).to_be_bytes());
+        }
+        Ok(payload)
+    }
+
+    fn deserialize_payload(payload: &[u8]) -> Result<Self, Error> {
+        let (count, children) = payload
+            .split_first()
+            .ok_or_else(|| anyhow!("Empty key export payload"))?;
+        ensure!(
+            children.len() == 4 * *count as usize,
+            "Invalid key export payload length: {}",
+            payload.len()
+        );
+        let path: Vec<ChildNumber> = children
+            .chunks_exact(4)
+            .map(|child| ChildNumber::from(u32::from_be_bytes(child.try_into().unwrap())))
+            .collect();
+        Ok(Self {
+            derivation_path: path.into(),
+        })
+    }
+}
+
+/// A grant of kind `K`, bound to one device and valid until `expires_at` (seconds since the Unix
+/// epoch).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Grant<K: GrantKind> {
+    pub device_id: [u8; DEVICE_ID_LEN],
+    pub nonce: [u8; NONCE_LEN],
+    pub expires_at: u64,
+    pub kind: K,
+}
+
+impl<K: GrantKind> Grant<K> {
+    pub fn serialize(&self) -> Result<Vec<u8>, Error> {
+        let payload = self.kind.serialize_payload()?;
+        let payload_len = u16::try_from(payload.len())
+            .map_err(|_| anyhow!("Grant payload too long: {} bytes", payload.len()))?;
+        let mut data = Vec::with_capacity(HEADER_LEN + payload.len());
+        data.push(GRANT_ENVELOPE_VERSION);
+        data.push(K::ID as u8);
+        data.extend_from_slice(&self.device_id);
+        data.extend_from_slice(&self.nonce);
+        data.extend_from_slice(&self.expires_at.to_be_bytes());
+        data.extend_from_slice(&payload_len.to_be_bytes());
+        data.extend_from_slice(&payload);
+        Ok(data)
+    }
+
+    pub fn deserialize(data: &[u8]) -> Result<Self, Error> {
+        ensure!(
+            data.len() >= HEADER_LEN,
+            "Grant too short: {} bytes",
+            data.len()
+        );
+        let kind = GrantKindId::of(data)?;
+        if kind != K::ID {
+            bail!("Unexpected grant kind: {:?} (expected {:?})", kind, K::ID);
+        }
+
+        let (device_id, rest) = data[2..].split_at(DEVICE_ID_LEN);
+        let (nonce, rest) = rest.split_at(NONCE_LEN);
+        let (expires_at, rest) = rest.split_at(8);
+        let (payload_len, payload) = rest.split_at(2);
+        let payload_len = u16::from_be_bytes(payload_len.try_into()?) as usize;
+        ensure!(
+            payload.len() == payload_len,
+            "Invalid grant payload length: {} (expected {})",
+            payload.len(),
+            payload_len
+        );
+
+        Ok(Self {
+            device_id: device_id.try_into()?,
+            nonce: nonce.try_into()?,
+            expires_at: u64::from_be_bytes(expires_at.try_into()?),
+            kind: K::deserialize_payload(payload)?,
+        })
+    }
+
+    /// The digest the WSM integrity key signs: SHA256("BKGrantV2" || envelope).
+    pub fn signing_message(&self) -> Result<Message, Error> {
+        let mut data = GRANT_ENVELOPE_SIG_PREFIX.to_vec();
+        data.extend_from_slice(&self.serialize()?);
+        Ok(Message::from_digest(
+            sha256::Hash::hash(&data).to_byte_array(),
+        ))
+    }
+
+    pub fn sign(self, wik_private_key: &SecretKey) -> Result<SignedGrant<K>, Error> {
+        let secp = Secp256k1::signing_only();
+        let signature = secp.sign_ecdsa(&self.signing_message()?, wik_private_key);
+        Ok(SignedGrant {
+            grant: self,
+            signature,
+        })
+    }
+}
+
+/// A grant and the WSM integrity key's signature over it. Serialized as the envelope followed by
+/// the 64-byte compact signature.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignedGrant<K: GrantKind> {
+    pub grant: Grant<K>,
+    pub signature: Signature,
+}
+
+impl<K: GrantKind> SignedGrant<K> {
+    pub fn serialize(&self) -> Result<Vec<u8>, Error> {
+        let mut data = self.grant.serialize()?;
+        data.extend_from_slice(&self.signature.serialize_compact());
+        Ok(data)
+    }
+
+    pub fn deserialize(data: &[u8]) -> Result<Self, Error> {
+        ensure!(
+            data.len() >= SIGNATURE_LEN,
+            "Signed grant too short: {} bytes",
+            data.len()
+        );
+        let (grant, signature) = data.split_at(data.len() - SIGNATURE_LEN);
+        Ok(Self {
+            grant: Grant::deserialize(grant)?,
+            signature: Signature::from_compact(signature).context("Invalid grant signature")?,
+        })
+    }
+
+    /// Checks everything about the grant except nonce reuse, which only the device can track.
+    pub fn verify(
+        &self,
+        wik_public_key: &PublicKey,
+        device_id: &[u8; DEVICE_ID_LEN],
+        now: u64,
+    ) -> Result<(), Error> {
+        if self.grant.device_id != *device_id {
+            bail!("Grant is for a different device");
+        }
+        if now >= self.grant.expires_at {
+            bail!("Grant expired at {}", self.grant.expires_at);
+        }
+
+        let secp = Secp256k1::verification_only();
+        secp.verify_ecdsa(
+            &self.grant.signing_message()?,
+            &self.signature,
+            wik_public_key,
+        )
+        .context("Failed to verify ECDSA signature")?;
+
+        Ok(())
+    }
+}
+
+/// The device id for hardware known only by its attested serial: the first 8 bytes of
+/// SHA256(serial). Firmware derives the same id from its own serial.
+pub fn device_id_from_serial(serial: &str) -> [u8; DEVICE_ID_LEN] {
+    let hash = sha256::Hash::hash(serial.as_bytes()).to_byte_array();
+    let mut device_id = [0; DEVICE_ID_LEN];
+    device_id.copy_from_slice(&hash[..DEVICE_ID_LEN]);
+    device_id
+}
+
+fn check_version(version: u8) -> Result<(), Error> {
+    if version != GRANT_ENVELOPE_VERSION {
+        bail!(
+            "Invalid grant version: {} (expected {})",
+            version,
+            GRANT_ENVELOPE_VERSION
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt::Debug;
+    use std::str::FromStr;
+
+    use super::*;
+
+    const DEVICE_ID: [u8; DEVICE_ID_LEN] = [0x38, 0x39, 0x8f, 0xff, 0xfe, 0xd0, 0x81, 0xb6];
+
+    fn grant<K: GrantKind>(kind: K) -> Grant<K> {
+        Grant {
+            device_id: DEVICE_ID,
+            nonce: [0x5a; NONCE_LEN],
+            expires_at: 1_700_000_000,
+            kind,
+        }
+    }
+
+    fn wik() -> (SecretKey, PublicKey) {
+        let secret_key = SecretKey::from_slice(&[0x42; 32]).unwrap();
+        let public_key = PublicKey::from_secret_key(&Secp256k1::new(), &secret_key);
+        (secret_key, public_key)
+    }
+
+    fn assert_round_trips<K: GrantKind + Debug + PartialEq>(kind: K) {
+        let grant = grant(kind);
+        let serialized = grant.serialize().unwrap();
+        assert_eq!(GrantKindId::of(&serialized).unwrap(), K::ID);
+        assert_eq!(Grant::<K>::deserialize(&serialized).unwrap(), grant);
+    }
+
+    #[test]
+    fn test_round_trip_all_kinds() {
+        let secp = Secp256k1::new();
+        let (_, hw_auth_public_key) = secp.generate_keypair(&mut rand::thread_rng());
+
+        assert_round_trips(FingerprintReset);
+        for network in [
+            Network::Bitcoin,
+            Network::Testnet,
+            Network::Signet,
+            Network::Regtest,
+        ] {
+            assert_round_trips(TransactionVerification {
+                hw_auth_public_key,
+                commitment: [7; 32],
+                network,
+            });
+        }
+        assert_round_trips(FirmwareDowngrade {
+            target_version: 0x0102_0304,
+        });
+        assert_round_trips(Wipe);
+        assert_round_trips(KeyExport {
+            derivation_path: DerivationPath::from_str("m/84'/0'/0'/0/1").unwrap(),
+        });
+    }
+
+    #[test]
+    fn test_key_export_rejects_overlong_path() {
+        let longest: DerivationPath = vec![ChildNumber::from(1); u8::MAX as usize].into();
+        assert_round_trips(KeyExport {
+            derivation_path: longest,
+        });
+
+        let too_long: DerivationPath = vec![ChildNumber::from(1); u8::MAX as usize + 1].into();
+        assert!(grant(KeyExport {
+            derivation_path: too_long
+        })
+        .serialize()
+        .is_err());
+    }
+
+    #[test]
+    fn test_serialization() {
+        let serialized = grant(FirmwareDowngrade { target_version: 9 })
+            .serialize()
+            .unwrap();
+        assert_eq!(
+            hex::encode(serialized),
+            concat!(
+                "02",                               // version
+                "03",                               // kind
+                "38398ffffed081b6",                 // device_id
+                "5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a", // nonce
+                "000000006553f100",                 // expires_at
+                "0004",                             // payload_len
+                "00000009",                         // target_version
+            )
+        );
+    }
+
+    #[test]
+    fn test_transaction_verification_rejects_unknown_network() {
+        let secp = Secp256k1::new();
+        let (_, hw_auth_public_key) = secp.generate_keypair(&mut rand::thread_rng());
+        let mut serialized = grant(TransactionVerification {
+            hw_auth_public_key,
+            commitment: [7; 32],
+            network: Network::Bitcoin,
+        })
+        .serialize()
+        .unwrap();
+        let len = serialized.len();
+        serialized[len - 4..].copy_from_slice(&[0xde, 0xad, 0xbe, 0xef]);
+        assert!(Grant::<TransactionVerification>::deserialize(&serialized).is_err());
+    }
+
+    #[test]
+    fn test_device_id_from_serial() {
+        assert_eq!(
+            device_id_from_serial("000WS27100000000"),
+            device_id_from_serial("000WS27100000000")
+        );
+        assert_ne!(
+            device_id_from_serial("000WS27100000000"),
+            device_id_from_serial("000WS27100000001")
+        );
+    }
+
+    #[test]
+    fn test_kind_registry() {
+        for kind in GrantKindId::ALL {
+            assert_eq!(GrantKindId::try_from(*kind as u8).unwrap(), *kind);
+        }
+        assert!(GrantKindId::try_from(0).is_err());
+        assert!(GrantKindId::try_from(0xff).is_err());
+    }
+
+    #[test]
+    fn test_deserialize_rejects_malformed() {
+        let serialized = grant(Wipe).serialize().unwrap();
+
+        // Wrong kind
+        assert!(Grant::<FingerprintReset>::deserialize(&serialized).is_err());
+
+        // Wrong version
+        let mut wrong_version = serialized.clone();
+        wrong_version[0] = 1;
+        assert!(Grant::<Wipe>::deserialize(&wrong_version).is_err());
+
+        // Truncated and trailing data
+        assert!(Grant::<Wipe>::deserialize(&serialized[..serialized.len() - 1]).is_err());
+        let mut trailing = serialized.clone();
+        trailing.push(0);
+        assert!(Grant::<Wipe>::deserialize(&trailing).is_err());
+
+        // Payload on a kind that takes none
+        let mut payload = grant(FirmwareDowngrade { target_version: 1 })
+            .serialize()
+            .unwrap();
+        payload[1] = GrantKindId::Wipe as u8;
+        assert!(Grant::<Wipe>::deserialize(&payload).is_err());
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        let (wik_private_key, wik_public_key) = wik();
+        let signed = grant(FirmwareDowngrade { target_version: 3 })
+            .sign(&wik_private_key)
+            .unwrap();
+        let serialized = signed.serialize().unwrap();
+
+        let parsed = SignedGrant::<FirmwareDowngrade>::deserialize(&serialized).unwrap();
+        assert_eq!(parsed, signed);
+        assert!(parsed
+            .verify(&wik_public_key, &DEVICE_ID, 1_600_000_000)
+            .is_ok());
+
+        // Expired
+        assert!(parsed
+            .verify(&wik_public_key, &DEVICE_ID, 1_700_000_000)
+            .is_err());
+
+        // Another device
+        assert!(parsed
+            .verify(&wik_public_key, &[0; DEVICE_ID_LEN], 1_600_000_000)
+            .is_err());
+
+        // Wrong key
+        let secp = Secp256k1::new();
+        let (_, other_public_key) = secp.generate_keypair(&mut rand::thread_rng());
+        assert!(parsed
+            .verify(&other_public_key, &DEVICE_ID, 1_600_000_000)
+            .is_err());
+
+        // Tampered payload
+        let mut tampered = serialized.clone();
+        tampered[HEADER_LEN + 3] ^= 1;
+        let tampered = SignedGrant::<FirmwareDowngrade>::deserialize(&tampered).unwrap();
+        assert!(tampered
+            .verify(&wik_public_key, &DEVICE_ID, 1_600_000_000)
+            .is_err());
+    }
+}
@@ -1,2 +1,3 @@
 pub mod fp_reset;
+pub mod grant;
 pub mod tx_verification;
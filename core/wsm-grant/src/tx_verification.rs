@@ -1,12 +1,29 @@
-use anyhow::{anyhow, Context, Error};
+use anyhow::{anyhow, bail, ensure, Context, Error};
 use bitcoin::hashes::{sha256, Hash, HashEngine};
 use bitcoin::psbt::Input;
-use bitcoin::secp256k1::Message;
+use bitcoin::secp256k1::ecdsa::Signature;
+use bitcoin::secp256k1::{Message, Secp256k1, SecretKey};
+use bitcoin::Network;
+
+use crate::grant::{
+    device_id_from_serial, Grant, SignedGrant, TransactionVerification, GRANT_ENVELOPE_VERSION,
+    NONCE_LEN,
+};
 
 pub use bitcoin::secp256k1::PublicKey;
 
 pub const GRANT_REQUEST_SIG_PREFIX: &[u8] = b"BKGrantReq";
 
+/// Version of "TVA1" approvals. They shipped with version 0 on the wire, and are kept that way.
+pub const TX_VERIFICATION_V1: u8 = 0;
+/// Version of approvals carried in a [`crate::grant`] envelope, which expire and are bound to a
+/// device and network.
+pub const TX_VERIFICATION_V2: u8 = GRANT_ENVELOPE_VERSION;
+
+/// V1 approvals can't expire or be tied to a device, so they're only accepted until this time
+/// (2027-04-01T00:00:00Z) to give clients time to migrate.
+pub const TX_VERIFICATION_V1_ACCEPTED_UNTIL: u64 = 1_806_537_600;
+
 fn verify_inputs_only_have_one_signature(inputs: &[Input]) -> Result<(), Error> {
     for input in inputs.iter() {
         if input.partial_sigs.len() != 1 {
@@ -97,6 +114,167 @@ pub fn generate_message(
     Ok(message)
 }
 
+/// A transaction verification approval, as signed by the WSM integrity key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TxVerificationApproval {
+    /// Signed over the "TVA1" message from [`generate_message`].
+    V1 {
+        hw_auth_public_key: PublicKey,
+        commitment: Vec<u8>,
+    },
+    /// A grant envelope, bound to one device and network and expiring.
+    V2(Grant<TransactionVerification>),
+}
+
+/// What the party checking an approval expects it to be bound to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TxVerificationExpectations<'a> {
+    pub hardware_serial: &'a str,
+    pub network: Network,
+    /// Seconds since the Unix epoch.
+    pub now: u64,
+    pub v1_accepted_until: u64,
+}
+
+impl TxVerificationApproval {
+    /// Builds a v2 approval for the hardware with the attested `hardware_serial`, valid until
+    /// `expires_at` (seconds since the Unix epoch).
+    pub fn v2(
+        hw_auth_public_key: PublicKey,
+        commitment: &[u8],
+        hardware_serial: &str,
+        network: Network,
+        nonce: [u8; NONCE_LEN],
+        expires_at: u64,
+    ) -> Result<Self, Error> {
+        Ok(Self::V2(Grant {
+            device_id: device_id_from_serial(hardware_serial),
+            nonce,
+            expires_at,
+            kind: TransactionVerification {
+                hw_auth_public_key,
+                commitment: commitment
+                    .try_into()
+                    .map_err(|_| anyhow!("Invalid commitment length: {}", commitment.len()))?,
+                network,
+            },
+        }))
+    }
+
+    /// Reassembles an approval from the fields of a transaction verification grant. A v2 grant
+    /// carries its serialized envelope, which must agree with the grant's key and commitment.
+    pub fn from_parts(
+        version: u8,
+        hw_auth_public_key: PublicKey,
+        commitment: &[u8],
+        envelope: Option<&[u8]>,
+    ) -> Result<Self, Error> {
+        match (version, envelope) {
+            (TX_VERIFICATION_V1, None) => Ok(Self::V1 {
+                hw_auth_public_key,
+                commitment: commitment.to_vec(),
+            }),
+            (TX_VERIFICATION_V2, Some(envelope)) => {
+                let grant = Grant::<TransactionVerification>::deserialize(envelope)?;
+                ensure!(
+                    grant.kind.hw_auth_public_key == hw_auth_public_key
+                        && grant.kind.commitment[..] == *commitment,
+                    "Envelope doesn't match the grant"
+                );
+                Ok(Self::V2(grant))
+            }
+            (TX_VERIFICATION_V2, None) => bail!("Version 2 grant has no envelope"),
+            (TX_VERIFICATION_V1, Some(_)) => bail!("Version 1 grant has an envelope"),
+            (version, _) => bail!("Unknown transaction verification version: {}", version),
+        }
+    }
+
+    pub fn version(&self) -> u8 {
+        match self {
+            Self::V1 { .. } => TX_VERIFICATION_V1,
+            Self::V2(_) => TX_VERIFICATION_V2,
+        }
+    }
+
+    pub fn hw_auth_public_key(&self) -> PublicKey {
+        match self {
+            Self::V1 {
+                hw_auth_public_key, ..
+            } => *hw_auth_public_key,
+            Self::V2(grant) => grant.kind.hw_auth_public_key,
+        }
+    }
+
+    pub fn commitment(&self) -> Vec<u8> {
+        match self {
+            Self::V1 { commitment, .. } => commitment.clone(),
+            Self::V2(grant) => grant.kind.commitment.to_vec(),
+        }
+    }
+
+    /// The serialized grant envelope, for v2 approvals.
+    pub fn envelope(&self) -> Result<Option<Vec<u8>>, Error> {
+        match self {
+            Self::V1 { .. } => Ok(None),
+            Self::V2(grant) => grant.serialize().map(Some),
+        }
+    }
+
+    pub fn message(&self) -> Result<Message, Error> {
+        match self {
+            Self::V1 {
+                hw_auth_public_key,
+                commitment,
+            } => generate_message(*hw_auth_public_key, commitment.clone()),
+            Self::V2(grant) => grant.signing_message(),
+        }
+    }
+
+    pub fn sign(&self, wik_private_key: &SecretKey) -> Result<Signature, Error> {
+        let secp = Secp256k1::signing_only();
+        Ok(secp.sign_ecdsa(&self.message()?, wik_private_key))
+    }
+
+    /// Checks the WSM integrity key's signature and, for v2, that the approval is for the
+    /// expected hardware and network and hasn't expired. V1 approvals are accepted only until
+    /// `expected.v1_accepted_until`.
+    pub fn verify(
+        &self,
+        signature: &Signature,
+        wik_public_key: &PublicKey,
+        expected: &TxVerificationExpectations,
+    ) -> Result<(), Error> {
+        match self {
+            Self::V1 { .. } => {
+                if expected.now >= expected.v1_accepted_until {
+                    bail!("Version 1 transaction verification approvals are no longer accepted");
+                }
+
+                let secp = Secp256k1::verification_only();
+                secp.verify_ecdsa(&self.message()?, signature, wik_public_key)
+                    .context("Failed to verify ECDSA signature")?;
+
+                Ok(())
+            }
+            Self::V2(grant) => {
+                if grant.kind.network != expected.network {
+                    bail!("Approval is for {}", grant.kind.network);
+                }
+
+                SignedGrant {
+                    grant: grant.clone(),
+                    signature: *signature,
+                }
+                .verify(
+                    wik_public_key,
+                    &device_id_from_serial(expected.hardware_serial),
+                    expected.now,
+                )
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::collections::BTreeMap;
@@ -298,4 +476,193 @@ mod tests {
         let message = generate_message(hw_auth_pub_key, commitment).unwrap();
         assert_eq!(message.as_ref().len(), 32);
     }
+
+    const SERIAL: &str = "000WS27100000000";
+
+    fn v2_approval(hw_auth_pub_key: PublicKey) -> TxVerificationApproval {
+        TxVerificationApproval::v2(
+            hw_auth_pub_key,
+            &[7; 32],
+            SERIAL,
+            Network::Bitcoin,
+            [0x5a; NONCE_LEN],
+            1_700_000_000,
+        )
+        .unwrap()
+    }
+
+    fn expectations() -> TxVerificationExpectations<'static> {
+        TxVerificationExpectations {
+            hardware_serial: SERIAL,
+            network: Network::Bitcoin,
+            now: 1_600_000_000,
+            v1_accepted_until: TX_VERIFICATION_V1_ACCEPTED_UNTIL,
+        }
+    }
+
+    #[test]
+    fn test_v2_approval_is_a_grant_envelope() {
+        let secp = Secp256k1::new();
+        let (_, hw_auth_pub_key) = secp.generate_keypair(&mut rand::thread_rng());
+        let approval = v2_approval(hw_auth_pub_key);
+        assert_eq!(approval.version(), TX_VERIFICATION_V2);
+
+        let envelope = approval.envelope().unwrap().unwrap();
+        let grant = Grant::<TransactionVerification>::deserialize(&envelope).unwrap();
+        assert_eq!(grant.device_id, device_id_from_serial(SERIAL));
+        assert_eq!(grant.kind.network, Network::Bitcoin);
+        assert_eq!(
+            approval.message().unwrap(),
+            grant.signing_message().unwrap()
+        );
+
+        // v2 never collides with v1
+        assert_ne!(
+            approval.message().unwrap(),
+            generate_message(hw_auth_pub_key, vec![7; 32]).unwrap()
+        );
+
+        assert!(TxVerificationApproval::v2(
+            hw_auth_pub_key,
+            &[7; 31],
+            SERIAL,
+            Network::Bitcoin,
+            [0; NONCE_LEN],
+            1_700_000_000,
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_from_parts() {
+        let secp = Secp256k1::new();
+        let mut rng = rand::thread_rng();
+        let (_, hw_auth_pub_key) = secp.generate_keypair(&mut rng);
+        let (_, other_pub_key) = secp.generate_keypair(&mut rng);
+        let approval = v2_approval(hw_auth_pub_key);
+        let envelope = approval.envelope().unwrap().unwrap();
+
+        assert_eq!(
+            TxVerificationApproval::from_parts(
+                TX_VERIFICATION_V2,
+                hw_auth_pub_key,
+                &[7; 32],
+                Some(&envelope)
+            )
+            .unwrap(),
+            approval
+        );
+        assert_eq!(
+            TxVerificationApproval::from_parts(TX_VERIFICATION_V1, hw_auth_pub_key, &[7; 32], None)
+                .unwrap()
+                .version(),
+            TX_VERIFICATION_V1
+        );
+
+        // The envelope must agree with the grant it came with
+        assert!(TxVerificationApproval::from_parts(
+            TX_VERIFICATION_V2,
+            other_pub_key,
+            &[7; 32],
+            Some(&envelope)
+        )
+        .is_err());
+        assert!(TxVerificationApproval::from_parts(
+            TX_VERIFICATION_V2,
+            hw_auth_pub_key,
+            &[8; 32],
+            Some(&envelope)
+        )
+        .is_err());
+
+        // Versions and envelopes must line up
+        assert!(TxVerificationApproval::from_parts(
+            TX_VERIFICATION_V2,
+            hw_auth_pub_key,
+            &[7; 32],
+            None
+        )
+        .is_err());
+        assert!(TxVerificationApproval::from_parts(
+            TX_VERIFICATION_V1,
+            hw_auth_pub_key,
+            &[7; 32],
+            Some(&envelope)
+        )
+        .is_err());
+        assert!(TxVerificationApproval::from_parts(1, hw_auth_pub_key, &[7; 32], None).is_err());
+    }
+
+    #[test]
+    fn test_verify_v2_approval() {
+        let secp = Secp256k1::new();
+        let mut rng = rand::thread_rng();
+        let (wik_priv_key, wik_pub_key) = secp.generate_keypair(&mut rng);
+        let (_, hw_auth_pub_key) = secp.generate_keypair(&mut rng);
+
+        let approval = v2_approval(hw_auth_pub_key);
+        let signature = approval.sign(&wik_priv_key).unwrap();
+        assert!(approval
+            .verify(&signature, &wik_pub_key, &expectations())
+            .is_ok());
+
+        let wrong_serial = TxVerificationExpectations {
+            hardware_serial: "000WS27100000001",
+            ..expectations()
+        };
+        let wrong_network = TxVerificationExpectations {
+            network: Network::Signet,
+            ..expectations()
+        };
+        let expired = TxVerificationExpectations {
+            now: 1_700_000_000,
+            ..expectations()
+        };
+        for expected in [wrong_serial, wrong_network, expired] {
+            assert!(approval
+                .verify(&signature, &wik_pub_key, &expected)
+                .is_err());
+        }
+
+        // A v1 signature over the same key and commitment doesn't verify as v2
+        let v1_signature = TxVerificationApproval::V1 {
+            hw_auth_public_key: hw_auth_pub_key,
+            commitment: vec![7; 32],
+        }
+        .sign(&wik_priv_key)
+        .unwrap();
+        assert!(approval
+            .verify(&v1_signature, &wik_pub_key, &expectations())
+            .is_err());
+    }
+
+    #[test]
+    fn test_verify_v1_approval_during_migration_window() {
+        let secp = Secp256k1::new();
+        let mut rng = rand::thread_rng();
+        let (wik_priv_key, wik_pub_key) = secp.generate_keypair(&mut rng);
+        let (_, hw_auth_pub_key) = secp.generate_keypair(&mut rng);
+
+        let approval = TxVerificationApproval::V1 {
+            hw_auth_public_key: hw_auth_pub_key,
+            commitment: vec![7; 32],
+        };
+        assert_eq!(approval.version(), TX_VERIFICATION_V1);
+        assert_eq!(approval.envelope().unwrap(), None);
+
+        // v1 signatures are the same as they always were
+        let message = generate_message(hw_auth_pub_key, vec![7; 32]).unwrap();
+        let signature = secp.sign_ecdsa(&message, &wik_priv_key);
+        assert!(approval
+            .verify(&signature, &wik_pub_key, &expectations())
+            .is_ok());
+
+        let after_window = TxVerificationExpectations {
+            now: TX_VERIFICATION_V1_ACCEPTED_UNTIL,
+            ..expectations()
+        };
+        assert!(approval
+            .verify(&signature, &wik_pub_key, &after_window)
+            .is_err());
+    }
 }
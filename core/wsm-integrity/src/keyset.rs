@@ -0,0 +1,156 @@
+use bitcoin::hashes::{sha256, Hash};
+use bitcoin::secp256k1::PublicKey;
+
+use crate::{WsmContext, WsmIntegrityVerifierError};
+
+/// Identifies an integrity key: the hex encoding of the first 8 bytes of the SHA256 of its
+/// compressed encoding.
+pub fn key_id(public_key: &PublicKey) -> String {
+    let hash = sha256::Hash::hash(&public_key.serialize());
+    hex::encode(&hash[..8])
+}
+
+/// A WSM integrity key and the window, in seconds since the Unix epoch, in which it signs.
+/// `not_after` is exclusive, and is `None` for the current key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IntegrityKey {
+    pub public_key: PublicKey,
+    pub not_before: u64,
+    pub not_after: Option<u64>,
+}
+
+impl IntegrityKey {
+    pub fn key_id(&self) -> String {
+        key_id(&self.public_key)
+    }
+
+    fn is_valid_at(&self, timestamp: u64) -> bool {
+        self.not_before <= timestamp && self.not_after.is_none_or(|not_after| timestamp < not_after)
+    }
+}
+
+/// Which of a verifier's keys may have produced a signature.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeySelector {
+    /// Any key the verifier knows, for signatures that don't say which key made them.
+    Any,
+    /// The key with this [`key_id`].
+    KeyId(String),
+    /// Whichever keys were valid at this time, in seconds since the Unix epoch.
+    Timestamp(u64),
+}
+
+/// Hands over signing from the current integrity key to a new one, signed by the current key
+/// under [`WsmContext::KeyRotationV1`]. Each statement must chain from the key the previous one
+/// introduced, so clients only ever trust keys vouched for by a key they already trust.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyRotationStatement {
+    pub previous_key: PublicKey,
+    pub new_key: PublicKey,
+    /// When the new key takes over, in seconds since the Unix epoch.
+    pub effective_at: u64,
+    /// Hex-encoded compact ECDSA signature by `previous_key`.
+    pub signature: String,
+}
+
+impl KeyRotationStatement {
+    pub const CONTEXT: WsmContext = WsmContext::KeyRotationV1;
+
+    /// The signed payload: both 33-byte compressed keys followed by `effective_at` as a big-endian
+    /// u64.
+    pub fn payload(previous_key: &PublicKey, new_key: &PublicKey, effective_at: u64) -> Vec<u8> {
+        let mut payload = Vec::with_capacity(33 * 2 + 8);
+        payload.extend_from_slice(&previous_key.serialize());
+        payload.extend_from_slice(&new_key.serialize());
+        payload.extend_from_slice(&effective_at.to_be_bytes());
+        payload
+    }
+}
+
+/// The integrity keys a verifier trusts. Validity windows can overlap, to give signers time to
+/// switch over after a rotation.
+#[derive(Debug)]
+pub(crate) struct Keyset(Vec<IntegrityKey>);
+
+impl Keyset {
+    pub(crate) fn new(keys: Vec<IntegrityKey>) -> Result<Self, WsmIntegrityVerifierError> {
+        if keys.is_empty() {
+            return Err(WsmIntegrityVerifierError::InvalidKeyset);
+        }
+        for (i, key) in keys.iter().enumerate() {
+            let is_duplicate = keys[..i]
+                .iter()
+                .any(|other| other.public_key == key.public_key);
+            let is_inverted = key
+                .not_after
+                .is_some_and(|not_after| not_after <= key.not_before);
+            if is_duplicate || is_inverted {
+                return Err(WsmIntegrityVerifierError::InvalidKeyset);
+            }
+        }
+        Ok(Self(keys))
+    }
+
+    pub(crate) fn select(
+        &self,
+        selector: &KeySelector,
+    ) -> Result<Vec<PublicKey>, WsmIntegrityVerifierError> {
+        let keys: Vec<PublicKey> = self
+            .0
+            .iter()
+            .filter(|key| match selector {
+                KeySelector::Any => true,
+                KeySelector::KeyId(key_id) => key.key_id() == *key_id,
+                KeySelector::Timestamp(timestamp) => key.is_valid_at(*timestamp),
+            })
+            .map(|key| key.public_key)
+            .collect();
+
+        if keys.is_empty() {
+            return Err(WsmIntegrityVerifierError::UnknownKey);
+        }
+        Ok(keys)
+    }
+
+    /// The key rotation statements must chain from: the only key without an expiry.
+    fn current(&self) -> Option<&IntegrityKey> {
+        let mut current = self.0.iter().filter(|key| key.not_after.is_none());
+        match (current.next(), current.next()) {
+            (Some(key), None) => Some(key),
+            _ => None,
+        }
+    }
+
+    /// Applies a rotation whose signature the caller has already verified.
+    pub(crate) fn rotate(
+        &mut self,
+        statement: &KeyRotationStatement,
+    ) -> Result<(), WsmIntegrityVerifierError> {
+        let current = self
+            .current()
+            .ok_or(WsmIntegrityVerifierError::InvalidKeyRotation)?;
+        if current.public_key != statement.previous_key
+            || statement.effective_at <= current.not_before
+            || self.0.iter().any(|key| key.public_key == statement.new_key)
+        {
+            return Err(WsmIntegrityVerifierError::InvalidKeyRotation);
+        }
+
+        let previous_key = statement.previous_key;
+        for key in self.0.iter_mut() {
+            if key.public_key == previous_key {
+                key.not_after = Some(statement.effective_at);
+            }
+        }
+        self.0.push(IntegrityKey {
+            public_key: statement.new_key,
+            not_before: statement.effective_at,
+            not_after: None,
+        });
+        Ok(())
+    }
+
+    pub(crate) fn keys(&self) -> &[IntegrityKey] {
+        &self.0
+    }
+}
@@ -6,13 +6,21 @@ use bitcoin::secp256k1::{Message, Secp256k1};
 use std::sync::Mutex;
 use thiserror::Error;
 
+mod keyset;
+
 pub use bitcoin::secp256k1::PublicKey;
+pub use keyset::{key_id, IntegrityKey, KeyRotationStatement, KeySelector};
+
+use keyset::Keyset;
 
-pub struct WsmIntegrityVerifier(Mutex<PublicKey>);
+pub struct WsmIntegrityVerifier(Mutex<Keyset>);
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum WsmContext {
     DeriveKeyV1,
     CreateKeyV1,
+    SignPublicKeysV1,
+    KeyRotationV1,
 }
 
 #[derive(Error, Debug)]
@@ -27,22 +35,91 @@ pub enum WsmIntegrityVerifierError {
     Base16DecodeFailure,
     #[error("PublicKeyLockFailure")]
     PublicKeyLockFailure,
+    #[error("InvalidKeyset")]
+    InvalidKeyset,
+    #[error("UnknownKey")]
+    UnknownKey,
+    #[error("InvalidKeyRotation")]
+    InvalidKeyRotation,
 }
 
 impl WsmContext {
-    fn to_bytes(&self) -> &'static [u8] {
+    /// Every context the WSM signs under. A new context only needs a variant and a label here.
+    pub const ALL: &'static [WsmContext] = &[
+        WsmContext::DeriveKeyV1,
+        WsmContext::CreateKeyV1,
+        WsmContext::SignPublicKeysV1,
+        WsmContext::KeyRotationV1,
+    ];
+
+    pub fn label(&self) -> &'static str {
         match self {
-            WsmContext::DeriveKeyV1 => b"DeriveKeyV1",
-            WsmContext::CreateKeyV1 => b"CreateKeyV1",
+            WsmContext::DeriveKeyV1 => "DeriveKeyV1",
+            WsmContext::CreateKeyV1 => "CreateKeyV1",
+            WsmContext::SignPublicKeysV1 => "SignPublicKeysV1",
+            WsmContext::KeyRotationV1 => "KeyRotationV1",
         }
     }
+
+    pub fn from_label(label: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .find(|context| context.label() == label)
+            .copied()
+    }
+
+    fn to_bytes(self) -> &'static [u8] {
+        self.label().as_bytes()
+    }
 }
 
 const GLOBAL_CONTEXT: &[u8] = b"WsmIntegrityV1";
 
 impl WsmIntegrityVerifier {
+    /// A verifier for a single integrity key, valid at all times.
     pub fn new(public_key: PublicKey) -> Self {
-        Self(Mutex::new(public_key))
+        Self(Mutex::new(
+            Keyset::new(vec![IntegrityKey {
+                public_key,
+                not_before: 0,
+                not_after: None,
+            }])
+            .expect("A single key is a valid keyset"),
+        ))
+    }
+
+    /// A verifier for a set of integrity keys, for example as persisted from [`Self::keys`].
+    /// Keys must be distinct and at most one may lack a `not_after`.
+    pub fn with_keys(keys: Vec<IntegrityKey>) -> Result<Self, WsmIntegrityVerifierError> {
+        Ok(Self(Mutex::new(Keyset::new(keys)?)))
+    }
+
+    pub fn keys(&self) -> Result<Vec<IntegrityKey>, WsmIntegrityVerifierError> {
+        Ok(self.keyset()?.keys().to_vec())
+    }
+
+    /// Learns a new integrity key from a rotation statement signed by the current key. Statements
+    /// must be applied in order, each chaining from the key introduced by the last.
+    pub fn apply_key_rotation(
+        &self,
+        statement: KeyRotationStatement,
+    ) -> Result<(), WsmIntegrityVerifierError> {
+        let payload = KeyRotationStatement::payload(
+            &statement.previous_key,
+            &statement.new_key,
+            statement.effective_at,
+        );
+        let signature = parse_signature(&statement.signature)?;
+        if !verify_with_keys(
+            &[statement.previous_key],
+            KeyRotationStatement::CONTEXT,
+            &payload,
+            &signature,
+        ) {
+            return Err(WsmIntegrityVerifierError::InvalidKeyRotation);
+        }
+
+        self.keyset()?.rotate(&statement)
     }
 
     pub fn verify(
@@ -99,7 +176,32 @@ impl WsmIntegrityVerifier {
             payload.extend_from_slice(&pk.serialize());
         }
 
-        self.verify_with_context(b"SignPublicKeysV1", &payload, &signature)
+        self.verify_with_context(
+            WsmContext::SignPublicKeysV1,
+            &payload,
+            &signature,
+            &KeySelector::Any,
+        )
+    }
+
+    /// Verify a signature over a hex message under a specific context, by the keys `selector`
+    /// picks out.
+    pub fn verify_hex_message_with_context(
+        &self,
+        context: WsmContext,
+        hex_message: String,
+        signature: String,
+        selector: KeySelector,
+    ) -> Result<bool, WsmIntegrityVerifierError> {
+        let message =
+            hex::decode(hex_message).map_err(|_| WsmIntegrityVerifierError::Base16DecodeFailure)?;
+        self.verify_with_context(context, &message, &signature, &selector)
+    }
+
+    fn keyset(&self) -> Result<std::sync::MutexGuard<'_, Keyset>, WsmIntegrityVerifierError> {
+        self.0
+            .lock()
+            .map_err(|_| WsmIntegrityVerifierError::PublicKeyLockFailure)
     }
 
     fn verify_with_unhashed_message(
@@ -111,61 +213,48 @@ impl WsmIntegrityVerifier {
         // Since 'derive' and 'create' key is essentially the same operation, this is totally fine.
         // If we ever add more context labels, we should not just add them to the list, provided the
         // operation in the enclave is meaningfully different.
-        let contexts = vec![WsmContext::DeriveKeyV1, WsmContext::CreateKeyV1];
-
-        let signature_bytes = hex::decode(signature_hex)
-            .map_err(|_| WsmIntegrityVerifierError::Base16DecodeFailure)?;
-        let sig = Signature::from_compact(&signature_bytes)
-            .map_err(|_| WsmIntegrityVerifierError::MalformedSignature)?;
-
-        let pk = self
-            .0
-            .lock()
-            .map_err(|_| WsmIntegrityVerifierError::PublicKeyLockFailure)?;
-
-        let secp = Secp256k1::new();
+        let contexts = [WsmContext::DeriveKeyV1, WsmContext::CreateKeyV1];
 
-        for ctx in contexts {
-            let mut hash_input = Vec::new();
-            hash_input.extend_from_slice(GLOBAL_CONTEXT);
-            hash_input.extend_from_slice(ctx.to_bytes());
-            hash_input.extend_from_slice(message);
+        let sig = parse_signature(signature_hex)?;
+        let keys = self.keyset()?.select(&KeySelector::Any)?;
 
-            let digest = Message::from_digest(sha256::Hash::hash(&hash_input).to_byte_array());
-            if secp.verify_ecdsa(&digest, &sig, &pk).is_ok() {
-                return Ok(true);
-            }
-        }
-
-        Ok(false)
+        Ok(contexts
+            .into_iter()
+            .any(|ctx| verify_with_keys(&keys, ctx, message, &sig)))
     }
 
     fn verify_with_context(
         &self,
-        context: &[u8],
+        context: WsmContext,
         data: &[u8],
         signature_hex: &str,
+        selector: &KeySelector,
     ) -> Result<bool, WsmIntegrityVerifierError> {
-        let signature_bytes = hex::decode(signature_hex)
-            .map_err(|_| WsmIntegrityVerifierError::Base16DecodeFailure)?;
-        let sig = Signature::from_compact(&signature_bytes)
-            .map_err(|_| WsmIntegrityVerifierError::MalformedSignature)?;
+        let sig = parse_signature(signature_hex)?;
+        let keys = self.keyset()?.select(selector)?;
 
-        let pk = self
-            .0
-            .lock()
-            .map_err(|_| WsmIntegrityVerifierError::PublicKeyLockFailure)?;
+        Ok(verify_with_keys(&keys, context, data, &sig))
+    }
+}
 
-        let secp = Secp256k1::new();
+fn parse_signature(signature_hex: &str) -> Result<Signature, WsmIntegrityVerifierError> {
+    let signature_bytes =
+        hex::decode(signature_hex).map_err(|_| WsmIntegrityVerifierError::Base16DecodeFailure)?;
+    Signature::from_compact(&signature_bytes)
+        .map_err(|_| WsmIntegrityVerifierError::MalformedSignature)
+}
 
-        let mut hash_input = Vec::new();
-        hash_input.extend_from_slice(GLOBAL_CONTEXT);
-        hash_input.extend_from_slice(context);
-        hash_input.extend_from_slice(data);
+fn verify_with_keys(keys: &[PublicKey], context: WsmContext, data: &[u8], sig: &Signature) -> bool {
+    let secp = Secp256k1::verification_only();
 
-        let digest = Message::from_digest(sha256::Hash::hash(&hash_input).to_byte_array());
-        Ok(secp.verify_ecdsa(&digest, &sig, &pk).is_ok())
-    }
+    let mut hash_input = Vec::new();
+    hash_input.extend_from_slice(GLOBAL_CONTEXT);
+    hash_input.extend_from_slice(context.to_bytes());
+    hash_input.extend_from_slice(data);
+
+    let digest = Message::from_digest(sha256::Hash::hash(&hash_input).to_byte_array());
+    keys.iter()
+        .any(|pk| secp.verify_ecdsa(&digest, sig, pk).is_ok())
 }
 
 #[cfg(test)]
@@ -318,4 +407,200 @@ mod tests {
         assert!(result.is_ok());
         assert!(result.unwrap());
     }
+
+    fn sign(
+        secret_key: &bitcoin::secp256k1::SecretKey,
+        context: WsmContext,
+        data: &[u8],
+    ) -> String {
+        let mut hash_input = Vec::new();
+        hash_input.extend_from_slice(GLOBAL_CONTEXT);
+        hash_input.extend_from_slice(context.label().as_bytes());
+        hash_input.extend_from_slice(data);
+        let digest = Message::from_digest(sha256::Hash::hash(&hash_input).to_byte_array());
+        hex::encode(
+            Secp256k1::new()
+                .sign_ecdsa(&digest, secret_key)
+                .serialize_compact(),
+        )
+    }
+
+    fn rotation(
+        previous: &bitcoin::secp256k1::SecretKey,
+        new: &bitcoin::secp256k1::SecretKey,
+        effective_at: u64,
+    ) -> KeyRotationStatement {
+        let secp = Secp256k1::new();
+        let previous_key = PublicKey::from_secret_key(&secp, previous);
+        let new_key = PublicKey::from_secret_key(&secp, new);
+        let payload = KeyRotationStatement::payload(&previous_key, &new_key, effective_at);
+        KeyRotationStatement {
+            previous_key,
+            new_key,
+            effective_at,
+            signature: sign(previous, KeyRotationStatement::CONTEXT, &payload),
+        }
+    }
+
+    #[test]
+    fn test_context_registry() {
+        for context in WsmContext::ALL {
+            assert_eq!(WsmContext::from_label(context.label()), Some(*context));
+        }
+        assert_eq!(
+            WsmContext::from_label("SignPublicKeysV1"),
+            Some(WsmContext::SignPublicKeysV1)
+        );
+        assert_eq!(WsmContext::from_label("UnknownV1"), None);
+    }
+
+    #[test]
+    fn test_key_rotation() {
+        use bitcoin::secp256k1::SecretKey;
+
+        let secp = Secp256k1::new();
+        let sk1 = SecretKey::from_slice(&[0x01; 32]).unwrap();
+        let sk2 = SecretKey::from_slice(&[0x02; 32]).unwrap();
+        let sk3 = SecretKey::from_slice(&[0x03; 32]).unwrap();
+        let pk1 = PublicKey::from_secret_key(&secp, &sk1);
+        let pk2 = PublicKey::from_secret_key(&secp, &sk2);
+        let verifier = WsmIntegrityVerifier::new(pk1);
+
+        let message = hex::encode(b"hello");
+        let old_signature = sign(&sk1, WsmContext::CreateKeyV1, b"hello");
+
+        // Rotations must chain from the current key.
+        assert!(matches!(
+            verifier.apply_key_rotation(rotation(&sk2, &sk3, 200)),
+            Err(WsmIntegrityVerifierError::InvalidKeyRotation)
+        ));
+        // And be signed by it.
+        let mut forged = rotation(&sk1, &sk2, 100);
+        forged.signature = rotation(&sk3, &sk2, 100).signature;
+        assert!(matches!(
+            verifier.apply_key_rotation(forged),
+            Err(WsmIntegrityVerifierError::InvalidKeyRotation)
+        ));
+
+        verifier
+            .apply_key_rotation(rotation(&sk1, &sk2, 100))
+            .unwrap();
+        // The old key can no longer rotate, nor can a rotation be replayed.
+        assert!(verifier
+            .apply_key_rotation(rotation(&sk1, &sk3, 200))
+            .is_err());
+        assert!(verifier
+            .apply_key_rotation(rotation(&sk1, &sk2, 100))
+            .is_err());
+        // Nor can the new key take effect before the key it replaces.
+        assert!(verifier
+            .apply_key_rotation(rotation(&sk2, &sk3, 50))
+            .is_err());
+
+        assert_eq!(
+            verifier.keys().unwrap(),
+            vec![
+                IntegrityKey {
+                    public_key: pk1,
+                    not_before: 0,
+                    not_after: Some(100),
+                },
+                IntegrityKey {
+                    public_key: pk2,
+                    not_before: 100,
+                    not_after: None,
+                },
+            ]
+        );
+
+        // Signatures by the old key still verify.
+        assert!(verifier
+            .verify_hex_message(message.clone(), old_signature.clone())
+            .unwrap());
+        let new_signature = sign(&sk2, WsmContext::CreateKeyV1, b"hello");
+        assert!(verifier.verify_hex_message(message, new_signature).unwrap());
+    }
+
+    #[test]
+    fn test_verify_with_selector() {
+        use bitcoin::secp256k1::SecretKey;
+
+        let secp = Secp256k1::new();
+        let sk1 = SecretKey::from_slice(&[0x01; 32]).unwrap();
+        let sk2 = SecretKey::from_slice(&[0x02; 32]).unwrap();
+        let pk1 = PublicKey::from_secret_key(&secp, &sk1);
+        let pk2 = PublicKey::from_secret_key(&secp, &sk2);
+        let verifier = WsmIntegrityVerifier::with_keys(vec![
+            IntegrityKey {
+                public_key: pk1,
+                not_before: 0,
+                not_after: Some(100),
+            },
+            IntegrityKey {
+                public_key: pk2,
+                not_before: 90,
+                not_after: None,
+            },
+        ])
+        .unwrap();
+
+        let message = hex::encode(b"payload");
+        let verify = |signature: &str, selector: KeySelector| {
+            verifier.verify_hex_message_with_context(
+                WsmContext::DeriveKeyV1,
+                message.clone(),
+                signature.to_string(),
+                selector,
+            )
+        };
+        let sig1 = sign(&sk1, WsmContext::DeriveKeyV1, b"payload");
+        let sig2 = sign(&sk2, WsmContext::DeriveKeyV1, b"payload");
+
+        assert!(verify(&sig1, KeySelector::KeyId(key_id(&pk1))).unwrap());
+        assert!(!verify(&sig1, KeySelector::KeyId(key_id(&pk2))).unwrap());
+        assert!(matches!(
+            verify(&sig1, KeySelector::KeyId("0000000000000000".to_string())),
+            Err(WsmIntegrityVerifierError::UnknownKey)
+        ));
+
+        assert!(verify(&sig1, KeySelector::Timestamp(50)).unwrap());
+        assert!(!verify(&sig2, KeySelector::Timestamp(50)).unwrap());
+        // Both keys are valid while the windows overlap.
+        assert!(verify(&sig1, KeySelector::Timestamp(95)).unwrap());
+        assert!(verify(&sig2, KeySelector::Timestamp(95)).unwrap());
+        assert!(!verify(&sig1, KeySelector::Timestamp(100)).unwrap());
+        assert!(verify(&sig2, KeySelector::Timestamp(100)).unwrap());
+
+        // Signatures are bound to their context.
+        assert!(!verifier
+            .verify_hex_message_with_context(
+                WsmContext::CreateKeyV1,
+                message.clone(),
+                sig1,
+                KeySelector::Any,
+            )
+            .unwrap());
+    }
+
+    #[test]
+    fn test_invalid_keyset() {
+        use bitcoin::secp256k1::SecretKey;
+
+        let secp = Secp256k1::new();
+        let pk = PublicKey::from_secret_key(&secp, &SecretKey::from_slice(&[0x01; 32]).unwrap());
+        let key = IntegrityKey {
+            public_key: pk,
+            not_before: 0,
+            not_after: None,
+        };
+
+        assert!(WsmIntegrityVerifier::with_keys(vec![]).is_err());
+        assert!(WsmIntegrityVerifier::with_keys(vec![key.clone(), key.clone()]).is_err());
+        assert!(WsmIntegrityVerifier::with_keys(vec![IntegrityKey {
+            not_before: 10,
+            not_after: Some(10),
+            ..key
+        }])
+        .is_err());
+    }
 }
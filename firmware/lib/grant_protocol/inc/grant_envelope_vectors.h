@@ -0,0 +1,136 @@
+/* Grant envelope test vectors
+ * Generated by core/wsm-grant/examples/grant_vectors.rs
+ * Auto-generated - DO NOT EDIT MANUALLY
+ */
+
+#pragma once
+
+#include <stdint.h>
+
+#define GRANT_ENVELOPE_SIG_PREFIX "BKGrantV2"
+#define GRANT_ENVELOPE_EXPIRES_AT 1700000000
+#define GRANT_ENVELOPE_NUM_VECTORS 5
+
+typedef struct {
+  uint8_t kind;          /* Grant kind id */
+  const uint8_t* grant;  /* Serialized envelope */
+  uint32_t grant_len;    /* Envelope length */
+  uint8_t digest[32];    /* SHA256(prefix || envelope) */
+  uint8_t signature[64]; /* Compact WIK signature over digest */
+} grant_envelope_vector_t;
+
+static const uint8_t grant_envelope_wik_pubkey[33] = {
+  0x03, 0x24, 0x65, 0x3e, 0xac, 0x43, 0x44, 0x88, 0x00, 0x2c, 0xc0, 0x6b, 0xbf, 0xb7, 0xf1, 0x0f,
+  0xe1, 0x89, 0x91, 0xe3, 0x5f, 0x9f, 0xe4, 0x30, 0x2d, 0xbe, 0xa6, 0xd2, 0x35, 0x3d, 0xc0, 0xab,
+  0x1c};
+
+static const uint8_t grant_envelope_device_id[8] = {
+  0x38, 0x39, 0x8f, 0xff, 0xfe, 0xd0, 0x81, 0xb6};
+
+static const uint8_t grant_envelope_fingerprint_reset[36] = {
+  0x02, 0x01, 0x38, 0x39, 0x8f, 0xff, 0xfe, 0xd0, 0x81, 0xb6, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01,
+  0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x00, 0x00, 0x00, 0x00, 0x65, 0x53,
+  0xf1, 0x00, 0x00, 0x00};
+
+static const uint8_t grant_envelope_transaction_verification[105] = {
+  0x02, 0x02, 0x38, 0x39, 0x8f, 0xff, 0xfe, 0xd0, 0x81, 0xb6, 0x02, 0x02, 0x02, 0x02, 0x02, 0x02,
+  0x02, 0x02, 0x02, 0x02, 0x02, 0x02, 0x02, 0x02, 0x02, 0x02, 0x00, 0x00, 0x00, 0x00, 0x65, 0x53,
+  0xf1, 0x00, 0x00, 0x45, 0x03, 0x4f, 0x35, 0x5b, 0xdc, 0xb7, 0xcc, 0x0a, 0xf7, 0x28, 0xef, 0x3c,
+  0xce, 0xb9, 0x61, 0x5d, 0x90, 0x68, 0x4b, 0xb5, 0xb2, 0xca, 0x5f, 0x85, 0x9a, 0xb0, 0xf0, 0xb7,
+  0x04, 0x07, 0x58, 0x71, 0xaa, 0xc0, 0xc0, 0xc0, 0xc0, 0xc0, 0xc0, 0xc0, 0xc0, 0xc0, 0xc0, 0xc0,
+  0xc0, 0xc0, 0xc0, 0xc0, 0xc0, 0xc0, 0xc0, 0xc0, 0xc0, 0xc0, 0xc0, 0xc0, 0xc0, 0xc0, 0xc0, 0xc0,
+  0xc0, 0xc0, 0xc0, 0xc0, 0xc0, 0xf9, 0xbe, 0xb4, 0xd9};
+
+static const uint8_t grant_envelope_firmware_downgrade[40] = {
+  0x02, 0x03, 0x38, 0x39, 0x8f, 0xff, 0xfe, 0xd0, 0x81, 0xb6, 0x03, 0x03, 0x03, 0x03, 0x03, 0x03,
+  0x03, 0x03, 0x03, 0x03, 0x03, 0x03, 0x03, 0x03, 0x03, 0x03, 0x00, 0x00, 0x00, 0x00, 0x65, 0x53,
+  0xf1, 0x00, 0x00, 0x04, 0x00, 0x01, 0x02, 0x03};
+
+static const uint8_t grant_envelope_wipe[36] = {
+  0x02, 0x04, 0x38, 0x39, 0x8f, 0xff, 0xfe, 0xd0, 0x81, 0xb6, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04,
+  0x04, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04, 0x00, 0x00, 0x00, 0x00, 0x65, 0x53,
+  0xf1, 0x00, 0x00, 0x00};
+
+static const uint8_t grant_envelope_key_export[49] = {
+  0x02, 0x05, 0x38, 0x39, 0x8f, 0xff, 0xfe, 0xd0, 0x81, 0xb6, 0x05, 0x05, 0x05, 0x05, 0x05, 0x05,
+  0x05, 0x05, 0x05, 0x05, 0x05, 0x05, 0x05, 0x05, 0x05, 0x05, 0x00, 0x00, 0x00, 0x00, 0x65, 0x53,
+  0xf1, 0x00, 0x00, 0x0d, 0x03, 0x80, 0x00, 0x00, 0x54, 0x80, 0x00, 0x00, 0x00, 0x80, 0x00, 0x00,
+  0x00};
+
+static const grant_envelope_vector_t grant_envelope_vectors[GRANT_ENVELOPE_NUM_VECTORS] = {
+  {
+    .kind = 1,
+    .grant = grant_envelope_fingerprint_reset,
+    .grant_len = 36,
+    .digest = {
+      0x68, 0x00, 0xf1, 0x8b, 0x78, 0xc7, 0x9b, 0x67, 0x96, 0xfe, 0xa6, 0xdf, 0xc0, 0xf4, 0x31, 0x40,
+      0xa0, 0x0a, 0x14, 0x0e, 0x2f, 0xba, 0x79, 0x73, 0xdb, 0xd1, 0x8c, 0x26, 0x74, 0xcf, 0xb3, 0xa0
+    },
+    .signature = {
+      0x4c, 0xde, 0x19, 0x54, 0x8d, 0x2b, 0x1d, 0xf5, 0xa5, 0x19, 0xd7, 0x77, 0xc9, 0x97, 0x10, 0xce,
+      0x4e, 0x19, 0xad, 0x52, 0x95, 0x56, 0x9a, 0x5c, 0x13, 0x3d, 0xda, 0x84, 0xb3, 0x3f, 0x10, 0x27,
+      0x56, 0x9a, 0x27, 0x3c, 0xd2, 0x5d, 0xa2, 0x64, 0x5f, 0xb0, 0xf6, 0x9c, 0x0b, 0x42, 0x57, 0x58,
+      0x73, 0x9b, 0x0b, 0x70, 0xba, 0x9f, 0xe1, 0xa4, 0xa3, 0x0a, 0x55, 0x5c, 0xe9, 0xbd, 0x90, 0x2b
+    },
+  },
+  {
+    .kind = 2,
+    .grant = grant_envelope_transaction_verification,
+    .grant_len = 105,
+    .digest = {
+      0xed, 0x42, 0x76, 0xcb, 0xe5, 0xeb, 0x48, 0x08, 0xe7, 0xc4, 0x87, 0x3a, 0x8d, 0x7a, 0x79, 0xbc,
+      0xf0, 0xf1, 0x47, 0x51, 0x13, 0xcd, 0xf6, 0x0c, 0xd5, 0x0f, 0x91, 0x6f, 0x7e, 0x2b, 0x15, 0x08
+    },
+    .signature = {
+      0xba, 0x31, 0x5c, 0x57, 0x2a, 0x9b, 0x5e, 0x71, 0x54, 0x52, 0x4e, 0x23, 0xe7, 0xb3, 0x35, 0xe4,
+      0x68, 0xda, 0xdf, 0x69, 0xa1, 0x83, 0xc9, 0xfb, 0x4d, 0x6b, 0x81, 0x56, 0xc1, 0x9a, 0xbd, 0x77,
+      0x64, 0x92, 0x1b, 0x6f, 0xfd, 0xe1, 0x9e, 0xa4, 0xfa, 0xcd, 0x97, 0xbf, 0x57, 0xf4, 0xa4, 0x04,
+      0xda, 0xf0, 0x67, 0x83, 0x3e, 0xc3, 0xdf, 0xad, 0xc0, 0x42, 0xf4, 0xcb, 0x94, 0x16, 0x03, 0x4d
+    },
+  },
+  {
+    .kind = 3,
+    .grant = grant_envelope_firmware_downgrade,
+    .grant_len = 40,
+    .digest = {
+      0xb8, 0x73, 0x5d, 0x4b, 0xbd, 0x62, 0x5a, 0x1f, 0xdf, 0x31, 0x6c, 0x80, 0x10, 0x6a, 0xd2, 0xc6,
+      0xdf, 0xd3, 0x75, 0xb0, 0xda, 0x08, 0xa5, 0x78, 0x74, 0xc6, 0xc9, 0x51, 0x55, 0x77, 0x7c, 0x11
+    },
+    .signature = {
+      0x57, 0x22, 0x5e, 0x6c, 0xb3, 0xbe, 0xaf, 0xed, 0x80, 0x18, 0x15, 0x07, 0x5c, 0x82, 0x0c, 0x85,
+      0x5e, 0x54, 0x42, 0x44, 0x40, 0xc3, 0x0e, 0x71, 0x68, 0x16, 0x9e, 0xab, 0x4f, 0xe6, 0xaa, 0x0d,
+      0x30, 0xde, 0xed, 0x5d, 0x4f, 0xf0, 0x6c, 0x3e, 0xfd, 0x77, 0x55, 0x99, 0xa7, 0xdd, 0xdd, 0xb7,
+      0x2b, 0x5c, 0x6e, 0x03, 0x69, 0x22, 0x9c, 0xa8, 0x9c, 0x79, 0xc5, 0x7d, 0xf3, 0x4c, 0x46, 0x0e
+    },
+  },
+  {
+    .kind = 4,
+    .grant = grant_envelope_wipe,
+    .grant_len = 36,
+    .digest = {
+      0x8c, 0x69, 0xd5, 0x8c, 0xe4, 0xe3, 0x09, 0xb6, 0x1a, 0xa9, 0x76, 0x4e, 0x38, 0xf8, 0xe4, 0x0f,
+      0xd4, 0xbd, 0x15, 0xca, 0xd0, 0x0a, 0x1c, 0xa3, 0x49, 0x18, 0xb5, 0x96, 0xf6, 0x4f, 0xa5, 0xc7
+    },
+    .signature = {
+      0xc5, 0x69, 0x74, 0x91, 0xe4, 0x01, 0x52, 0x64, 0x41, 0xee, 0xe7, 0x5e, 0x6b, 0x47, 0xa1, 0x0d,
+      0x71, 0x37, 0x7d, 0xae, 0x72, 0xbe, 0x82, 0x53, 0x81, 0x9f, 0x57, 0x57, 0x9e, 0xab, 0xcb, 0x20,
+      0x30, 0x60, 0x5b, 0x1c, 0x9e, 0x38, 0x87, 0xe6, 0x3f, 0x8e, 0x29, 0x7f, 0x22, 0xb8, 0x95, 0xd0,
+      0xcb, 0x7d, 0x91, 0x43, 0x0b, 0xe1, 0x73, 0x20, 0x59, 0xd7, 0xa5, 0xe2, 0x1c, 0xae, 0x1a, 0xae
+    },
+  },
+  {
+    .kind = 5,
+    .grant = grant_envelope_key_export,
+    .grant_len = 49,
+    .digest = {
+      0xef, 0xe4, 0xf3, 0x34, 0xe7, 0x3b, 0xab, 0x18, 0x72, 0x5c, 0x26, 0xd0, 0xb7, 0xd4, 0xaa, 0xde,
+      0xac, 0xf9, 0x66, 0xf6, 0xcd, 0x7b, 0x4f, 0x16, 0xe6, 0xa6, 0xc2, 0x0f, 0xeb, 0x33, 0x50, 0xa8
+    },
+    .signature = {
+      0x73, 0x83, 0xc5, 0xe5, 0x0e, 0x59, 0x7c, 0x6e, 0x6c, 0x0a, 0xf5, 0x57, 0xe4, 0x5e, 0x1f, 0xfd,
+      0xaa, 0xd3, 0x5a, 0x88, 0x62, 0x39, 0x52, 0x20, 0x7c, 0xc6, 0x42, 0xe2, 0xd1, 0x0d, 0x85, 0x28,
+      0x5f, 0x59, 0xa4, 0xd0, 0xee, 0x7e, 0x2f, 0x7c, 0x81, 0x73, 0x04, 0x12, 0x08, 0xdf, 0x53, 0xbc,
+      0xe9, 0x69, 0x7d, 0x09, 0x9e, 0xf8, 0xfd, 0xb2, 0xbc, 0x76, 0x6d, 0xc7, 0xef, 0x76, 0x12, 0x02
+    },
+  },
+};
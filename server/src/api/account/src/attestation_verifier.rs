@@ -3,7 +3,12 @@
 //! the compressed `hardware_pub` to form the signed payload.
 
 use bdk_utils::bdk::bitcoin::secp256k1::PublicKey;
-use device_attestation::AttestationError;
+use device_attestation::TrustStore;
+use once_cell::sync::OnceCell;
+use serde::Deserialize;
+use serde_with::{base64::Base64, serde_as};
+
+pub use device_attestation::AttestationError;
 
 pub mod test_fixture;
 
@@ -11,9 +16,51 @@ pub mod test_fixture;
 /// device identity key signs `HWV1 || hardware_pub_compressed`.
 const HWV1_PREFIX: &[u8] = b"HWV1";
 
+/// Installed once at startup by [`load_trust_store`]. Until then, and
+/// when no bundle is configured, the prod Silicon Labs certs baked into
+/// [`device_attestation`] are trusted.
+static TRUST_STORE: OnceCell<TrustStore> = OnceCell::new();
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct Config {
+    #[serde(default)]
+    pub hardware_attestation_trust_store: Option<TrustStoreBundle>,
+}
+
+/// A trust store bundle from [`TrustStore::serialize_bundle`], signed by
+/// one of the bundle signing keys pinned in [`device_attestation`]. Ships
+/// new intermediates and revocations through config instead of a server
+/// release.
+#[serde_as]
+#[derive(Clone, Debug, Deserialize)]
+pub struct TrustStoreBundle {
+    #[serde_as(as = "Base64")]
+    pub bundle: Vec<u8>,
+    #[serde_as(as = "Base64")]
+    pub signature: Vec<u8>,
+}
+
+/// Verifies the configured bundle against the pinned signing keys, the
+/// same way the app does, and installs it. The store can only be installed once per process; loading the same
+/// store again is a no-op, and loading a different one is an error.
+pub fn load_trust_store(config: &Config) -> Result<(), AttestationError> {
+    let trust_store = match &config.hardware_attestation_trust_store {
+        Some(bundle) => TrustStore::from_signed_bundle(&bundle.bundle, &bundle.signature)?,
+        None => TrustStore::builtin(),
+    };
+    if *TRUST_STORE.get_or_init(|| trust_store.clone()) != trust_store {
+        return Err(AttestationError::InvalidTrustStore);
+    }
+    Ok(())
+}
+
+fn trust_store() -> &'static TrustStore {
+    TRUST_STORE.get_or_init(TrustStore::builtin)
+}
+
 /// `cert_chain` must contain the device identity cert followed by its
-/// batch (intermediate) cert in DER form; the Silicon Labs roots are
-/// baked into [`device_attestation`].
+/// batch (intermediate) cert in DER form; the chain is walked to the
+/// roots in the installed trust store.
 pub fn verify_hardware_attestation(
     cert_chain: &[Vec<u8>],
     signature: &[u8],
@@ -22,7 +69,11 @@ pub fn verify_hardware_attestation(
     if cert_chain.len() < 2 {
         return Err(AttestationError::InvalidChain);
     }
-    let device = device_attestation::verify_device_identity_chain(&cert_chain[0], &cert_chain[1])?;
+    let device = device_attestation::verify_device_identity_chain(
+        trust_store(),
+        &cert_chain[0],
+        &cert_chain[1],
+    )?;
 
     let mut payload = Vec::with_capacity(HWV1_PREFIX.len() + 33);
     payload.extend_from_slice(HWV1_PREFIX);
@@ -50,6 +101,24 @@ mod tests {
         assert_eq!(err, AttestationError::InvalidChain);
     }
 
+    #[test]
+    fn load_trust_store_rejects_unsigned_bundle() {
+        let config = Config {
+            hardware_attestation_trust_store: Some(TrustStoreBundle {
+                bundle: TrustStore::builtin().serialize_bundle().unwrap(),
+                signature: vec![0u8; 64],
+            }),
+        };
+        assert_eq!(
+            load_trust_store(&config).unwrap_err(),
+            AttestationError::VerificationFailure
+        );
+
+        // Without a bundle, the builtin store is used.
+        assert!(load_trust_store(&Config::default()).is_ok());
+        assert_eq!(trust_store(), &TrustStore::builtin());
+    }
+
     #[test]
     fn rejects_garbage_cert_bytes() {
         let err = verify_hardware_attestation(
@@ -10,7 +10,8 @@
 //!
 //! - **Policy** (`AuthorizationRequirements`): Server-authoritative, action-dependent elements
 //!   including the action, value, and proof requirements. Routes define what
-//!   authorization is required.
+//!   authorization is required; by default the signers come from the action's
+//!   declared `Action::signer_policy()`.
 //!
 //! - **Credentials** (`Authorization`): Client-provided elements including JWT, signatures,
 //!   nonce, and public keys. Clients provide proof they're authorized.
@@ -210,6 +211,7 @@ pub struct AuthorizationRequirements {
     alt_actions: Vec<Action>,
     hardware_type: HardwareType,
     value: Option<String>,
+    fields: Vec<(String, String)>,
     extra_bindings: Vec<(String, String)>,
     proof: ProofRequirement,
 }
@@ -224,15 +226,17 @@ impl AuthorizationRequirements {
     /// - `HardwareType::W1` — requires KeyClaims (rejects ActionProof)
     /// - `HardwareType::W3` — requires ActionProof (rejects KeyClaims)
     ///
-    /// Defaults to `ProofRequirement::BothFactors`. Use `.proof()` to specify different requirements.
+    /// Defaults to `ProofRequirement::ActionPolicy`, i.e. the signers declared by
+    /// `Action::signer_policy()`. Use `.proof()` to override for a route.
     pub fn new(action: Action, hardware_type: HardwareType) -> Self {
         Self {
             action: Some(action),
             alt_actions: vec![],
             hardware_type,
             value: None,
+            fields: vec![],
             extra_bindings: vec![],
-            proof: ProofRequirement::BothFactors,
+            proof: ProofRequirement::ActionPolicy,
         }
     }
 
@@ -251,6 +255,7 @@ impl AuthorizationRequirements {
             alt_actions: vec![],
             hardware_type: HardwareType::W1,
             value: None,
+            fields: vec![],
             extra_bindings: vec![],
             proof: ProofRequirement::BothFactors,
         }
@@ -266,6 +271,7 @@ impl AuthorizationRequirements {
             alt_actions: vec![],
             hardware_type,
             value: None,
+            fields: vec![],
             extra_bindings: vec![],
             proof: ProofRequirement::JwtOnly,
         }
@@ -283,6 +289,16 @@ impl AuthorizationRequirements {
         self
     }
 
+    /// Adds a typed field for version-2 Action Proofs.
+    ///
+    /// Fields must be added in the order declared by `Action::schema()`. If no
+    /// fields are set, v2 proofs derive them from `value`. Ignored for v1 proofs.
+    pub fn field(mut self, name: impl AsRef<str>, value: impl AsRef<str>) -> Self {
+        self.fields
+            .push((name.as_ref().to_string(), value.as_ref().to_string()));
+        self
+    }
+
     /// Adds a custom extra binding to the context.
     pub fn extra(mut self, key: impl AsRef<str>, value: impl AsRef<str>) -> Self {
         self.extra_bindings
@@ -309,7 +325,8 @@ impl AuthorizationRequirements {
 
     /// Sets the proof requirement for authorization.
     ///
-    /// Default is `ProofRequirement::BothFactors`.
+    /// Default is `ProofRequirement::ActionPolicy` for `new()` and
+    /// `ProofRequirement::BothFactors` for `keyclaims_only()`.
     pub fn proof(mut self, proof: ProofRequirement) -> Self {
         self.proof = proof;
         self
@@ -396,7 +413,7 @@ impl AuthorizationRequirements {
         // Resolve proof results based on mechanism and hardware type.
         // W3 proof must come from ActionProof; W1 proof must come from KeyClaims.
         // If the correct mechanism wasn't used, treat as unsigned.
-        let (hw_signed, app_signed, content_hash, nonce) =
+        let (hw_signed, app_signed, content_hash, nonce, verified_action) =
             match (self.hardware_type, &auth.inner, self.proof) {
                 // W3 with ActionProof (and not JwtOnly): verify signatures
                 (
@@ -420,6 +437,7 @@ impl AuthorizationRequirements {
                     // Try the primary action first, then alternatives.
                     let mut last_err = None;
                     let mut result = None;
+                    let mut verified_action = primary_action;
                     for action in
                         std::iter::once(primary_action).chain(self.alt_actions.iter().copied())
                     {
@@ -432,10 +450,12 @@ impl AuthorizationRequirements {
                             &auth.jwt,
                             action,
                             self.value.as_deref(),
+                            &self.fields,
                             &self.extra_bindings,
                         ) {
                             Ok(r) => {
                                 result = Some(r);
+                                verified_action = action;
                                 break;
                             }
                             Err(e) => last_err = Some(e),
@@ -453,6 +473,7 @@ impl AuthorizationRequirements {
                         app_signed,
                         Some(content_hash),
                         Some(nonce.clone()),
+                        Some(verified_action),
                     )
                 }
 
@@ -466,15 +487,16 @@ impl AuthorizationRequirements {
                         app_signed,
                     },
                     _,
-                ) => (*hw_signed, *app_signed, None, None),
+                ) => (*hw_signed, *app_signed, None, None, self.action),
 
                 // Everything else: unsigned context
                 // - W3 without ActionProof header (KeyClaims ignored for W3)
-                _ => (false, false, None, None),
+                _ => (false, false, None, None, self.action),
             };
 
-        // Validate against proof requirement
-        validate_proof_requirement(hw_signed, app_signed, self.proof)?;
+        // Validate against proof requirement, using the policy of whichever
+        // action the proof actually verified against.
+        validate_proof_requirement(hw_signed, app_signed, self.proof, verified_action)?;
 
         Ok(AuthorizedContext {
             account_id: auth.account_id.clone(),
@@ -721,10 +743,39 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn check_defaults_to_action_signer_policy() {
+        // CreateLostHardwareRecovery declares an any-factor policy, so the app
+        // alone suffices without an explicit .proof().
+        let auth = make_key_claims_auth(false, true);
+        let result =
+            AuthorizationRequirements::new(Action::CreateLostHardwareRecovery, HardwareType::W1)
+                .check_for_test(&auth);
+        assert!(result.is_ok());
+
+        let auth = make_key_claims_auth(false, false);
+        let err =
+            AuthorizationRequirements::new(Action::CreateLostHardwareRecovery, HardwareType::W1)
+                .check_for_test(&auth)
+                .unwrap_err();
+        assert!(
+            matches!(err, ApiError::GenericForbidden(ref msg) if msg == "at least one signature required"),
+            "got: {err:?}"
+        );
+
+        // An explicit .proof() still overrides the action's policy.
+        let auth = make_key_claims_auth(false, true);
+        let result =
+            AuthorizationRequirements::new(Action::CreateLostHardwareRecovery, HardwareType::W1)
+                .proof(ProofRequirement::BothFactors)
+                .check_for_test(&auth);
+        assert!(result.is_err());
+    }
+
     #[test]
     fn check_defaults_to_both_factors() {
         let auth = make_key_claims_auth(true, true);
-        // Don't explicitly call .proof() - should default to BothFactors
+        // Don't explicitly call .proof() - SetRecoveryEmail's policy requires both factors
         let result = AuthorizationRequirements::new(Action::SetRecoveryEmail, HardwareType::W1)
             .value("test@example.com")
             .check_for_test(&auth);
@@ -1224,6 +1275,109 @@ mod tests {
         assert!(result.is_err());
     }
 
+    fn with_version(mut auth: Authorization, v: u8) -> Authorization {
+        if let AuthorizationInner::ActionProof { version, .. } = &mut auth.inner {
+            *version = v;
+        }
+        auth
+    }
+
+    #[test]
+    fn check_action_proof_v2_fields_derived_from_value() {
+        use crate::test_utils::{
+            get_test_access_token, get_test_app_key, get_test_app_pubkey, get_test_hw_key,
+            get_test_hw_pubkey, sign_action_proof,
+        };
+        use action_proof::{build_payload_v2, compute_token_binding, Action, ContextBinding};
+
+        let jwt = get_test_access_token();
+        let action = Action::SetDelayNotifyPeriod;
+        let nonce = test_nonce();
+
+        let token_binding = compute_token_binding(&jwt);
+        let payload = build_payload_v2(
+            action,
+            &[("days", "7")],
+            &[
+                (ContextBinding::Nonce.key(), nonce),
+                (ContextBinding::TokenBinding.key(), &token_binding),
+            ],
+        )
+        .unwrap();
+
+        let auth = with_version(
+            make_action_proof_auth(
+                Some(sign_action_proof(&payload, get_test_hw_key())),
+                Some(sign_action_proof(&payload, get_test_app_key())),
+                Some(get_test_hw_pubkey()),
+                Some(get_test_app_pubkey()),
+                &jwt,
+                nonce,
+            ),
+            action_proof::CANONICAL_VERSION_V2,
+        );
+
+        let result = AuthorizationRequirements::new(action, HardwareType::W3)
+            .value("7")
+            .check_for_test(&auth);
+
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn check_action_proof_v2_explicit_fields() {
+        use crate::test_utils::{
+            get_test_access_token, get_test_app_key, get_test_app_pubkey, get_test_hw_key,
+            get_test_hw_pubkey, sign_action_proof,
+        };
+        use action_proof::{build_payload_v2, compute_token_binding, Action, ContextBinding};
+
+        let jwt = get_test_access_token();
+        let action = Action::SetSpendWithoutHardware;
+        let nonce = test_nonce();
+
+        let token_binding = compute_token_binding(&jwt);
+        let sign_fields = |amount: &str| {
+            let payload = build_payload_v2(
+                action,
+                &[("amount", amount), ("currency", "USD")],
+                &[
+                    (ContextBinding::Nonce.key(), nonce),
+                    (ContextBinding::TokenBinding.key(), &token_binding),
+                ],
+            )
+            .unwrap();
+            with_version(
+                make_action_proof_auth(
+                    Some(sign_action_proof(&payload, get_test_hw_key())),
+                    Some(sign_action_proof(&payload, get_test_app_key())),
+                    Some(get_test_hw_pubkey()),
+                    Some(get_test_app_pubkey()),
+                    &jwt,
+                    nonce,
+                ),
+                action_proof::CANONICAL_VERSION_V2,
+            )
+        };
+
+        // Display value differs from the typed fields; v2 verifies the fields.
+        let requirements = AuthorizationRequirements::new(action, HardwareType::W3)
+            .value("50.00 USD")
+            .field("amount", "5000")
+            .field("currency", "USD");
+
+        assert!(requirements
+            .clone()
+            .check_for_test(&sign_fields("5000"))
+            .is_ok());
+
+        // Client signed a different amount than the server will apply
+        assert!(matches!(
+            requirements.check_for_test(&sign_fields("9000")),
+            Err(ApiError::GenericForbidden(_))
+        ));
+    }
+
     #[test]
     fn entity_id_adds_binding() {
         let reqs = AuthorizationRequirements::new(Action::SetRecoveryEmail, HardwareType::W1)
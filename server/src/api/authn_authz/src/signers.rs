@@ -3,10 +3,18 @@
 //! `ProofRequirement` expresses the route's intent for what level of
 //! cryptographic proof of possession is needed, independent of the
 //! underlying mechanism (ActionProof for W3, KeyClaims for W1).
+//! Requirements that demand signatures resolve to an
+//! `action_proof::SignerPolicy`, which is what actually gets evaluated.
+
+use action_proof::{Action, SignerPolicy};
 
 /// Specifies what level of proof of possession is required.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ProofRequirement {
+    /// Enforce the signer policy the bound action declares via
+    /// `Action::signer_policy()`. Falls back to `BothFactors` if no action is bound.
+    ActionPolicy,
+
     /// Both app AND hardware must prove possession.
     /// Reject if either signature is missing.
     BothFactors,
@@ -29,3 +37,17 @@ pub enum ProofRequirement {
     /// Signatures are neither expected nor verified.
     JwtOnly,
 }
+
+impl ProofRequirement {
+    /// The signer policy to evaluate, or `None` if signatures aren't enforced.
+    pub fn signer_policy(&self, action: Option<Action>) -> Option<SignerPolicy> {
+        match self {
+            Self::ActionPolicy => Some(action.map_or(SignerPolicy::HARDWARE_AND_APP, |action| {
+                action.signer_policy()
+            })),
+            Self::BothFactors => Some(SignerPolicy::HARDWARE_AND_APP),
+            Self::AnyFactor => Some(SignerPolicy::ANY_FACTOR),
+            Self::Conditional | Self::JwtOnly => None,
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use bdk_utils::bdk::bitcoin::secp256k1::PublicKey;
+use bdk_utils::bdk::bitcoin::{secp256k1::PublicKey, Network};
 use serde::{Deserialize, Serialize};
 use types::{
     account::{entities::TransactionVerificationPolicy, spend_limit::SpendingLimit},
@@ -26,4 +26,7 @@ pub struct TransactionVerificationFeatures {
     pub grant: Option<TransactionVerificationGrantView>,
     pub wik_pub_key: PublicKey,
     pub expected_hw_auth_public_key: PublicKey,
+    /// The attested serial of the active keyset's hardware, which version 2 grants must be bound to.
+    pub expected_hardware_serial: Option<String>,
+    pub network: Network,
 }
@@ -567,6 +567,8 @@ async fn setup_mobile_pay(
 
     let result = AuthorizationRequirements::new(Action::SetSpendWithoutHardware, hardware_type)
         .value(action_proof_value)
+        .field("amount", request.limit.amount.amount.to_string())
+        .field("currency", request.limit.amount.currency_code.to_string())
         .extra("currency", request.limit.amount.currency_code.to_string())
         .execute(&auth, &anti_replay_repository, |_ctx| async move {
             if !Currency::supported_currency_codes().contains(&request.limit.amount.currency_code) {
@@ -515,11 +515,19 @@ impl SigningStrategyFactory {
             daily_limit_sats,
         };
 
+        let expected_hardware_serial = full_account
+            .active_spending_keyset()
+            .and_then(SpendingKeyset::optional_private_multi_sig)
+            .and_then(|keyset| keyset.attested_hardware_serial.as_ref())
+            .map(|serial| serial.serial().to_string());
+
         let transaction_verification_features = Self::create_transaction_verification_features(
             &full_account.id,
             &full_account.transaction_verification_policy,
             grant,
             full_account.hardware_auth_pubkey,
+            expected_hardware_serial,
+            network.into(),
             config,
             exchange_rate_service,
             feature_flags_service,
@@ -595,6 +603,8 @@ impl SigningStrategyFactory {
         policy: &Option<TransactionVerificationPolicy>,
         grant: Option<TransactionVerificationGrantView>,
         expected_hw_auth_public_key: PublicKey,
+        expected_hardware_serial: Option<String>,
+        network: Network,
         config: &Config,
         exchange_rate_service: &ExchangeRateService,
         feature_flags_service: &FeatureFlagsService,
@@ -615,6 +625,8 @@ impl SigningStrategyFactory {
                     grant,
                     wik_pub_key: config.wik_pub_key,
                     expected_hw_auth_public_key,
+                    expected_hardware_serial,
+                    network,
                 }))
             }
             Some(TransactionVerificationPolicy::Always) => {
@@ -624,6 +636,8 @@ impl SigningStrategyFactory {
                     grant,
                     wik_pub_key: config.wik_pub_key,
                     expected_hw_auth_public_key,
+                    expected_hardware_serial,
+                    network,
                 }))
             }
             Some(TransactionVerificationPolicy::Never) | None => Ok(None),
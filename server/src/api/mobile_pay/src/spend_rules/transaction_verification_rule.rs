@@ -3,14 +3,17 @@ use crate::spend_rules::{errors::SpendRuleCheckError, Rule};
 
 use bdk_utils::bdk::bitcoin::psbt::Psbt;
 use bdk_utils::bdk::bitcoin::secp256k1::PublicKey;
-use bdk_utils::bdk::bitcoin::secp256k1::Secp256k1;
 use bdk_utils::bdk::Wallet;
 use bdk_utils::{
     get_total_outflow_for_psbt, ChaincodeDelegationCollaboratorWallet, ChaincodeDelegationPsbt,
 };
+use time::OffsetDateTime;
 use types::account::spending::PrivateMultiSigSpendingKeyset;
 use types::transaction_verification::router::TransactionVerificationGrantView;
-use wsm_compat::{wsm_inputs_from_bdk_psbt, wsm_pubkey_from_bdk, wsm_signature_from_bdk};
+use wsm_compat::wsm_inputs_from_bdk_psbt;
+use wsm_grant::tx_verification::{
+    TxVerificationApproval, TxVerificationExpectations, TX_VERIFICATION_V1_ACCEPTED_UNTIL,
+};
 
 pub struct TransactionVerificationRule<'a> {
     wallet: &'a Wallet,
@@ -122,7 +125,18 @@ impl TransactionVerificationFeaturesExt for TransactionVerificationFeatures {
         };
 
         validate_commitment(grant, psbt)?;
-        verify_grant_signature(grant, &self.wik_pub_key, &self.expected_hw_auth_public_key)?;
+        let expected = TxVerificationExpectations {
+            hardware_serial: self.expected_hardware_serial.as_deref().unwrap_or_default(),
+            network: self.network,
+            now: OffsetDateTime::now_utc().unix_timestamp() as u64,
+            v1_accepted_until: TX_VERIFICATION_V1_ACCEPTED_UNTIL,
+        };
+        verify_grant_signature(
+            grant,
+            &self.wik_pub_key,
+            &self.expected_hw_auth_public_key,
+            &expected,
+        )?;
 
         Ok(false)
     }
@@ -132,28 +146,20 @@ fn verify_grant_signature(
     grant: &TransactionVerificationGrantView,
     wik_pub_key: &PublicKey,
     expected_hw_auth_public_key: &PublicKey,
+    expected: &TxVerificationExpectations,
 ) -> Result<(), SpendRuleCheckError> {
     if &grant.hw_auth_public_key != expected_hw_auth_public_key {
         return Err(SpendRuleCheckError::InvalidTransactionVerificationGrant);
     }
 
-    let secp = Secp256k1::verification_only();
-    let hw_auth_public_key_wsm = wsm_pubkey_from_bdk(&grant.hw_auth_public_key)
-        .map_err(|_| SpendRuleCheckError::InvalidTransactionVerificationGrant)?;
-    let message = wsm_grant::tx_verification::generate_message(
+    TxVerificationApproval::from_parts(
+        grant.version,
         grant.hw_auth_public_key,
-        grant.commitment.clone(),
+        &grant.commitment,
+        grant.envelope.as_deref(),
     )
-    .map_err(|_| SpendRuleCheckError::GenerateTransactionVerificationMessage)?;
-
-    let signature_wsm = wsm_signature_from_bdk(&grant.signature)
-        .map_err(|_| SpendRuleCheckError::InvalidTransactionVerificationGrant)?;
-    let wik_pub_key_wsm = wsm_pubkey_from_bdk(wik_pub_key)
-        .map_err(|_| SpendRuleCheckError::InvalidTransactionVerificationGrant)?;
-    secp.verify_ecdsa(&message, &signature_wsm, &wik_pub_key_wsm)
-        .map_err(|_| SpendRuleCheckError::InvalidCommitment)?;
-
-    Ok(())
+    .and_then(|approval| approval.verify(&grant.signature, wik_pub_key, expected))
+    .map_err(|_| SpendRuleCheckError::InvalidTransactionVerificationGrant)
 }
 
 fn validate_commitment(
@@ -186,10 +192,23 @@ fn validate_commitment(
 
 #[cfg(test)]
 mod tests {
-    use bdk_utils::bdk::bitcoin::secp256k1::{ecdsa::Signature, SecretKey};
+    use bdk_utils::bdk::bitcoin::secp256k1::{ecdsa::Signature, Secp256k1, SecretKey};
+    use bdk_utils::bdk::bitcoin::Network;
 
     use super::*;
 
+    const HARDWARE_SERIAL: &str = "000WS27100000000";
+    const NOW: u64 = 1_700_000_000;
+
+    fn expectations() -> TxVerificationExpectations<'static> {
+        TxVerificationExpectations {
+            hardware_serial: HARDWARE_SERIAL,
+            network: Network::Bitcoin,
+            now: NOW,
+            v1_accepted_until: TX_VERIFICATION_V1_ACCEPTED_UNTIL,
+        }
+    }
+
     #[test]
     fn grant_from_another_account_is_rejected() {
         let secp = Secp256k1::new();
@@ -213,17 +232,117 @@ mod tests {
             )
             .unwrap(),
             reverse_hash_chain: vec![vec![0u8; 32]],
+            envelope: None,
         };
 
-        assert!(verify_grant_signature(&grant, &wik_public_key, &grant_hw_auth_key).is_ok());
+        assert!(verify_grant_signature(
+            &grant,
+            &wik_public_key,
+            &grant_hw_auth_key,
+            &expectations()
+        )
+        .is_ok());
         let result = verify_grant_signature(
             &grant,
             &wik_public_key,
             &current_account_hw_auth_key,
+            &expectations(),
         );
         assert!(matches!(
             result,
             Err(SpendRuleCheckError::InvalidTransactionVerificationGrant)
         ));
     }
+
+    #[test]
+    fn bound_grant_is_checked_against_hardware_and_expiry() {
+        let secp = Secp256k1::new();
+        let wik_secret_key = SecretKey::from_slice(&[1u8; 32]).unwrap();
+        let wik_public_key = wik_secret_key.public_key(&secp);
+        let hw_auth_key = SecretKey::from_slice(&[2u8; 32]).unwrap().public_key(&secp);
+        let approval = TxVerificationApproval::v2(
+            hw_auth_key,
+            &[4u8; 32],
+            HARDWARE_SERIAL,
+            Network::Bitcoin,
+            [5u8; 16],
+            NOW + 60,
+        )
+        .unwrap();
+        let grant = TransactionVerificationGrantView {
+            version: approval.version(),
+            hw_auth_public_key: hw_auth_key,
+            commitment: approval.commitment(),
+            signature: approval.sign(&wik_secret_key).unwrap(),
+            reverse_hash_chain: vec![vec![0u8; 32]],
+            envelope: approval.envelope().unwrap(),
+        };
+
+        assert!(
+            verify_grant_signature(&grant, &wik_public_key, &hw_auth_key, &expectations()).is_ok()
+        );
+
+        let other_hardware = TxVerificationExpectations {
+            hardware_serial: "000WS27100000001",
+            ..expectations()
+        };
+        let other_network = TxVerificationExpectations {
+            network: Network::Testnet,
+            ..expectations()
+        };
+        let expired = TxVerificationExpectations {
+            now: NOW + 60,
+            ..expectations()
+        };
+        for expected in [other_hardware, other_network, expired] {
+            assert!(matches!(
+                verify_grant_signature(&grant, &wik_public_key, &hw_auth_key, &expected),
+                Err(SpendRuleCheckError::InvalidTransactionVerificationGrant)
+            ));
+        }
+    }
+
+    #[test]
+    fn grant_with_mismatched_envelope_is_rejected() {
+        let secp = Secp256k1::new();
+        let wik_secret_key = SecretKey::from_slice(&[1u8; 32]).unwrap();
+        let wik_public_key = wik_secret_key.public_key(&secp);
+        let hw_auth_key = SecretKey::from_slice(&[2u8; 32]).unwrap().public_key(&secp);
+        let approval = TxVerificationApproval::v2(
+            hw_auth_key,
+            &[4u8; 32],
+            HARDWARE_SERIAL,
+            Network::Bitcoin,
+            [5u8; 16],
+            NOW + 60,
+        )
+        .unwrap();
+        let valid = TransactionVerificationGrantView {
+            version: approval.version(),
+            hw_auth_public_key: hw_auth_key,
+            commitment: approval.commitment(),
+            signature: approval.sign(&wik_secret_key).unwrap(),
+            reverse_hash_chain: vec![vec![0u8; 32]],
+            envelope: approval.envelope().unwrap(),
+        };
+
+        let other_commitment = TransactionVerificationGrantView {
+            commitment: vec![6u8; 32],
+            ..valid.clone()
+        };
+        let missing_envelope = TransactionVerificationGrantView {
+            envelope: None,
+            ..valid.clone()
+        };
+        let downgraded = TransactionVerificationGrantView {
+            version: 0,
+            ..valid
+        };
+        for grant in [other_commitment, missing_envelope, downgraded] {
+            assert!(matches!(
+                verify_grant_signature(&grant, &wik_public_key, &hw_auth_key, &expectations()),
+                Err(SpendRuleCheckError::InvalidTransactionVerificationGrant)
+            ));
+        }
+    }
 }
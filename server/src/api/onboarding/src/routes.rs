@@ -2018,6 +2018,7 @@ pub async fn rotate_spending_keyset(
         .map_err(|e| ApiError::GenericInternalApplicationError(e.to_string()))?;
 
     let response = AuthorizationRequirements::new(Action::RotateSpendingKeyset, hardware_type)
+        .field("keyset_id", keyset_id.to_string())
         .entity_id(keyset_id.to_string())
         .execute(&auth, &anti_replay_repository, |_ctx| async move {
             // Rotate to the new keyset
@@ -425,7 +425,6 @@ pub async fn create_delay_notify(
 
     let result =
         AuthorizationRequirements::new(create_recovery_action(request.lost_factor), hardware_type)
-            .proof(authn_authz::ProofRequirement::AnyFactor)
             .execute(
                 &auth,
                 &anti_replay_repository,
@@ -663,7 +662,6 @@ pub async fn cancel_delay_notify(
 
     AuthorizationRequirements::new(cancel_action, hardware_type)
         .or_action(Action::CancelConflictingRecovery)
-        .proof(authn_authz::ProofRequirement::AnyFactor)
         .execute(
             &auth,
             &anti_replay_repository,
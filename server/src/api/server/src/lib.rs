@@ -168,6 +168,8 @@ pub enum BootstrapError {
     Telemetry(#[from] wallet_telemetry::Error),
     #[error(transparent)]
     Metrics(#[from] instrumentation::metrics::error::MetricsError),
+    #[error("hardware attestation trust store: {0}")]
+    TrustStore(#[from] account::attestation_verifier::AttestationError),
 }
 
 #[derive(Default)]
@@ -386,6 +388,8 @@ impl BootstrapBuilder {
         profile: Option<&str>,
         repositories: &Repositories,
     ) -> Result<Services, BootstrapError> {
+        account::attestation_verifier::load_trust_store(&config::extract(profile)?)?;
+
         let cognito_config = config::extract::<userpool::userpool::Config>(profile)?;
         let cognito_connection = cognito_config.to_connection().await;
         let userpool_service = UserPoolService::new(cognito_connection);
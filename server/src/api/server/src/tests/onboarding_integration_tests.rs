@@ -23,7 +23,7 @@ use bdk_utils::{
     generate_rpc_client, treasury_fund_address,
 };
 use comms_verification::TEST_CODE;
-use crypto::ssb::server::SelfSovereignBackup;
+use crypto::ssb::server::SelfSovereignBackupV1;
 use crypto::ssb::testapp::{decrypt_ssb, generate_lka_lkn};
 use errors::ApiError;
 use external_identifier::ExternalIdentifier;
@@ -2138,11 +2138,12 @@ async fn software_onboarding_keygen_activation_test() {
     decrypt_ssb(
         lka,
         lkn,
-        SelfSovereignBackup {
+        SelfSovereignBackupV1 {
             eph_pub,
             nonce,
             ciphertext,
-        },
+        }
+        .into(),
     )
     .unwrap();
 }
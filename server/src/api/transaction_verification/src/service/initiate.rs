@@ -1,4 +1,4 @@
-use super::Service;
+use super::{hardware_binding, Service};
 use crate::error::TransactionVerificationError;
 use account::service::FetchAccountInput;
 use bdk_utils::bdk::bitcoin::psbt::Psbt;
@@ -65,7 +65,11 @@ impl Service {
         let result = if !needs_verify {
             let grant = self
                 .grant_service
-                .approve_psbt(&psbt.to_string(), hardware_auth_pubkey)
+                .approve_psbt(
+                    &psbt.to_string(),
+                    hardware_auth_pubkey,
+                    hardware_binding(account),
+                )
                 .await
                 .map_err(TransactionVerificationError::from)?;
 
@@ -85,6 +89,7 @@ impl Service {
                     commitment: grant.commitment,
                     signature,
                     reverse_hash_chain: grant.reverse_hash_chain,
+                    envelope: grant.envelope,
                 },
             }
         } else if !should_prompt_user {
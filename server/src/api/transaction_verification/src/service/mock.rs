@@ -4,7 +4,7 @@ use wsm_common::bitcoin::hashes::{sha256, Hash, HashEngine};
 use wsm_common::bitcoin::secp256k1::{Message, Secp256k1, SecretKey};
 use wsm_common::messages::api::TransactionVerificationGrant;
 use wsm_compat::wsm_pubkey_from_bdk;
-use wsm_rust_client::{Error, GrantService};
+use wsm_rust_client::{Error, GrantService, HardwareBinding};
 
 /// Mock implementation of GrantService for testing
 pub struct MockGrantService {
@@ -33,6 +33,7 @@ impl GrantService for MockGrantService {
         &self,
         psbt: &str,
         hw_auth_public_key: PublicKey,
+        _hardware_binding: Option<HardwareBinding>,
     ) -> Result<TransactionVerificationGrant, Error> {
         // Create a deterministic signature based on the PSBT and public key
         let mut hasher = sha256::HashEngine::default();
@@ -55,6 +56,7 @@ impl GrantService for MockGrantService {
             commitment: commitment.to_byte_array().to_vec(),
             reverse_hash_chain: vec![commitment.to_byte_array().to_vec()],
             signature,
+            envelope: None,
         })
     }
 }
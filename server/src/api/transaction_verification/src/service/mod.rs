@@ -6,7 +6,9 @@ use notification::service::Service as NotificationService;
 use screener::screening::SanctionsScreener;
 use serde::Deserialize;
 use std::sync::Arc;
-use wsm_rust_client::GrantService;
+use types::account::bitcoin::to_wsm_bitcoin_network;
+use types::account::entities::FullAccount;
+use wsm_rust_client::{GrantService, HardwareBinding};
 
 mod cancel;
 mod fetch;
@@ -58,3 +60,17 @@ impl Service {
         }
     }
 }
+
+/// Binds grants to the hardware attested for the account's active keyset. Keysets without an
+/// attested serial get version 1 grants while those remain accepted.
+fn hardware_binding(account: &FullAccount) -> Option<HardwareBinding> {
+    let keyset = account.active_spending_keyset()?;
+    let serial = keyset
+        .optional_private_multi_sig()?
+        .attested_hardware_serial
+        .as_ref()?;
+    Some(HardwareBinding {
+        hardware_serial: serial.serial().to_string(),
+        network: to_wsm_bitcoin_network(keyset.network().into()),
+    })
+}
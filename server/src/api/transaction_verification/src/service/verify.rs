@@ -1,6 +1,6 @@
 use tracing::instrument;
 
-use super::Service;
+use super::{hardware_binding, Service};
 use crate::error::TransactionVerificationError;
 use types::transaction_verification::TransactionVerificationId;
 use types::{
@@ -47,7 +47,7 @@ impl Service {
 
         let grant = self
             .grant_service
-            .approve_psbt(&psbt_str, hardware_auth_pubkey)
+            .approve_psbt(&psbt_str, hardware_auth_pubkey, hardware_binding(&account))
             .await
             .map_err(TransactionVerificationError::from)?;
 
@@ -63,6 +63,7 @@ impl Service {
             reverse_hash_chain: grant.reverse_hash_chain,
             commitment: grant.commitment,
             signature,
+            envelope: grant.envelope,
         };
 
         // Update the transaction verification status to success with the signed grant
@@ -304,6 +304,7 @@ mod tests {
             commitment: vec![],
             reverse_hash_chain: vec![],
             signature: Signature::from_compact(&[0u8; 64]).unwrap(),
+            envelope: None,
         };
 
         if let TransactionVerification::Pending(pending) = TransactionVerification::new_pending(
@@ -358,6 +359,7 @@ mod tests {
                 commitment: vec![],
                 reverse_hash_chain: vec![],
                 signature: Signature::from_compact(&[0u8; 64]).unwrap(),
+                envelope: None,
             };
             // Test for all variants
             let pending_tx = TransactionVerification::Pending(pending.clone());
@@ -59,6 +59,12 @@ pub struct TransactionVerificationGrantView {
     pub signature: Signature,
     #[serde_as(as = "Vec<Base64>")]
     pub reverse_hash_chain: Vec<Vec<u8>>,
+    /// The serialized grant envelope, which binds a version 2 grant to one device and network
+    /// and sets its expiry.
+    #[serde_as(as = "Option<Base64>")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<String>)]
+    pub envelope: Option<Vec<u8>>,
 }
 
 #[derive(Serialize, Deserialize, ToSchema)]
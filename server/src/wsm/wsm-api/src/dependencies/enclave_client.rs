@@ -257,6 +257,24 @@ impl EnclaveClient {
         Ok(result.json().await?)
     }
 
+    #[instrument(skip(self, bundle, server_static_pubkey))]
+    pub async fn resume_secure_channel(
+        &self,
+        bundle: Vec<u8>,
+        server_static_pubkey: &str,
+    ) -> anyhow::Result<NoiseInitiateBundleResponse> {
+        let result = self
+            .client
+            .post(self.endpoint.join("resume-secure-channel")?)
+            .json(&NoiseInitiateBundleRequest {
+                bundle,
+                server_static_pubkey: server_static_pubkey.to_string(),
+            })
+            .send()
+            .await?;
+        Ok(result.json().await?)
+    }
+
     #[instrument(skip(self, sealed_request))]
     pub async fn evaluate_pin(
         &self,
@@ -91,6 +91,7 @@ impl From<RouteState> for Router {
                 post(create_self_sovereign_backup),
             )
             .route("/initiate-secure-channel", post(initiate_secure_channel))
+            .route("/resume-secure-channel", post(resume_secure_channel))
             .route("/evaluate-pin", post(evaluate_pin))
             .route("/initiate-share-refresh", post(initiate_distributed_keygen))
             .route("/approve-grant", post(approve_grant))
@@ -665,6 +666,18 @@ async fn initiate_secure_channel(
     Ok(Json(result))
 }
 
+#[instrument(err, skip(enclave_client, request))]
+async fn resume_secure_channel(
+    State(enclave_client): State<Arc<EnclaveClient>>,
+    Json(request): Json<NoiseInitiateBundleRequest>,
+) -> Result<Json<NoiseInitiateBundleResponse>, ApiError> {
+    let result = enclave_client
+        .resume_secure_channel(request.bundle, &request.server_static_pubkey)
+        .await
+        .map_err(|e| ApiError::ServerError(format!("Failed to resume secure channel: {e}")))?;
+    Ok(Json(result))
+}
+
 #[instrument(err, skip(enclave_client, request))]
 async fn evaluate_pin(
     State(enclave_client): State<Arc<EnclaveClient>>,
@@ -196,6 +196,10 @@ pub struct NoiseInitiateBundleResponse {
     #[serde_as(as = "Base64")]
     pub bundle: Vec<u8>,
     pub noise_session_id: String,
+    /// Resumes the session later through `resume-secure-channel`, skipping the static-key DH.
+    #[serde(default)]
+    #[serde_as(as = "Option<Base64>")]
+    pub session_ticket: Option<Vec<u8>>,
 }
 
 #[serde_as]
@@ -252,6 +256,16 @@ pub struct TransactionVerificationGrant {
     pub reverse_hash_chain: Vec<Vec<u8>>,
     #[serde_as(as = "DisplayFromStr")]
     pub signature: Signature,
+    /// The serialized grant envelope that `signature` covers. Set on version 2 grants only.
+    #[serde_as(as = "Option<Base64>")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub envelope: Option<Vec<u8>>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct HardwareBinding {
+    pub hardware_serial: String,
+    pub network: Network,
 }
 
 #[serde_as]
@@ -259,6 +273,10 @@ pub struct TransactionVerificationGrant {
 pub struct ApprovePsbtRequest {
     pub psbt: String,
     pub hw_auth_public_key: PublicKey,
+    /// When set, the WSM issues an expiring version 2 grant bound to this hardware. Otherwise it
+    /// issues a version 1 grant, for the migration window.
+    #[serde(default)]
+    pub hardware_binding: Option<HardwareBinding>,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
@@ -6,6 +6,7 @@ use wsm_common::bitcoin::secp256k1::PublicKey;
 use wsm_common::messages::api::GrantResponse;
 use wsm_common::messages::enclave::GrantRequest;
 use wsm_grant::fp_reset::verify_grant_request_signature;
+use wsm_grant::grant::{Grant, GrantKind, SignedGrant};
 
 const GRANT_PROTOCOL_VERSION: u8 = 1;
 const GRANT_SIG_PREFIX: &[u8] = b"BKGrant";
@@ -42,6 +43,16 @@ impl GrantCreator {
         })
     }
 
+    /// Signs a grant in the shared envelope format. Callers are responsible for having authorized
+    /// the grant's kind; this only refuses grants that are already expired.
+    pub fn sign_grant<K: GrantKind>(&self, grant: Grant<K>, now: u64) -> Result<SignedGrant<K>> {
+        if now >= grant.expires_at {
+            bail!("Grant already expired at {}", grant.expires_at);
+        }
+
+        grant.sign(&self.wik_private_key)
+    }
+
     fn create_wsm_signature(&self, version: u8, request: &GrantRequest) -> Result<Signature> {
         let mut signing_input = Vec::new();
 
@@ -66,6 +77,7 @@ mod tests {
     use rand::{rngs::StdRng, SeedableRng};
     use std::str::FromStr;
     use wsm_grant::fp_reset::GRANT_REQUEST_SIG_PREFIX;
+    use wsm_grant::grant::{FirmwareDowngrade, Wipe};
 
     const TEST_RNG_SEED: [u8; 32] = [0x42; 32];
 
@@ -251,4 +263,57 @@ mod tests {
         assert_eq!(grant.app_signature, request.app_signature);
         assert_eq!(grant.wsm_signature, wsm_signature);
     }
+
+    #[test]
+    fn test_sign_grant_envelope() {
+        // arrange
+        let (wik_private_key, hw_auth_public_key, _, _, _) = setup_test_keys();
+        let wik_public_key = PublicKey::from_secret_key(&Secp256k1::new(), &wik_private_key);
+        let device_id = [0x38, 0x39, 0x8f, 0xff, 0xfe, 0xd0, 0x81, 0xb6];
+        let grant = Grant {
+            device_id,
+            nonce: [0x5a; 16],
+            expires_at: 1_700_000_000,
+            kind: FirmwareDowngrade { target_version: 7 },
+        };
+
+        let processor = GrantCreator {
+            wik_private_key,
+            hw_auth_public_key,
+        };
+
+        // act
+        let signed = processor.sign_grant(grant.clone(), 1_600_000_000).unwrap();
+
+        // assert
+        assert_eq!(signed.grant, grant);
+        let parsed =
+            SignedGrant::<FirmwareDowngrade>::deserialize(&signed.serialize().unwrap()).unwrap();
+        assert!(parsed
+            .verify(&wik_public_key, &device_id, 1_600_000_000)
+            .is_ok());
+    }
+
+    #[test]
+    fn test_sign_grant_rejects_expired() {
+        // arrange
+        let (wik_private_key, hw_auth_public_key, _, _, _) = setup_test_keys();
+        let grant = Grant {
+            device_id: [0; 8],
+            nonce: [0; 16],
+            expires_at: 1_700_000_000,
+            kind: Wipe,
+        };
+
+        let processor = GrantCreator {
+            wik_private_key,
+            hw_auth_public_key,
+        };
+
+        // act
+        let result = processor.sign_grant(grant, 1_700_000_000);
+
+        // assert
+        assert!(result.unwrap_err().to_string().contains("expired"));
+    }
 }
@@ -41,7 +41,7 @@ use crypto::frost::zkp::frost::FrostPartialSignature;
 use crypto::frost::KeyCommitments;
 use crypto::frost::Participant;
 use crypto::frost::ShareDetails;
-use crypto::ssb::server::create_ssb;
+use crypto::ssb::server::create_v1_ssb;
 use noise_cache::KeyType;
 use noise_cache::NoiseCache;
 use rand::rngs::StdRng;
@@ -415,6 +415,23 @@ async fn attestation_doc(
 async fn initiate_secure_channel(
     State(noise_cache): State<Arc<RwLock<NoiseCache>>>,
     Json(request): Json<NoiseInitiateBundleRequest>,
+) -> Result<Json<NoiseInitiateBundleResponse>, WsmError> {
+    establish_secure_channel(&noise_cache, request, false).await
+}
+
+// Resumes a secure channel with a session ticket issued by `initiate_secure_channel`, or by an
+// earlier resumption.
+async fn resume_secure_channel(
+    State(noise_cache): State<Arc<RwLock<NoiseCache>>>,
+    Json(request): Json<NoiseInitiateBundleRequest>,
+) -> Result<Json<NoiseInitiateBundleResponse>, WsmError> {
+    establish_secure_channel(&noise_cache, request, true).await
+}
+
+async fn establish_secure_channel(
+    noise_cache: &Arc<RwLock<NoiseCache>>,
+    request: NoiseInitiateBundleRequest,
+    resume: bool,
 ) -> Result<Json<NoiseInitiateBundleResponse>, WsmError> {
     let log_buffer = LogBuffer::new();
 
@@ -442,7 +459,11 @@ async fn initiate_secure_channel(
     };
 
     let mut noise_cache = noise_cache.write().await;
-    let noise_ctx = noise_cache.add_session(session_id.clone(), key_type);
+    let noise_ctx = if resume {
+        noise_cache.add_resumed_session(session_id.clone(), key_type)
+    } else {
+        noise_cache.add_session(session_id.clone(), key_type)
+    };
 
     let response =
         noise_ctx
@@ -470,9 +491,17 @@ async fn initiate_secure_channel(
             log_buffer: log_buffer.clone(),
         })?;
 
+    let session_ticket = noise_cache
+        .issue_session_ticket(&session_id, key_type)
+        .map_err(|e| WsmError::ServerError {
+            message: format!("Failed to issue session ticket: {}", e),
+            log_buffer: log_buffer.clone(),
+        })?;
+
     Ok(Json(NoiseInitiateBundleResponse {
         bundle: response,
         noise_session_id: session_id,
+        session_ticket: Some(session_ticket),
     }))
 }
 
@@ -1118,7 +1147,8 @@ async fn create_self_sovereign_backup(
             log_buffer: log_buffer.clone(),
         })?;
 
-    let backup = create_ssb(
+    // The app only decrypts v1 backups so far.
+    let backup = create_v1_ssb(
         unsealed_request
             .lka_pub
             .try_into()
@@ -1349,12 +1379,25 @@ async fn approve_psbt(
             log_buffer: log_buffer.clone(),
         })?;
 
-    let approval =
-        crate::psbt_approval::approve_psbt(integrity_key, request.hw_auth_public_key, psbt)
-            .map_err(|e| WsmError::ServerError {
-                message: format!("Failed to approve PSBT: {}", e),
-                log_buffer: log_buffer.clone(),
-            })?;
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_err(|e| WsmError::ServerError {
+            message: format!("Failed to read the clock: {}", e),
+            log_buffer: log_buffer.clone(),
+        })?
+        .as_secs();
+
+    let approval = crate::psbt_approval::approve_psbt(
+        integrity_key,
+        request.hw_auth_public_key,
+        psbt,
+        request.hardware_binding,
+        now,
+    )
+    .map_err(|e| WsmError::ServerError {
+        message: format!("Failed to approve PSBT: {}", e),
+        log_buffer: log_buffer.clone(),
+    })?;
 
     Ok(Json(ApprovePsbtResponse { approval }))
 }
@@ -1777,6 +1820,7 @@ impl From<RouteState> for Router {
                 post(create_self_sovereign_backup),
             )
             .route("/initiate-secure-channel", post(initiate_secure_channel))
+            .route("/resume-secure-channel", post(resume_secure_channel))
             .route("/evaluate-pin", post(evaluate_pin))
             .route("/initiate-share-refresh", post(initiate_share_refresh))
             .route("/continue-share-refresh", post(continue_share_refresh))
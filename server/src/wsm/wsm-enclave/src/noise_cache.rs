@@ -1,13 +1,17 @@
-use crypto::noise::{NoiseContext, NoiseRole, PrivateKey};
+use crypto::hkdf::Hkdf;
+use crypto::noise::{NoiseContext, NoiseRole, NoiseWrapperError, PrivateKey, ReplayGuard};
 use std::{
     collections::{BinaryHeap, HashMap},
+    sync::Arc,
     time::{Duration, Instant},
 };
 
 // Session ID 8 random bytes, base64 encoded
 type SessionId = String;
 
-#[derive(Debug, Hash, Eq, PartialEq)]
+const SESSION_TICKET_KEY_INFO: &[u8] = b"bitkey/wsm/noise-session-ticket";
+
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
 pub enum KeyType {
     TestKey,
     ProductionKey,
@@ -61,12 +65,19 @@ impl PartialOrd for ExpiryEntry {
 /// The cache is implemented as a priority queue of session ids with expiry times. We purge
 /// the cache opportunistically. The priority queue allows us to efficiently get the oldest
 /// (i.e. soonest to expire) session.
+///
+/// Finished sessions can be resumed with session tickets, which let the app skip the static-key DH
+/// after a cold start. Ticket keys are derived from the static keys, so tickets survive enclave
+/// restarts. The replay guard doesn't, but replaying a ticket without its resumption secret can't
+/// complete a handshake anyway.
 #[derive(Debug)]
 pub struct NoiseCache {
     sessions: HashMap<SessionId, Session>,
     expiry_queue: BinaryHeap<ExpiryEntry>, // Priority queue based on expiry time
     key_map: HashMap<KeyType, Vec<u8>>,
     max_duration: Duration,
+    replay_guard: Arc<ReplayGuard>,
+    ticket_lifetime: Duration,
 }
 
 impl Default for NoiseCache {
@@ -89,6 +100,8 @@ impl NoiseCache {
             expiry_queue: BinaryHeap::new(),
             key_map,
             max_duration: Duration::from_secs(600), // 10 minutes
+            replay_guard: Arc::new(ReplayGuard::new()),
+            ticket_lifetime: Duration::from_secs(24 * 60 * 60), // 1 day
         }
     }
 
@@ -104,6 +117,44 @@ impl NoiseCache {
         )
         .expect("Failed to create noise context");
 
+        self.insert_session(session_id, noise_ctx)
+    }
+
+    /// Adds a session for an initiator resuming with a ticket from
+    /// [`NoiseCache::issue_session_ticket`].
+    pub fn add_resumed_session(
+        &mut self,
+        session_id: SessionId,
+        key_type: KeyType,
+    ) -> &NoiseContext {
+        let noise_ctx =
+            NoiseContext::resume_responder(self.ticket_key(&key_type), self.replay_guard.clone())
+                .expect("Failed to create noise context");
+
+        self.insert_session(session_id, noise_ctx)
+    }
+
+    /// Issues a ticket for resuming a finished session.
+    pub fn issue_session_ticket(
+        &mut self,
+        session_id: &SessionId,
+        key_type: KeyType,
+    ) -> Result<Vec<u8>, NoiseWrapperError> {
+        let ticket_key = self.ticket_key(&key_type);
+        let ticket_lifetime = self.ticket_lifetime;
+        self.get_session(session_id)
+            .ok_or(NoiseWrapperError::IllegalState)?
+            .issue_session_ticket(&ticket_key, ticket_lifetime)
+    }
+
+    fn ticket_key(&self, key_type: &KeyType) -> Vec<u8> {
+        let private_key = self.key_map.get(key_type).expect("Key type not found");
+        Hkdf::new(&[], private_key)
+            .expand(SESSION_TICKET_KEY_INFO, 32)
+            .expect("Failed to derive session ticket key")
+    }
+
+    fn insert_session(&mut self, session_id: SessionId, noise_ctx: NoiseContext) -> &NoiseContext {
         let expiry = Instant::now() + self.max_duration;
         self.sessions.insert(
             session_id.clone(),
@@ -194,6 +245,44 @@ mod tests {
         assert_eq!(pq.pop().unwrap().session_id, "temp2");
     }
 
+    #[test]
+    fn test_resumed_session() {
+        let mut cache = NoiseCache::new();
+        let client_keypair = crypto::noise::generate_keypair();
+        let client = NoiseContext::new(
+            NoiseRole::Initiator,
+            PrivateKey::InMemory {
+                secret_bytes: client_keypair.0,
+            },
+            Some(hex::decode("046d0f2d82024c8a9defa34ac4a82f659247b38e0fdf3024d579d981f9ed7a8661f8efe8bd86dc1ba05fc986f1c9f12e450edcb1c34d072c7cde13a897767050ab").unwrap()),
+            None,
+        )
+        .unwrap();
+
+        let session_id = "cookie".to_string();
+        let server = cache.add_session(session_id.clone(), KeyType::TestKey);
+        let server_message = server
+            .advance_handshake(client.initiate_handshake().unwrap())
+            .unwrap();
+        server.finalize_handshake().unwrap();
+        client.advance_handshake(server_message.unwrap()).unwrap();
+        client.finalize_handshake().unwrap();
+        let ticket = cache
+            .issue_session_ticket(&session_id, KeyType::TestKey)
+            .unwrap();
+
+        // Tickets still work after a restart, but only once.
+        let mut cache = NoiseCache::new();
+        for (session_id, should_resume) in [("resumed", true), ("replayed", false)] {
+            let resumed_client =
+                NoiseContext::resume_initiator(ticket.clone(), client.resumption_secret().unwrap())
+                    .unwrap();
+            let server = cache.add_resumed_session(session_id.to_string(), KeyType::TestKey);
+            let result = server.advance_handshake(resumed_client.initiate_handshake().unwrap());
+            assert_eq!(result.is_ok(), should_resume);
+        }
+    }
+
     #[test]
     fn test_get_nonexistent_session() {
         let mut cache = NoiseCache::new();
@@ -1,29 +1,39 @@
 use anyhow::Result;
 use bdk_wallet::bitcoin::psbt::Input;
-use bdk_wallet::bitcoin::{
-    psbt::Psbt as PartiallySignedTransaction,
-    secp256k1::{Secp256k1, SecretKey},
-};
+use bdk_wallet::bitcoin::{psbt::Psbt as PartiallySignedTransaction, secp256k1::SecretKey};
 use rand::random;
 use wsm_common::bitcoin::secp256k1::PublicKey;
-use wsm_common::messages::api::TransactionVerificationGrant;
+use wsm_common::messages::api::{HardwareBinding, TransactionVerificationGrant};
+use wsm_grant::tx_verification::TxVerificationApproval;
 
-/// Signs a transaction verification message with the format:
-/// "TVA1" || hw_auth_pubkey || chained_sighashes_final
+/// How long a version 2 grant stays valid, matching how long a verification stays pending.
+const GRANT_LIFETIME_SECS: u64 = 24 * 60 * 60;
+
+/// Signs a transaction verification approval. With a hardware binding this is a version 2 grant
+/// envelope, bound to the hardware and network and expiring after [`GRANT_LIFETIME_SECS`];
+/// without one it's the version 1 message, "TVA1" || hw_auth_pubkey || chained_sighashes_final.
 pub(crate) fn approve_psbt(
     wik_private_key: SecretKey,
     hw_auth_public_key: PublicKey,
     psbt: PartiallySignedTransaction,
+    hardware_binding: Option<HardwareBinding>,
+    now: u64,
 ) -> Result<TransactionVerificationGrant> {
-    approve_inputs(wik_private_key, hw_auth_public_key, psbt.inputs)
+    approve_inputs(
+        wik_private_key,
+        hw_auth_public_key,
+        psbt.inputs,
+        hardware_binding,
+        now,
+    )
 }
 
-/// Signs a transaction verification message with the format:
-/// "TVA1" || hw_auth_pubkey || chained_sighashes_final
 fn approve_inputs(
     wik_private_key: SecretKey,
     hw_auth_public_key: PublicKey,
     inputs: Vec<Input>,
+    hardware_binding: Option<HardwareBinding>,
+    now: u64,
 ) -> Result<TransactionVerificationGrant> {
     // Calculate the chained sighashes from lexicographically sorted inputs
     let init: [u8; 32] = random();
@@ -34,22 +44,31 @@ fn approve_inputs(
         .first()
         .expect("hash chain cannot be empty");
 
-    // Generate the message to sign
-    let message =
-        wsm_grant::tx_verification::generate_message(hw_auth_public_key, commitment.to_owned())?;
-
-    // Create secp context
-    let secp = Secp256k1::new();
+    let approval = match hardware_binding {
+        Some(binding) => TxVerificationApproval::v2(
+            hw_auth_public_key,
+            commitment,
+            &binding.hardware_serial,
+            binding.network,
+            random(),
+            now + GRANT_LIFETIME_SECS,
+        )?,
+        None => TxVerificationApproval::V1 {
+            hw_auth_public_key,
+            commitment: commitment.to_owned(),
+        },
+    };
 
     // Sign the message
-    let signature = secp.sign_ecdsa(&message, &wik_private_key);
+    let signature = approval.sign(&wik_private_key)?;
 
     Ok(TransactionVerificationGrant {
-        version: 0,
+        version: approval.version(),
         hw_auth_public_key,
-        commitment: commitment.to_vec(),
+        commitment: approval.commitment(),
         reverse_hash_chain,
         signature,
+        envelope: approval.envelope()?,
     })
 }
 
@@ -131,7 +150,13 @@ mod tests {
         let s1 = &sorted_signatures[1];
 
         // Call the function we're testing
-        let approval_result = approve_inputs(wik_priv_key, hw_auth_pub_key, inputs_vec);
+        let approval_result = approve_inputs(
+            wik_priv_key,
+            hw_auth_pub_key,
+            inputs_vec,
+            None,
+            1_700_000_000,
+        );
 
         // Verify that the function returns a valid approval
         assert!(approval_result.is_ok());
@@ -203,4 +228,64 @@ mod tests {
         // Final client state is proof_1 (h[2]), which was the initial random data.
         // This implies the chain was correctly consumed.
     }
+
+    #[test]
+    fn test_approve_psbt_with_hardware_binding() {
+        use wsm_common::bitcoin::Network;
+        use wsm_grant::tx_verification::{
+            TxVerificationExpectations, TX_VERIFICATION_V1_ACCEPTED_UNTIL, TX_VERIFICATION_V2,
+        };
+
+        let secp = Secp256k1::new();
+        let mut rng = rand::thread_rng();
+        let (wik_priv_key, wik_pub_key) = secp.generate_keypair(&mut rng);
+        let (_, hw_auth_pub_key) = secp.generate_keypair(&mut rng);
+        let (input, _, _, _) = generate_random_input(&secp, &mut rng);
+        let mut psbt =
+            PartiallySignedTransaction::from_unsigned_tx(bdk_wallet::bitcoin::Transaction {
+                version: bdk_wallet::bitcoin::transaction::Version::TWO,
+                lock_time: bdk_wallet::bitcoin::absolute::LockTime::ZERO,
+                input: vec![Default::default()],
+                output: vec![],
+            })
+            .unwrap();
+        psbt.inputs = vec![input];
+
+        let now = 1_700_000_000;
+        let grant = approve_psbt(
+            wik_priv_key,
+            hw_auth_pub_key,
+            psbt,
+            Some(HardwareBinding {
+                hardware_serial: "W1A123456789".to_string(),
+                network: Network::Bitcoin,
+            }),
+            now,
+        )
+        .unwrap();
+
+        assert_eq!(grant.version, TX_VERIFICATION_V2);
+        let envelope = grant.envelope.clone().unwrap();
+        let approval = TxVerificationApproval::from_parts(
+            grant.version,
+            grant.hw_auth_public_key,
+            &grant.commitment,
+            Some(&envelope),
+        )
+        .unwrap();
+        let TxVerificationApproval::V2(ref envelope_grant) = approval else {
+            panic!("Expected a version 2 approval");
+        };
+        assert_eq!(envelope_grant.expires_at, now + GRANT_LIFETIME_SECS);
+
+        let expected = TxVerificationExpectations {
+            hardware_serial: "W1A123456789",
+            network: Network::Bitcoin,
+            now,
+            v1_accepted_until: TX_VERIFICATION_V1_ACCEPTED_UNTIL,
+        };
+        assert!(approval
+            .verify(&grant.signature, &wik_pub_key, &expected)
+            .is_ok());
+    }
 }
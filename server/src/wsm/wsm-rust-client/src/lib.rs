@@ -6,8 +6,8 @@ pub use wsm_common::derivation::WSMSupportedDomain;
 pub use wsm_common::messages::api::{
     AttestationDocResponse, ContinueDistributedKeygenResponse, ContinueShareRefreshResponse,
     CreateSelfSovereignBackupResponse, CreatedSigningKey, EvaluatePinResponse,
-    GeneratePartialSignaturesResponse, GetIntegritySigResponse, InitiateDistributedKeygenResponse,
-    InitiateShareRefreshResponse, TransactionVerificationGrant,
+    GeneratePartialSignaturesResponse, GetIntegritySigResponse, HardwareBinding,
+    InitiateDistributedKeygenResponse, InitiateShareRefreshResponse, TransactionVerificationGrant,
 };
 
 use std::fmt::Debug;
@@ -211,10 +211,12 @@ pub trait SigningService {
 
 #[async_trait]
 pub trait GrantService {
+    /// Issues a version 2 grant when `hardware_binding` is set, and a version 1 grant otherwise.
     async fn approve_psbt(
         &self,
         psbt: &str,
         hw_auth_public_key: PublicKey,
+        hardware_binding: Option<HardwareBinding>,
     ) -> Result<TransactionVerificationGrant, Error>;
 }
 
@@ -643,6 +645,7 @@ impl GrantService for WsmClient {
         &self,
         psbt: &str,
         hw_auth_public_key: PublicKey,
+        hardware_binding: Option<HardwareBinding>,
     ) -> Result<TransactionVerificationGrant, Error> {
         let hw_auth_public_key_wsm = wsm_pubkey_from_bytes(&hw_auth_public_key.serialize())
             .map_err(|_| {
@@ -652,6 +655,7 @@ impl GrantService for WsmClient {
         let request = ApprovePsbtRequest {
             psbt: psbt.to_string(),
             hw_auth_public_key: hw_auth_public_key_wsm,
+            hardware_binding,
         };
 
         let res = self